@@ -0,0 +1,78 @@
+//! Compares the buffers the editor can be built on, run with `cargo bench`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use text_editor::{
+    buffer::{GapBuffer, PieceTable, TextBuffer, VecBuffer},
+    LineCol,
+};
+
+const LINES: usize = 100_000;
+
+fn text() -> Vec<String> {
+    (0..LINES)
+        .map(|line| format!("let value_{line} = compute({line}, \"some text\");"))
+        .collect()
+}
+
+fn at(line: usize, col: usize) -> LineCol {
+    LineCol { line, col }
+}
+
+/// Types a few lines in the middle of the text, as if entered in Insert mode, reading the typed
+/// line and the whole text back after each one as the renderer does.
+fn type_lines(buf: &mut impl TextBuffer) -> Duration {
+    let start = Instant::now();
+    let mut pos = at(LINES / 2, 0);
+    for _ in 0..100 {
+        for ch in "fn typed() {}".chars() {
+            pos = buf.insert(pos, ch).unwrap();
+        }
+        black_box(buf.line(pos.line).unwrap());
+        black_box(buf.get_normal_text().len());
+        pos = buf.insert_newline(pos);
+    }
+    start.elapsed()
+}
+
+/// Reads positions spread over the whole text, as offsets, ranges and whole lines.
+fn read_at_random(buf: &impl TextBuffer) -> Duration {
+    let start = Instant::now();
+    let mut line = 0;
+    for _ in 0..1000 {
+        line = (line + 7919) % LINES;
+        let pos = at(line, 3);
+        black_box(buf.get_byte_offset(pos));
+        black_box(buf.get_text(pos, at(line, 8)).unwrap());
+        black_box(buf.line(line).unwrap());
+        black_box(&buf.get_normal_text()[line]);
+    }
+    start.elapsed()
+}
+
+/// Prints how long every buffer took, in the order `VecBuffer`, `GapBuffer` and `PieceTable`.
+fn report(name: &str, [vec, gap, pieces]: [Duration; 3]) {
+    println!("{name}: VecBuffer {vec:?}, GapBuffer {gap:?}, PieceTable {pieces:?}");
+}
+
+fn main() {
+    report(
+        "sequential insert",
+        [
+            type_lines(&mut VecBuffer::new(text())),
+            type_lines(&mut GapBuffer::new(text())),
+            type_lines(&mut PieceTable::new(text())),
+        ],
+    );
+    report(
+        "random access",
+        [
+            read_at_random(&VecBuffer::new(text())),
+            read_at_random(&GapBuffer::new(text())),
+            read_at_random(&PieceTable::new(text())),
+        ],
+    );
+}
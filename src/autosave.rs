@@ -0,0 +1,119 @@
+use std::path::{Path, PathBuf};
+
+use crate::{buffer::TextBuffer, editor::Editor, notif_bar, LineCol, Result};
+
+/// Path the buffer of `path` is auto-saved to, so the canonical file is never overwritten.
+pub fn autosave_path(path: &Path) -> PathBuf {
+    let mut autosave = path.as_os_str().to_owned();
+    autosave.push(".autosave");
+    PathBuf::from(autosave)
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Writes the buffer to the autosave file of the edited file, if it has unsaved changes.
+    pub(crate) fn auto_save(&mut self) {
+        let Some(path) = &self.file_path else {
+            return;
+        };
+        if !self.buffer.is_dirty() {
+            return;
+        }
+        let autosave = autosave_path(path);
+        if let Err(e) = self.save_to_file(&autosave) {
+            notif_bar!(self => format!(
+                "Auto-save to {} failed: {}",
+                autosave.display(),
+                e.display_user()
+            ););
+        }
+    }
+
+    /// Deletes the autosave file of the edited file, if there is one.
+    pub(crate) fn remove_autosave(&self) {
+        if let Some(path) = &self.file_path {
+            let _ = std::fs::remove_file(autosave_path(path));
+        }
+    }
+
+    /// Offers to restore the buffer from the autosave file left behind by a previous session.
+    /// The recovered text replaces the buffer but is not written to the edited file until saved.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the prompt can't be drawn or the autosave file can't be read.
+    pub fn recover_autosave(&mut self) -> Result<()> {
+        let Some(path) = &self.file_path else {
+            return Ok(());
+        };
+        let autosave = autosave_path(path);
+        if !autosave.exists() {
+            return Ok(());
+        }
+        let question = format!(
+            "Found unsaved changes in {}. Recover? (y/N)",
+            autosave.display()
+        );
+        if !self.prompt(&question)? {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(&autosave)?;
+        self.load_text(&content)?;
+        notif_bar!(self => format!("Recovered {}", autosave.display()););
+        Ok(())
+    }
+
+    /// Replaces the whole normal text buffer with `content`.
+    pub(crate) fn load_text(&mut self, content: &str) -> Result<()> {
+        let content = content.strip_suffix('\n').unwrap_or(content);
+        let end = self.buffer.max_linecol();
+        self.buffer.replace(LineCol::default(), end, content)?;
+        self.go(LineCol::default());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::parse_command, editor::tests::new_test_editor, tasks::EditorMessage};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("neotext_{name}_{}", std::process::id()))
+    }
+
+    #[test]
+    fn test_autosave_path() {
+        assert_eq!(
+            autosave_path(Path::new("/tmp/main.rs")),
+            PathBuf::from("/tmp/main.rs.autosave")
+        );
+    }
+
+    #[test]
+    fn test_auto_save_only_when_dirty() {
+        let path = temp_path("autosave_dirty");
+        let mut editor = new_test_editor(&["hello"]);
+        editor.set_file_path(&path);
+
+        editor
+            .handle_message(EditorMessage::TriggerAutoSave)
+            .unwrap();
+        assert!(!autosave_path(&path).exists());
+
+        editor.push('x');
+        editor
+            .handle_message(EditorMessage::TriggerAutoSave)
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(autosave_path(&path)).unwrap(),
+            "xhello\n"
+        );
+        assert!(!path.exists());
+
+        let command = parse_command(":w").unwrap();
+        editor.execute_command(command).unwrap();
+        assert!(!autosave_path(&path).exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "xhello\n");
+        assert!(!editor.buffer.is_dirty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
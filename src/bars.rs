@@ -4,7 +4,7 @@ use crossterm::{
     style::{self, Color},
     terminal::{self, ClearType},
 };
-use std::io::{stdout, Write};
+use std::{collections::VecDeque, io::Write};
 
 pub const INFO_BAR_Y_LOCATION: u16 = 1;
 pub const NOTIFICATION_BAR_Y_LOCATION: u16 = 0;
@@ -70,6 +70,10 @@ where
     F: FnOnce(usize, usize) -> String,
 {
     let (term_width, term_height) = terminal::size()?;
+    // Nothing fits into a terminal without columns, or one too short to have a line for the bar
+    if term_width == 0 || term_height <= bar.y_offset {
+        return Ok(());
+    }
     let y_position = term_height - 1 - bar.y_offset;
 
     execute!(
@@ -83,7 +87,7 @@ where
     print!("{}{}", " ".repeat(bar.x_padding as usize), content);
 
     let remaining_width = (term_width as usize)
-        .saturating_sub(content.len())
+        .saturating_sub(content.chars().count())
         .saturating_sub(bar.x_padding as usize);
     print!("{}", " ".repeat(remaining_width));
     term.flush()?;
@@ -106,20 +110,31 @@ where
 ///
 /// # Message Handling
 /// - Messages exceeding the available width are truncated with an ellipsis ("...").
-/// - After displaying, the message is removed from the queue.
+/// - After displaying, the message is removed from `queue`. Messages of the global queue are
+///   shown once `queue` is empty.
 ///
 /// # Errors
 /// Returns a `Result` which is:
 /// - `Ok(())` if all terminal operations succeed.
 /// - `Err(...)` if any terminal operation fails (e.g., writing to stdout, flushing).
-pub fn get_notif_bar_content() -> String {
-    get_debug_messages()
-        .lock()
-        .unwrap()
+pub fn get_notif_bar_content(queue: &mut VecDeque<String>) -> String {
+    queue
         .pop_front()
+        .or_else(|| get_debug_messages().lock().ok()?.pop_front())
         .unwrap_or_default()
 }
 
+/// Shortens `text` to at most `width` symbols, ending it with `…` if anything was cut off.
+fn truncate_with_ellipsis(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    match width.checked_sub(1) {
+        Some(kept) => text.chars().take(kept).chain(['…']).collect(),
+        None => String::new(),
+    }
+}
+
 /// Draws the information bar at the bottom of the editor.
 ///
 /// This function renders an information bar that displays the current cursor position
@@ -130,6 +145,8 @@ pub fn get_notif_bar_content() -> String {
 /// - Background: Dark grey
 /// - Text Color: White
 /// - Content: Displays the cursor position, starting at `INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION`
+/// - Narrow terminals: The mode and file label are truncated first, then the cursor position,
+///   both ending with `…` when cut off.
 ///
 /// # Returns
 /// `Ok(())` if the info bar is successfully drawn, or an error if any terminal operation fails.
@@ -140,17 +157,26 @@ pub fn get_notif_bar_content() -> String {
 /// - Cursor movement fails
 /// - Writing to stdout fails
 /// - Color setting or resetting fails
-pub fn get_info_bar_content(term_width: usize, mode: &Modal, pos: LineCol) -> String {
-    let modal_string = format!("{mode}");
-    let mut pos = pos.clone();
+pub fn get_info_bar_content(
+    term_width: usize,
+    mode: &Modal,
+    file_label: &str,
+    pos: LineCol,
+) -> String {
+    let mut pos = pos;
     pos.line += 1;
-    let pos_string = format!("{pos}");
-
-    let middle_space = term_width
-        - INFO_BAR_MODAL_INDICATOR_X_LOCATION as usize
-        - modal_string.len()
-        - pos_string.len()
-        - INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION_NEGATIVE as usize;
+    let available = term_width
+        .saturating_sub(INFO_BAR_MODAL_INDICATOR_X_LOCATION as usize)
+        .saturating_sub(INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION_NEGATIVE as usize);
+    let pos_string = truncate_with_ellipsis(&format!("{pos}"), available);
+    let modal_string = truncate_with_ellipsis(
+        &format!("{mode}  {file_label}"),
+        available.saturating_sub(pos_string.chars().count()),
+    );
+
+    let middle_space = available
+        .saturating_sub(modal_string.chars().count())
+        .saturating_sub(pos_string.chars().count());
 
     #[allow(clippy::repeat_once)]
     let loc_neg = " ".repeat(INFO_BAR_LINEWIDTH_INDICATOR_X_LOCATION_NEGATIVE as usize);
@@ -162,3 +188,45 @@ pub fn get_info_bar_content(term_width: usize, mode: &Modal, pos: LineCol) -> St
         loc_neg
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info_bar(term_width: usize) -> String {
+        get_info_bar_content(
+            term_width,
+            &Modal::Normal,
+            "src/some/long/path.rs",
+            LineCol { line: 41, col: 7 },
+        )
+    }
+
+    #[test]
+    fn test_info_bar_fills_wide_terminal() {
+        let content = info_bar(200);
+        assert_eq!(content.chars().count(), 199);
+        assert!(content.starts_with("NORMAL  src/some/long/path.rs "));
+        assert!(content.trim_end().ends_with("42:7"));
+    }
+
+    #[test]
+    fn test_info_bar_truncates_mode_and_file_first() {
+        let content = info_bar(20);
+        assert_eq!(content.chars().count(), 19);
+        assert!(content.starts_with("NORMAL  src/"));
+        assert!(content.contains('…'));
+        assert!(content.trim_end().ends_with("42:7"));
+    }
+
+    #[test]
+    fn test_info_bar_truncates_position_last() {
+        let content = info_bar(10);
+        assert_eq!(content.chars().count(), 9);
+        assert!(content.trim_end().ends_with("42:7"));
+
+        let content = info_bar(4);
+        assert_eq!(content, "4… ");
+        assert_eq!(info_bar(0), " ");
+    }
+}
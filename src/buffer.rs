@@ -1,13 +1,42 @@
 use crate::{modals::Modal, LineCol, Pattern};
 use crate::{Error, Result};
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+use tree_sitter::{InputEdit, Point};
+
+mod gap_buffer;
+mod piece_table;
+mod storage;
+
+pub use gap_buffer::GapBuffer;
+pub use piece_table::PieceTable;
+
+#[cfg(not(feature = "piece-table"))]
+use gap_buffer::GapBuffer as Selected;
+#[cfg(feature = "piece-table")]
+use piece_table::PieceTable as Selected;
+
+/// The `TextBuffer` the editor is built with, a `PieceTable` with the `piece-table` feature and
+/// a `GapBuffer` without it.
+pub type DefaultBuffer = Selected;
 
 /// Trait defining the interface for a text buffer
 #[allow(clippy::module_name_repetitions)]
 pub trait TextBuffer {
     fn set_plane(&mut self, modal: &Modal);
     fn insert_newline(&mut self, at: LineCol) -> LineCol;
-    fn get_byte_offset(&self, to: LineCol) -> usize;
+    /// Byte offset of `at` into the bytes returned by `get_coalesced_bytes`
+    fn get_byte_offset(&self, at: LineCol) -> usize {
+        let preceding_lines: usize = self
+            .get_normal_text()
+            .iter()
+            .take(at.line)
+            .map(|line| line.len() + 1)
+            .sum();
+        preceding_lines + at.col
+    }
     /// Insert a single symbol at specified position
     fn insert(&mut self, at: LineCol, insertable: char) -> Result<LineCol>;
 
@@ -22,7 +51,14 @@ pub trait TextBuffer {
     /// Delete text in the specified range
     fn delete_selection(&mut self, from: LineCol, to: LineCol) -> Result<LineCol>;
 
-    /// Delete the symbol at the specified position
+    /// Delete the columns `from.col..to.col` on every line from `from.line` to `to.line`, as
+    /// selected in Visual Block mode, and return `from`. Lines ending before `from.col` are left
+    /// alone and no line is removed.
+    fn delete_block(&mut self, from: LineCol, to: LineCol) -> Result<LineCol>;
+
+    /// Delete the symbol right before the specified position, like a backspace would, and
+    /// return the position it started at. At the start of a line the line is joined onto the
+    /// previous one instead. Fails with `ImATeacup` at the very start of the buffer.
     fn delete(&mut self, at: LineCol) -> Result<LineCol>;
 
     /// Replace text in the specified range with new text
@@ -31,7 +67,7 @@ pub trait TextBuffer {
     /// Get the text in the specified range
     fn get_text(&self, from: LineCol, to: LineCol) -> Result<String>;
 
-    /// Get the length of the entire buffer
+    /// Get the length of the entire buffer in bytes
     fn len(&self) -> usize;
 
     /// Check if the buffer is empty
@@ -42,21 +78,136 @@ pub trait TextBuffer {
     /// Get the number of lines in the buffer
     fn line_count(&self) -> usize;
 
-    /// Get a single continuous vec of bytes containing the entire text
-    fn get_coalesced_bytes(&self) -> Vec<u8>;
+    /// Get a single continuous vec of bytes containing the entire normal text, lines separated by
+    /// a newline. This is what the highlighter parses.
+    fn get_coalesced_bytes(&self) -> Vec<u8> {
+        self.get_normal_text().join("\n").into_bytes()
+    }
 
     /// Get the contents of a specific line
     fn line(&self, line_number: usize) -> Result<&str>;
 
-    /// Find the next occurrence of a Pattern
-    fn find(&self, query: impl Pattern, at: LineCol) -> Result<LineCol>;
+    /// Get the word containing the symbol at the specified position, along with the position the
+    /// word starts at. Symbols for which `is_keyword` holds are considered part of a word.
+    fn word_at(&self, at: LineCol, is_keyword: impl Fn(char) -> bool) -> Option<(LineCol, &str)> {
+        let line = self.line(at.line).ok()?;
+        let under = line.get(at.col..)?.chars().next()?;
+        if !is_keyword(under) {
+            return None;
+        }
+        let start = line[..at.col]
+            .char_indices()
+            .rev()
+            .take_while(|(_, ch)| is_keyword(*ch))
+            .last()
+            .map_or(at.col, |(idx, _)| idx);
+        let end = line[at.col..]
+            .char_indices()
+            .find(|(_, ch)| !is_keyword(*ch))
+            .map_or(line.len(), |(idx, _)| at.col + idx);
+        Some((
+            LineCol {
+                line: at.line,
+                col: start,
+            },
+            &line[start..end],
+        ))
+    }
 
-    /// Find the previous occurrence of a Pattern
-    fn rfind(&self, query: impl Pattern, at: LineCol) -> Result<LineCol>;
+    /// Searches for a query string in the buffer, starting from a given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The string to search for.
+    /// * `at` - The position (line and column) to start the search from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
+    /// * `Err(Error::PatternNotFound)` - If the query string is not found in the buffer.
+    ///
+    /// # Behavior
+    ///
+    /// Finds the first occurrence starting at or after `at`, so a match starting exactly at `at`
+    /// is found. It searches the remainder of the starting line, then subsequent lines in their
+    /// entirety. Together with `rfind`, which only finds occurrences starting strictly before
+    /// `at`, every occurrence is found by exactly one of the two.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let buffer = // ... initialize buffer ...
+    /// let result = buffer.find("example", LineCol{line: 1, col: 5});
+    /// assert_eq!(result, Ok(LineCol{line: 2, col: 10})); // Found on line 2, column 10
+    /// ```
+    fn find(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
+        query
+            .find_pattern(&self.get_buffer_window(Some(at), None)?)
+            .ok_or(Error::PatternNotFound)
+            .map(|v| LineCol {
+                line: v.line + at.line,
+                col: if v.line == 0 { v.col + at.col } else { v.col },
+            })
+    }
+
+    /// Searches backwards for a query string in the buffer, ending at a given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The string to search for.
+    /// * `at` - The position (line and column) to start the reverse search from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
+    /// * `Err(Error::PatternNotFound)` - If the query string is not found in the buffer.
+    ///
+    /// # Behavior
+    ///
+    /// Finds the last occurrence starting strictly before `at`, a match starting exactly at `at`
+    /// is left to `find`. Occurrences on the starting line may extend past `at`, previous lines
+    /// are searched in their entirety from end to start.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let buffer = // ... initialize buffer ...
+    /// let result = buffer.rfind("example", LineCol{line: 2, col: 15});
+    /// assert_eq!(result, Ok(LineCol{line: 1, col: 5})); // Found on line 1, column 5
+    /// ```
+    fn rfind(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
+        let text = self.get_normal_text();
+        if text.is_empty() {
+            return Err(Error::PatternNotFound);
+        }
+        let line = at.line.min(self.max_line());
+        let on_line = query
+            .find_all_pattern(&text[line..=line])
+            .into_iter()
+            .rev()
+            .find(|found| found.col < at.col);
+        if let Some(found) = on_line {
+            return Ok(LineCol {
+                line,
+                col: found.col,
+            });
+        }
+        query
+            .rfind_pattern(&text[..line])
+            .ok_or(Error::PatternNotFound)
+    }
+
+    /// Find every occurrence of a Pattern in the normal buffer, in order
+    fn find_all(&self, query: impl Pattern) -> Vec<LineCol> {
+        query.find_all_pattern(self.get_normal_text())
+    }
 
     /// Undo the last operation
     fn undo(&mut self, at: LineCol) -> Result<LineCol>;
 
+    /// Record the current text as a state `undo` returns to, with the cursor at `at`
+    fn save_state(&mut self, at: LineCol);
+
     /// Redo the last undone operation
     fn redo(&mut self, at: LineCol) -> Result<LineCol>;
 
@@ -65,38 +216,163 @@ pub trait TextBuffer {
     /// Get the entire text for the normal buffer
     fn get_normal_text(&self) -> &[String];
 
-    /// Get partial window to the normal buffer, ranging from -> to
-    fn get_buffer_window(&self, from: Option<LineCol>, to: Option<LineCol>) -> Result<Vec<String>>;
+    /// Get partial window to the normal buffer, ranging from -> to. The first and last lines are
+    /// cut at the columns of `from` and `to`, `None` reaches to the start or end of the buffer.
+    fn get_buffer_window(&self, from: Option<LineCol>, to: Option<LineCol>) -> Result<Vec<&str>> {
+        if from.is_none() && to.is_none() {
+            return Ok(self.get_normal_text().iter().map(String::as_str).collect());
+        }
+        let from = from.unwrap_or(LineCol { line: 0, col: 0 });
+        let mut to = to.unwrap_or_else(|| self.max_linecol());
+        to.line = self.max_line().min(to.line);
+        if from.line > to.line || (from.line == to.line && from.col > to.col) {
+            return Err(Error::InvalidInput);
+        }
 
-    /// Get the entire text for the terminal buffer
+        let mut vec: Vec<&str> = self.get_normal_text()[from.line..=to.line]
+            .iter()
+            .map(String::as_str)
+            .collect();
+        vec[0] = &vec[0][from.col..];
+        let last = vec.len() - 1;
+        if from.line == to.line {
+            vec[last] = &vec[last][..to.col - from.col];
+        } else {
+            vec[last] = &vec[last][..to.col];
+        }
+        if to.col == 0 {
+            let _ = vec.pop();
+        }
+
+        Ok(vec)
+    }
+
+    /// Get the last line of the terminal buffer, the one output is currently written to
     fn get_terminal_text(&self) -> &str;
+    /// Get every line of the terminal buffer
+    fn get_terminal_lines(&self) -> &[String];
+    /// Append output to the terminal buffer, starting a new line at every `\n`
+    fn append_terminal_output(&mut self, output: &str);
     /// Get the entire text for the command buffer
     fn get_command_text(&self) -> &[String];
-    /// Get the entire text for the command buffer
+    /// Replace the text of the command buffer with `new`, e.g. an entry of the history
     fn replace_command_text(&mut self, new: impl Into<String>);
 
     /// Get maximum line bound for the current buffer
-    fn max_line(&self) -> usize;
+    fn max_line(&self) -> usize {
+        self.get_normal_text().len().saturating_sub(1)
+    }
     /// Get maximum column bound for the current buffer
     fn max_col(&self, at: LineCol) -> usize;
     fn is_command_empty(&self) -> bool;
+    /// Empty the command buffer, leaving a single empty line
     fn clear_command(&mut self);
-    fn max_linecol(&self) -> LineCol;
-    fn delete_line(&mut self, at: usize);
+    /// Replace the normal text with `lines`, as when another file is opened. The undo history
+    /// is dropped, it belongs to the previous text.
+    fn reset_text(&mut self, lines: Vec<String>);
+    fn max_linecol(&self) -> LineCol {
+        let buf = self.get_normal_text();
+        let line = buf.len() - 1;
+        let col = buf[line].len();
+        LineCol { line, col }
+    }
+    /// Delete the line `at` of the normal buffer and return where the cursor goes, the start of
+    /// the line that took its place. The last remaining line is emptied instead.
+    fn delete_line(&mut self, at: usize) -> Result<LineCol>;
+    /// Get the whole lines of the normal buffer from the line of `from` to the one of `to`, as
+    /// shown in the viewport. Columns are ignored and `to` is clamped to the last line.
     fn get_full_lines_buffer_window(
         &self,
         from: Option<LineCol>,
         to: Option<LineCol>,
-    ) -> Result<Vec<String>>;
+    ) -> Result<Vec<&str>> {
+        let full_text = self.get_normal_text();
+
+        let start_line = from.map_or(0, |lc| lc.line);
+        let end_line = to.map_or_else(|| full_text.len().saturating_sub(1), |lc| lc.line);
+
+        if start_line > end_line || start_line >= full_text.len() {
+            return Err(Error::InvalidInput);
+        }
+
+        let end_line = end_line.min(full_text.len().saturating_sub(1));
+        Ok(full_text[start_line..=end_line]
+            .iter()
+            .map(String::as_str)
+            .collect())
+    }
+
+    /// Whether the normal text differs from the last saved state
+    fn is_dirty(&self) -> bool;
+    /// Marks the current normal text as saved
+    fn mark_saved(&mut self);
+    /// Takes the changes made to the normal text since they were last taken, oldest first
+    fn take_pending_changes(&mut self) -> Vec<IncrementalChange>;
+    /// Takes the changes made to the normal text since they were last taken as the edits the
+    /// syntax tree of the highlighter needs, oldest first
+    fn take_pending_edits(&mut self) -> Vec<InputEdit>;
+}
+
+/// A single edit of the normal text, as sent to the language server in `didChange`. Positions
+/// and lengths are counted in characters and refer to the text before the edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalChange {
+    pub start: LineCol,
+    pub end: LineCol,
+    /// Amount of characters replaced, line breaks included.
+    pub range_length: usize,
+    pub text: String,
+}
+
+/// The syntax tree edit replacing the bytes between `from` and `to` of the normal text by `text`.
+/// `start_byte` and `old_end_byte` are the offsets of `from` and `to`, whose columns must not be
+/// past the end of their lines.
+fn input_edit(
+    from: LineCol,
+    to: LineCol,
+    start_byte: usize,
+    old_end_byte: usize,
+    text: &str,
+) -> InputEdit {
+    let point = |pos: LineCol| Point {
+        row: pos.line,
+        column: pos.col,
+    };
+    let new_end_position = match text.rfind('\n') {
+        Some(last_break) => Point {
+            row: from.line + text.matches('\n').count(),
+            column: text.len() - last_break - 1,
+        },
+        None => Point {
+            row: from.line,
+            column: from.col + text.len(),
+        },
+    };
+    InputEdit {
+        start_byte,
+        old_end_byte,
+        new_end_byte: start_byte + text.len(),
+        start_position: point(from),
+        old_end_position: point(to),
+        new_end_position,
+    }
 }
 
 /// A stack implementation using a `VecDeque` as the underlying storage.
-#[derive(Debug, Default)]
-pub struct Stack {
-    content: VecDeque<StateCapsule>,
+#[derive(Debug)]
+pub struct Stack<T = StateCapsule> {
+    content: VecDeque<T>,
 }
 
-impl Stack {
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self {
+            content: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> Stack<T> {
     /// Truncates the stack to a maximum of 1000 elements.
     /// If the stack has more than 1000 elements, it removes the excess from the back.
     fn truncate(&mut self) {
@@ -108,13 +384,13 @@ impl Stack {
 
     /// Removes and returns the top element from the stack.
     /// Returns None if the stack is empty.
-    pub fn pop(&mut self) -> Option<StateCapsule> {
+    pub fn pop(&mut self) -> Option<T> {
         self.content.pop_front()
     }
 
     /// Pushes a new element onto the top of the stack.
     /// After pushing, it truncates the stack to maintain a maximum of 1000 elements.
-    pub fn push(&mut self, el: StateCapsule) {
+    pub fn push(&mut self, el: T) {
         self.content.push_front(el);
         self.truncate();
     }
@@ -149,6 +425,12 @@ pub struct VecBuffer {
     /// Stack to store future states for redo operations.
     future: Stack,
     plane: BufferPlane,
+    /// Hash of the normal text at the time it was last saved, used for dirty checking.
+    saved_hash: u64,
+    /// Edits of the normal text not yet sent to the language server.
+    pending_changes: Vec<IncrementalChange>,
+    /// Edits of the normal text not yet applied to the syntax tree of the highlighter.
+    pending_edits: Vec<InputEdit>,
 }
 
 #[derive(Default, Debug, Clone, Copy)]
@@ -159,6 +441,22 @@ enum BufferPlane {
     Command,
 }
 
+impl From<&Modal> for BufferPlane {
+    fn from(modal: &Modal) -> Self {
+        match modal {
+            Modal::Command | Modal::Find(_) => Self::Command,
+            Modal::Terminal => Self::Terminal,
+            Modal::Normal
+            | Modal::Insert
+            | Modal::Visual
+            | Modal::VisualLine
+            | Modal::VisualBlock
+            | Modal::CmdHistory
+            | Modal::Picker => Self::Normal,
+        }
+    }
+}
+
 impl Default for VecBuffer {
     fn default() -> Self {
         Self {
@@ -168,6 +466,9 @@ impl Default for VecBuffer {
             past: Stack::default(),
             future: Stack::default(),
             plane: BufferPlane::Normal,
+            saved_hash: hash_lines(&[String::new()]),
+            pending_changes: Vec::new(),
+            pending_edits: Vec::new(),
         }
     }
 }
@@ -175,14 +476,91 @@ impl Default for VecBuffer {
 impl VecBuffer {
     pub fn new(text: Vec<String>) -> Self {
         Self {
+            saved_hash: hash_lines(&text),
             text,
             terminal: vec![String::new()],
             command: vec![String::new()],
             past: Stack::default(),
             future: Stack::default(),
             plane: BufferPlane::Normal,
+            pending_changes: Vec::new(),
+            pending_edits: Vec::new(),
+        }
+    }
+    /// Records the replacement of `from..to` of the normal text by `text`. Must be called before
+    /// the text is changed, edits of the other planes are ignored.
+    fn record_change(&mut self, from: LineCol, to: LineCol, text: &str) {
+        if !matches!(self.plane, BufferPlane::Normal) {
+            return;
+        }
+        let char_pos = |pos: LineCol| {
+            let line = &self.text[pos.line];
+            let col = line
+                .get(..pos.col)
+                .map_or_else(|| line.chars().count(), |before| before.chars().count());
+            LineCol {
+                line: pos.line,
+                col,
+            }
+        };
+        let (start, end) = (char_pos(from), char_pos(to));
+        let range_length = if from.line == to.line {
+            end.col - start.col
+        } else {
+            self.text[from.line].chars().count() - start.col
+                + self.text[from.line + 1..to.line]
+                    .iter()
+                    .map(|line| line.chars().count() + 1)
+                    .sum::<usize>()
+                + 1
+                + end.col
+        };
+        self.pending_changes.push(IncrementalChange {
+            start,
+            end,
+            range_length,
+            text: text.to_string(),
+        });
+        let clamp = |pos: LineCol| LineCol {
+            line: pos.line,
+            col: pos.col.min(self.text[pos.line].len()),
+        };
+        let (from, to) = (clamp(from), clamp(to));
+        let (start_byte, old_end_byte) = (self.get_byte_offset(from), self.get_byte_offset(to));
+        self.pending_edits
+            .push(input_edit(from, to, start_byte, old_end_byte, text));
+    }
+    /// Records the removal of the whole lines `from..=to` of the normal text.
+    fn record_lines_removal(&mut self, from: usize, to: usize) {
+        let end_of = |line: usize| LineCol {
+            line,
+            col: self.text[line].len(),
+        };
+        if to + 1 < self.text.len() {
+            self.record_change(
+                LineCol { line: from, col: 0 },
+                LineCol {
+                    line: to + 1,
+                    col: 0,
+                },
+                "",
+            );
+        } else if from > 0 {
+            self.record_change(end_of(from - 1), end_of(to), "");
+        } else {
+            self.record_change(LineCol::default(), end_of(to), "");
         }
     }
+    /// Records the normal text being replaced as a whole by `lines`, as done by undo and redo.
+    fn record_full_replacement(&mut self, lines: &[String]) {
+        let end = LineCol {
+            line: self.text.len() - 1,
+            col: self.text[self.text.len() - 1].len(),
+        };
+        let plane = std::mem::replace(&mut self.plane, BufferPlane::Normal);
+        self.record_change(LineCol::default(), end, &lines.join("\n"));
+        self.plane = plane;
+    }
     fn get_mut_buffer(&mut self) -> &mut Vec<String> {
         match &self.plane {
             BufferPlane::Normal => &mut self.text,
@@ -199,77 +577,72 @@ impl VecBuffer {
     }
 }
 
-impl TextBuffer for VecBuffer {
-    /// Get entire text as a single vec of bytes.
-    /// This method clones the buffer, and thus should be only done for the initial parsing of the
-    /// tree
-    fn get_coalesced_bytes(&self) -> Vec<u8> {
-        self.text.join("\n").as_bytes().to_owned()
+/// Byte range of the columns `from..to` of a Visual Block selection on `line`, widened to whole
+/// symbols. `None` if the line ends before `from`.
+pub(crate) fn block_columns(line: &str, from: usize, to: usize) -> Option<(usize, usize)> {
+    if from >= line.len() {
+        return None;
     }
-    // Gets only partial buffer from a position to a position
-    fn get_buffer_window(&self, from: Option<LineCol>, to: Option<LineCol>) -> Result<Vec<String>> {
-        if from.is_none() && to.is_none() {
-            return Ok(self.get_normal_text().to_owned());
-        }
-        let from = from.unwrap_or(LineCol { line: 0, col: 0 });
-        let mut to = to.unwrap_or_else(|| self.max_linecol());
-        to.line = self.max_line().min(to.line);
-        if from.line > to.line || (from.line == to.line && from.col > to.col) {
-            return Err(Error::InvalidInput);
-        }
+    let start = (0..=from).rev().find(|col| line.is_char_boundary(*col))?;
+    let end =
+        (to.clamp(from + 1, line.len())..=line.len()).find(|col| line.is_char_boundary(*col))?;
+    Some((start, end))
+}
 
-        let mut vec = self.get_normal_text()[from.line..=to.line].to_owned();
-        vec[0] = vec[0][from.col..].to_string();
-        let last = vec.len() - 1;
-        if from.line == to.line {
-            vec[last] = vec[last][..to.col - from.col].to_string();
-        } else {
-            vec[last].truncate(to.col);
-        }
-        if to.col == 0 {
-            let _ = vec.pop();
-        }
+fn hash_lines(lines: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    lines.hash(&mut hasher);
+    hasher.finish()
+}
 
-        Ok(vec)
+impl TextBuffer for VecBuffer {
+    fn is_dirty(&self) -> bool {
+        hash_lines(&self.text) != self.saved_hash
     }
-    fn get_full_lines_buffer_window(
-        &self,
-        from: Option<LineCol>,
-        to: Option<LineCol>,
-    ) -> Result<Vec<String>> {
-        let full_text = self.get_normal_text();
-
-        let start_line = from.map_or(0, |lc| lc.line);
-        let end_line = to.map_or_else(|| full_text.len().saturating_sub(1), |lc| lc.line);
-
-        if start_line > end_line || start_line >= full_text.len() {
-            return Err(Error::InvalidInput);
-        }
-
-        let end_line = end_line.min(full_text.len().saturating_sub(1));
-        let result = full_text[start_line..=end_line].to_vec();
-        Ok(result)
+    fn mark_saved(&mut self) {
+        self.saved_hash = hash_lines(&self.text);
+    }
+    fn take_pending_changes(&mut self) -> Vec<IncrementalChange> {
+        std::mem::take(&mut self.pending_changes)
+    }
+    fn take_pending_edits(&mut self) -> Vec<InputEdit> {
+        std::mem::take(&mut self.pending_edits)
     }
     fn replace_command_text(&mut self, new: impl Into<String>) {
         self.command = vec![new.into()];
     }
-    fn delete_line(&mut self, at: usize) {
-        let _ = self.text.remove(at);
+    fn delete_line(&mut self, at: usize) -> Result<LineCol> {
+        if at >= self.text.len() {
+            return Err(Error::InvalidLineNumber);
+        }
+        let plane = std::mem::replace(&mut self.plane, BufferPlane::Normal);
+        self.record_lines_removal(at, at);
+        self.plane = plane;
+        if self.text.len() == 1 {
+            self.text[0].clear();
+        } else {
+            self.text.remove(at);
+        }
+        Ok(LineCol {
+            line: at.min(self.text.len() - 1),
+            col: 0,
+        })
     }
     fn clear_command(&mut self) {
         self.command.clear();
         self.command.push(String::new());
     }
+    fn reset_text(&mut self, lines: Vec<String>) {
+        self.record_full_replacement(&lines);
+        self.text = lines;
+        self.past = Stack::default();
+        self.future = Stack::default();
+    }
     fn is_command_empty(&self) -> bool {
         self.command[0].is_empty()
     }
     fn set_plane(&mut self, modal: &Modal) {
-        self.plane = match modal {
-            Modal::Command | Modal::Find(_) => BufferPlane::Command,
-            Modal::Normal | Modal::Insert | Modal::Visual | Modal::VisualLine => {
-                BufferPlane::Normal
-            }
-        };
+        self.plane = BufferPlane::from(modal);
     }
     fn max_col(&self, at: LineCol) -> usize {
         let buf = self.get_buffer();
@@ -279,35 +652,33 @@ impl TextBuffer for VecBuffer {
             buf[at.line].len()
         }
     }
-    fn max_line(&self) -> usize {
-        self.get_normal_text().len().saturating_sub(1)
-    }
-    fn max_linecol(&self) -> LineCol {
-        let buf = self.get_normal_text();
-        let line = buf.len() - 1;
-        let col = buf[line].len();
-        LineCol { line, col }
-    }
     fn insert_newline(&mut self, mut at: LineCol) -> LineCol {
+        let end = LineCol {
+            line: at.line,
+            col: self.get_buffer()[at.line].len(),
+        };
+        self.record_change(end, end, "\n");
         self.get_mut_buffer().insert(at.line + 1, String::new());
         at.line += 1;
         at.col = 0;
         at
     }
     fn insert(&mut self, mut at: LineCol, ch: char) -> Result<LineCol> {
-        if at.line > self.get_buffer().len() || at.col > self.get_buffer()[at.line].len() {
+        if at.line >= self.get_buffer().len() || at.col > self.get_buffer()[at.line].len() {
             return Err(Error::InvalidPosition);
         }
+        self.record_change(at, at, ch.encode_utf8(&mut [0; 4]));
         self.get_mut_buffer()[at.line].insert(at.col, ch);
         at.col += 1;
         Ok(at)
     }
     /// Performs a redo operation, moving the current state to the next future state if available.
-    /// Returns an error if there are no `future` states to redo to.
+    /// Returns an error if there are no `future` states to redo to. Always acts on the normal text.
     fn redo(&mut self, at: LineCol) -> Result<LineCol> {
         self.future
             .pop()
             .map(|future_state| {
+                self.record_full_replacement(&future_state.content);
                 let current_state = std::mem::replace(&mut self.text, future_state.content);
                 self.past.push(StateCapsule {
                     content: current_state,
@@ -319,11 +690,22 @@ impl TextBuffer for VecBuffer {
     }
 
     /// Performs an undo operation, moving the current state to the previous past state if available.
-    /// Returns an error if there are no `past` states to undo to.
+    /// Returns an error if there are no `past` states to undo to. Always acts on the normal text.
     fn undo(&mut self, at: LineCol) -> Result<LineCol> {
+        // States saved before edits that never happened, like an Insert session left without
+        // typing, would undo nothing
+        while self
+            .past
+            .content
+            .front()
+            .is_some_and(|state| state.content == self.text)
+        {
+            self.past.pop();
+        }
         self.past
             .pop()
             .map(|past_state| {
+                self.record_full_replacement(&past_state.content);
                 let current_state = std::mem::replace(&mut self.text, past_state.content);
                 self.future.push(StateCapsule {
                     content: current_state,
@@ -334,96 +716,46 @@ impl TextBuffer for VecBuffer {
             .map_or_else(|| Err(Error::NowhereToGo), Ok)
     }
 
-    /// Searches for a query string in the buffer, starting from a given position.
-    ///
-    /// # Arguments
-    ///
-    /// * `query` - The string to search for.
-    /// * `at` - The position (line and column) to start the search from.
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
-    /// * `Err(BufferError::PatternNotFound)` - If the query string is not found in the buffer.
-    ///
-    /// # Behavior
-    ///
-    /// The search starts at the given position and continues to the end of the buffer.
-    /// It searches the remainder of the starting line, then subsequent lines in their entirety.
-    /// The search is case-sensitive and returns the position of the first occurrence found.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// let buffer = // ... initialize buffer ...
-    /// let result = buffer.find("example", LineCol{line: 1, col: 5});
-    /// assert_eq!(result, Ok(LineCol{line: 2, col: 10})); // Found on line 2, column 10
-    /// ```
-    fn find(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
-        query
-            .find_pattern(&self.get_buffer_window(Some(at), None)?)
-            .ok_or(Error::PatternNotFound)
-            .map(|v| LineCol {
-                line: v.line + at.line,
-                col: if v.line == 0 { v.col + at.col } else { v.col },
-            })
-    }
-
-    /// Searches backwards for a query string in the buffer, ending at a given position.
-    ///
-    /// # Arguments
-    ///
-    /// * `query` - The string to search for.
-    /// * `at` - The position (line and column) to start the reverse search from.
-    ///
-    /// # Returns
-    ///
-    /// * `Ok(LineCol)` - The position (line and column) where the query was found.
-    /// * `Err(BufferError::PatternNotFound)` - If the query string is not found in the buffer.
-    ///
-    /// # Behavior
-    ///
-    /// The search starts at the given position and continues backwards to the beginning of the buffer.
-    /// It first searches the portion of the starting line from the given position to its start,
-    /// then searches previous lines in their entirety from end to start.
-    /// The search is case-sensitive and returns the position of the last occurrence found
-    /// (i.e., the first occurrence when searching backwards).
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// let buffer = // ... initialize buffer ...
-    /// let result = buffer.rfind("example", LineCol{line: 2, col: 15});
-    /// assert_eq!(result, Ok(LineCol{line: 1, col: 5})); // Found on line 1, column 5
-    /// ```
-    fn rfind(&self, query: impl Pattern, at: LineCol) -> Result<LineCol> {
-        query
-            .rfind_pattern(&self.get_buffer_window(None, Some(at))?)
-            .ok_or(Error::PatternNotFound)
-            .map(|v| LineCol {
-                line: v.line,
-                col: v.col,
-            })
+    /// Pushes the current normal text onto the `past` stack. Any undone states are dropped, as
+    /// they no longer follow from the new one. Does nothing while the command bar or terminal is
+    /// edited, their edits aren't part of the undo history.
+    fn save_state(&mut self, at: LineCol) {
+        if !matches!(self.plane, BufferPlane::Normal) {
+            return;
+        }
+        self.future = Stack::default();
+        // Saving the same text twice in a row would take two undos to get past it
+        if self
+            .past
+            .content
+            .front()
+            .is_some_and(|state| state.content == self.text)
+        {
+            return;
+        }
+        self.past.push(StateCapsule {
+            content: self.text.clone(),
+            loc: at,
+        });
     }
 
+    /// Byte length of the normal text, counting a newline between each pair of lines.
     fn len(&self) -> usize {
-        // Currently length of the entire file seems unnecessary to implement. If I realize it
-        // needs to be implemented it might be as a counter at the level of a struct attribute.
-        0
+        self.text
+            .iter()
+            .map(|line| line.len() + 1)
+            .sum::<usize>()
+            .saturating_sub(1)
     }
 
     fn line_count(&self) -> usize {
         self.get_buffer().len()
     }
     fn line(&self, line_number: usize) -> Result<&str> {
-        if line_number <= self.line_count() {
-            Ok(self
-                .get_buffer()
-                .get(line_number)
-                .expect("Checks already passed"))
-        } else {
-            Err(Error::InvalidLineNumber)
-        }
+        self.get_buffer()
+            .get(line_number)
+            .map(String::as_str)
+            .ok_or(Error::InvalidLineNumber)
     }
     /// Retrieves text from the buffer within the specified range.
     ///
@@ -435,7 +767,7 @@ impl TextBuffer for VecBuffer {
     /// # Returns
     ///
     /// * `Ok(String)` containing the requested text if the operation was successful.
-    /// * `Err(BufferError::InvalidRange)` if the specified range is invalid.
+    /// * `Err(Error::InvalidRange)` if the specified range is invalid.
     ///
     /// # Behavior
     ///
@@ -444,7 +776,7 @@ impl TextBuffer for VecBuffer {
     ///
     /// # Errors
     ///
-    /// Returns `BufferError::InvalidRange` in the following cases:
+    /// Returns `Error::InvalidRange` in the following cases:
     /// - If the start position exceeds the end position.
     /// - If either the start or end position is beyond the buffer's contents.
     ///
@@ -456,7 +788,7 @@ impl TextBuffer for VecBuffer {
     /// let to = LineCol{line: 2, col: 10};
     /// match buffer.get_text(&from, &to) {
     ///     Ok(text) => println!("Retrieved text: {}", text),
-    ///     Err(BufferError::InvalidRange) => println!("Invalid range specified"),
+    ///     Err(Error::InvalidRange) => println!("Invalid range specified"),
     ///     Err(_) => println!("An error occurred"),
     /// }
     /// ```
@@ -497,18 +829,13 @@ impl TextBuffer for VecBuffer {
     /// # Returns
     ///
     /// * `Ok(())` if the replacement was successful.
-    /// * `Err(BufferError::InvalidInput)` if the input text is empty.
     ///
     /// # Behavior
     ///
     /// This function replaces the text between `from` and `to` positions with the provided `text`.
     /// It handles multi-line replacements, preserving the start of the first line before `from`
-    /// and the end of the last line after `to`.
-    ///
-    /// # Note
-    ///
-    /// The caller must ensure that `text` is not empty. If empty text replacement is needed,
-    /// use the `delete` method instead.
+    /// and the end of the last line after `to`. An empty `text` deletes the range, joining the
+    /// start of its first line with the end of its last.
     ///
     /// # Examples
     ///
@@ -519,16 +846,11 @@ impl TextBuffer for VecBuffer {
     /// let new_text = "replacement text";
     /// buffer.replace(&from, &to, new_text).expect("Replace operation failed");
     /// ```
-    ///
-    /// # Errors
-    ///
-    /// Returns `BufferError::InvalidInput` if `text` is empty.
     fn replace(&mut self, from: LineCol, to: LineCol, text: &str) -> Result<()> {
-        if text.is_empty() {
-            return Err(Error::InvalidInput);
-        }
         let mut new_lines = Vec::new();
-        let mut lines = text.lines();
+        let mut lines = text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line));
 
         if let Some(first_line) = lines.next() {
             let start = &self.get_buffer()[from.line][..from.col];
@@ -539,6 +861,7 @@ impl TextBuffer for VecBuffer {
 
         new_lines.extend(lines.map(String::from));
 
+        self.record_change(from, to, &text.replace('\r', ""));
         let last = new_lines.last_mut().expect("We know there is a last line");
         last.push_str(&self.get_buffer()[to.line][to.col..]);
 
@@ -557,7 +880,7 @@ impl TextBuffer for VecBuffer {
     /// # Returns
     ///
     /// * `Ok(())` if the insertion was successful.
-    /// * `Err(BufferError::InvalidPosition)` if the specified position is out of bounds.
+    /// * `Err(Error::InvalidPosition)` if the specified position is out of bounds.
     ///
     /// # Behavior
     ///
@@ -598,12 +921,18 @@ impl TextBuffer for VecBuffer {
 
         let mut lines: Vec<String> = text.lines().map(String::from).collect();
         if newline {
+            let end = LineCol {
+                line: at.line,
+                col: self.get_buffer()[at.line].len(),
+            };
+            self.record_change(end, end, &format!("\n{}", lines.join("\n")));
             lines.into_iter().rev().for_each(|line| {
                 self.get_mut_buffer().insert(at.line + 1, line);
             });
             resulting_cursor_pos.line += 1;
             resulting_cursor_pos.col = 0;
         } else {
+            self.record_change(at, at, &lines.join("\n"));
             let current_line = &mut self.get_mut_buffer()[at.line];
             let tail = current_line.split_off(at.col);
             current_line.push_str(&lines[0]);
@@ -628,7 +957,7 @@ impl TextBuffer for VecBuffer {
     /// # Returns
     ///
     /// * `Ok(())` if the deletion was successful.
-    /// * `Err(BufferError::InvalidRange)` if the specified range is invalid.
+    /// * `Err(Error::InvalidRange)` if the specified range is invalid.
     ///
     /// # Behavior
     ///
@@ -644,7 +973,7 @@ impl TextBuffer for VecBuffer {
     ///
     /// # Errors
     ///
-    /// Returns `BufferError::InvalidRange` in the following cases:
+    /// Returns `Error::InvalidRange` in the following cases:
     /// - If either `from` or `to` positions are beyond the buffer's contents.
     /// - If `from` position comes after `to` position.
     /// - If `from` and `to` are the same position.
@@ -657,7 +986,7 @@ impl TextBuffer for VecBuffer {
     /// let to = LineCol { line: 2, col: 10 };
     /// match buffer.delete(&from, &to) {
     ///     Ok(_) => println!("Text deleted successfully"),
-    ///     Err(BufferError::InvalidRange) => println!("Invalid range specified"),
+    ///     Err(Error::InvalidRange) => println!("Invalid range specified"),
     ///     Err(_) => println!("An error occurred"),
     /// }
     /// ```
@@ -667,7 +996,7 @@ impl TextBuffer for VecBuffer {
     /// This function modifies the buffer's content. After calling this function,
     /// line numbers and column positions after the deleted range may change.
     fn delete_selection(&mut self, from: LineCol, to: LineCol) -> Result<LineCol> {
-        let buf = self.get_mut_buffer();
+        let buf = self.get_buffer();
         if from.line >= buf.len()
             || to.line >= buf.len()
             || (from.line == to.line && from.col > to.col)
@@ -676,6 +1005,17 @@ impl TextBuffer for VecBuffer {
         {
             return Err(Error::InvalidRange);
         }
+        let to_line_len = buf[to.line].len();
+        if from.col == 0 && to.col >= to_line_len {
+            self.record_lines_removal(from.line, to.line);
+        } else {
+            let to = LineCol {
+                line: to.line,
+                col: to.col.min(to_line_len),
+            };
+            self.record_change(from, to, "");
+        }
+        let buf = self.get_mut_buffer();
 
         if from.col == 0 && to.col >= buf[to.line].len() {
             buf.drain(from.line..=to.line);
@@ -718,58 +1058,223 @@ impl TextBuffer for VecBuffer {
         &self.command
     }
     fn get_terminal_text(&self) -> &str {
-        &self.terminal[0]
+        self.terminal.last().map_or("", String::as_str)
+    }
+    fn get_terminal_lines(&self) -> &[String] {
+        &self.terminal
+    }
+    fn append_terminal_output(&mut self, output: &str) {
+        let mut lines = output.split('\n');
+        let first = lines.next().unwrap_or_default();
+        match self.terminal.last_mut() {
+            Some(last) => last.push_str(first),
+            None => self.terminal.push(first.to_string()),
+        }
+        self.terminal.extend(lines.map(ToString::to_string));
+    }
+    fn delete_block(&mut self, from: LineCol, to: LineCol) -> Result<LineCol> {
+        if from.line > to.line || from.col > to.col || to.line >= self.get_buffer().len() {
+            return Err(Error::InvalidRange);
+        }
+        for line in from.line..=to.line {
+            let Some((start, end)) = block_columns(&self.get_buffer()[line], from.col, to.col)
+            else {
+                continue;
+            };
+            self.record_change(LineCol { line, col: start }, LineCol { line, col: end }, "");
+            self.get_mut_buffer()[line].replace_range(start..end, "");
+        }
+        Ok(from)
     }
     fn delete(&mut self, mut at: LineCol) -> Result<LineCol> {
-        let buf = self.get_mut_buffer();
-        if at.line >= buf.len() || at.col > buf[at.line].len() {
+        let buf = self.get_buffer();
+        if at.line >= buf.len() || !buf[at.line].is_char_boundary(at.col) {
             return Err(Error::InvalidPosition);
         }
+        let symbol_start = buf[at.line][..at.col]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(col, _)| col);
         if at.col == 0 {
             if at.line == 0 {
                 return Err(Error::ImATeacup);
             }
-
+            let end_of_previous = LineCol {
+                line: at.line - 1,
+                col: buf[at.line - 1].len(),
+            };
+            self.record_change(end_of_previous, at, "");
+        } else {
+            self.record_change(
+                LineCol {
+                    line: at.line,
+                    col: symbol_start,
+                },
+                at,
+                "",
+            );
+        }
+        let buf = self.get_mut_buffer();
+        if at.col == 0 {
             let line_content = buf.remove(at.line);
             at.line -= 1;
             at.col = buf[at.line].len();
             buf[at.line].push_str(&line_content);
         } else {
-            buf[at.line].remove(at.col - 1);
-            at.col -= 1;
+            buf[at.line].remove(symbol_start);
+            at.col = symbol_start;
         }
         Ok(at)
     }
-    /// Return the byte offset at which a character at a given linecol starts.
-    fn get_byte_offset(&self, at: LineCol) -> usize {
-        self.get_buffer_window(None, Some(at))
-            .map(|window| window.iter().map(|s| s.len() + 1).sum())
-            .unwrap_or(0)
-    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    pub(super) fn at(line: usize, col: usize) -> LineCol {
+        LineCol { line, col }
+    }
+
+    /// Runs the same edits on `buf`, returning their results as text to compare them across
+    /// buffer types.
+    pub(super) fn edit_normal_text(buf: &mut impl TextBuffer) -> Vec<String> {
+        vec![
+            format!("{:?}", buf.insert(at(0, 5), 'X')),
+            format!("{:?}", buf.insert_text(at(1, 2), "one\ntwo", false)),
+            format!("{:?}", buf.insert_text(at(0, 1), "below\nlines", true)),
+            format!("{:?}", buf.insert_newline(at(4, 0))),
+            format!("{:?}", buf.delete(at(2, 0))),
+            format!("{:?}", buf.delete(at(1, 3))),
+            format!("{:?}", buf.delete_selection(at(0, 2), at(1, 1))),
+            format!("{:?}", buf.delete_selection(at(2, 0), at(3, 40))),
+            format!("{:?}", buf.replace(at(0, 0), at(1, 2), "new\r\ntext")),
+            format!("{:?}", buf.delete_line(0)),
+            format!("{:?}", buf.get_text(at(0, 1), at(1, 2))),
+            format!("{:?}", buf.get_byte_offset(at(1, 2))),
+            format!("{:?}", buf.len()),
+            format!("{:?}", buf.insert(at(9, 0), 'X')),
+            format!("{:?}", buf.delete_selection(at(1, 0), at(0, 0))),
+            format!("{:?}", buf.delete_block(at(0, 1), at(2, 3))),
+            format!("{:?}", buf.delete_block(at(2, 0), at(1, 0))),
+        ]
+    }
+
+    /// Saves, edits, undoes and redoes `buf`, returning every text seen along the way.
+    pub(super) fn walk_history(buf: &mut impl TextBuffer) -> Vec<Vec<String>> {
+        let mut seen = vec![];
+        buf.save_state(at(0, 0));
+        buf.insert_text(at(0, 0), "abc", false).unwrap();
+        buf.save_state(at(0, 3));
+        buf.save_state(at(0, 3));
+        buf.delete_line(1).unwrap();
+        seen.push(buf.get_normal_text().to_vec());
+        for _ in 0..3 {
+            seen.push(vec![format!("{:?}", buf.undo(at(0, 0)))]);
+            seen.push(buf.get_normal_text().to_vec());
+        }
+        seen.push(vec![format!("{:?}", buf.redo(at(0, 0)))]);
+        seen.push(buf.get_normal_text().to_vec());
+        buf.save_state(at(0, 0));
+        seen.push(vec![format!("{:?}", buf.redo(at(0, 0)))]);
+        seen
+    }
+
     /// "First line"
     /// "Second line"
     /// "Third line"
-    fn new_test_buffer() -> VecBuffer {
-        VecBuffer {
-            text: vec![
-                "First line".to_string(),
-                "Second line".to_string(),
-                "Third line".to_string(),
-            ],
-            past: Stack {
-                content: VecDeque::new(),
-            },
-            future: Stack {
-                content: VecDeque::new(),
-            },
-            command: vec![],
-            terminal: vec![],
-            plane: BufferPlane::Normal,
-        }
+    fn new_test_buffer() -> DefaultBuffer {
+        DefaultBuffer::new(vec![
+            "First line".to_string(),
+            "Second line".to_string(),
+            "Third line".to_string(),
+        ])
+    }
+
+    #[test]
+    fn test_len_counts_bytes_and_newlines() {
+        // "First line\nSecond line\nThird line"
+        assert_eq!(new_test_buffer().len(), 10 + 1 + 11 + 1 + 10);
+        assert_eq!(DefaultBuffer::new(vec!["héllo".to_string()]).len(), 6);
+        assert_eq!(DefaultBuffer::default().len(), 0);
+    }
+
+    #[test]
+    fn test_byte_offset_into_coalesced_bytes() {
+        let mut buf = new_test_buffer();
+        buf.set_plane(&Modal::Command);
+        let bytes = buf.get_coalesced_bytes();
+        assert_eq!(bytes, b"First line\nSecond line\nThird line");
+        let offset = buf.get_byte_offset(LineCol { line: 1, col: 7 });
+        assert_eq!(&bytes[offset..offset + 4], b"line");
+    }
+
+    #[test]
+    fn test_pending_changes_of_inserts_and_deletes() {
+        let mut buf = new_test_buffer();
+        let at = |line, col| LineCol { line, col };
+        let change = |start, end, range_length, text: &str| IncrementalChange {
+            start,
+            end,
+            range_length,
+            text: text.to_string(),
+        };
+
+        buf.insert(at(0, 5), 'é').unwrap();
+        buf.insert(at(0, 7), '!').unwrap();
+        buf.delete(at(0, 8)).unwrap();
+        buf.delete(at(1, 0)).unwrap();
+        buf.insert_newline(at(1, 0));
+        buf.delete_selection(at(0, 0), at(0, 7)).unwrap();
+        assert_eq!(
+            buf.get_normal_text(),
+            [" lineSecond line", "Third line", ""]
+        );
+        assert_eq!(
+            buf.take_pending_changes(),
+            [
+                change(at(0, 5), at(0, 5), 0, "é"),
+                change(at(0, 6), at(0, 6), 0, "!"),
+                change(at(0, 6), at(0, 7), 1, ""),
+                change(at(0, 11), at(1, 0), 1, ""),
+                change(at(1, 10), at(1, 10), 0, "\n"),
+                change(at(0, 0), at(0, 6), 6, ""),
+            ]
+        );
+        assert!(buf.take_pending_changes().is_empty());
+
+        buf.replace(at(0, 2), at(2, 0), "x\ny").unwrap();
+        buf.delete_line(1).unwrap();
+        assert_eq!(buf.get_normal_text(), [" lx"]);
+        assert_eq!(
+            buf.take_pending_changes(),
+            [
+                change(at(0, 2), at(2, 0), 26, "x\ny"),
+                change(at(0, 3), at(1, 1), 2, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delete_block_keeps_lines() {
+        let mut buf = DefaultBuffer::new(
+            ["abcdef", "ab", "", "aéxyz", "abcdef"]
+                .map(String::from)
+                .to_vec(),
+        );
+        let at = |line, col| LineCol { line, col };
+        assert_eq!(buf.delete_block(at(0, 2), at(3, 4)).unwrap(), at(0, 2));
+        // The second byte of `é` widens the block over the whole symbol
+        assert_eq!(buf.get_normal_text(), ["abef", "ab", "", "ayz", "abcdef"]);
+        buf.delete_block(at(4, 0), at(4, 6)).unwrap();
+        assert_eq!(buf.get_normal_text()[4], "");
+        assert!(matches!(
+            buf.delete_block(at(1, 3), at(1, 1)),
+            Err(Error::InvalidRange)
+        ));
+        assert!(matches!(
+            buf.delete_block(at(4, 0), at(5, 1)),
+            Err(Error::InvalidRange)
+        ));
     }
 
     #[test]
@@ -781,7 +1286,7 @@ mod tests {
             "text",
         )
         .unwrap();
-        assert_eq!(buf.text[0], "First text");
+        assert_eq!(buf.get_normal_text()[0], "First text");
     }
 
     #[test]
@@ -794,7 +1299,7 @@ mod tests {
         )
         .unwrap();
         assert_eq!(
-            buf.text,
+            buf.get_normal_text(),
             vec![
                 "First new".to_string(),
                 "replacement".to_string(),
@@ -803,6 +1308,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_replace_with_empty_text_deletes_the_range() {
+        let mut buf = new_test_buffer();
+        buf.replace(
+            LineCol { line: 1, col: 0 },
+            LineCol { line: 1, col: 11 },
+            "",
+        )
+        .unwrap();
+        assert_eq!(buf.get_normal_text(), ["First line", "", "Third line"]);
+        buf.replace(LineCol { line: 0, col: 5 }, LineCol { line: 2, col: 5 }, "")
+            .unwrap();
+        assert_eq!(buf.get_normal_text(), ["First line"]);
+    }
+
     #[test]
     fn test_replacing_at_line_end() {
         let mut buf = new_test_buffer();
@@ -813,7 +1333,7 @@ mod tests {
         )
         .unwrap();
         assert_eq!(
-            buf.text,
+            buf.get_normal_text(),
             vec![
                 "First line".to_string(),
                 "Second replacement".to_string(),
@@ -832,7 +1352,7 @@ mod tests {
         )
         .unwrap();
         assert_eq!(
-            buf.text,
+            buf.get_normal_text(),
             vec![
                 "First new".to_string(),
                 "replacement".to_string(),
@@ -852,7 +1372,7 @@ mod tests {
         )
         .unwrap();
         assert_eq!(
-            buf.text,
+            buf.get_normal_text(),
             vec![
                 "First line".to_string(),
                 "Second line".to_string(),
@@ -864,23 +1384,12 @@ mod tests {
     /// "First line with some text"
     /// "Second line also has text"
     /// "Third line is here too"
-    fn new_test_buffer_find() -> VecBuffer {
-        VecBuffer {
-            text: vec![
-                "First line with some text".to_string(),
-                "Second line also has text".to_string(),
-                "Third line is here too".to_string(),
-            ],
-            past: Stack {
-                content: VecDeque::new(),
-            },
-            future: Stack {
-                content: VecDeque::new(),
-            },
-            command: vec![],
-            terminal: vec![],
-            plane: BufferPlane::Normal,
-        }
+    fn new_test_buffer_find() -> DefaultBuffer {
+        DefaultBuffer::new(vec![
+            "First line with some text".to_string(),
+            "Second line also has text".to_string(),
+            "Third line is here too".to_string(),
+        ])
     }
 
     #[test]
@@ -901,6 +1410,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rfind_match_spanning_start() {
+        let buf = new_test_buffer_find();
+        assert_eq!(
+            buf.rfind("line", LineCol { line: 1, col: 9 }).unwrap(),
+            LineCol { line: 1, col: 7 }
+        );
+    }
+
+    #[test]
+    fn test_find_and_rfind_are_complementary() {
+        let buf = new_test_buffer_find();
+        let found = buf.find("line", LineCol { line: 1, col: 0 }).unwrap();
+        assert_eq!(found, LineCol { line: 1, col: 7 });
+        assert_eq!(buf.find("line", found).unwrap(), found);
+        assert_eq!(
+            buf.rfind("line", found).unwrap(),
+            LineCol { line: 0, col: 6 }
+        );
+        let after = LineCol {
+            line: found.line,
+            col: found.col + 1,
+        };
+        assert_eq!(buf.rfind("line", after).unwrap(), found);
+    }
+
     #[test]
     fn test_rfind_across_lines() {
         let buf = new_test_buffer_find();
@@ -983,8 +1518,12 @@ mod tests {
 
     #[test]
     fn test_find_from_empty_line() {
-        let mut buf = new_test_buffer_find();
-        buf.text.insert(1, String::new());
+        let buf = DefaultBuffer::new(vec![
+            "First line with some text".to_string(),
+            String::new(),
+            "Second line also has text".to_string(),
+            "Third line is here too".to_string(),
+        ]);
         assert_eq!(
             buf.find("Third", LineCol { line: 1, col: 0 }).unwrap(),
             LineCol { line: 3, col: 0 }
@@ -994,24 +1533,13 @@ mod tests {
     /// "Second line"
     /// "Third line"
     /// "Fourth line"
-    fn new_test_buffer_get() -> VecBuffer {
-        VecBuffer {
-            text: vec![
-                "First line".to_string(),
-                "Second line".to_string(),
-                "Third line".to_string(),
-                "Fourth line".to_string(),
-            ],
-            past: Stack {
-                content: VecDeque::new(),
-            },
-            future: Stack {
-                content: VecDeque::new(),
-            },
-            command: vec![],
-            terminal: vec![],
-            plane: BufferPlane::Normal,
-        }
+    fn new_test_buffer_get() -> DefaultBuffer {
+        DefaultBuffer::new(vec![
+            "First line".to_string(),
+            "Second line".to_string(),
+            "Third line".to_string(),
+            "Fourth line".to_string(),
+        ])
     }
 
     #[test]
@@ -1070,7 +1598,7 @@ mod tests {
         buffer
             .delete_selection(LineCol { line: 0, col: 6 }, LineCol { line: 0, col: 10 })
             .unwrap();
-        assert_eq!(buffer.text[0], "First ");
+        assert_eq!(buffer.get_normal_text()[0], "First ");
     }
 
     #[test]
@@ -1079,7 +1607,7 @@ mod tests {
         buffer
             .delete_selection(LineCol { line: 0, col: 6 }, LineCol { line: 0, col: 11 })
             .unwrap();
-        assert_eq!(buffer.text[0], "First ");
+        assert_eq!(buffer.get_normal_text()[0], "First ");
     }
 
     #[test]
@@ -1088,8 +1616,8 @@ mod tests {
         buffer
             .delete_selection(LineCol { line: 1, col: 0 }, LineCol { line: 1, col: 11 })
             .unwrap();
-        assert_eq!(buffer.text.len(), 3);
-        assert_eq!(buffer.text[1], "Third line");
+        assert_eq!(buffer.get_normal_text().len(), 3);
+        assert_eq!(buffer.get_normal_text()[1], "Third line");
     }
 
     #[test]
@@ -1098,8 +1626,8 @@ mod tests {
         buffer
             .delete_selection(LineCol { line: 0, col: 6 }, LineCol { line: 2, col: 6 })
             .unwrap();
-        assert_eq!(buffer.text.len(), 2);
-        assert_eq!(buffer.text[0], "First line");
+        assert_eq!(buffer.get_normal_text().len(), 2);
+        assert_eq!(buffer.get_normal_text()[0], "First line");
     }
 
     #[test]
@@ -1108,8 +1636,8 @@ mod tests {
         buffer
             .delete_selection(LineCol { line: 1, col: 0 }, LineCol { line: 2, col: 10 })
             .unwrap();
-        assert_eq!(buffer.text.len(), 2);
-        assert_eq!(buffer.text[1], "Fourth line");
+        assert_eq!(buffer.get_normal_text().len(), 2);
+        assert_eq!(buffer.get_normal_text()[1], "Fourth line");
     }
 
     #[test]
@@ -1128,13 +1656,49 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_delete_line_edge_cases() {
+        let mut buf = new_test_buffer_find();
+        assert_eq!(buf.delete_line(2).unwrap(), LineCol { line: 1, col: 0 });
+        assert_eq!(buf.delete_line(0).unwrap(), LineCol { line: 0, col: 0 });
+        assert_eq!(buf.get_normal_text(), ["Second line also has text"]);
+        assert!(matches!(buf.delete_line(1), Err(Error::InvalidLineNumber)));
+
+        assert_eq!(buf.delete_line(0).unwrap(), LineCol { line: 0, col: 0 });
+        assert_eq!(buf.get_normal_text(), [""]);
+        assert_eq!(buf.delete_line(0).unwrap(), LineCol { line: 0, col: 0 });
+        assert_eq!(buf.line_count(), 1);
+    }
+
+    #[test]
+    fn test_delete_symbol_before_position() {
+        let mut buffer = DefaultBuffer::new(vec!["aé".to_string(), "b".to_string()]);
+        assert_eq!(
+            buffer.delete(LineCol { line: 0, col: 3 }).unwrap(),
+            LineCol { line: 0, col: 1 }
+        );
+        assert!(matches!(
+            buffer.delete(LineCol { line: 0, col: 0 }),
+            Err(Error::ImATeacup)
+        ));
+        assert!(matches!(
+            buffer.delete(LineCol { line: 0, col: 5 }),
+            Err(Error::InvalidPosition)
+        ));
+        assert_eq!(
+            buffer.delete(LineCol { line: 1, col: 0 }).unwrap(),
+            LineCol { line: 0, col: 1 }
+        );
+        assert_eq!(buffer.get_normal_text(), ["ab"]);
+    }
+
     #[test]
     fn test_insert_single_line_not_newline() {
         let mut buffer = new_test_buffer();
         buffer
             .insert_text(LineCol { line: 0, col: 5 }, "inserted ".to_string(), false)
             .unwrap();
-        assert_eq!(buffer.text[0], "Firstinserted  line");
+        assert_eq!(buffer.get_normal_text()[0], "Firstinserted  line");
     }
 
     #[test]
@@ -1147,8 +1711,8 @@ mod tests {
                 false,
             )
             .unwrap();
-        assert_eq!(buffer.text[0], "Firstinserted");
-        assert_eq!(buffer.text[1], "text line");
+        assert_eq!(buffer.get_normal_text()[0], "Firstinserted");
+        assert_eq!(buffer.get_normal_text()[1], "text line");
     }
 
     #[test]
@@ -1157,9 +1721,9 @@ mod tests {
         buffer
             .insert_text(LineCol { line: 1, col: 0 }, "New line".to_string(), true)
             .unwrap();
-        assert_eq!(buffer.text[1], "Second line");
-        assert_eq!(buffer.text[2], "New line");
-        assert_eq!(buffer.text[3], "Third line");
+        assert_eq!(buffer.get_normal_text()[1], "Second line");
+        assert_eq!(buffer.get_normal_text()[2], "New line");
+        assert_eq!(buffer.get_normal_text()[3], "Third line");
     }
 
     #[test]
@@ -1168,10 +1732,10 @@ mod tests {
         buffer
             .insert_text(LineCol { line: 1, col: 0 }, "New\nlines".to_string(), true)
             .unwrap();
-        assert_eq!(buffer.text[1], "Second line");
-        assert_eq!(buffer.text[2], "New");
-        assert_eq!(buffer.text[3], "lines");
-        assert_eq!(buffer.text[4], "Third line");
+        assert_eq!(buffer.get_normal_text()[1], "Second line");
+        assert_eq!(buffer.get_normal_text()[2], "New");
+        assert_eq!(buffer.get_normal_text()[3], "lines");
+        assert_eq!(buffer.get_normal_text()[4], "Third line");
     }
 
     #[test]
@@ -1180,7 +1744,7 @@ mod tests {
         buffer
             .insert_text(LineCol { line: 0, col: 10 }, " added".to_string(), false)
             .unwrap();
-        assert_eq!(buffer.text[0], "First line added");
+        assert_eq!(buffer.get_normal_text()[0], "First line added");
     }
 
     #[test]
@@ -1189,7 +1753,7 @@ mod tests {
         buffer
             .insert_text(LineCol { line: 0, col: 0 }, "Start: ".to_string(), false)
             .unwrap();
-        assert_eq!(buffer.text[0], "Start: First line");
+        assert_eq!(buffer.get_normal_text()[0], "Start: First line");
     }
 
     #[test]
@@ -1202,14 +1766,14 @@ mod tests {
                 true,
             )
             .unwrap();
-        assert_eq!(buffer.text.last().unwrap(), "New last line");
+        assert_eq!(buffer.get_normal_text().last().unwrap(), "New last line");
     }
     #[test]
     fn test_set_plane_and_buffer_operations() {
-        let mut buffer = VecBuffer::default();
+        let mut buffer = DefaultBuffer::default();
 
         // Start in Normal mode
-        assert_eq!(buffer.get_buffer(), &buffer.text);
+        assert_eq!(buffer.get_entire_text(), buffer.get_normal_text());
 
         // Insert text in Normal mode
         buffer
@@ -1219,11 +1783,11 @@ mod tests {
                 false,
             )
             .unwrap();
-        assert_eq!(buffer.text, vec!["Normal text"]);
+        assert_eq!(buffer.get_normal_text(), vec!["Normal text"]);
 
         // Switch to Command mode
         buffer.set_plane(&Modal::Command);
-        assert_eq!(buffer.get_buffer(), &buffer.command);
+        assert_eq!(buffer.get_entire_text(), buffer.get_command_text());
 
         // Insert text in Command mode
         buffer
@@ -1233,17 +1797,17 @@ mod tests {
                 false,
             )
             .unwrap();
-        assert_eq!(buffer.command, vec!["Command text"]);
+        assert_eq!(buffer.get_command_text(), vec!["Command text"]);
 
         // Switch to Normal mode and verify text
         buffer.set_plane(&Modal::Normal);
-        assert_eq!(buffer.get_buffer(), &buffer.text);
-        assert_eq!(buffer.text, vec!["Normal text"]);
+        assert_eq!(buffer.get_entire_text(), buffer.get_normal_text());
+        assert_eq!(buffer.get_normal_text(), vec!["Normal text"]);
     }
 
     #[test]
     fn test_buffer_independence() {
-        let mut buffer = VecBuffer::default();
+        let mut buffer = DefaultBuffer::default();
 
         // Insert text in Normal mode
         buffer.set_plane(&Modal::Normal);
@@ -1267,14 +1831,14 @@ mod tests {
 
         // Verify that buffers remain independent
         buffer.set_plane(&Modal::Normal);
-        assert_eq!(buffer.text, vec!["Normal text"]);
+        assert_eq!(buffer.get_normal_text(), vec!["Normal text"]);
         buffer.set_plane(&Modal::Command);
-        assert_eq!(buffer.command, vec!["Command text"]);
+        assert_eq!(buffer.get_command_text(), vec!["Command text"]);
     }
 
     #[test]
     fn test_delete_across_buffers() {
-        let mut buffer = VecBuffer::default();
+        let mut buffer = DefaultBuffer::default();
 
         // Insert and delete in Normal mode
         buffer.set_plane(&Modal::Normal);
@@ -1288,7 +1852,7 @@ mod tests {
         buffer
             .delete_selection(LineCol { line: 0, col: 0 }, LineCol { line: 0, col: 6 })
             .unwrap();
-        assert_eq!(buffer.text, vec![" text"]);
+        assert_eq!(buffer.get_normal_text(), vec![" text"]);
 
         // Insert and delete in Command mode
         buffer.set_plane(&Modal::Command);
@@ -1302,11 +1866,11 @@ mod tests {
         buffer
             .delete_selection(LineCol { line: 0, col: 0 }, LineCol { line: 0, col: 7 })
             .unwrap();
-        assert_eq!(buffer.command, vec![" text"]);
+        assert_eq!(buffer.get_command_text(), vec![" text"]);
 
         // Verify Normal mode text remains unchanged
         buffer.set_plane(&Modal::Normal);
-        assert_eq!(buffer.text, vec![" text"]);
+        assert_eq!(buffer.get_normal_text(), vec![" text"]);
     }
 
     #[test]
@@ -1359,7 +1923,7 @@ mod tests {
     fn test_get_partial_buffer_full_range() {
         let buf = new_test_buffer_find();
         let result = buf.get_buffer_window(None, None).unwrap();
-        assert_eq!(result, buf.text);
+        assert_eq!(result, buf.get_normal_text());
     }
 
     #[test]
@@ -1437,4 +2001,74 @@ mod tests {
             .unwrap();
         assert_eq!(result, vec!["line is here too"]);
     }
+
+    #[test]
+    fn test_command_bar_edits_stay_out_of_undo_history() {
+        let mut buf = new_test_buffer_find();
+        buf.save_state(LineCol::default());
+        buf.delete_line(0).unwrap();
+
+        buf.set_plane(&Modal::Command);
+        buf.replace_command_text("");
+        buf.save_state(LineCol::default());
+        buf.insert(LineCol::default(), 'x').unwrap();
+        assert_eq!(buf.get_command_text(), ["x"]);
+
+        buf.undo(LineCol::default()).unwrap();
+        assert_eq!(buf.get_command_text(), ["x"]);
+        assert_eq!(buf.get_normal_text()[0], "First line with some text");
+        assert!(buf.undo(LineCol::default()).is_err());
+        buf.redo(LineCol::default()).unwrap();
+        assert_eq!(buf.get_normal_text()[0], "Second line also has text");
+    }
+
+    #[test]
+    fn test_terminal_output_split_into_lines() {
+        let mut buf = DefaultBuffer::new(vec![]);
+        buf.append_terminal_output("$ ls");
+        buf.append_terminal_output("\nCargo.toml\nsrc\n$ ");
+        assert_eq!(
+            buf.get_terminal_lines(),
+            ["$ ls", "Cargo.toml", "src", "$ "]
+        );
+        assert_eq!(buf.get_terminal_text(), "$ ");
+        buf.append_terminal_output("echo");
+        assert_eq!(buf.get_terminal_text(), "$ echo");
+    }
+
+    #[test]
+    fn test_replace_and_clear_command_text() {
+        let mut buf = new_test_buffer_find();
+        buf.replace_command_text("hello");
+        assert_eq!(buf.get_command_text()[0], "hello");
+        assert!(!buf.is_command_empty());
+        buf.clear_command();
+        assert_eq!(buf.get_command_text(), [""]);
+        assert!(buf.is_command_empty());
+    }
+
+    #[test]
+    fn test_get_full_lines_buffer_window() {
+        let buf = new_test_buffer_find();
+        let result = buf
+            .get_full_lines_buffer_window(
+                Some(LineCol { line: 1, col: 5 }),
+                Some(LineCol { line: 40, col: 2 }),
+            )
+            .unwrap();
+        assert_eq!(result, buf.get_normal_text()[1..]);
+        assert!(buf
+            .get_full_lines_buffer_window(Some(LineCol { line: 40, col: 0 }), None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_dirty_tracking() {
+        let mut buf = DefaultBuffer::new(vec!["First line".to_string()]);
+        assert!(!buf.is_dirty());
+        buf.insert(LineCol { line: 0, col: 0 }, 'x').unwrap();
+        assert!(buf.is_dirty());
+        buf.mark_saved();
+        assert!(!buf.is_dirty());
+    }
 }
@@ -0,0 +1,147 @@
+use super::storage::{Storage, StorageBuffer};
+
+/// Capacity of the gap after it has to grow, so a run of typed symbols doesn't reallocate on
+/// every keystroke.
+const MIN_GAP: usize = 4096;
+
+/// The text as a single byte vector with a movable gap at the last edited position. Edits close
+/// to each other only move the bytes between them, instead of shifting every following line.
+#[derive(Debug)]
+pub struct Gap {
+    data: Vec<u8>,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+/// A `TextBuffer` keeping every plane in a gap buffer, the `DefaultBuffer` unless another one is
+/// picked with a feature. Typing only moves the bytes between the gap and the cursor.
+pub type GapBuffer = StorageBuffer<Gap>;
+
+impl Gap {
+    /// The text before and after the gap.
+    fn halves(&self) -> (&[u8], &[u8]) {
+        (&self.data[..self.gap_start], &self.data[self.gap_end..])
+    }
+
+    /// Moves the gap so it starts at the byte `offset` of the text.
+    fn move_gap(&mut self, offset: usize) {
+        if offset < self.gap_start {
+            let moved = self.gap_start - offset;
+            self.data
+                .copy_within(offset..self.gap_start, self.gap_end - moved);
+            self.gap_start = offset;
+            self.gap_end -= moved;
+        } else if offset > self.gap_start {
+            let moved = offset - self.gap_start;
+            self.data
+                .copy_within(self.gap_end..self.gap_end + moved, self.gap_start);
+            self.gap_start += moved;
+            self.gap_end += moved;
+        }
+    }
+
+    /// Grows the gap to hold at least `needed` more bytes.
+    fn reserve_gap(&mut self, needed: usize) {
+        let gap = self.gap_end - self.gap_start;
+        if gap >= needed {
+            return;
+        }
+        let grow = needed.max(MIN_GAP) - gap;
+        let tail_len = self.data.len() - self.gap_end;
+        self.data.resize(self.data.len() + grow, 0);
+        let new_end = self.data.len() - tail_len;
+        self.data
+            .copy_within(self.gap_end..self.gap_end + tail_len, new_end);
+        self.gap_end = new_end;
+    }
+}
+
+impl Storage for Gap {
+    type Snapshot = Vec<u8>;
+
+    fn from_text(text: String) -> Self {
+        let mut data = text.into_bytes();
+        let gap_start = data.len();
+        data.resize(gap_start + MIN_GAP, 0);
+        Self {
+            gap_end: data.len(),
+            data,
+            gap_start,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.data.len() - (self.gap_end - self.gap_start)
+    }
+
+    fn text_between(&self, start: usize, end: usize) -> String {
+        let (before, after) = self.halves();
+        let bytes = if end <= before.len() {
+            before[start..end].to_vec()
+        } else if start >= before.len() {
+            after[start - before.len()..end - before.len()].to_vec()
+        } else {
+            [&before[start..], &after[..end - before.len()]].concat()
+        };
+        String::from_utf8(bytes).expect("Edits keep the text valid UTF-8")
+    }
+
+    /// Only the bytes between the gap and `start` are moved.
+    fn splice(&mut self, start: usize, end: usize, text: &str) {
+        self.move_gap(start);
+        self.gap_end += end - start;
+        self.reserve_gap(text.len());
+        self.data[self.gap_start..self.gap_start + text.len()].copy_from_slice(text.as_bytes());
+        self.gap_start += text.len();
+    }
+
+    fn snapshot(&self) -> Vec<u8> {
+        let (before, after) = self.halves();
+        [before, after].concat()
+    }
+
+    fn restore(&mut self, snapshot: Vec<u8>) {
+        *self = Self::from_text(String::from_utf8(snapshot).expect("Snapshots are valid UTF-8"));
+    }
+
+    fn snapshot_text(&self, snapshot: &Vec<u8>) -> String {
+        String::from_utf8_lossy(snapshot).into_owned()
+    }
+
+    fn is_snapshot(&self, snapshot: &Vec<u8>) -> bool {
+        let (before, after) = self.halves();
+        snapshot.len() == before.len() + after.len()
+            && snapshot[..before.len()] == *before
+            && snapshot[before.len()..] == *after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splice_moves_the_gap() {
+        let mut gap = Gap::from_text("first\nthird\n".to_string());
+        gap.splice(6, 6, "second\n");
+        gap.splice(0, 0, "zeroth\n");
+        assert_eq!(gap.gap_start, 7);
+        assert_eq!(
+            gap.text_between(0, gap.len()),
+            "zeroth\nfirst\nsecond\nthird\n"
+        );
+        gap.splice(3, 16, "");
+        assert_eq!(gap.text_between(4, 10), "nd\nthi");
+    }
+
+    #[test]
+    fn test_gap_grows_past_its_capacity() {
+        let mut gap = Gap::from_text("\n".to_string());
+        let long = "x".repeat(MIN_GAP * 2 + 3);
+        gap.splice(0, 0, &long);
+        gap.splice(1, 1, "\n");
+        assert_eq!(gap.len(), long.len() + 2);
+        assert_eq!(gap.text_between(0, 3), "x\nx");
+        assert!(gap.is_snapshot(&gap.snapshot()));
+    }
+}
@@ -0,0 +1,208 @@
+use super::storage::{Storage, StorageBuffer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Original,
+    Added,
+}
+
+/// A run of text taken from one of the two buffers of a `Pieces`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece {
+    source: Source,
+    start: usize,
+    len: usize,
+}
+
+/// The text as a piece table. The original text is never changed, inserted text is appended to
+/// `added`, and `pieces` lists the runs of both that make up the current text, in order.
+#[derive(Debug)]
+pub struct Pieces {
+    original: String,
+    added: String,
+    pieces: Vec<Piece>,
+    /// Length of the text the pieces make up.
+    len: usize,
+}
+
+/// A `TextBuffer` keeping every plane in a piece table, built instead of `GapBuffer` with the
+/// `piece-table` feature. Edits only replace the few pieces around them, and saving an undo
+/// state copies the list of pieces instead of the text.
+pub type PieceTable = StorageBuffer<Pieces>;
+
+impl Pieces {
+    fn bytes_of(&self, piece: &Piece) -> &[u8] {
+        let buffer = match piece.source {
+            Source::Original => &self.original,
+            Source::Added => &self.added,
+        };
+        &buffer.as_bytes()[piece.start..piece.start + piece.len]
+    }
+
+    /// Every byte of the text `pieces` make up.
+    fn bytes<'a>(&'a self, pieces: &'a [Piece]) -> impl Iterator<Item = u8> + 'a {
+        pieces
+            .iter()
+            .flat_map(|piece| self.bytes_of(piece).iter().copied())
+    }
+
+    /// The part `from..to` of `piece`, relative to its start.
+    fn sub_piece(piece: &Piece, from: usize, to: usize) -> Piece {
+        Piece {
+            source: piece.source,
+            start: piece.start + from,
+            len: to - from,
+        }
+    }
+}
+
+impl Storage for Pieces {
+    type Snapshot = Vec<Piece>;
+
+    fn from_text(original: String) -> Self {
+        let pieces = if original.is_empty() {
+            Vec::new()
+        } else {
+            vec![Piece {
+                source: Source::Original,
+                start: 0,
+                len: original.len(),
+            }]
+        };
+        Self {
+            len: original.len(),
+            original,
+            added: String::new(),
+            pieces,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn text_between(&self, start: usize, end: usize) -> String {
+        let mut bytes = Vec::with_capacity(end - start);
+        let mut offset = 0;
+        for piece in &self.pieces {
+            let piece_end = offset + piece.len;
+            if piece_end > start && offset < end {
+                let from = start.saturating_sub(offset);
+                let to = end.min(piece_end) - offset;
+                bytes.extend_from_slice(&self.bytes_of(piece)[from..to]);
+            }
+            if piece_end >= end {
+                break;
+            }
+            offset = piece_end;
+        }
+        String::from_utf8(bytes).expect("Edits keep the text valid UTF-8")
+    }
+
+    /// Only the pieces touching the range are replaced, the text itself is never moved. Text
+    /// typed right after the last inserted text grows its piece instead of adding one.
+    fn splice(&mut self, start: usize, end: usize, text: &str) {
+        let mut first_start = 0;
+        let first = self
+            .pieces
+            .iter()
+            .position(|piece| {
+                let found = first_start + piece.len > start;
+                if !found {
+                    first_start += piece.len;
+                }
+                found
+            })
+            .unwrap_or(self.pieces.len());
+        self.len = self.len - (end - start) + text.len();
+
+        let added_len = self.added.len();
+        self.added.push_str(text);
+        if start == end && start == first_start && first > 0 {
+            let previous = &mut self.pieces[first - 1];
+            if previous.source == Source::Added && previous.start + previous.len == added_len {
+                previous.len += text.len();
+                return;
+            }
+        }
+
+        let (mut last, mut last_end) = (first, first_start);
+        while last < self.pieces.len() && last_end < end {
+            last_end += self.pieces[last].len;
+            last += 1;
+        }
+        let mut replacement = Vec::with_capacity(3);
+        if start > first_start {
+            replacement.push(Self::sub_piece(&self.pieces[first], 0, start - first_start));
+        }
+        if !text.is_empty() {
+            replacement.push(Piece {
+                source: Source::Added,
+                start: added_len,
+                len: text.len(),
+            });
+        }
+        if last_end > end {
+            let piece = self.pieces[last - 1];
+            replacement.push(Self::sub_piece(
+                &piece,
+                piece.len - (last_end - end),
+                piece.len,
+            ));
+        }
+        self.pieces.splice(first..last, replacement);
+    }
+
+    /// As the text the pieces point into is never changed, the pieces are all it takes to go
+    /// back to it.
+    fn snapshot(&self) -> Vec<Piece> {
+        self.pieces.clone()
+    }
+
+    fn restore(&mut self, snapshot: Vec<Piece>) {
+        self.len = snapshot.iter().map(|piece| piece.len).sum();
+        self.pieces = snapshot;
+    }
+
+    fn snapshot_text(&self, snapshot: &Vec<Piece>) -> String {
+        String::from_utf8_lossy(&self.bytes(snapshot).collect::<Vec<_>>()).into_owned()
+    }
+
+    fn is_snapshot(&self, snapshot: &Vec<Piece>) -> bool {
+        *snapshot == self.pieces || self.bytes(snapshot).eq(self.bytes(&self.pieces))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splice_keeps_pieces_in_order() {
+        let mut pieces = Pieces::from_text("first\nthird\n".to_string());
+        pieces.splice(6, 6, "second\n");
+        pieces.splice(0, 0, "zeroth\n");
+        assert_eq!(pieces.pieces.len(), 4);
+        assert_eq!(
+            pieces.text_between(0, pieces.len()),
+            "zeroth\nfirst\nsecond\nthird\n"
+        );
+        pieces.splice(3, 16, "");
+        assert_eq!(pieces.text_between(4, 10), "nd\nthi");
+        assert_eq!(pieces.len(), 13);
+    }
+
+    #[test]
+    fn test_typing_grows_the_last_piece() {
+        let mut pieces = Pieces::from_text("ab\n".to_string());
+        let before = pieces.snapshot();
+        for (offset, ch) in ["x", "y", "z"].into_iter().enumerate() {
+            pieces.splice(1 + offset, 1 + offset, ch);
+        }
+        assert_eq!(pieces.pieces.len(), 3);
+        assert_eq!(pieces.text_between(0, pieces.len()), "axyzb\n");
+        assert_eq!(pieces.snapshot_text(&before), "ab\n");
+        pieces.splice(1, 4, "");
+        assert!(pieces.is_snapshot(&before));
+    }
+}
@@ -0,0 +1,782 @@
+use std::cell::RefCell;
+
+use tree_sitter::InputEdit;
+
+use super::{
+    block_columns, hash_lines, input_edit, BufferPlane, IncrementalChange, Stack, TextBuffer,
+};
+use crate::{modals::Modal, Error, LineCol, Result};
+
+/// The bytes of the text of a plane, with a line break after every line, as kept by a
+/// `StorageBuffer`. Only offsets are dealt with here, the buffer finds the lines in the text.
+pub trait Storage {
+    /// The text at a point of the editing process, as kept for undo and redo.
+    type Snapshot;
+
+    fn from_text(text: String) -> Self;
+    fn len(&self) -> usize;
+    /// The text between the offsets `start` and `end`.
+    fn text_between(&self, start: usize, end: usize) -> String;
+    /// Replaces the text between the offsets `start` and `end` with `text`.
+    fn splice(&mut self, start: usize, end: usize, text: &str);
+    fn snapshot(&self) -> Self::Snapshot;
+    fn restore(&mut self, snapshot: Self::Snapshot);
+    /// The text `snapshot` was taken of.
+    fn snapshot_text(&self, snapshot: &Self::Snapshot) -> String;
+    /// Whether the text is still the one `snapshot` was taken of.
+    fn is_snapshot(&self, snapshot: &Self::Snapshot) -> bool;
+}
+
+/// The text of a single plane, kept in `S` along with the lines it's made of. An edit reads back
+/// only the lines it touched, so the lines handed out by `TextBuffer` are never built anew.
+/// A plane without any lines has no text at all, one with a single empty line a lone line break.
+#[derive(Debug)]
+struct Plane<S> {
+    storage: S,
+    lines: Vec<String>,
+    /// Offsets of the starts of the first lines. An edit keeps the ones up to the line it starts
+    /// on, the ones after it are added back from the lengths of the lines when looked up.
+    line_starts: RefCell<Vec<usize>>,
+}
+
+impl<S: Storage> Plane<S> {
+    fn new(lines: &[String]) -> Self {
+        let text = lines.iter().map(|line| format!("{line}\n")).collect();
+        Self {
+            storage: S::from_text(text),
+            lines: lines.to_vec(),
+            line_starts: RefCell::new(vec![0]),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    fn line_text(&self, line: usize) -> Option<&str> {
+        self.lines.get(line).map(String::as_str)
+    }
+
+    fn line_len(&self, line: usize) -> Option<usize> {
+        self.lines.get(line).map(String::len)
+    }
+
+    /// Makes sure the starts of the lines up to `line` are known, `line` being at most the line
+    /// count, whose start is the end of the text.
+    fn index_line_starts(&self, line: usize) {
+        let mut starts = self.line_starts.borrow_mut();
+        while starts.len() <= line {
+            let previous = starts.len() - 1;
+            let next = starts[previous] + self.lines[previous].len() + 1;
+            starts.push(next);
+        }
+    }
+
+    /// Offset of the start of `line`, the end of the text for lines past the last one.
+    fn line_start(&self, line: usize) -> usize {
+        let line = line.min(self.lines.len());
+        self.index_line_starts(line);
+        self.line_starts.borrow()[line]
+    }
+
+    /// Offsets of the start and of the line break of `line`.
+    fn line_range(&self, line: usize) -> Option<(usize, usize)> {
+        let len = self.line_len(line)?;
+        let start = self.line_start(line);
+        Some((start, start + len))
+    }
+
+    /// Offset of `at`, if it is on a line and not past its end.
+    fn offset(&self, at: LineCol) -> Option<usize> {
+        let (start, end) = self.line_range(at.line)?;
+        (at.col <= end - start).then_some(start + at.col)
+    }
+
+    /// The line the offset `offset` is on, its line break included. Offsets at the end of the
+    /// text are past the last line.
+    fn line_at(&self, offset: usize) -> usize {
+        let line_count = self.lines.len();
+        let mut starts = self.line_starts.borrow_mut();
+        while starts.len() <= line_count && starts.last().is_some_and(|start| *start <= offset) {
+            let previous = starts.len() - 1;
+            let next = starts[previous] + self.lines[previous].len() + 1;
+            starts.push(next);
+        }
+        starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    fn text_between(&self, start: usize, end: usize) -> String {
+        self.storage.text_between(start, end)
+    }
+
+    fn text(&self) -> String {
+        self.storage.text_between(0, self.storage.len())
+    }
+
+    /// Replaces the text between the offsets `start` and `end` with `text`, then reads back the
+    /// lines the two offsets were on.
+    fn splice(&mut self, start: usize, end: usize, text: &str) {
+        let (first, last) = (self.line_at(start), self.line_at(end));
+        let line_count = self.lines.len();
+        let region_start = self.line_start(first);
+        let region_end = match self.line_range(last) {
+            Some((_, newline)) => newline + 1,
+            None => self.storage.len(),
+        };
+        self.storage.splice(start, end, text);
+
+        let region_end = region_end - (end - start) + text.len();
+        let region = self.storage.text_between(region_start, region_end);
+        self.lines.splice(
+            first..(last + 1).min(line_count),
+            region.split_terminator('\n').map(String::from),
+        );
+        self.line_starts.get_mut().truncate(first + 1);
+    }
+
+    fn snapshot(&self) -> S::Snapshot {
+        self.storage.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: S::Snapshot) {
+        self.storage.restore(snapshot);
+        self.lines = self
+            .text()
+            .split_terminator('\n')
+            .map(String::from)
+            .collect();
+        self.line_starts = RefCell::new(vec![0]);
+    }
+}
+
+/// The normal text and the cursor location at a point of the editing process.
+#[derive(Debug)]
+struct Snapshot<T> {
+    text: T,
+    loc: LineCol,
+}
+
+/// Drops the line break after the last line of `text`, as the text of a plane is stored.
+fn joined_lines(mut text: String) -> String {
+    if text.ends_with('\n') {
+        text.pop();
+    }
+    text
+}
+
+/// A `TextBuffer` keeping the text of every plane in a `Storage`, the gap buffer of `GapBuffer`
+/// or the piece table of `PieceTable`.
+#[derive(Debug)]
+pub struct StorageBuffer<S: Storage> {
+    text: Plane<S>,
+    terminal: Plane<S>,
+    command: Plane<S>,
+    past: Stack<Snapshot<S::Snapshot>>,
+    future: Stack<Snapshot<S::Snapshot>>,
+    plane: BufferPlane,
+    /// Hash of the normal text at the time it was last saved, used for dirty checking.
+    saved_hash: u64,
+    /// Edits of the normal text not yet sent to the language server.
+    pending_changes: Vec<IncrementalChange>,
+    /// Edits of the normal text not yet applied to the syntax tree of the highlighter.
+    pending_edits: Vec<InputEdit>,
+}
+
+impl<S: Storage> Default for StorageBuffer<S> {
+    fn default() -> Self {
+        Self::new(vec![String::new()])
+    }
+}
+
+impl<S: Storage> StorageBuffer<S> {
+    pub fn new(text: Vec<String>) -> Self {
+        Self {
+            saved_hash: hash_lines(&text),
+            text: Plane::new(&text),
+            terminal: Plane::new(&[String::new()]),
+            command: Plane::new(&[String::new()]),
+            past: Stack::default(),
+            future: Stack::default(),
+            plane: BufferPlane::Normal,
+            pending_changes: Vec::new(),
+            pending_edits: Vec::new(),
+        }
+    }
+
+    fn current(&self) -> &Plane<S> {
+        match &self.plane {
+            BufferPlane::Normal => &self.text,
+            BufferPlane::Terminal => &self.terminal,
+            BufferPlane::Command => &self.command,
+        }
+    }
+
+    fn current_mut(&mut self) -> &mut Plane<S> {
+        match &self.plane {
+            BufferPlane::Normal => &mut self.text,
+            BufferPlane::Terminal => &mut self.terminal,
+            BufferPlane::Command => &mut self.command,
+        }
+    }
+
+    /// Offset of `at` in the current plane, with the column clamped to the end of its line.
+    fn clamped_offset(&self, at: LineCol) -> Result<usize> {
+        let (start, end) = self
+            .current()
+            .line_range(at.line)
+            .ok_or(Error::InvalidPosition)?;
+        Ok(start + at.col.min(end - start))
+    }
+
+    /// Records the replacement of `from..to` of the normal text by `text`. Must be called before
+    /// the text is changed, edits of the other planes are ignored.
+    fn record_change(&mut self, from: LineCol, to: LineCol, text: &str) {
+        if !matches!(self.plane, BufferPlane::Normal) {
+            return;
+        }
+        let char_pos = |pos: LineCol| {
+            let line = self.text.line_text(pos.line).unwrap_or_default();
+            let col = line
+                .get(..pos.col)
+                .map_or_else(|| line.chars().count(), |before| before.chars().count());
+            LineCol {
+                line: pos.line,
+                col,
+            }
+        };
+        let (start, end) = (char_pos(from), char_pos(to));
+        let offsets = (self.clamped_offset(from), self.clamped_offset(to));
+        let range_length = match offsets {
+            (Ok(from), Ok(to)) if from <= to => self.text.text_between(from, to).chars().count(),
+            _ => 0,
+        };
+        self.pending_changes.push(IncrementalChange {
+            start,
+            end,
+            range_length,
+            text: text.to_string(),
+        });
+        if let (Ok(start_byte), Ok(old_end_byte)) = offsets {
+            let clamp = |pos: LineCol| LineCol {
+                line: pos.line,
+                col: pos
+                    .col
+                    .min(self.text.line_len(pos.line).unwrap_or_default()),
+            };
+            self.pending_edits.push(input_edit(
+                clamp(from),
+                clamp(to),
+                start_byte,
+                old_end_byte,
+                text,
+            ));
+        }
+    }
+
+    /// Records the removal of the whole lines `from..=to` of the normal text.
+    fn record_lines_removal(&mut self, from: usize, to: usize) {
+        let end_of = |line: usize| LineCol {
+            line,
+            col: self.text.line_len(line).unwrap_or_default(),
+        };
+        let (start, end) = if to + 1 < self.text.line_count() {
+            (
+                LineCol { line: from, col: 0 },
+                LineCol {
+                    line: to + 1,
+                    col: 0,
+                },
+            )
+        } else if from > 0 {
+            (end_of(from - 1), end_of(to))
+        } else {
+            (LineCol::default(), end_of(to))
+        };
+        self.record_change(start, end, "");
+    }
+
+    /// Records the normal text being replaced as a whole by `lines`, as done by undo and redo.
+    fn record_full_replacement(&mut self, lines: &str) {
+        let end = self
+            .text
+            .line_count()
+            .checked_sub(1)
+            .map_or_else(LineCol::default, |line| LineCol {
+                line,
+                col: self.text.line_len(line).unwrap_or_default(),
+            });
+        let plane = std::mem::replace(&mut self.plane, BufferPlane::Normal);
+        self.record_change(LineCol::default(), end, lines);
+        self.plane = plane;
+    }
+
+    /// The lines of the normal text `snapshot` was taken of, joined by line breaks.
+    fn snapshot_lines(&self, snapshot: &S::Snapshot) -> String {
+        joined_lines(self.text.storage.snapshot_text(snapshot))
+    }
+
+    /// Whether the normal text is the one `snapshot` was taken of.
+    fn is_current_text(&self, snapshot: &S::Snapshot) -> bool {
+        self.text.storage.is_snapshot(snapshot)
+    }
+}
+
+impl<S: Storage> TextBuffer for StorageBuffer<S> {
+    fn is_dirty(&self) -> bool {
+        hash_lines(self.text.lines()) != self.saved_hash
+    }
+    fn mark_saved(&mut self) {
+        self.saved_hash = hash_lines(self.text.lines());
+    }
+    fn take_pending_changes(&mut self) -> Vec<IncrementalChange> {
+        std::mem::take(&mut self.pending_changes)
+    }
+    fn take_pending_edits(&mut self) -> Vec<InputEdit> {
+        std::mem::take(&mut self.pending_edits)
+    }
+    fn set_plane(&mut self, modal: &Modal) {
+        self.plane = BufferPlane::from(modal);
+    }
+    fn insert_newline(&mut self, mut at: LineCol) -> LineCol {
+        let Some((_, end)) = self.current().line_range(at.line) else {
+            return at;
+        };
+        let end_of_line = LineCol {
+            line: at.line,
+            col: self.current().line_len(at.line).unwrap_or_default(),
+        };
+        self.record_change(end_of_line, end_of_line, "\n");
+        self.current_mut().splice(end + 1, end + 1, "\n");
+        at.line += 1;
+        at.col = 0;
+        at
+    }
+    fn get_byte_offset(&self, at: LineCol) -> usize {
+        self.text.line_start(at.line) + at.col
+    }
+    fn get_coalesced_bytes(&self) -> Vec<u8> {
+        joined_lines(self.text.text()).into_bytes()
+    }
+    fn insert(&mut self, mut at: LineCol, ch: char) -> Result<LineCol> {
+        let offset = self.current().offset(at).ok_or(Error::InvalidPosition)?;
+        let symbol = ch.encode_utf8(&mut [0; 4]).to_string();
+        self.record_change(at, at, &symbol);
+        self.current_mut().splice(offset, offset, &symbol);
+        at.col += 1;
+        Ok(at)
+    }
+    fn insert_text(
+        &mut self,
+        at: LineCol,
+        text: impl Into<String>,
+        newline: bool,
+    ) -> Result<LineCol> {
+        let text = text.into();
+        let offset = self.current().offset(at).ok_or(Error::InvalidPosition)?;
+        if text.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+        let lines: Vec<&str> = text.lines().collect();
+        if newline {
+            let (_, end) = self
+                .current()
+                .line_range(at.line)
+                .expect("The position was already checked");
+            let end_of_line = LineCol {
+                line: at.line,
+                col: self.current().line_len(at.line).unwrap_or_default(),
+            };
+            self.record_change(end_of_line, end_of_line, &format!("\n{}", lines.join("\n")));
+            let inserted: String = lines.iter().map(|line| format!("{line}\n")).collect();
+            self.current_mut().splice(end + 1, end + 1, &inserted);
+            Ok(LineCol {
+                line: at.line + 1,
+                col: 0,
+            })
+        } else {
+            let inserted = lines.join("\n");
+            self.record_change(at, at, &inserted);
+            self.current_mut().splice(offset, offset, &inserted);
+            Ok(at)
+        }
+    }
+    fn delete_selection(&mut self, from: LineCol, to: LineCol) -> Result<LineCol> {
+        let line_count = self.current().line_count();
+        if from.line >= line_count
+            || to.line >= line_count
+            || (from.line == to.line && from.col > to.col)
+            || from.line > to.line
+            || from == to
+        {
+            return Err(Error::InvalidRange);
+        }
+        let plane = self.current();
+        let (to_line_start, to_line_end) = plane.line_range(to.line).ok_or(Error::InvalidRange)?;
+        if from.col == 0 && to.col >= to_line_end - to_line_start {
+            let (start, _) = plane.line_range(from.line).ok_or(Error::InvalidRange)?;
+            self.record_lines_removal(from.line, to.line);
+            self.current_mut().splice(start, to_line_end + 1, "");
+        } else {
+            let (start, end) = (self.clamped_offset(from)?, self.clamped_offset(to)?);
+            let clamped_to = LineCol {
+                line: to.line,
+                col: end - to_line_start,
+            };
+            self.record_change(from, clamped_to, "");
+            self.current_mut().splice(start, end, "");
+        }
+        Ok(LineCol {
+            col: to.col,
+            line: from.line,
+        })
+    }
+    fn delete_block(&mut self, from: LineCol, to: LineCol) -> Result<LineCol> {
+        if from.line > to.line || from.col > to.col || to.line >= self.current().line_count() {
+            return Err(Error::InvalidRange);
+        }
+        for line in from.line..=to.line {
+            let plane = self.current();
+            let (Some(text), Some((line_start, _))) =
+                (plane.line_text(line), plane.line_range(line))
+            else {
+                continue;
+            };
+            let Some((start, end)) = block_columns(text, from.col, to.col) else {
+                continue;
+            };
+            self.record_change(LineCol { line, col: start }, LineCol { line, col: end }, "");
+            self.current_mut()
+                .splice(line_start + start, line_start + end, "");
+        }
+        Ok(from)
+    }
+    fn delete(&mut self, at: LineCol) -> Result<LineCol> {
+        let plane = self.current();
+        let line = plane.line_text(at.line).ok_or(Error::InvalidPosition)?;
+        let (line_start, _) = plane.line_range(at.line).ok_or(Error::InvalidPosition)?;
+        if !line.is_char_boundary(at.col) {
+            return Err(Error::InvalidPosition);
+        }
+        if at.col == 0 {
+            if at.line == 0 {
+                return Err(Error::ImATeacup);
+            }
+            let end_of_previous = LineCol {
+                line: at.line - 1,
+                col: plane.line_len(at.line - 1).unwrap_or_default(),
+            };
+            self.record_change(end_of_previous, at, "");
+            self.current_mut().splice(line_start - 1, line_start, "");
+            return Ok(end_of_previous);
+        }
+        let symbol_start = line[..at.col]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(col, _)| col);
+        let symbol_pos = LineCol {
+            line: at.line,
+            col: symbol_start,
+        };
+        self.record_change(symbol_pos, at, "");
+        self.current_mut()
+            .splice(line_start + symbol_start, line_start + at.col, "");
+        Ok(symbol_pos)
+    }
+    fn replace(&mut self, from: LineCol, to: LineCol, text: &str) -> Result<()> {
+        let plane = self.current();
+        let start = plane.offset(from).ok_or(Error::InvalidPosition)?;
+        let end = plane.offset(to).ok_or(Error::InvalidPosition)?;
+        let replacement = text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.record_change(from, to, &text.replace('\r', ""));
+        self.current_mut().splice(start, end, &replacement);
+        Ok(())
+    }
+    fn get_text(&self, from: LineCol, to: LineCol) -> Result<String> {
+        let plane = self.current();
+        let start_exceeds_end = from.line > to.line || (from.line == to.line && from.col > to.col);
+        match (plane.offset(from), plane.offset(to)) {
+            (Some(start), Some(end)) if !start_exceeds_end => Ok(plane.text_between(start, end)),
+            _ => Err(Error::InvalidRange),
+        }
+    }
+    /// Byte length of the normal text, counting a newline between each pair of lines.
+    fn len(&self) -> usize {
+        self.text.len().saturating_sub(1)
+    }
+    fn is_empty(&self) -> bool {
+        self.current().line_count() == 0
+    }
+    fn line_count(&self) -> usize {
+        self.current().line_count()
+    }
+    fn line(&self, line_number: usize) -> Result<&str> {
+        self.current()
+            .line_text(line_number)
+            .ok_or(Error::InvalidLineNumber)
+    }
+    /// Performs a redo operation, moving the current state to the next future state if available.
+    /// Returns an error if there are no `future` states to redo to. Always acts on the normal text.
+    fn redo(&mut self, at: LineCol) -> Result<LineCol> {
+        let future_state = self.future.pop().ok_or(Error::NowhereToGo)?;
+        self.record_full_replacement(&self.snapshot_lines(&future_state.text));
+        self.past.push(Snapshot {
+            text: self.text.snapshot(),
+            loc: at,
+        });
+        self.text.restore(future_state.text);
+        Ok(future_state.loc)
+    }
+    /// Performs an undo operation, moving the current state to the previous past state if available.
+    /// Returns an error if there are no `past` states to undo to. Always acts on the normal text.
+    fn undo(&mut self, at: LineCol) -> Result<LineCol> {
+        // States saved before edits that never happened would undo nothing
+        while self
+            .past
+            .content
+            .front()
+            .is_some_and(|state| self.is_current_text(&state.text))
+        {
+            self.past.pop();
+        }
+        let past_state = self.past.pop().ok_or(Error::NowhereToGo)?;
+        self.record_full_replacement(&self.snapshot_lines(&past_state.text));
+        self.future.push(Snapshot {
+            text: self.text.snapshot(),
+            loc: at,
+        });
+        self.text.restore(past_state.text);
+        Ok(past_state.loc)
+    }
+    /// Pushes a snapshot of the normal text onto the `past` stack, dropping any undone states.
+    /// Does nothing while the command bar or terminal is edited.
+    fn save_state(&mut self, at: LineCol) {
+        if !matches!(self.plane, BufferPlane::Normal) {
+            return;
+        }
+        self.future = Stack::default();
+        if self
+            .past
+            .content
+            .front()
+            .is_some_and(|state| self.is_current_text(&state.text))
+        {
+            return;
+        }
+        self.past.push(Snapshot {
+            text: self.text.snapshot(),
+            loc: at,
+        });
+    }
+    fn get_entire_text(&self) -> &[String] {
+        self.current().lines()
+    }
+    fn get_normal_text(&self) -> &[String] {
+        self.text.lines()
+    }
+    fn get_terminal_text(&self) -> &str {
+        self.terminal.lines().last().map_or("", String::as_str)
+    }
+    fn get_terminal_lines(&self) -> &[String] {
+        self.terminal.lines()
+    }
+    fn append_terminal_output(&mut self, output: &str) {
+        match self.terminal.len() {
+            0 => self.terminal.splice(0, 0, &format!("{output}\n")),
+            // Right before the line break of the last line
+            len => self.terminal.splice(len - 1, len - 1, output),
+        }
+    }
+    fn get_command_text(&self) -> &[String] {
+        self.command.lines()
+    }
+    fn replace_command_text(&mut self, new: impl Into<String>) {
+        self.command = Plane::new(&[new.into()]);
+    }
+    fn max_col(&self, at: LineCol) -> usize {
+        self.current().line_len(at.line).unwrap_or_default()
+    }
+    fn is_command_empty(&self) -> bool {
+        self.command.line_len(0).is_none_or(|len| len == 0)
+    }
+    fn clear_command(&mut self) {
+        self.command = Plane::new(&[String::new()]);
+    }
+    fn reset_text(&mut self, lines: Vec<String>) {
+        self.record_full_replacement(&lines.join("\n"));
+        self.text = Plane::new(&lines);
+        self.past = Stack::default();
+        self.future = Stack::default();
+    }
+    fn delete_line(&mut self, at: usize) -> Result<LineCol> {
+        let line_count = self.text.line_count();
+        let (start, end) = self.text.line_range(at).ok_or(Error::InvalidLineNumber)?;
+        let plane = std::mem::replace(&mut self.plane, BufferPlane::Normal);
+        self.record_lines_removal(at, at);
+        self.plane = plane;
+        if line_count == 1 {
+            self.text.splice(start, end, "");
+        } else {
+            self.text.splice(start, end + 1, "");
+        }
+        Ok(LineCol {
+            line: at.min(self.text.line_count() - 1),
+            col: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::{
+        gap_buffer::Gap,
+        piece_table::Pieces,
+        tests::{at, edit_normal_text, walk_history},
+        VecBuffer,
+    };
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(ToString::to_string).collect()
+    }
+
+    /// Declares the tests every `Storage` has to pass, in a module named after it. The buffers
+    /// built on them are compared against `VecBuffer`.
+    macro_rules! conformance_tests {
+        ($($module:ident => $storage:ty),+ $(,)?) => {$(
+            mod $module {
+                use super::*;
+
+                type Buffer = StorageBuffer<$storage>;
+
+                #[test]
+                fn test_edits_match_vec_buffer() {
+                    let text = lines(&[
+                        "First line",
+                        "Second line",
+                        "Third line",
+                        "Fourth line",
+                        "Last",
+                    ]);
+                    let mut vec_buffer = VecBuffer::new(text.clone());
+                    let mut buffer = Buffer::new(text);
+                    assert_eq!(
+                        edit_normal_text(&mut buffer),
+                        edit_normal_text(&mut vec_buffer)
+                    );
+                    assert_eq!(buffer.get_normal_text(), vec_buffer.get_normal_text());
+                    assert_eq!(
+                        buffer.get_coalesced_bytes(),
+                        vec_buffer.get_coalesced_bytes()
+                    );
+                    assert_eq!(
+                        buffer.take_pending_changes(),
+                        vec_buffer.take_pending_changes()
+                    );
+                    assert_eq!(
+                        buffer.take_pending_edits(),
+                        vec_buffer.take_pending_edits()
+                    );
+                }
+
+                #[test]
+                fn test_history_matches_vec_buffer() {
+                    let text = lines(&["one", "two", "three"]);
+                    let mut vec_buffer = VecBuffer::new(text.clone());
+                    let mut buffer = Buffer::new(text);
+                    assert_eq!(walk_history(&mut buffer), walk_history(&mut vec_buffer));
+                    assert_eq!(
+                        buffer.take_pending_changes(),
+                        vec_buffer.take_pending_changes()
+                    );
+                    assert_eq!(
+                        buffer.take_pending_edits(),
+                        vec_buffer.take_pending_edits()
+                    );
+                    assert!(buffer.is_dirty());
+                }
+
+                #[test]
+                fn test_planes_stay_apart() {
+                    let mut buf = Buffer::new(lines(&["normal"]));
+                    buf.set_plane(&Modal::Command);
+                    buf.insert_text(at(0, 0), "w", false).unwrap();
+                    buf.delete(at(0, 1)).unwrap();
+                    assert!(buf.is_command_empty());
+                    buf.replace_command_text("edit");
+                    assert_eq!(buf.get_entire_text(), ["edit"]);
+
+                    buf.set_plane(&Modal::Terminal);
+                    buf.append_terminal_output("$ ls\nsrc");
+                    buf.append_terminal_output("\n$ ");
+                    assert_eq!(buf.get_terminal_lines(), ["$ ls", "src", "$ "]);
+                    assert_eq!(buf.get_terminal_text(), "$ ");
+
+                    buf.set_plane(&Modal::Normal);
+                    assert_eq!(buf.get_entire_text(), ["normal"]);
+                    assert!(buf.take_pending_changes().is_empty());
+                    assert!(!buf.is_dirty());
+                }
+
+                #[test]
+                fn test_deleting_every_line() {
+                    let mut buf = Buffer::new(lines(&["a", "b"]));
+                    buf.delete_selection(at(0, 0), at(1, 1)).unwrap();
+                    assert!(buf.is_empty());
+                    assert_eq!(buf.line_count(), 0);
+                    assert!(buf.get_normal_text().is_empty());
+                    assert!(matches!(buf.line(0), Err(Error::InvalidLineNumber)));
+                }
+
+                #[test]
+                fn test_lines_follow_splices() {
+                    let mut plane = Plane::<$storage>::new(&lines(&["first", "third"]));
+                    plane.splice(6, 6, "second\n");
+                    plane.splice(0, 0, "zeroth\n");
+                    assert_eq!(plane.lines(), ["zeroth", "first", "second", "third"]);
+                    assert_eq!(plane.line_range(2), Some((13, 19)));
+                    assert_eq!(plane.line_at(19), 2);
+                    assert_eq!(plane.line_at(26), 4);
+
+                    plane.splice(3, 16, "");
+                    assert_eq!(plane.lines(), ["zerond", "third"]);
+                    assert_eq!(plane.text_between(4, 10), "nd\nthi");
+                    assert_eq!(plane.line_range(1), Some((7, 12)));
+                    assert_eq!(plane.line_range(2), None);
+
+                    plane.splice(0, 13, "");
+                    assert!(plane.lines().is_empty());
+                    plane.splice(0, 0, "again\n");
+                    assert_eq!(plane.lines(), ["again"]);
+                }
+
+                #[test]
+                fn test_lines_follow_undo() {
+                    let mut buf = Buffer::new(lines(&["ab", "cd"]));
+                    buf.save_state(at(0, 0));
+                    buf.insert_newline(at(0, 0));
+                    buf.insert(at(1, 0), 'x').unwrap();
+                    assert_eq!(buf.get_normal_text(), ["ab", "x", "cd"]);
+                    assert_eq!(buf.get_byte_offset(at(2, 1)), 6);
+                    buf.undo(at(1, 1)).unwrap();
+                    assert_eq!(buf.get_normal_text(), ["ab", "cd"]);
+                    assert_eq!(buf.get_byte_offset(at(1, 1)), 4);
+                    assert_eq!(buf.line(1).unwrap(), "cd");
+                }
+            }
+        )+};
+    }
+
+    conformance_tests!(gap => Gap, pieces => Pieces);
+}
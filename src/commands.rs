@@ -0,0 +1,757 @@
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    buffer::TextBuffer,
+    common::RegexPattern,
+    config::KeySpec,
+    editor::Editor,
+    modals::{FindMode, Modal},
+    notif_bar,
+    options::SetAction,
+    snippets::Snippet,
+    utils::expand_tilde,
+    Error, LineCol, Result,
+};
+
+/// Script sourced on startup, if it exists.
+pub const INIT_SCRIPT: &str = "~/.config/neotext/init.neotext";
+
+/// Ex commands that can be issued from the command bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    Set {
+        option: String,
+        action: SetAction,
+    },
+    Iabbrev {
+        trigger: String,
+        expansion: String,
+    },
+    Source(PathBuf),
+    Snippet {
+        trigger: String,
+        body: String,
+    },
+    Write(Option<PathBuf>),
+    /// `:wq [file]`, writes like `:w` and quits if that succeeded.
+    WriteQuit(Option<PathBuf>),
+    /// `:{number}`, jumps to the line as numbered in the gutter, counting from 1.
+    GoToLine(usize),
+    /// `:e[dit][!] [file]`, reopens the current file when no path is given.
+    Edit {
+        path: Option<PathBuf>,
+        force: bool,
+    },
+    Map {
+        modes: Vec<Modal>,
+        from: KeySpec,
+        to: KeySpec,
+    },
+    Unmap {
+        modes: Vec<Modal>,
+        key: KeySpec,
+    },
+    /// `:make`, runs the build command in the background.
+    Make,
+    /// `:copen`, shows the quickfix window.
+    QuickfixOpen,
+    /// `:cclose`, hides the quickfix window.
+    QuickfixClose,
+    QuickfixNext,
+    QuickfixPrevious,
+    /// `:reg[isters]`, shows the content of the registers.
+    Registers,
+    /// `:colo[rscheme] [name]`, switches the theme or shows the current one without a name.
+    Colorscheme(Option<String>),
+    /// `:Grep pattern`, searches the workspace into the picker.
+    Grep(String),
+    /// `:[range]s/pattern/replacement/[g]`, replaces regex matches on the lines of the range.
+    /// The replacement may refer to capture groups as `\1` to `\9`.
+    Substitute {
+        pattern: String,
+        replacement: String,
+        /// Replace every match on a line instead of only the first one, the `g` flag.
+        global: bool,
+        range: LineRange,
+    },
+}
+
+/// Splits the leading decimal number off `text`.
+fn split_number(text: &str) -> Option<(usize, &str)> {
+    let digits = text.len()
+        - text
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let number = text[..digits].parse().ok()?;
+    Some((number, &text[digits..]))
+}
+
+/// Lines an ex command acts on, given before the command name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineRange {
+    /// No range, the cursor line.
+    Current,
+    /// `%`, every line.
+    All,
+    /// `{start},{end}` or a single `{line}`, numbered from 1 as in the gutter.
+    Lines(usize, usize),
+}
+
+impl LineRange {
+    /// Splits a range off the start of `text`, returning it along with the rest.
+    fn parse(text: &str) -> (Self, &str) {
+        if let Some(rest) = text.strip_prefix('%') {
+            return (Self::All, rest);
+        }
+        let Some((start, rest)) = split_number(text) else {
+            return (Self::Current, text);
+        };
+        match rest.strip_prefix(',').and_then(split_number) {
+            Some((end, rest)) => (Self::Lines(start, end), rest),
+            None => (Self::Lines(start, start), rest),
+        }
+    }
+
+    /// The lines of the range in a buffer whose last line is `max_line`, counted from 0. A
+    /// backwards range is swapped, `None` if it reaches past the last line.
+    pub fn lines(self, current: usize, max_line: usize) -> Option<RangeInclusive<usize>> {
+        let (start, end) = match self {
+            Self::Current => (current, current),
+            Self::All => (0, max_line),
+            Self::Lines(start, end) => (
+                start.min(end).saturating_sub(1),
+                start.max(end).saturating_sub(1),
+            ),
+        };
+        (end <= max_line).then_some(start..=end)
+    }
+}
+
+/// Modes affected by a mapping command, derived from its prefix: `map`, `nmap`, `imap` or
+/// `vmap`.
+fn map_modes(prefix: &str) -> Option<Vec<Modal>> {
+    Some(match prefix {
+        "" => vec![
+            Modal::Normal,
+            Modal::Insert,
+            Modal::Visual,
+            Modal::VisualLine,
+            Modal::VisualBlock,
+            Modal::Find(FindMode::Forwards),
+            Modal::Find(FindMode::Backwards),
+        ],
+        "n" => vec![Modal::Normal],
+        "i" => vec![Modal::Insert],
+        "v" => vec![Modal::Visual, Modal::VisualLine, Modal::VisualBlock],
+        _ => return None,
+    })
+}
+
+/// Parses the text of the command bar into a `Command`. The leading `:` is optional.
+///
+/// # Errors
+/// Returns `Error::ParsingError` if the text isn't a recognized command.
+pub fn parse_command(text: &str) -> Result<Command> {
+    let text = text.trim();
+    let text = text.strip_prefix(':').unwrap_or(text).trim_start();
+    if let Some(command) = parse_substitute(text) {
+        return command;
+    }
+    let (name, args) = text
+        .split_once(char::is_whitespace)
+        .map_or((text, ""), |(name, args)| (name, args.trim()));
+
+    if let Ok(line) = name.parse() {
+        return Ok(Command::GoToLine(line));
+    }
+    match name {
+        "q" | "quit" | "q!" | "quit!" => Ok(Command::Quit),
+        "set" | "se" => parse_set(args),
+        "iabbrev" | "iab" => {
+            let (trigger, expansion) = args
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| Error::ParsingError(format!("Invalid argument: {args}")))?;
+            Ok(Command::Iabbrev {
+                trigger: trigger.to_string(),
+                expansion: expansion.trim_start().to_string(),
+            })
+        }
+        "snippet" => {
+            let (trigger, body) = args
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| Error::ParsingError(format!("Invalid argument: {args}")))?;
+            Ok(Command::Snippet {
+                trigger: trigger.to_string(),
+                body: body.trim_start().replace("\\n", "\n").replace("\\t", "\t"),
+            })
+        }
+        "w" | "write" => Ok(Command::Write(
+            (!args.is_empty()).then(|| expand_tilde(args)),
+        )),
+        "wq" => Ok(Command::WriteQuit(
+            (!args.is_empty()).then(|| expand_tilde(args)),
+        )),
+        "e" | "edit" | "e!" | "edit!" => Ok(Command::Edit {
+            path: (!args.is_empty()).then(|| expand_tilde(args)),
+            force: name.ends_with('!'),
+        }),
+        "make" | "mak" => Ok(Command::Make),
+        "copen" | "cope" => Ok(Command::QuickfixOpen),
+        "cclose" | "ccl" => Ok(Command::QuickfixClose),
+        "cnext" | "cn" => Ok(Command::QuickfixNext),
+        "cprevious" | "cprev" | "cp" => Ok(Command::QuickfixPrevious),
+        "registers" | "reg" | "display" | "di" => Ok(Command::Registers),
+        "colorscheme" | "colo" => Ok(Command::Colorscheme(
+            (!args.is_empty()).then(|| args.to_string()),
+        )),
+        "colorscheme?" | "colo?" => Ok(Command::Colorscheme(None)),
+        "Grep" | "grep" | "gr" => Ok(Command::Grep(args.to_string())),
+        map if map.ends_with("unmap") => {
+            let modes = map_modes(map.trim_end_matches("unmap"))
+                .ok_or_else(|| Error::ParsingError(format!("Not an editor command: {map}")))?;
+            Ok(Command::Unmap {
+                modes,
+                key: KeySpec::parse(args)?,
+            })
+        }
+        map if map.ends_with("map") => {
+            let modes = map_modes(map.trim_end_matches("map"))
+                .ok_or_else(|| Error::ParsingError(format!("Not an editor command: {map}")))?;
+            let (from, to) = args
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| Error::ParsingError(format!("Invalid argument: {args}")))?;
+            Ok(Command::Map {
+                modes,
+                from: KeySpec::parse(from)?,
+                to: KeySpec::parse(to.trim_start())?,
+            })
+        }
+        "source" | "so" if !args.is_empty() => Ok(Command::Source(expand_tilde(args))),
+        otherwise => Err(Error::ParsingError(format!(
+            "Not an editor command: {otherwise}"
+        ))),
+    }
+}
+
+/// Parses `s/pattern/replacement/flags`, optionally prefixed with a `LineRange`. Returns `None`
+/// if `text` isn't a substitute command at all. A `/` is kept in the pattern or replacement by
+/// escaping it as `\/`, and the trailing delimiter may be left out.
+fn parse_substitute(text: &str) -> Option<Result<Command>> {
+    let (range, text) = LineRange::parse(text);
+    let rest = text
+        .strip_prefix("substitute/")
+        .or_else(|| text.strip_prefix("s/"))?;
+
+    let mut parts = vec![String::new()];
+    let mut chars = rest.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '/' && parts.len() < 3 {
+            parts.push(String::new());
+            continue;
+        }
+        let part = parts.last_mut().expect("Starts with a part");
+        match ch {
+            '\\' => match chars.next() {
+                Some('/') => part.push('/'),
+                Some(escaped) => {
+                    part.push('\\');
+                    part.push(escaped);
+                }
+                None => part.push('\\'),
+            },
+            ch => part.push(ch),
+        }
+    }
+    let mut parts = parts.into_iter();
+    let pattern = parts.next().unwrap_or_default();
+    let replacement = parts.next().unwrap_or_default();
+    let flags = parts.next().unwrap_or_default();
+    if pattern.is_empty() {
+        return Some(Err(Error::ParsingError("Empty pattern".to_string())));
+    }
+    if let Some(flag) = flags.chars().find(|flag| *flag != 'g') {
+        return Some(Err(Error::ParsingError(format!("Invalid flag: {flag}"))));
+    }
+    Some(Ok(Command::Substitute {
+        pattern,
+        replacement,
+        global: flags.contains('g'),
+        range,
+    }))
+}
+
+/// Parses the arguments of a `:set` command, e.g. `iskeyword+=-` or `iskeyword&`.
+fn parse_set(args: &str) -> Result<Command> {
+    let set = |option: &str, action| {
+        Ok(Command::Set {
+            option: option.to_string(),
+            action,
+        })
+    };
+    if let Some((option, value)) = args.split_once("+=") {
+        return set(option, SetAction::Add(value.to_string()));
+    }
+    if let Some((option, value)) = args.split_once("-=") {
+        return set(option, SetAction::Remove(value.to_string()));
+    }
+    if let Some((option, value)) = args.split_once('=') {
+        return set(option, SetAction::Assign(value.to_string()));
+    }
+    if let Some(option) = args.strip_suffix('&') {
+        return set(option, SetAction::Reset);
+    }
+    // Boolean options are switched on by their name and off by their name prefixed with `no`
+    if !args.is_empty() && !args.contains(char::is_whitespace) {
+        return match args.strip_prefix("no") {
+            Some(option) => set(option, SetAction::Assign("false".to_string())),
+            None => set(args, SetAction::Assign("true".to_string())),
+        };
+    }
+    Err(Error::ParsingError(format!("Invalid argument: {args}")))
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Executes a parsed ex command against the editor.
+    pub(crate) fn execute_command(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Quit => return Err(Error::ExitCall),
+            Command::Set { option, action } => {
+                if self.options.apply(&option, action).is_err() {
+                    notif_bar!(self => format!("Unknown option: {option}"););
+                }
+                self.tasks
+                    .set_auto_save_interval(self.options.auto_save_interval)?;
+                self.tasks.set_swap_interval(self.options.swap_interval)?;
+            }
+            Command::Iabbrev { trigger, expansion } => {
+                self.set_abbreviation(&trigger, &expansion);
+            }
+            Command::Source(path) => self.source(&path)?,
+            Command::Snippet { trigger, body } => self.snippets.add(Snippet {
+                trigger,
+                body,
+                filetype: None,
+            }),
+            Command::Edit { path, force } => match path.or_else(|| self.file_path.clone()) {
+                Some(path) => self.open_file(path, force)?,
+                None => {
+                    notif_bar!(self => "No file name";);
+                }
+            },
+            Command::Map { modes, from, to } => {
+                for mode in modes {
+                    self.keymap.map(mode, from, to);
+                }
+            }
+            Command::Unmap { modes, key } => {
+                for mode in modes {
+                    self.keymap.unmap(mode, key);
+                }
+            }
+            Command::Make => self.make()?,
+            Command::Grep(query) => self.grep(query)?,
+            Command::Substitute {
+                pattern,
+                replacement,
+                global,
+                range,
+            } => {
+                let Some(lines) = range.lines(self.pos().line, self.buffer.max_line()) else {
+                    notif_bar!(self => "Invalid range";);
+                    return Ok(());
+                };
+                match RegexPattern::new(&pattern) {
+                    Ok(pattern) => self.substitute(&pattern, &replacement, global, lines)?,
+                    Err(e) => {
+                        notif_bar!(self => e.display_user().to_string(););
+                    }
+                }
+            }
+            Command::Colorscheme(Some(name)) => {
+                if let Err(e) = self.set_colorscheme(&name) {
+                    notif_bar!(self => format!("Can't load color scheme: {}", e.display_user()););
+                }
+            }
+            Command::Colorscheme(None) => {
+                let name = self.theme.name().to_string();
+                notif_bar!(self => name;);
+            }
+            Command::Registers => {
+                let listing = self
+                    .copy_register
+                    .all_registers()
+                    .into_iter()
+                    .map(|(name, preview)| format!("\"{name} {preview}"))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                notif_bar!(self => listing;);
+            }
+            Command::QuickfixOpen => self.quickfix.open = true,
+            Command::QuickfixClose => self.quickfix.open = false,
+            Command::QuickfixNext => match self.quickfix.next().cloned() {
+                Some(entry) => self.jump_to_quickfix_entry(entry)?,
+                None => {
+                    notif_bar!(self => "No errors";);
+                }
+            },
+            Command::QuickfixPrevious => match self.quickfix.previous().cloned() {
+                Some(entry) => self.jump_to_quickfix_entry(entry)?,
+                None => {
+                    notif_bar!(self => "No errors";);
+                }
+            },
+            Command::Write(path) => {
+                self.write_and_report(path);
+            }
+            Command::WriteQuit(path) => {
+                if self.write_and_report(path) {
+                    return Err(Error::ExitCall);
+                }
+            }
+            Command::GoToLine(line) => {
+                // Like `:0`, a line before the first one goes to the first one
+                let line = line.saturating_sub(1);
+                if line > self.buffer.max_line() {
+                    notif_bar!(self => "Invalid line number";);
+                } else {
+                    self.push_jump(self.pos());
+                    self.go(LineCol { line, col: 0 });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the buffer like `write_file`, telling in the notification bar how it went. Returns
+    /// whether the file was written.
+    fn write_and_report(&mut self, path: Option<PathBuf>) -> bool {
+        match self.write_file(path) {
+            Ok(()) => {
+                let lines = self.buffer.get_normal_text().len();
+                notif_bar!(self => format!("Written {lines} lines"););
+                true
+            }
+            Err(Error::InvalidInput) => {
+                notif_bar!(self => "No file name";);
+                false
+            }
+            Err(e) => {
+                notif_bar!(self => format!("Can't write file: {}", e.display_user()););
+                false
+            }
+        }
+    }
+
+    /// Executes every line of a script file as an ex command. Blank lines and lines starting
+    /// with `"` are skipped. Failing lines are reported in the notification bar without aborting
+    /// the rest of the script.
+    fn source(&mut self, path: &Path) -> Result<()> {
+        let Ok(script) = std::fs::read_to_string(path) else {
+            notif_bar!(self => format!("Can't open file {}", path.display()););
+            return Ok(());
+        };
+
+        for (line_number, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('"') {
+                continue;
+            }
+            match parse_command(line).and_then(|command| self.execute_command(command)) {
+                Err(Error::ExitCall) => return Err(Error::ExitCall),
+                Err(e) => {
+                    notif_bar!(self => format!(
+                        "{}:{}: {}",
+                        path.display(),
+                        line_number + 1,
+                        e.display_user()
+                    ););
+                }
+                Ok(()) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::tests::new_test_editor;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn test_parse_quit() {
+        assert_eq!(parse_command(":q").unwrap(), Command::Quit);
+        assert_eq!(parse_command(":q!").unwrap(), Command::Quit);
+    }
+
+    #[test]
+    fn test_parse_set_add() {
+        assert_eq!(
+            parse_command(":set iskeyword+=-").unwrap(),
+            Command::Set {
+                option: "iskeyword".to_string(),
+                action: SetAction::Add("-".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_set_reset() {
+        assert_eq!(
+            parse_command(":set iskeyword&").unwrap(),
+            Command::Set {
+                option: "iskeyword".to_string(),
+                action: SetAction::Reset
+            }
+        );
+    }
+
+    #[test]
+    fn test_source_script() {
+        let path = std::env::temp_dir().join(format!("neotext_source_{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "\" a comment\n\n:set iskeyword+=-\nnotacommand\niabbrev teh the\n",
+        )
+        .unwrap();
+
+        let mut editor = new_test_editor(&[""]);
+        let command = parse_command(&format!(":source {}", path.display())).unwrap();
+        editor.execute_command(command).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(editor.options.is_keyword('-'));
+        assert_eq!(editor.abbreviations.get("teh").unwrap(), "the");
+    }
+
+    #[test]
+    fn test_source_script_sets_options_and_mappings() {
+        let path = std::env::temp_dir().join(format!("neotext_init_{}", std::process::id()));
+        std::fs::write(&path, ":set number\n:map j k\n").unwrap();
+
+        let mut editor = new_test_editor(&[""]);
+        editor.options.number = false;
+        let command = parse_command(&format!(":source {}", path.display())).unwrap();
+        editor.execute_command(command).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(editor.options.number);
+        let key = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(
+            editor.keymap.translate(Modal::Normal, key),
+            KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn test_parse_set_boolean() {
+        assert_eq!(
+            parse_command(":set noexpandtab").unwrap(),
+            Command::Set {
+                option: "expandtab".to_string(),
+                action: SetAction::Assign("false".to_string())
+            }
+        );
+        assert_eq!(
+            parse_command(":set wrap").unwrap(),
+            Command::Set {
+                option: "wrap".to_string(),
+                action: SetAction::Assign("true".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_write() {
+        assert_eq!(parse_command(":w").unwrap(), Command::Write(None));
+        assert_eq!(
+            parse_command(":write out.txt").unwrap(),
+            Command::Write(Some(PathBuf::from("out.txt")))
+        );
+        assert_eq!(parse_command(":wq").unwrap(), Command::WriteQuit(None));
+    }
+
+    #[test]
+    fn test_write_and_write_quit() {
+        let dir = std::env::temp_dir().join(format!("neotext_write_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (first, second) = (dir.join("first.txt"), dir.join("second.txt"));
+
+        let mut editor = new_test_editor(&["one", "two"]);
+        assert!(editor.execute_command(Command::Write(None)).is_ok());
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("No file name"));
+
+        let command = parse_command(&format!(":w {}", first.display())).unwrap();
+        editor.execute_command(command).unwrap();
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("Written 2 lines"));
+        assert_eq!(editor.file_path, Some(first.clone()));
+
+        editor
+            .buffer
+            .insert_text(LineCol { line: 1, col: 3 }, "!", false)
+            .unwrap();
+        let command = parse_command(&format!(":wq {}", second.display())).unwrap();
+        assert!(matches!(
+            editor.execute_command(command),
+            Err(Error::ExitCall)
+        ));
+        let (first_content, second_content) = (
+            std::fs::read_to_string(&first).unwrap(),
+            std::fs::read_to_string(&second).unwrap(),
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(first_content, "one\ntwo\n");
+        assert_eq!(second_content, "one\ntwo!\n");
+        assert_eq!(editor.file_path, Some(second));
+        assert!(!editor.buffer.is_dirty());
+    }
+
+    #[test]
+    fn test_parse_map() {
+        assert_eq!(
+            parse_command(":nmap <C-s> :").unwrap(),
+            Command::Map {
+                modes: vec![Modal::Normal],
+                from: KeySpec::parse("<C-s>").unwrap(),
+                to: KeySpec::parse(":").unwrap(),
+            }
+        );
+        assert_eq!(
+            parse_command(":vunmap x").unwrap(),
+            Command::Unmap {
+                modes: vec![Modal::Visual, Modal::VisualLine, Modal::VisualBlock],
+                key: KeySpec::parse("x").unwrap(),
+            }
+        );
+        assert!(parse_command(":xmap a b").is_err());
+    }
+
+    #[test]
+    fn test_edit_forced_clears_readonly() {
+        let path = std::env::temp_dir().join(format!("neotext_edit_{}", std::process::id()));
+        std::fs::write(&path, "first\nsecond\n").unwrap();
+
+        let mut editor = new_test_editor(&[""]);
+        editor.options.readonly = true;
+        let command = parse_command(&format!(":e! {}", path.display())).unwrap();
+        editor.execute_command(command).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(editor.buffer.get_normal_text(), ["first", "second"]);
+        assert!(!editor.options.readonly);
+        assert_eq!(editor.file_path, Some(path));
+    }
+
+    #[test]
+    fn test_edit_refuses_unsaved_changes() {
+        let path = std::env::temp_dir().join(format!("neotext_edit_new_{}", std::process::id()));
+        let mut editor = new_test_editor(&["old text"]);
+        editor.handle_char_input('x', None).unwrap();
+
+        let command = parse_command(&format!(":e {}", path.display())).unwrap();
+        editor.execute_command(command).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["ld text"]);
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("No write since last change"));
+
+        // A file that doesn't exist yet opens as an empty buffer without the previous history
+        let command = parse_command(&format!(":e! {}", path.display())).unwrap();
+        editor.execute_command(command).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), [""]);
+        assert_eq!(editor.pos(), LineCol::default());
+        assert!(!editor.buffer.is_dirty());
+        editor.handle_char_input('u', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), [""]);
+        assert_eq!(editor.file_path, Some(path));
+    }
+
+    #[test]
+    fn test_go_to_line() {
+        assert_eq!(parse_command(":42").unwrap(), Command::GoToLine(42));
+        let mut editor = new_test_editor(&["one", "two", "three"]);
+        editor.go(LineCol { line: 0, col: 2 });
+
+        editor
+            .execute_command(parse_command(":3").unwrap())
+            .unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+        editor
+            .execute_command(parse_command(":0").unwrap())
+            .unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+
+        editor
+            .execute_command(parse_command(":4").unwrap())
+            .unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("Invalid line number"));
+
+        editor.jump_back();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+    }
+
+    #[test]
+    fn test_parse_substitute() {
+        assert_eq!(
+            parse_command(r":%s/a\/b/c/g").unwrap(),
+            Command::Substitute {
+                pattern: "a/b".to_string(),
+                replacement: "c".to_string(),
+                global: true,
+                range: LineRange::All,
+            }
+        );
+        assert_eq!(
+            parse_command(r":s/(\w+) x/\1").unwrap(),
+            Command::Substitute {
+                pattern: r"(\w+) x".to_string(),
+                replacement: r"\1".to_string(),
+                global: false,
+                range: LineRange::Current,
+            }
+        );
+        assert_eq!(
+            parse_command(":2,4s/a/b").unwrap(),
+            Command::Substitute {
+                pattern: "a".to_string(),
+                replacement: "b".to_string(),
+                global: false,
+                range: LineRange::Lines(2, 4),
+            }
+        );
+        assert!(matches!(
+            parse_command(":3s/a/b/g"),
+            Ok(Command::Substitute {
+                range: LineRange::Lines(3, 3),
+                ..
+            })
+        ));
+        assert!(parse_command(":s//x/").is_err());
+        assert!(parse_command(":s/a/b/q").is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown_command() {
+        assert!(parse_command(":nonexistent").is_err());
+    }
+}
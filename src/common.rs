@@ -10,6 +10,12 @@ pub struct LineCol {
 }
 
 static DEBUG_MESSAGES: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+static NOTIFICATION_HISTORY: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+
+/// Amount of pending notifications kept, older ones are dropped first.
+pub const NOTIFICATION_QUEUE_LEN: usize = 10;
+/// Amount of past notifications kept around for crash reports.
+pub const NOTIFICATION_HISTORY_LEN: usize = 20;
 
 impl std::ops::Sub for LineCol {
     type Output = LineCol;
@@ -50,11 +56,33 @@ impl PartialOrd for LineCol {
 ///
 /// Returns a static reference to a `Mutex<VecDeque<String>>` which stores
 /// debug messages used by the `bar_dbg!` macro. Initializes the queue
-/// on first call.
+/// on first call. Only used by callers without access to an editor, the editor keeps its own
+/// queue.
 pub fn get_debug_messages() -> &'static Mutex<VecDeque<String>> {
     DEBUG_MESSAGES.get_or_init(|| Mutex::new(VecDeque::new()))
 }
 
+/// Remembers a notification, unlike the debug message queue this isn't drained when the
+/// notification is displayed.
+pub fn record_notification(message: &str) {
+    let history = NOTIFICATION_HISTORY.get_or_init(|| Mutex::new(VecDeque::new()));
+    if let Ok(mut history) = history.lock() {
+        history.push_back(message.to_string());
+        if history.len() > NOTIFICATION_HISTORY_LEN {
+            history.pop_front();
+        }
+    }
+}
+
+/// Returns the last `NOTIFICATION_HISTORY_LEN` notifications, oldest first.
+pub fn notification_history() -> Vec<String> {
+    NOTIFICATION_HISTORY
+        .get()
+        .and_then(|history| history.lock().ok())
+        .map(|history| history.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
 /// A versatile debugging macro that logs expressions and their values to an info bar,
 /// similar to the standard `dbg!` macro, with additional flexibility.
 ///
@@ -77,8 +105,12 @@ pub fn get_debug_messages() -> &'static Mutex<VecDeque<String>> {
 /// notif_bar!(5 + 3;)  // Logs without returning
 /// let (a, b) = notif_bar!(1, "two");  // Logs and returns (1, "two")
 /// notif_bar!(1, "two";)  // Logs multiple values without returning
+/// notif_bar!(self => "saved";)  // Logs to the notification queue of the editor `self`
 /// ```
 ///
+/// Without an editor the message goes to the global queue, which is only meant for code that
+/// has no access to one.
+///
 /// # Notes
 /// - The expression(s) must implement the `Debug` trait for proper formatting
 /// - If the debug message queue exceeds 10 messages, the oldest message is removed
@@ -89,15 +121,30 @@ pub fn get_debug_messages() -> &'static Mutex<VecDeque<String>> {
 /// the lock on the debug message queue.the debug message queue.
 #[macro_export]
 macro_rules! notif_bar {
+    // Editor versions, pushing to the queue of the given editor
+    ($editor:expr => $val:expr) => {{
+        let val = $val;
+        let message = format!("[{}:{}] {} = {:?}", file!(), line!(), stringify!($val), &val);
+        $crate::record_notification(&message);
+        $editor.notify(message);
+        val
+    }};
+    ($editor:expr => $val:expr;) => {{
+        let message = format!("[{}:{}] {} = {:?}", file!(), line!(), stringify!($val), &$val);
+        $crate::record_notification(&message);
+        $editor.notify(message);
+    }};
+
     // Version that returns the value (no semicolon)
     ($val:expr) => {{
         let file = file!();
         let line = line!();
         let val = $val;
         let message = format!("[{}:{}] {} = {:?}", file, line, stringify!($val), &val);
+        $crate::record_notification(&message);
         if let Ok(mut messages) = $crate::get_debug_messages().lock() {
             messages.push_back(message);
-            if messages.len() > 10 {
+            if messages.len() > $crate::NOTIFICATION_QUEUE_LEN {
                 messages.pop_front();
             }
         }
@@ -109,9 +156,10 @@ macro_rules! notif_bar {
         let file = file!();
         let line = line!();
         let message = format!("[{}:{}] {} = {:?}", file, line, stringify!($val), &$val);
+        $crate::record_notification(&message);
         if let Ok(mut messages) = get_debug_messages().lock() {
             messages.push_back(message);
-            if messages.len() > 10 {
+            if messages.len() > $crate::NOTIFICATION_QUEUE_LEN {
                 messages.pop_front();
             }
         }
@@ -142,7 +190,9 @@ macro_rules! notif_bar {
 // So essentially what I need is for each type to implement a function that takes a &[impl AsRef<str>]
 // and returns a LineCol
 
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashSet};
+
+use regex::{Regex, RegexBuilder};
 
 pub trait Pattern {
     /// The caller has two main responsibilities:
@@ -154,6 +204,25 @@ pub trait Pattern {
     /// Thus find and rfind will require to be split at the cursor
     fn find_pattern(&self, haystack: &[impl AsRef<str>]) -> Option<LineCol>;
     fn rfind_pattern(&self, haystack: &[impl AsRef<str>]) -> Option<LineCol>;
+
+    /// Every match in `haystack`, in order. Unlike `find_pattern` the whole haystack is searched,
+    /// so no columns need to be adjusted by the caller.
+    fn find_all_pattern(&self, haystack: &[impl AsRef<str>]) -> Vec<LineCol> {
+        let mut found = vec![];
+        for (line, content) in haystack.iter().enumerate() {
+            let content = content.as_ref();
+            let mut offset = 0;
+            while let Some(pos) = self.find_pattern(&[&content[offset..]]) {
+                let col = offset + pos.col;
+                found.push(LineCol { line, col });
+                offset = col + content[col..].chars().next().map_or(1, char::len_utf8);
+                if offset >= content.len() {
+                    break;
+                }
+            }
+        }
+        found
+    }
 }
 
 impl Pattern for &str {
@@ -254,9 +323,9 @@ where
             .find_map(|(line_num, line_content)| {
                 line_content
                     .as_ref()
-                    .chars()
-                    .position(self)
-                    .map(|col| LineCol {
+                    .char_indices()
+                    .find(|(_, ch)| self(*ch))
+                    .map(|(col, _)| LineCol {
                         line: line_num,
                         col,
                     })
@@ -270,16 +339,262 @@ where
             .find_map(|(line_num, line_content)| {
                 line_content
                     .as_ref()
-                    .chars()
+                    .char_indices()
                     .rev()
-                    .position(self)
-                    .map(|rcol| LineCol {
+                    .find(|(_, ch)| self(*ch))
+                    .map(|(col, _)| LineCol {
+                        line: line_num,
+                        col,
+                    })
+            })
+    }
+}
+/// A regular expression usable as a search pattern, matches don't span lines. Columns of matches
+/// are byte offsets.
+#[derive(Debug, Clone)]
+pub struct RegexPattern {
+    source: String,
+    case_insensitive: bool,
+    multiline: bool,
+    regex: Regex,
+}
+
+impl RegexPattern {
+    /// Compiles `pattern` with the default flags.
+    ///
+    /// # Errors
+    /// Returns `Error::ParsingError` if `pattern` isn't a valid regular expression.
+    pub fn new(pattern: &str) -> crate::Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| crate::Error::ParsingError(format!("Invalid pattern: {e}")))?;
+        Ok(Self {
+            source: pattern.to_string(),
+            case_insensitive: false,
+            multiline: false,
+            regex,
+        })
+    }
+
+    /// Compiles `pattern`, or a pattern matching its text literally if it isn't a valid regular
+    /// expression. The flag tells whether the literal fallback was taken.
+    pub fn or_literal(pattern: &str) -> (Self, bool) {
+        match Self::new(pattern) {
+            Ok(compiled) => (compiled, false),
+            Err(_) => (
+                Self::new(&regex::escape(pattern)).expect("An escaped pattern is always valid."),
+                true,
+            ),
+        }
+    }
+
+    #[must_use]
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self.rebuild()
+    }
+
+    /// Makes `^` and `$` match at line boundaries within a haystack line as well.
+    #[must_use]
+    pub fn multiline(mut self) -> Self {
+        self.multiline = true;
+        self.rebuild()
+    }
+
+    /// Finds the first match in `haystack`, returning where it starts, where it ends and the text
+    /// of every capture group. Group `0` is the whole match, groups that didn't participate in
+    /// the match are empty.
+    pub fn find_with_captures(
+        &self,
+        haystack: &[impl AsRef<str>],
+    ) -> Option<(LineCol, LineCol, Vec<String>)> {
+        haystack.iter().enumerate().find_map(|(line, content)| {
+            let (start, end, groups) = self.captures_at(content.as_ref(), 0)?;
+            Some((
+                LineCol { line, col: start },
+                LineCol { line, col: end },
+                groups,
+            ))
+        })
+    }
+
+    /// Like `find_with_captures` on a single `line`, but only considers matches starting at or
+    /// after the byte offset `start`. Unlike searching a slice of the line, anchors and word
+    /// boundaries still see the text before `start`.
+    pub fn captures_at(&self, line: &str, start: usize) -> Option<(usize, usize, Vec<String>)> {
+        let captures = self.regex.captures_at(line, start)?;
+        let found = captures.get(0)?;
+        let groups = captures
+            .iter()
+            .map(|group| group.map_or_else(String::new, |group| group.as_str().to_string()))
+            .collect();
+        Some((found.start(), found.end(), groups))
+    }
+
+    fn rebuild(mut self) -> Self {
+        self.regex = RegexBuilder::new(&self.source)
+            .case_insensitive(self.case_insensitive)
+            .multi_line(self.multiline)
+            .build()
+            .expect("Flags don't affect whether an already compiled pattern is valid.");
+        self
+    }
+}
+
+impl Pattern for RegexPattern {
+    fn find_pattern(&self, haystack: &[impl AsRef<str>]) -> Option<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .find_map(|(line_num, line_content)| {
+                self.regex.find(line_content.as_ref()).map(|found| LineCol {
+                    line: line_num,
+                    col: found.start(),
+                })
+            })
+    }
+    fn rfind_pattern(&self, haystack: &[impl AsRef<str>]) -> Option<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(line_num, line_content)| {
+                self.regex
+                    .find_iter(line_content.as_ref())
+                    .last()
+                    .map(|found| LineCol {
                         line: line_num,
-                        col: line_content.as_ref().len() - rcol,
+                        col: found.start(),
                     })
             })
     }
+
+    fn find_all_pattern(&self, haystack: &[impl AsRef<str>]) -> Vec<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .flat_map(|(line, content)| {
+                self.regex
+                    .find_iter(content.as_ref())
+                    .map(move |found| LineCol {
+                        line,
+                        col: found.start(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Matches a word only where it isn't part of a longer one, as searched for by `*` and `#`.
+/// Whether a neighbouring symbol extends the word is decided by `iskeyword`.
+#[derive(Debug, Clone)]
+pub struct WholeWordPattern {
+    word: String,
+    iskeyword: HashSet<char>,
+    ignore_case: bool,
+}
+
+impl WholeWordPattern {
+    pub fn new(word: &str, iskeyword: HashSet<char>) -> Self {
+        Self {
+            word: word.to_string(),
+            iskeyword,
+            ignore_case: false,
+        }
+    }
+
+    #[must_use]
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    fn is_keyword(&self, ch: char) -> bool {
+        ch.is_alphanumeric() || self.iskeyword.contains(&ch)
+    }
+
+    /// The word as an escaped regular expression, as remembered for `n` and `N`. Word boundaries
+    /// are only asserted next to symbols the regex counts as word characters, it doesn't know
+    /// about the other symbols of `iskeyword`.
+    pub fn as_regex(&self) -> String {
+        let boundary = |edge: Option<char>| match edge {
+            Some(ch) if ch.is_alphanumeric() || ch == '_' => r"\b",
+            _ => "",
+        };
+        format!(
+            "{}{}{}{}",
+            if self.ignore_case { "(?i)" } else { "" },
+            boundary(self.word.chars().next()),
+            regex::escape(&self.word),
+            boundary(self.word.chars().next_back()),
+        )
+    }
+
+    /// Byte columns at which the word occurs in `line`.
+    fn matches_in<'a>(&'a self, line: &'a str) -> impl DoubleEndedIterator<Item = usize> + 'a {
+        line.char_indices().filter_map(move |(col, _)| {
+            let end = col + self.word.len();
+            let candidate = line.get(col..end)?;
+            let same = if self.ignore_case {
+                candidate.eq_ignore_ascii_case(&self.word)
+            } else {
+                candidate == self.word
+            };
+            let bounded = !line[..col]
+                .chars()
+                .next_back()
+                .is_some_and(|ch| self.is_keyword(ch))
+                && !line[end..]
+                    .chars()
+                    .next()
+                    .is_some_and(|ch| self.is_keyword(ch));
+            (same && bounded).then_some(col)
+        })
+    }
 }
+
+impl Pattern for WholeWordPattern {
+    fn find_pattern(&self, haystack: &[impl AsRef<str>]) -> Option<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .find_map(|(line_num, line_content)| {
+                self.matches_in(line_content.as_ref())
+                    .next()
+                    .map(|col| LineCol {
+                        line: line_num,
+                        col,
+                    })
+            })
+    }
+    fn rfind_pattern(&self, haystack: &[impl AsRef<str>]) -> Option<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(line_num, line_content)| {
+                self.matches_in(line_content.as_ref())
+                    .next_back()
+                    .map(|col| LineCol {
+                        line: line_num,
+                        col,
+                    })
+            })
+    }
+
+    fn find_all_pattern(&self, haystack: &[impl AsRef<str>]) -> Vec<LineCol> {
+        haystack
+            .iter()
+            .enumerate()
+            .flat_map(|(line, content)| {
+                self.matches_in(content.as_ref())
+                    .map(move |col| LineCol { line, col })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -403,6 +718,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_char_predicate_byte_columns() {
+        let buffer = vec!["äb c".to_string(), "x y ".to_string()];
+        let pattern = |c: char| !c.is_whitespace();
+        assert_eq!(
+            pattern.find_pattern(&buffer[..1]),
+            Some(LineCol { line: 0, col: 0 })
+        );
+        assert_eq!(
+            (|c: char| c == 'b').find_pattern(&buffer),
+            Some(LineCol { line: 0, col: 2 })
+        );
+        assert_eq!(
+            pattern.rfind_pattern(&buffer),
+            Some(LineCol { line: 1, col: 2 })
+        );
+    }
+
     #[test]
     fn test_pattern_with_special_chars() {
         let buffer = vec!["Special: !@#$%^&*()".to_string()];
@@ -454,4 +787,100 @@ mod tests {
         });
         assert_eq!(final_result, Some(LineCol { line: 1, col: 0 }));
     }
+
+    #[test]
+    fn test_regex_character_classes() {
+        let buffer = create_test_buffer();
+        let pattern = RegexPattern::new(r"\d{3}").unwrap();
+        assert_eq!(
+            pattern.find_pattern(&buffer),
+            Some(LineCol { line: 2, col: 0 })
+        );
+        assert_eq!(
+            pattern.rfind_pattern(&buffer),
+            Some(LineCol { line: 2, col: 0 })
+        );
+        assert!(RegexPattern::new("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_regex_find_with_captures() {
+        let haystack = ["nothing here", "say foobar twice", "foobar"];
+        let pattern = RegexPattern::new("(foo)(x)?(bar)").unwrap();
+        let (start, end, groups) = pattern.find_with_captures(&haystack).unwrap();
+        assert_eq!(start, LineCol { line: 1, col: 4 });
+        assert_eq!(end, LineCol { line: 1, col: 10 });
+        assert_eq!(groups, ["foobar", "foo", "", "bar"]);
+        assert!(pattern.find_with_captures(&["fo bar"]).is_none());
+    }
+
+    #[test]
+    fn test_regex_or_literal() {
+        let buffer = vec!["f(a) fn  main".to_string()];
+        let (pattern, literal) = RegexPattern::or_literal(r"fn\s+\w+");
+        assert!(!literal);
+        assert_eq!(
+            pattern.find_pattern(&buffer),
+            Some(LineCol { line: 0, col: 5 })
+        );
+        let (pattern, literal) = RegexPattern::or_literal("f(a");
+        assert!(literal);
+        assert_eq!(
+            pattern.find_pattern(&buffer),
+            Some(LineCol { line: 0, col: 0 })
+        );
+    }
+
+    #[test]
+    fn test_regex_case_insensitive() {
+        let buffer = vec!["Case Sensitive".to_string()];
+        let pattern = RegexPattern::new("case").unwrap();
+        assert_eq!(pattern.find_pattern(&buffer), None);
+        let pattern = pattern.case_insensitive();
+        assert_eq!(
+            pattern.find_pattern(&buffer),
+            Some(LineCol { line: 0, col: 0 })
+        );
+    }
+
+    #[test]
+    fn test_regex_anchors() {
+        let buffer = vec!["foo bar foo".to_string(), "bar foo".to_string()];
+        let pattern = RegexPattern::new("^foo").unwrap();
+        assert_eq!(
+            pattern.find_all_pattern(&buffer),
+            [LineCol { line: 0, col: 0 }]
+        );
+        let pattern = RegexPattern::new("foo$").unwrap().multiline();
+        assert_eq!(
+            pattern.find_all_pattern(&buffer),
+            [LineCol { line: 0, col: 8 }, LineCol { line: 1, col: 4 }]
+        );
+    }
+
+    #[test]
+    fn test_regex_rfind_returns_last_match() {
+        let buffer = vec!["ab ab ab".to_string(), "no match".to_string()];
+        let pattern = RegexPattern::new("a[bc]").unwrap();
+        assert_eq!(
+            pattern.rfind_pattern(&buffer),
+            Some(LineCol { line: 0, col: 6 })
+        );
+        assert_eq!(pattern.find_all_pattern(&buffer).len(), 3);
+    }
+
+    #[test]
+    fn test_whole_word_pattern() {
+        let buffer = vec!["letter let".to_string(), "Let_x LET".to_string()];
+        let pattern = WholeWordPattern::new("let", HashSet::from(['_']));
+        assert_eq!(
+            pattern.find_all_pattern(&buffer),
+            [LineCol { line: 0, col: 7 }]
+        );
+        let pattern = pattern.ignore_case();
+        assert_eq!(
+            pattern.rfind_pattern(&buffer),
+            Some(LineCol { line: 1, col: 6 })
+        );
+    }
 }
@@ -0,0 +1,332 @@
+use crate::{lsp::CompletionItem, LineCol};
+use std::collections::HashSet;
+
+/// Maximum amount of candidates drawn in the completion popup at once.
+pub const COMPLETION_MENU_HEIGHT: usize = 10;
+
+/// How closely a candidate matches the typed prefix, from the weakest to the strongest match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The prefix characters appear in the candidate in order, with gaps between them.
+    Subsequence,
+    /// The prefix appears in the candidate as is, but not at its start.
+    Contiguous,
+    /// The candidate starts with the prefix.
+    Prefix,
+}
+
+impl MatchKind {
+    /// Classifies how `candidate` matches `prefix`, or returns `None` if it doesn't match at all.
+    fn of(prefix: &str, candidate: &str) -> Option<Self> {
+        if candidate.starts_with(prefix) {
+            return Some(Self::Prefix);
+        }
+        if candidate.contains(prefix) {
+            return Some(Self::Contiguous);
+        }
+        let mut chars = candidate.chars();
+        prefix
+            .chars()
+            .all(|ch| chars.any(|other| other == ch))
+            .then_some(Self::Subsequence)
+    }
+}
+
+/// A completion candidate along with what it's ranked by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedItem {
+    /// Text inserted when the candidate is accepted.
+    pub word: String,
+    pub kind: MatchKind,
+    /// Edit distance between the prefix and the candidate. Only breaks ties between fuzzy
+    /// matches, as every prefix match is an equally good continuation of what was typed.
+    pub distance: usize,
+    /// Position of the candidate in the recency ordered buffer words, `0` being the most recent
+    /// and `None` if the buffer doesn't use it.
+    pub recency: Option<usize>,
+}
+
+/// Where the candidates of a completion popup come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    /// Words of the buffer, most recently occurring first.
+    Words(Vec<String>),
+    /// Items returned by the language server, along with the buffer words ranking them by
+    /// recency.
+    Lsp {
+        items: Vec<CompletionItem>,
+        recent_words: Vec<String>,
+    },
+}
+
+/// Insert mode completion popup, listing the candidates matching the word prefix typed before
+/// the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionMenu {
+    /// Position at which the completed prefix starts.
+    pub prefix_start: LineCol,
+    source: Source,
+    /// Candidates from the source matching the current prefix.
+    pub candidates: Vec<String>,
+    /// Index into `candidates` of the highlighted entry.
+    pub selected: usize,
+}
+
+impl CompletionMenu {
+    /// Opens a menu of the buffer `words` starting with the `prefix` that starts at
+    /// `prefix_start`. Returns `None` if no word matches the prefix.
+    pub fn new(prefix_start: LineCol, prefix: &str, words: Vec<String>) -> Option<Self> {
+        Self::open(prefix_start, prefix, Source::Words(words))
+    }
+
+    /// Opens a menu of the completion `items` of the language server, fuzzy matched against the
+    /// `prefix` that starts at `prefix_start`. `recent_words` are the buffer words as returned by
+    /// [`collect_words`]. Returns `None` if no item matches the prefix.
+    pub fn from_items(
+        prefix_start: LineCol,
+        prefix: &str,
+        items: Vec<CompletionItem>,
+        recent_words: Vec<String>,
+    ) -> Option<Self> {
+        Self::open(
+            prefix_start,
+            prefix,
+            Source::Lsp {
+                items,
+                recent_words,
+            },
+        )
+    }
+
+    fn open(prefix_start: LineCol, prefix: &str, source: Source) -> Option<Self> {
+        let mut menu = Self {
+            prefix_start,
+            source,
+            candidates: vec![],
+            selected: 0,
+        };
+        menu.filter(prefix);
+        (!menu.candidates.is_empty()).then_some(menu)
+    }
+
+    /// Narrows down the candidates to those matching `prefix`. Buffer words have to start with
+    /// it and exclude the prefix itself, language server items are ranked by
+    /// [`Self::filter_and_rank`].
+    pub fn filter(&mut self, prefix: &str) {
+        self.candidates = match &self.source {
+            Source::Words(words) => words
+                .iter()
+                .filter(|word| word.starts_with(prefix) && word.as_str() != prefix)
+                .cloned()
+                .collect(),
+            Source::Lsp {
+                items,
+                recent_words,
+            } => Self::filter_and_rank(prefix, items, recent_words)
+                .into_iter()
+                .map(|item| item.word)
+                .collect(),
+        };
+        self.selected = 0;
+    }
+
+    /// Ranks the `items` whose filter text matches `prefix`. Prefix matches come first, then
+    /// items containing the prefix, then items containing its characters in order. Fuzzy matches
+    /// closer to the prefix by edit distance rank higher, remaining ties go to the item used most
+    /// recently among the buffer's `recent_words`, and then to the server's order.
+    pub fn filter_and_rank(
+        prefix: &str,
+        items: &[CompletionItem],
+        recent_words: &[String],
+    ) -> Vec<RankedItem> {
+        let mut ranked: Vec<RankedItem> = items
+            .iter()
+            .filter_map(|item| {
+                let text = item.filter_text();
+                let kind = MatchKind::of(prefix, text)?;
+                let distance = match kind {
+                    MatchKind::Prefix => 0,
+                    _ => edit_distance(prefix, text),
+                };
+                Some(RankedItem {
+                    word: item.insert_text().to_string(),
+                    kind,
+                    distance,
+                    recency: recent_words.iter().position(|word| word == text),
+                })
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.kind.cmp(&a.kind).then(a.distance.cmp(&b.distance)).then(
+                a.recency
+                    .unwrap_or(usize::MAX)
+                    .cmp(&b.recency.unwrap_or(usize::MAX)),
+            )
+        });
+        ranked
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.candidates.is_empty() {
+            self.selected = (self.selected + 1) % self.candidates.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.candidates.is_empty() {
+            self.selected = self
+                .selected
+                .checked_sub(1)
+                .unwrap_or(self.candidates.len() - 1);
+        }
+    }
+
+    pub fn selected_word(&self) -> Option<&str> {
+        self.candidates.get(self.selected).map(String::as_str)
+    }
+}
+
+/// Levenshtein distance between `a` and `b`, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_ch) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_ch) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_ch != *b_ch);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Collects the unique words of `lines`, ordered by the recency of their last occurrence, so the
+/// word occurring closest to the end of the buffer comes first.
+pub fn collect_words(lines: &[impl AsRef<str>], is_keyword: impl Fn(char) -> bool) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut words = vec![];
+    for line in lines.iter().rev() {
+        let line = line.as_ref();
+        let line_words: Vec<&str> = line
+            .split(|ch: char| !is_keyword(ch))
+            .filter(|word| !word.is_empty())
+            .collect();
+        for word in line_words.into_iter().rev() {
+            if seen.insert(word) {
+                words.push(word.to_string());
+            }
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words() -> Vec<String> {
+        let lines = ["let format = 1;", "let foo = format + fold;", "forward"];
+        collect_words(&lines, |ch| ch.is_alphanumeric() || ch == '_')
+    }
+
+    #[test]
+    fn test_collect_words_by_recency() {
+        assert_eq!(
+            words(),
+            vec!["forward", "fold", "format", "foo", "let", "1"]
+        );
+    }
+
+    #[test]
+    fn test_prefix_matching() {
+        let menu = CompletionMenu::new(LineCol::default(), "fo", words()).unwrap();
+        assert_eq!(menu.candidates, vec!["forward", "fold", "format", "foo"]);
+
+        let mut menu = menu;
+        menu.filter("for");
+        assert_eq!(menu.candidates, vec!["forward", "format"]);
+    }
+
+    fn items(labels: &[&str]) -> Vec<CompletionItem> {
+        labels
+            .iter()
+            .map(|label| CompletionItem {
+                label: (*label).to_string(),
+                insert_text: None,
+                filter_text: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_words_only_match_by_prefix() {
+        assert!(CompletionMenu::new(LineCol::default(), "frd", words()).is_none());
+        let menu = CompletionMenu::new(LineCol::default(), "fo", words()).unwrap();
+        assert!(!menu.candidates.contains(&"to_fmt".to_string()));
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        let items = items(&["from_utf8", "format", "fmt_args", "to_fmt", "xyz"]);
+        let ranked: Vec<_> = CompletionMenu::filter_and_rank("fmt", &items, &[])
+            .into_iter()
+            .map(|item| (item.word, item.kind))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                ("fmt_args".to_string(), MatchKind::Prefix),
+                ("to_fmt".to_string(), MatchKind::Contiguous),
+                ("format".to_string(), MatchKind::Subsequence),
+                ("from_utf8".to_string(), MatchKind::Subsequence),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recently_used_items_rank_higher() {
+        let items = items(&["foo", "fold", "forward", "force"]);
+        let ranked: Vec<_> = CompletionMenu::filter_and_rank("fo", &items, &words())
+            .into_iter()
+            .map(|item| item.word)
+            .collect();
+        // Ties without a buffer occurrence keep the server's order
+        assert_eq!(ranked, ["forward", "fold", "foo", "force"]);
+    }
+
+    #[test]
+    fn test_items_refiltered_as_prefix_grows() {
+        let mut items = items(&["from_utf8", "format"]);
+        items[1].insert_text = Some("format!".to_string());
+        let mut menu = CompletionMenu::from_items(LineCol::default(), "f", items, words()).unwrap();
+        assert_eq!(menu.candidates, ["format!", "from_utf8"]);
+        menu.filter("fmt");
+        assert_eq!(menu.candidates, ["format!", "from_utf8"]);
+        menu.filter("fu");
+        assert_eq!(menu.candidates, ["from_utf8"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("fmt", "format"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_no_match_closes_menu() {
+        assert!(CompletionMenu::new(LineCol::default(), "xyz", words()).is_none());
+    }
+
+    #[test]
+    fn test_cycling_wraps() {
+        let mut menu = CompletionMenu::new(LineCol::default(), "fo", words()).unwrap();
+        menu.select_previous();
+        assert_eq!(menu.selected_word(), Some("foo"));
+        menu.select_next();
+        menu.select_next();
+        assert_eq!(menu.selected_word(), Some("fold"));
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::{
+    buffer::TextBuffer,
+    editor::Editor,
+    error,
+    lsp::FileType,
+    modals::Modal,
+    options::{parse_iskeyword, FileTypeOverride, SetAction},
+    utils::{config_dir, parse_modeline},
+    Error, Result,
+};
+
+/// Amount of lines at the start and at the end of a file searched for modelines.
+const MODELINE_SEARCH_LINES: usize = 5;
+
+/// Name of the project-level configuration file, looked up in the directory of the opened file
+/// and all of its ancestors.
+pub const PROJECT_CONFIG_FILE: &str = ".neotext.toml";
+
+/// Settings read from the configuration files. Every key is optional so that the project-level
+/// file only overrides what it sets.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub iskeyword: Option<String>,
+    /// Auto-save interval in seconds, `0` disables it.
+    pub autosave: Option<u64>,
+    /// Swap file interval in seconds, `0` disables it.
+    pub swapinterval: Option<u64>,
+    /// Characters typed before the swap file is written, `0` disables it.
+    pub updatecount: Option<usize>,
+    pub shiftwidth: Option<usize>,
+    pub tabstop: Option<usize>,
+    pub expandtab: Option<bool>,
+    /// Whether modelines in opened files are applied.
+    pub modeline: Option<bool>,
+    /// Command run by `:make`.
+    pub build_cmd: Option<String>,
+    /// Directories searched by `gf`.
+    pub path: Option<Vec<PathBuf>>,
+    /// Indentation and `iskeyword` overrides per file type, keyed by names like `go` or `make`.
+    pub filetype: HashMap<String, FileTypeOverride>,
+}
+
+/// User-level configuration file, `~/.config/neotext/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Finds the closest `.neotext.toml`, walking up from the directory containing `file_path`.
+pub fn find_project_config(file_path: &Path) -> Option<PathBuf> {
+    let file_path = std::path::absolute(file_path).ok()?;
+    file_path
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|candidate| candidate.is_file())
+}
+
+impl Config {
+    /// Parses the config file at `path`. Missing or malformed files are treated as empty, the
+    /// latter get logged.
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        toml::from_str(&content).unwrap_or_else(|e| {
+            error!("Invalid config file {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    /// Loads the user-level config, overridden by the project-level config of `file_path`.
+    pub fn load_layered(file_path: &Path) -> Self {
+        Self::load_layered_from(user_config_path().as_deref(), file_path)
+    }
+
+    /// Same as `load_layered`, reading the user-level config from `user_config` instead.
+    pub fn load_layered_from(user_config: Option<&Path>, file_path: &Path) -> Self {
+        let user = user_config.map(Self::load).unwrap_or_default();
+        match find_project_config(file_path) {
+            Some(project) => user.merge(Self::load(&project)),
+            None => user,
+        }
+    }
+
+    /// Combines two configs, the keys set in `other` take precedence.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            iskeyword: other.iskeyword.or(self.iskeyword),
+            autosave: other.autosave.or(self.autosave),
+            swapinterval: other.swapinterval.or(self.swapinterval),
+            updatecount: other.updatecount.or(self.updatecount),
+            shiftwidth: other.shiftwidth.or(self.shiftwidth),
+            tabstop: other.tabstop.or(self.tabstop),
+            expandtab: other.expandtab.or(self.expandtab),
+            modeline: other.modeline.or(self.modeline),
+            build_cmd: other.build_cmd.or(self.build_cmd),
+            path: other.path.or(self.path),
+            filetype: {
+                let mut filetype = self.filetype;
+                for (name, indent) in other.filetype {
+                    let merged = indent.or(filetype.get(&name).cloned().unwrap_or_default());
+                    filetype.insert(name, merged);
+                }
+                filetype
+            },
+        }
+    }
+}
+
+/// A single key press, written as `j`, `<C-n>`, `<Esc>`, `<S-Tab>` and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeySpec {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeySpec {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Parses the vim-like notation of a key.
+    ///
+    /// # Errors
+    /// Returns `Error::ParsingError` if `notation` doesn't describe a single key.
+    pub fn parse(notation: &str) -> Result<Self> {
+        let invalid = || Error::ParsingError(format!("Invalid key: {notation}"));
+        let mut chars = notation.chars();
+        if let (Some(ch), None) = (chars.next(), chars.next()) {
+            return Ok(Self::new(KeyCode::Char(ch), KeyModifiers::NONE));
+        }
+
+        let mut inner = notation
+            .strip_prefix('<')
+            .and_then(|rest| rest.strip_suffix('>'))
+            .ok_or_else(invalid)?;
+        let mut modifiers = KeyModifiers::NONE;
+        while let Some((modifier, rest)) =
+            inner.split_once('-').filter(|(_, rest)| !rest.is_empty())
+        {
+            modifiers |= match modifier.to_ascii_uppercase().as_str() {
+                "C" => KeyModifiers::CONTROL,
+                "S" => KeyModifiers::SHIFT,
+                "A" | "M" => KeyModifiers::ALT,
+                _ => return Err(invalid()),
+            };
+            inner = rest;
+        }
+
+        let mut chars = inner.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match inner.to_ascii_lowercase().as_str() {
+                "esc" => KeyCode::Esc,
+                "cr" | "enter" | "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "bs" | "backspace" => KeyCode::Backspace,
+                "del" => KeyCode::Delete,
+                "space" => KeyCode::Char(' '),
+                "lt" => KeyCode::Char('<'),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                _ => return Err(invalid()),
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeySpec {
+    fn from(event: KeyEvent) -> Self {
+        // Shifted characters are already reported as their uppercase variant
+        let modifiers = match event.code {
+            KeyCode::Char(_) => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+        Self::new(event.code, modifiers)
+    }
+}
+
+/// Key remappings registered through `:map` and friends, per mode.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: HashMap<(Modal, KeySpec), KeySpec>,
+}
+
+impl KeyMap {
+    pub fn map(&mut self, mode: Modal, from: KeySpec, to: KeySpec) {
+        self.bindings.insert((mode, from), to);
+    }
+
+    pub fn unmap(&mut self, mode: Modal, key: KeySpec) -> Option<KeySpec> {
+        self.bindings.remove(&(mode, key))
+    }
+
+    /// Replaces `event` with the key it's mapped to in `mode`. Mappings aren't recursive.
+    pub fn translate(&self, mode: Modal, event: KeyEvent) -> KeyEvent {
+        match self.bindings.get(&(mode, KeySpec::from(event))) {
+            Some(to) => KeyEvent {
+                code: to.code,
+                modifiers: to.modifiers,
+                ..event
+            },
+            None => event,
+        }
+    }
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Applies every key set in `config` to the editor options.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the auto-save timer can't be started.
+    pub fn apply_config(&mut self, config: &Config) -> Result<()> {
+        if let Some(iskeyword) = &config.iskeyword {
+            self.options.iskeyword = parse_iskeyword(iskeyword);
+        }
+        if let Some(shiftwidth) = config.shiftwidth {
+            self.options.shiftwidth = shiftwidth;
+        }
+        if let Some(tabstop) = config.tabstop {
+            self.options.tabstop = tabstop;
+        }
+        if let Some(expandtab) = config.expandtab {
+            self.options.expandtab = expandtab;
+        }
+        if let Some(modeline) = config.modeline {
+            self.options.modeline = modeline;
+        }
+        if let Some(build_cmd) = &config.build_cmd {
+            self.options.build_cmd = Some(build_cmd.clone());
+        }
+        if let Some(path) = &config.path {
+            self.options.path.clone_from(path);
+        }
+        for (name, indent) in &config.filetype {
+            match FileType::from_name(name) {
+                Some(file_type) => {
+                    let entry = self
+                        .options
+                        .filetype_overrides
+                        .entry(file_type)
+                        .or_default();
+                    *entry = indent.clone().or(entry.clone());
+                }
+                None => error!("Unknown file type in config: {name}"),
+            }
+        }
+        self.options.use_filetype_overrides(self.file_type);
+        if let Some(seconds) = config.autosave {
+            self.set_auto_save_interval((seconds > 0).then(|| Duration::from_secs(seconds)))?;
+        }
+        if let Some(seconds) = config.swapinterval {
+            self.options.swap_interval = (seconds > 0).then(|| Duration::from_secs(seconds));
+        }
+        if let Some(count) = config.updatecount {
+            self.options.update_count = (count > 0).then_some(count);
+        }
+        Ok(())
+    }
+
+    /// Applies the modelines found in the first and last `MODELINE_SEARCH_LINES` lines of the
+    /// buffer, unless disabled by the `modeline` option. Unknown options are ignored.
+    pub fn apply_modelines(&mut self) {
+        if !self.options.modeline {
+            return;
+        }
+        let lines = self.buffer.get_normal_text();
+        let head = lines.len().min(MODELINE_SEARCH_LINES);
+        let tail_start = lines.len().saturating_sub(MODELINE_SEARCH_LINES).max(head);
+        let settings: Vec<_> = lines[..head]
+            .iter()
+            .chain(&lines[tail_start..])
+            .filter_map(|line| parse_modeline(line))
+            .flatten()
+            .collect();
+
+        for (key, value) in settings {
+            match key.as_str() {
+                "ft" | "filetype" => {
+                    if let Some(file_type) = FileType::from_name(&value) {
+                        self.set_file_type(file_type);
+                    }
+                }
+                // Opening a file must never be able to choose what `:make` runs
+                "build_cmd" | "makeprg" => {}
+                _ => {
+                    let _ = self.options.apply(&key, SetAction::Assign(value));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::EditorOptions;
+
+    #[test]
+    fn test_project_config_overrides_user_config() {
+        let root = std::env::temp_dir().join(format!("neotext_config_{}", std::process::id()));
+        let project = root.join("project");
+        let nested = project.join("src").join("components");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let user_config = root.join("config.toml");
+        std::fs::write(&user_config, "shiftwidth = 4\nautosave = 30\n").unwrap();
+        std::fs::write(project.join(PROJECT_CONFIG_FILE), "shiftwidth = 2\n").unwrap();
+
+        let config = Config::load_layered_from(Some(&user_config), &nested.join("index.js"));
+        let outside = Config::load_layered_from(Some(&user_config), &root.join("notes.txt"));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(config.shiftwidth, Some(2));
+        assert_eq!(config.autosave, Some(30));
+        assert_eq!(outside.shiftwidth, Some(4));
+    }
+
+    #[test]
+    fn test_parse_key_spec() {
+        assert_eq!(
+            KeySpec::parse("j").unwrap(),
+            KeySpec::new(KeyCode::Char('j'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeySpec::parse("<C-n>").unwrap(),
+            KeySpec::new(KeyCode::Char('n'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            KeySpec::parse("<Esc>").unwrap(),
+            KeySpec::new(KeyCode::Esc, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            KeySpec::parse("<S-Tab>").unwrap(),
+            KeySpec::new(KeyCode::Tab, KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            KeySpec::parse("<C-->").unwrap(),
+            KeySpec::new(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+        assert!(KeySpec::parse("jk").is_err());
+        assert!(KeySpec::parse("<X-a>").is_err());
+    }
+
+    #[test]
+    fn test_remapped_key_triggers_mapped_action() {
+        let mut editor = crate::editor::tests::new_test_editor(&[""]);
+        for command in [":map a b", ":imap <C-l> <Esc>"] {
+            let command = crate::commands::parse_command(command).unwrap();
+            editor.execute_command(command).unwrap();
+        }
+        editor.set_mode(Modal::Insert);
+
+        let key = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
+        let translated = editor.keymap.translate(editor.mode, key);
+        editor.handle_insert_key(translated).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "b");
+
+        let key = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        let translated = editor.keymap.translate(editor.mode, key);
+        editor.handle_insert_key(translated).unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+    }
+
+    #[test]
+    fn test_unmap_removes_binding() {
+        let mut keymap = KeyMap::default();
+        let from = KeySpec::parse("j").unwrap();
+        keymap.map(Modal::Normal, from, KeySpec::parse("k").unwrap());
+        assert!(keymap.unmap(Modal::Normal, from).is_some());
+
+        let key = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(keymap.translate(Modal::Normal, key), key);
+    }
+
+    #[test]
+    fn test_modeline_sets_filetype_and_tabstop() {
+        let mut editor = crate::editor::tests::new_test_editor(&[
+            "print('hello')",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "# vim: set ft=rust ts=2:",
+        ]);
+        editor.apply_modelines();
+        assert_eq!(editor.file_type, FileType::Rust);
+        assert_eq!(editor.options.tabstop, 2);
+    }
+
+    #[test]
+    fn test_modeline_disabled() {
+        let mut editor = crate::editor::tests::new_test_editor(&["# vim: set ts=2:"]);
+        editor.options.modeline = false;
+        editor.apply_modelines();
+        assert_eq!(editor.options.tabstop, EditorOptions::default().tabstop);
+    }
+
+    #[test]
+    fn test_apply_config() {
+        let mut editor = crate::editor::tests::new_test_editor(&[""]);
+        let config = Config {
+            iskeyword: Some("_,-".to_string()),
+            shiftwidth: Some(2),
+            ..Config::default()
+        };
+        editor.apply_config(&config).unwrap();
+        assert_eq!(editor.options.shiftwidth, 2);
+        assert!(editor.options.is_keyword('-'));
+    }
+
+    #[test]
+    fn test_filetype_iskeyword_from_config() {
+        let config: Config = toml::from_str("[filetype.lua]\niskeyword = \"_,-\"\n").unwrap();
+        let mut editor = crate::editor::tests::new_test_editor(&[""]);
+        editor.apply_config(&config).unwrap();
+        assert!(!editor.options.is_keyword('-'));
+        editor.set_file_type(FileType::Lua);
+        assert!(editor.options.is_keyword('-'));
+    }
+}
@@ -3,6 +3,44 @@ use std::collections::{HashMap, VecDeque};
 
 const MAX_NAMED_REGISTERS: usize = 26;
 const MAX_NUMBERED_REGISTERS: usize = 10;
+/// Amount of characters of a register shown by `:registers`.
+const REGISTER_PREVIEW_LEN: usize = 50;
+
+/// Text stored in a register, built from either a `&str` or the characters themselves.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RegisterContent(Vec<char>);
+
+impl From<Vec<char>> for RegisterContent {
+    fn from(chars: Vec<char>) -> Self {
+        Self(chars)
+    }
+}
+
+impl From<&str> for RegisterContent {
+    fn from(text: &str) -> Self {
+        Self(text.chars().collect())
+    }
+}
+
+impl From<String> for RegisterContent {
+    fn from(text: String) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+/// Shortens register content to `REGISTER_PREVIEW_LEN` characters for display, control
+/// characters are shown in caret notation, e.g. `^J` for a newline.
+fn register_preview(content: &[char]) -> String {
+    content
+        .iter()
+        .flat_map(|&ch| match ch {
+            '\u{7f}' => vec!['^', '?'],
+            ch if ch.is_ascii_control() => vec!['^', char::from(b'@' + ch as u8)],
+            ch => vec![ch],
+        })
+        .take(REGISTER_PREVIEW_LEN)
+        .collect()
+}
 
 pub struct CopyRegister {
     named_registers: HashMap<char, Vec<char>>,
@@ -24,8 +62,8 @@ impl Default for CopyRegister {
 }
 
 impl CopyRegister {
-    pub fn yank(&mut self, text: impl Into<Vec<char>>, named: Option<char>) -> Result<()> {
-        let mut text = text.into();
+    pub fn yank(&mut self, text: impl Into<RegisterContent>, named: Option<char>) -> Result<()> {
+        let RegisterContent(mut text) = text.into();
 
         if let Some(reg) = named {
             if !reg.is_alphabetic() || !reg.is_ascii_lowercase() {
@@ -46,16 +84,82 @@ impl CopyRegister {
     fn unnamed_register_mut(&mut self) -> &mut Vec<char> {
         &mut self.numbered_register[0]
     }
+    /// Looks up the register `named`, the unnamed one for `None`. Digits select the numbered
+    /// registers, `1` holding the most recent deletion.
     pub fn get_from_register(&self, named: Option<char>) -> Result<&Vec<char>> {
-        named.map_or_else(
-            || Ok(self.unnamed_register()),
-            |reg| self.named_registers.get(&reg).ok_or(Error::PatternNotFound),
-        )
+        match named {
+            None => Ok(self.unnamed_register()),
+            Some(reg @ '0'..='9') => {
+                let index = reg.to_digit(10).expect("Register is a digit.") as usize;
+                self.numbered_register
+                    .get(index)
+                    .ok_or(Error::PatternNotFound)
+            }
+            Some(reg) => self.named_registers.get(&reg).ok_or(Error::PatternNotFound),
+        }
     }
-    pub fn push_into_numbered_registers(&mut self, text: impl Into<Vec<char>>) {
-        self.numbered_register.insert(1, text.into());
+    /// Stores deleted text in register `1`, shifting the previous deletions up by one. The
+    /// oldest one is dropped once all of `1` to `9` are taken.
+    pub fn push_into_numbered_registers(&mut self, text: impl Into<RegisterContent>) {
+        self.numbered_register.insert(1, text.into().0);
         if self.numbered_register.len() > MAX_NUMBERED_REGISTERS {
             self.numbered_register.pop_back();
         }
     }
+    /// Names and previews of the registers listed by `:registers`: the unnamed one, `"`, even if
+    /// empty, then the non-empty numbered registers `1` to `9` and named registers `a` to `z`.
+    /// Register `0` is the unnamed one and isn't listed twice.
+    pub fn all_registers(&self) -> Vec<(char, String)> {
+        let numbered = self
+            .numbered_register
+            .iter()
+            .zip('0'..='9')
+            .skip(1)
+            .map(|(content, name)| (name, content));
+        let named = ('a'..='z').filter_map(|name| Some((name, self.named_registers.get(&name)?)));
+        std::iter::once(('"', self.unnamed_register()))
+            .chain(
+                numbered
+                    .chain(named)
+                    .filter(|(_, content)| !content.is_empty()),
+            )
+            .map(|(name, content)| (name, register_preview(content)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbered_registers_shift() {
+        let mut register = CopyRegister::default();
+        assert!(register.get_from_register(Some('1')).is_err());
+        for n in 1..=10 {
+            register.push_into_numbered_registers(n.to_string());
+        }
+        assert_eq!(register.get_from_register(Some('1')).unwrap(), &['1', '0']);
+        assert_eq!(register.get_from_register(Some('9')).unwrap(), &['2']);
+        assert!(register.get_from_register(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_all_registers_previews() {
+        let mut register = CopyRegister::default();
+        assert_eq!(register.all_registers(), [('"', String::new())]);
+
+        register.yank("\nline\tend", Some('b')).unwrap();
+        register.yank("x".repeat(60), Some('a')).unwrap();
+        register.push_into_numbered_registers("deleted");
+        assert_eq!(
+            register.all_registers(),
+            [
+                ('"', String::new()),
+                ('1', "deleted".to_string()),
+                ('a', "x".repeat(REGISTER_PREVIEW_LEN)),
+                ('b', "^Jline^Iend".to_string()),
+            ]
+        );
+    }
 }
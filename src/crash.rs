@@ -0,0 +1,121 @@
+use std::{
+    fmt::Write as _,
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{notification_history, LineCol};
+
+/// Editor state at the time of the last main loop iteration, included in crash reports.
+static CRASH_CONTEXT: Mutex<Option<CrashContext>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CrashContext {
+    mode: String,
+    pos: LineCol,
+}
+
+/// Records the editor state that ends up in the crash report should the editor panic.
+pub fn update_crash_context(mode: impl std::fmt::Display, pos: LineCol) {
+    if let Ok(mut context) = CRASH_CONTEXT.lock() {
+        *context = Some(CrashContext {
+            mode: mode.to_string(),
+            pos,
+        });
+    }
+}
+
+/// Marks the crash reports in `dir` nobody was told about yet as seen, by renaming
+/// `crash_<timestamp>.log` to `crash_<timestamp>.seen.log`. Returns how many there were, so a
+/// crash is only reported on the first start after it.
+pub fn mark_crash_reports_seen(dir: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let stem = name.strip_prefix("crash_")?.strip_suffix(".log")?;
+            if stem.ends_with(".seen") {
+                return None;
+            }
+            std::fs::rename(entry.path(), dir.join(format!("crash_{stem}.seen.log"))).ok()
+        })
+        .count()
+}
+
+/// Writes a crash report for the panic described by `info` into `dir`, returning its path.
+///
+/// # Errors
+/// Returns the I/O error if the directory or the report can't be written.
+pub fn write_crash_report(dir: &Path, info: &PanicHookInfo) -> std::io::Result<PathBuf> {
+    let location = info
+        .location()
+        .map_or(("<unknown>", 0), |loc| (loc.file(), loc.line()));
+    let cause = info
+        .payload()
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| info.payload().downcast_ref::<&str>().copied())
+        .unwrap_or("<cause unknown>");
+    write_report(dir, cause, location)
+}
+
+/// Writes a crash report for a panic with the message `cause` at the file and line `location`.
+fn write_report(dir: &Path, cause: &str, (file, line): (&str, u32)) -> std::io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut report = format!("timestamp: {timestamp}\npanic: {cause}\nlocation: {file}:{line}\n");
+    // The panic may have happened while the context was being updated, don't panic again then
+    match CRASH_CONTEXT
+        .lock()
+        .ok()
+        .and_then(|context| context.clone())
+    {
+        Some(context) => {
+            let _ = write!(report, "mode: {}\ncursor: {}\n", context.mode, context.pos);
+        }
+        None => report.push_str("mode: <unknown>\ncursor: <unknown>\n"),
+    }
+    report.push_str("notifications:\n");
+    for notification in notification_history() {
+        let _ = writeln!(report, "  {notification}");
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash_{timestamp}.log"));
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_debug_messages, modals::Modal, notif_bar};
+
+    #[test]
+    fn test_crash_report_contents() {
+        let dir = std::env::temp_dir().join(format!("neotext_crash_{}", std::process::id()));
+        update_crash_context(Modal::Insert, LineCol { line: 3, col: 7 });
+        notif_bar!("before the crash";);
+
+        let report_path = write_report(&dir, "something went wrong", ("src/crash.rs", 42)).unwrap();
+        let report = std::fs::read_to_string(report_path).unwrap();
+
+        assert_eq!(mark_crash_reports_seen(&dir), 1);
+        assert_eq!(mark_crash_reports_seen(&dir), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(report.contains("panic: something went wrong"));
+        assert!(report.contains("location: src/crash.rs:42"));
+        assert!(report.contains(&format!("mode: {}", Modal::Insert)));
+        assert!(report.contains("cursor: 3:7"));
+        assert!(report.contains("before the crash"));
+    }
+}
@@ -158,7 +158,17 @@ impl Cursor {
                 self.plane = CursorPlane::CommandBar;
                 self.pos = LineCol { line: 0, col: 0 };
             }
-            Modal::Normal | Modal::Insert | Modal::Visual | Modal::VisualLine => {
+            Modal::Terminal => {
+                self.plane = CursorPlane::Terminal;
+                self.pos = LineCol { line: 0, col: 0 };
+            }
+            Modal::Normal
+            | Modal::Insert
+            | Modal::Visual
+            | Modal::VisualLine
+            | Modal::VisualBlock
+            | Modal::CmdHistory
+            | Modal::Picker => {
                 self.plane = CursorPlane::Text;
                 self.pos = self.last_text_mode_pos;
             }
@@ -4,27 +4,49 @@ use crate::bars::{
     NOTIFICATION_BAR, NOTIFICATION_BAR_Y_LOCATION,
 };
 use crate::buffer::TextBuffer;
+use crate::commands::parse_command;
+use crate::common::RegexPattern;
+use crate::completion::CompletionMenu;
+use crate::config::KeyMap;
 use crate::copy_register::CopyRegister;
+use crate::crash::update_crash_context;
 use crate::cursor::{Cursor, Selection};
-use crate::highlighter::{Highlighter, Style};
-use crate::modals::{FindMode, Modal};
-use crate::utils::draw_ascii_art;
+use crate::folds::Folds;
+use crate::highlighter::Highlighter;
+use crate::history::history_path;
+use crate::lsp::{overlay_semantic_tokens, FileType, LspManager, SemanticToken};
+use crate::marks::marks_path;
+use crate::modals::{
+    CharMotionKind, CmdHistoryWindow, FindMode, LinewiseInsert, Modal, PendingOperator,
+    RecordedAction,
+};
+use crate::options::EditorOptions;
+use crate::picker::FilePicker;
+use crate::quickfix::QuickfixList;
+use crate::renderer::{EditorState, Renderer, TerminalRenderer};
+use crate::snippets::SnippetEngine;
+use crate::swap::swap_dir;
+use crate::tasks::{BackgroundTasks, EditorMessage};
+use crate::theme::{MonoAndromeda, Theme};
+use crate::utils::{detect_filetype, git_diff_lines, is_readonly, DiffStatus};
 use crate::viewport::Viewport;
-use crate::{get_debug_messages, notif_bar, Error, LineCol, Result};
+use crate::{error, notif_bar, Error, LineCol, Result, NOTIFICATION_QUEUE_LEN};
 use crossterm::{
     event::{self, Event, KeyCode},
-    style::{self, Color, ResetColor, SetBackgroundColor, SetForegroundColor},
-    terminal::{self, ClearType},
+    terminal,
 };
 use rangemap::RangeMap;
-use std::{collections::VecDeque, io::Write};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-const MAX_HISTORY: usize = 50;
-const WINDOW_MAX_CURSOR_PROXIMITY_TO_WINDOW_BOUNDS: usize = 6;
+/// How long to wait for terminal input before checking for messages from background tasks.
+const MESSAGE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How many lines the search for a matching bracket looks through before giving up.
+const BRACKET_SEARCH_MAX_LINES: usize = 1000;
 pub const LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS: usize = 4;
-pub const LINE_NUMBER_RESERVED_COLUMNS: usize = 5;
-pub const LEFT_RESERVED_COLUMNS: usize =
-    LINE_NUMBER_RESERVED_COLUMNS + LINE_NUMBER_RESERVED_COLUMNS;
 
 /// The main editor is used as the main API for all commands
 pub struct Editor<Buff: TextBuffer> {
@@ -43,7 +65,69 @@ pub struct Editor<Buff: TextBuffer> {
     // target file
     pub(crate) is_initial_launch: bool,
     pub(crate) copy_register: CopyRegister,
-    highlighter: Highlighter,
+    /// Anchor, cursor position and mode of the last visual selection, restored by `gv`.
+    pub(crate) last_visual_selection: Option<(LineCol, LineCol, Modal)>,
+    /// Register selected with `"` for the next yank or paste.
+    pub(crate) pending_register: Option<char>,
+    pub(crate) options: EditorOptions,
+    pub(crate) file_type: FileType,
+    /// Insert mode abbreviations, mapping the typed trigger to its expansion.
+    pub(crate) abbreviations: HashMap<String, String>,
+    pub(crate) snippets: SnippetEngine,
+    /// Insert mode word completion popup, `None` while closed.
+    pub(crate) completion: Option<CompletionMenu>,
+    /// Path of the edited file, `None` if the editor was opened without a target.
+    pub(crate) file_path: Option<PathBuf>,
+    pub(crate) tasks: BackgroundTasks,
+    pub(crate) keymap: KeyMap,
+    /// Lines changed since the last commit, drawn as markers in the gutter.
+    pub(crate) git_diff: HashMap<usize, DiffStatus>,
+    pub(crate) quickfix: QuickfixList,
+    /// When the running `:make` was started, `None` if no build is running.
+    pub(crate) build_started: Option<Instant>,
+    /// Results of the last `:Grep`, `None` when the picker is closed.
+    pub(crate) picker: Option<FilePicker>,
+    /// Messages waiting to be shown in the notification bar, oldest first.
+    pub(crate) notification_queue: VecDeque<String>,
+    /// Language servers of the session, one per file type.
+    pub(crate) lsp: LspManager,
+    /// Semantic tokens from the language server, drawn over the tree-sitter highlights.
+    pub(crate) semantic_tokens: Vec<SemanticToken>,
+    /// Named marks of the edited file, set with `m`.
+    pub(crate) marks: HashMap<char, LineCol>,
+    /// File the marks are persisted to, `None` keeps them in memory only.
+    pub(crate) marks_file: Option<PathBuf>,
+    /// Directory swap files are written to, `None` disables them.
+    pub(crate) swap_dir: Option<PathBuf>,
+    /// Characters typed in Insert mode since the swap file was last written.
+    pub(crate) chars_since_swap: usize,
+    /// Query typed in Find mode and how many times it occurs, shown next to it.
+    pub(crate) find_match_status: Option<(String, String)>,
+    /// Pattern and direction of the last completed search, repeated by `n` and `N`.
+    pub(crate) last_search: Option<(String, FindMode)>,
+    /// Positions the cursor jumped away from, most recent last.
+    pub(crate) jump_list: Vec<LineCol>,
+    /// Where Insert mode was last left, returned to by `gi`.
+    pub(crate) last_insert_pos: Option<LineCol>,
+    /// Target and kind of the last `f`, `F`, `t` or `T`, repeated by `;` and `,`.
+    pub(crate) last_char_motion: Option<(char, CharMotionKind)>,
+    /// Operator waiting for the motion it acts on, such as the fold of `zf`.
+    pub(crate) pending_operator: Option<PendingOperator>,
+    /// Last change made in Normal mode, repeated by `.`.
+    pub(crate) last_action: Option<RecordedAction>,
+    pub(crate) folds: Folds,
+    /// Lines the text typed in the current Insert session is repeated on, see `LinewiseInsert`.
+    pub(crate) linewise_insert: Option<LinewiseInsert>,
+    /// The command history window, while in `Modal::CmdHistory`.
+    pub(crate) cmd_history: CmdHistoryWindow,
+    /// Start and byte length of the search matches highlighted in the text.
+    pub(crate) search_highlights: Vec<(LineCol, usize)>,
+    /// Highlighter for the language of the edited file, `None` if there is no grammar for it.
+    pub(crate) highlighter: Option<Highlighter>,
+    pub(crate) theme: Box<dyn Theme>,
+    pub(crate) renderer: Box<dyn Renderer>,
+    /// Title last set on the terminal window, so it's only written when it changes.
+    window_title: String,
 }
 
 impl<Buff: TextBuffer> Editor<Buff> {
@@ -55,9 +139,9 @@ impl<Buff: TextBuffer> Editor<Buff> {
     /// # Returns
     /// A new `MainEditor` instance initialized with the given buffer and default cursor position.
     pub fn new(buffer: Buff, launch_without_target: bool) -> Self {
-        Self {
-            highlighter: Highlighter::new(buffer.get_coalesced_bytes())
-                .expect("Tree sitter needs to parse."),
+        let mut editor = Self {
+            highlighter: None,
+            theme: Box::new(MonoAndromeda),
             buffer,
             prev_pos: LineCol { line: 0, col: 0 },
             cursor: Cursor::default(),
@@ -69,16 +153,424 @@ impl<Buff: TextBuffer> Editor<Buff> {
             viewport: Viewport::default(),
             is_initial_launch: launch_without_target,
             copy_register: CopyRegister::default(),
+            pending_register: None,
+            last_visual_selection: None,
+            options: EditorOptions::default(),
+            file_type: FileType::default(),
+            abbreviations: HashMap::new(),
+            snippets: SnippetEngine::default(),
+            completion: None,
+            file_path: None,
+            tasks: BackgroundTasks::default(),
+            keymap: KeyMap::default(),
+            git_diff: HashMap::new(),
+            quickfix: QuickfixList::default(),
+            build_started: None,
+            picker: None,
+            notification_queue: VecDeque::new(),
+            lsp: LspManager::default(),
+            semantic_tokens: Vec::new(),
+            marks: HashMap::new(),
+            marks_file: marks_path(),
+            swap_dir: swap_dir(),
+            chars_since_swap: 0,
+            find_match_status: None,
+            last_search: None,
+            jump_list: Vec::new(),
+            linewise_insert: None,
+            cmd_history: CmdHistoryWindow::default(),
+            last_insert_pos: None,
+            last_char_motion: None,
+            pending_operator: None,
+            last_action: None,
+            folds: Folds::default(),
+            search_highlights: Vec::new(),
+            renderer: Box::new(TerminalRenderer::default()),
+            window_title: String::new(),
+        };
+        if cfg!(not(test)) {
+            if let Some(path) = history_path() {
+                if let Err(e) = editor.load_history_from(&path) {
+                    error!("Failed to load the history file: {e}");
+                }
+            }
+        }
+        editor
+    }
+
+    /// Sets the file type of the edited buffer and rebuilds the highlighter for its language.
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        // The new highlighter parses the current text, earlier edits don't apply to its tree
+        for edit in self.buffer.take_pending_edits() {
+            self.folds.apply_edit(&edit);
+        }
+        self.highlighter =
+            match Highlighter::for_file_type(self.buffer.get_coalesced_bytes(), file_type) {
+                Ok(highlighter) => highlighter,
+                Err(e) => {
+                    error!("Failed to load the grammar of {file_type:?}: {e:?}");
+                    None
+                }
+            };
+        self.file_type = file_type;
+        self.options.use_filetype_overrides(file_type);
+    }
+
+    pub fn set_file_path(&mut self, path: impl Into<PathBuf>) {
+        self.file_path = Some(path.into());
+    }
+
+    /// Sets how often a dirty buffer is auto-saved and restarts the timer, `None` disables it.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn set_auto_save_interval(&mut self, interval: Option<Duration>) -> Result<()> {
+        self.options.auto_save_interval = interval;
+        self.tasks.set_auto_save_interval(interval)
+    }
+
+    /// Sets how often the buffer is written to its swap file and restarts the timer, `None`
+    /// disables it.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn set_swap_interval(&mut self, interval: Option<Duration>) -> Result<()> {
+        self.options.swap_interval = interval;
+        self.tasks.set_swap_interval(interval)
+    }
+
+    /// Writes the normal text buffer to `path`, terminating every line with a newline.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file can't be written.
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        let mut content = self.buffer.get_normal_text().join("\n");
+        content.push('\n');
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Writes the buffer to `path`, or to the edited file if no path is given. A given `path`
+    /// becomes the edited file. Once the edited file is saved, its autosave file is no longer
+    /// needed and gets removed. With `trim_on_save` the trailing whitespace of every line is
+    /// removed beforehand.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidInput` if no path is given and the editor has no file open, and
+    /// `Error::Io` if the file can't be written.
+    pub fn write_file(&mut self, path: Option<PathBuf>) -> Result<()> {
+        let path = path
+            .or_else(|| self.file_path.clone())
+            .ok_or(Error::InvalidInput)?;
+        if self.options.trim_on_save {
+            self.trim_trailing_whitespace()?;
+        }
+        self.save_to_file(&path)?;
+        // The autosave of the previous file is covered by the written one as well
+        self.remove_autosave();
+        self.file_path = Some(path);
+        self.buffer.mark_saved();
+        self.refresh_git_diff();
+        Ok(())
+    }
+
+    /// Removes the whitespace at the end of every line, keeping the cursor within its line.
+    fn trim_trailing_whitespace(&mut self) -> Result<()> {
+        for line in 0..=self.buffer.max_line() {
+            let content = self.buffer.line(line)?;
+            let (trimmed, len) = (content.trim_end().len(), content.len());
+            if trimmed < len {
+                self.buffer
+                    .delete_selection(LineCol { line, col: trimmed }, LineCol { line, col: len })?;
+            }
+        }
+        self.force_within_bounds();
+        Ok(())
+    }
+
+    /// Replaces the buffer with the contents of the file at `path`, which doesn't need to exist
+    /// yet, and drops the undo history of the previous file. Refuses to discard unsaved changes
+    /// unless `force` is set, which also opens files without write permission in read-write
+    /// mode.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file exists but can't be read.
+    pub fn open_file(&mut self, path: PathBuf, force: bool) -> Result<()> {
+        if self.buffer.is_dirty() && !force {
+            notif_bar!(self => "No write since last change (add ! to override)";);
+            return Ok(());
+        }
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        self.remove_swap();
+        if let Err(e) = self.save_marks() {
+            error!("Failed to save the marks: {e}");
+        }
+        self.marks.clear();
+        let lines = match content.lines().map(String::from).collect::<Vec<_>>() {
+            lines if lines.is_empty() => vec![String::new()],
+            lines => lines,
+        };
+        self.buffer.reset_text(lines);
+        self.go(LineCol::default());
+        self.buffer.mark_saved();
+        self.is_initial_launch = false;
+        self.options.readonly = !force && is_readonly(&path);
+        self.set_file_type(detect_filetype(
+            &path,
+            content.lines().next().unwrap_or_default(),
+        ));
+        self.file_path = Some(path);
+        self.apply_modelines();
+        self.open_document();
+        self.refresh_git_diff();
+        self.recover_swap()?;
+        if let Err(e) = self.load_marks() {
+            error!("Failed to load the marks: {e}");
+        }
+        if let Err(e) = self.watch_file_changes() {
+            error!("Failed to watch the edited file: {e}");
+        }
+        Ok(())
+    }
+
+    /// URI of the edited file as the language server knows it.
+    pub(crate) fn document_uri(&self) -> Option<String> {
+        let path = self.file_path.as_ref()?;
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+        Some(format!("file://{}", path.display()))
+    }
+
+    /// Sends the whole edited file to the language server of its file type, starting the server
+    /// if none runs for the type yet. Edits made before are dropped, the server gets them as
+    /// part of the text.
+    pub fn open_document(&mut self) {
+        let _ = self.buffer.take_pending_changes();
+        let Some(uri) = self.document_uri() else {
+            return;
+        };
+        let text = self.buffer.get_normal_text().join("\n");
+        let Some(client) = self.lsp.get_or_start(self.file_type) else {
+            return;
+        };
+        match client.did_open(&uri, &text) {
+            Ok(()) | Err(Error::LspConnectionLost) => {}
+            Err(e) => error!("Failed to open the document: {e}"),
+        }
+    }
+
+    /// Sends the edits made since the last call to the language server of the file type. They're
+    /// dropped when there's no file or no server to send them to.
+    pub(crate) fn sync_document_changes(&mut self) {
+        let changes = self.buffer.take_pending_changes();
+        let Some(uri) = self.document_uri() else {
+            return;
+        };
+        let Some(client) = self.lsp.get_mut(self.file_type) else {
+            return;
+        };
+        match client.did_change(&uri, &changes) {
+            Ok(()) | Err(Error::LspConnectionLost) => {}
+            Err(e) => error!("Failed to send the document changes: {e}"),
+        }
+    }
+
+    /// Recomputes the git changes of the edited file shown in the gutter.
+    pub fn refresh_git_diff(&mut self) {
+        self.git_diff = match &self.file_path {
+            Some(path) => git_diff_lines(path).unwrap_or_else(|e| {
+                error!("Failed to run git diff: {e}");
+                HashMap::new()
+            }),
+            None => HashMap::new(),
+        };
+    }
+
+    /// Blocks until the next terminal event, handling messages from background tasks while
+    /// waiting. Key presses are translated through the key mappings of the current mode.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if reading from the terminal fails.
+    pub(crate) fn read_event(&mut self) -> Result<Event> {
+        while !event::poll(MESSAGE_POLL_INTERVAL)? {
+            let mut handled = false;
+            while let Some(message) = self.tasks.try_recv() {
+                self.handle_message(message)?;
+                handled = true;
+            }
+            if handled || self.build_started.is_some() {
+                self.redraw_status()?;
+            }
+        }
+        Ok(match event::read()? {
+            Event::Key(key_event) => Event::Key(self.keymap.translate(self.mode, key_event)),
+            otherwise => otherwise,
+        })
+    }
+
+    /// Reacts to a message sent by one of the background tasks.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if a prompt shown in response fails to draw or read input.
+    pub(crate) fn handle_message(&mut self, message: EditorMessage) -> Result<()> {
+        match message {
+            EditorMessage::TriggerAutoSave => self.auto_save(),
+            EditorMessage::TriggerSwap => self.write_swap(),
+            EditorMessage::FileModified => self.on_file_modified()?,
+            EditorMessage::BuildFinished {
+                output,
+                dir,
+                success,
+            } => self.on_build_finished(&output, &dir, success),
+            EditorMessage::SearchMatches { query, matches } => {
+                self.on_search_matches(&query, &matches);
+            }
+            EditorMessage::GrepMatch { query, entry } => self.on_grep_match(&query, entry),
+            EditorMessage::GrepFinished { query, error } => self.on_grep_finished(&query, error),
+        }
+        Ok(())
+    }
+
+    /// Finds the bracket matching the one at `pos`, searching forwards from opening and backwards
+    /// from closing brackets while skipping nested pairs. Gives up after
+    /// `BRACKET_SEARCH_MAX_LINES` lines.
+    pub fn find_matching_bracket(&self, pos: LineCol) -> Option<LineCol> {
+        let lines = self.buffer.get_normal_text();
+        let under = lines.get(pos.line)?.get(pos.col..)?.chars().next()?;
+        let (partner, forwards) = match under {
+            '(' => (')', true),
+            '[' => (']', true),
+            '{' => ('}', true),
+            ')' => ('(', false),
+            ']' => ('[', false),
+            '}' => ('{', false),
+            _ => return None,
+        };
+
+        // Counts the brackets like the one at `pos` that are still waiting for their partner
+        let mut depth = 0usize;
+        let mut visit = |line: usize, col: usize, ch: char| {
+            if ch == under {
+                depth += 1;
+            } else if ch == partner {
+                depth -= 1;
+            }
+            (depth == 0).then_some(LineCol { line, col })
+        };
+
+        if forwards {
+            let last = lines.len().min(pos.line + BRACKET_SEARCH_MAX_LINES);
+            (pos.line..last).find_map(|line| {
+                let skip = if line == pos.line { pos.col } else { 0 };
+                lines[line][skip..]
+                    .char_indices()
+                    .find_map(|(col, ch)| visit(line, skip + col, ch))
+            })
+        } else {
+            let first = pos.line.saturating_sub(BRACKET_SEARCH_MAX_LINES - 1);
+            (first..=pos.line).rev().find_map(|line| {
+                let end = if line == pos.line {
+                    pos.col + under.len_utf8()
+                } else {
+                    lines[line].len()
+                };
+                lines[line][..end]
+                    .char_indices()
+                    .rev()
+                    .find_map(|(col, ch)| visit(line, col, ch))
+            })
+        }
+    }
+
+    /// Queues a message for the notification bar, dropping the oldest one if the queue is full.
+    pub(crate) fn notify(&mut self, message: impl Into<String>) {
+        self.notification_queue.push_back(message.into());
+        if self.notification_queue.len() > NOTIFICATION_QUEUE_LEN {
+            self.notification_queue.pop_front();
         }
     }
 
-    /// Stores a command in the search history
-    fn add_to_search_history(&mut self, command: impl Into<String>) {
-        self.forwards_history.push_front(command.into());
-        if self.forwards_history.len() > MAX_HISTORY {
-            self.forwards_history.pop_back();
+    /// Redraws the info and notification bars, the quickfix window and the picker without
+    /// touching the cursor, so that background progress shows up while waiting for input.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if drawing to the terminal fails.
+    fn redraw_status(&mut self) -> Result<()> {
+        crossterm::queue!(self.viewport.terminal, crossterm::cursor::SavePosition)?;
+        self.draw_quickfix()?;
+        self.draw_picker()?;
+        let pos = self.pos();
+        let file_label = self.file_label();
+        draw_bar(&mut self.viewport.terminal, &INFO_BAR, |term_width, _| {
+            get_info_bar_content(term_width, &self.mode, &file_label, pos)
+        })?;
+        match self.mode {
+            Modal::Command => {}
+            Modal::Find(_) => {
+                let content = self.command_bar_content();
+                draw_bar(&mut self.viewport.terminal, &COMMAND_BAR, content)?;
+            }
+            _ => {
+                draw_bar(&mut self.viewport.terminal, &NOTIFICATION_BAR, |_, _| {
+                    get_notif_bar_content(&mut self.notification_queue)
+                })?;
+            }
+        }
+        crossterm::execute!(self.viewport.terminal, crossterm::cursor::RestorePosition)?;
+        Ok(())
+    }
+
+    /// Shows a yes/no question in the notification bar and waits for the answer. Anything but
+    /// `y` counts as no.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if drawing or reading from the terminal fails.
+    pub(crate) fn prompt(&mut self, question: &str) -> Result<bool> {
+        draw_bar(&mut self.viewport.terminal, &NOTIFICATION_BAR, |_, _| {
+            question.to_string()
+        })?;
+        loop {
+            if let Event::Key(key_event) = event::read()? {
+                return Ok(matches!(key_event.code, KeyCode::Char('y' | 'Y')));
+            }
+        }
+    }
+
+    /// Stores a command in the command history, unless nothing was typed after the colon.
+    pub(crate) fn add_to_command_history(&mut self, command: &str) {
+        if command.trim_start_matches(':').trim().is_empty() {
+            return;
+        }
+        self.command_history.push_front(command.to_string());
+        if self.command_history.len() > self.options.max_history {
+            self.command_history.pop_back();
         }
     }
+
+    /// Stores a command in the search history of its direction
+    fn add_to_search_history(&mut self, command: impl Into<String>, find_mode: FindMode) {
+        let history = match find_mode {
+            FindMode::Forwards => &mut self.forwards_history,
+            FindMode::Backwards => &mut self.backwards_history,
+        };
+        history.push_front(command.into());
+        if history.len() > self.options.max_history {
+            history.pop_back();
+        }
+    }
+    /// Entry `nth` of the command history, counted from the most recent one. The 0th entry is
+    /// the empty command line the command bar starts with.
+    fn get_from_command_history(&self, nth: u8) -> Option<String> {
+        match nth {
+            0 => Some(String::new()),
+            nth => self.command_history.get((nth - 1) as usize).cloned(),
+        }
+    }
+    /// Entry `nth` of the search history of `find_mode`, counted from the most recent one. The
+    /// 0th entry is the empty query the command bar starts with.
     fn get_from_search_history(&self, nth: u8, find_mode: FindMode) -> Option<String> {
         if nth == 0 {
             return Some(String::new());
@@ -88,30 +580,19 @@ impl<Buff: TextBuffer> Editor<Buff> {
             FindMode::Backwards => self.backwards_history.get((nth - 1) as usize).cloned(),
         }
     }
-    fn replay_from_search_history(&self) -> Result<()> {
-        let pat = self
-            .forwards_history
-            .front()
-            .ok_or(Error::NoCommandAvailable)?;
-        let (flag, pat) = pat.split_at(1);
-        match flag {
-            "/" => self.buffer.find(pat, self.last_normal_pos())?,
-            "?" => self.buffer.rfind(pat, self.last_normal_pos())?,
-            otherwise => Err(Error::ProgrammingBug {
-                descr: format!(
-                    "Only commands starting with `?` or `/` should be found. Instead got ``{otherwise}"
-                ),
-            })?,
-        };
-        Ok(())
-    }
-
     /// If the cursor is in an invalid position, applies a cursor movement that results in a valid position within the buffer bounds.
     pub fn force_within_bounds(&mut self) {
+        // The cursor is in the terminal buffer, the text bounds don't apply to it
+        if self.mode.is_terminal() {
+            return;
+        }
         let original_pos = self.cursor.previous_pos;
         if self.pos().line > self.buffer.max_line() {
             self.cursor.pos = original_pos;
-            return;
+        }
+        // The previous position may have become invalid as well, e.g. after deleting lines
+        if self.pos().line > self.buffer.max_line() {
+            self.cursor.pos.line = self.buffer.max_line();
         }
         let new_pos = self.pos();
         let max_col = self.buffer.max_col(new_pos);
@@ -121,15 +602,36 @@ impl<Buff: TextBuffer> Editor<Buff> {
     }
 
     #[inline]
-    pub(crate) const fn pos(&self) -> LineCol {
+    pub const fn pos(&self) -> LineCol {
         self.cursor.pos
     }
 
+    #[inline]
+    pub const fn mode(&self) -> &Modal {
+        &self.mode
+    }
+
+    /// Content of the line `n`, `None` past the end of the buffer.
+    pub fn buffer_line(&self, n: usize) -> Option<&str> {
+        self.buffer.line(n).ok()
+    }
+
     #[inline]
     const fn last_normal_pos(&self) -> LineCol {
         self.cursor.last_text_mode_pos
     }
     pub(crate) fn set_mode(&mut self, modal: Modal) {
+        if self.mode.is_insert() && !modal.is_insert() {
+            self.last_insert_pos = Some(self.pos());
+        }
+        // A whole Insert session is undone at once
+        if modal.is_insert() && !self.mode.is_insert() {
+            self.buffer.save_state(self.pos());
+        }
+        if self.mode.is_any_visual() && !modal.is_any_visual() {
+            self.last_visual_selection =
+                Some((self.cursor.last_text_mode_pos, self.pos(), self.mode));
+        }
         self.cursor.mod_change(&modal);
         self.buffer.set_plane(&modal);
         self.mode = modal;
@@ -139,23 +641,98 @@ impl<Buff: TextBuffer> Editor<Buff> {
     pub(crate) fn go(&mut self, to: LineCol) {
         self.cursor.go(to);
     }
-    fn delete(&mut self) {
+    /// Checks whether the edit about to happen is refused because the buffer is read-only,
+    /// notifying the user if so. The command bar stays editable.
+    pub(crate) fn blocked_by_readonly(&mut self) -> bool {
+        let blocked =
+            self.options.readonly && !matches!(self.mode, Modal::Command | Modal::Find(_));
+        if blocked {
+            notif_bar!(self => "Cannot edit: read-only buffer";);
+        }
+        blocked
+    }
+
+    /// Name of the edited file as shown in the info bar, marked with `[RO]` when read-only and
+    /// followed by a spinner while `:make` runs.
+    pub(crate) fn file_label(&self) -> String {
+        let name = self.file_path.as_ref().map_or_else(
+            || "[No Name]".to_string(),
+            |path| path.display().to_string(),
+        );
+        let readonly = if self.options.readonly { " [RO]" } else { "" };
+        format!("{name}{readonly}{}", self.build_indicator())
+    }
+
+    /// Title of the terminal window, the name of the edited file marked with `*` while it has
+    /// unsaved changes.
+    pub fn window_title(&self) -> String {
+        let Some(path) = &self.file_path else {
+            return "[No File] - NeoText".to_string();
+        };
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let dirty = if self.buffer.is_dirty() { "*" } else { "" };
+        format!("{name}{dirty} - NeoText")
+    }
+
+    /// Sets the title of the terminal window if it changed since it was last set.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if writing to the terminal fails.
+    pub fn update_window_title(&mut self) -> Result<()> {
+        let title = self.window_title();
+        if title != self.window_title {
+            crossterm::execute!(
+                self.viewport.terminal,
+                crossterm::terminal::SetTitle(&title)
+            )?;
+            self.window_title = title;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn delete(&mut self) {
+        if self.blocked_by_readonly() {
+            return;
+        }
         match self.buffer.delete(self.pos()) {
             Ok(new_pos) => self.go(new_pos),
-            Err(Error::InvalidPosition) => panic!("Cursor found in a position it should never appear in: ({}), please contact the developers.", self.pos()),
             Err(Error::ImATeacup) => {}
-            Err(_) => panic!("UnexpectedError, please contact the developers.")
+            Err(e) => self.recover_from(&e),
         }
     }
     pub fn push(&mut self, c: char) {
+        if self.blocked_by_readonly() {
+            return;
+        }
         match self.buffer.insert(self.pos(), c) {
             Ok(new_pos) => self.go(new_pos),
-            Err(Error::InvalidPosition) => panic!("Cursor found in a position it should never appear in: ({}), please contact the developers.", self.pos()),
             Err(Error::ImATeacup) => {}
-            Err(_) => panic!("UnexpectedError, please contact the developers.")
+            Err(e) => self.recover_from(&e),
         };
     }
+
+    /// Recovers from an error that left the editor in an unexpected state, such as the cursor
+    /// pointing outside of the buffer, by logging it and snapping the cursor back into bounds.
+    pub(crate) fn recover_from(&mut self, e: &Error) {
+        match e {
+            Error::InvalidPosition => {
+                error!("Cursor found out of bounds at ({})", self.pos());
+                notif_bar!(self => "Cursor out of bounds — recovering";);
+            }
+            otherwise => {
+                error!("Recovering from {otherwise:?} at ({})", self.pos());
+                notif_bar!(self => otherwise.display_user(););
+            }
+        }
+        self.force_within_bounds();
+    }
     pub fn newline(&mut self) {
+        if self.blocked_by_readonly() {
+            return;
+        }
         self.cursor.pos = self.buffer.insert_newline(self.pos());
     }
 
@@ -177,6 +754,7 @@ impl<Buff: TextBuffer> Editor<Buff> {
         terminal::enable_raw_mode()?;
 
         loop {
+            update_crash_context(self.mode, self.pos());
             let empty_buffer = self.buffer.is_empty()
                 || self.buffer.line(0).is_err()
                 || self.buffer.line(0).unwrap().is_empty();
@@ -185,20 +763,32 @@ impl<Buff: TextBuffer> Editor<Buff> {
                 self.force_within_bounds();
                 self.control_view_window();
             } else {
-                notif_bar!("empty buffer");
+                notif_bar!(self => "empty buffer");
             }
+            self.update_window_title()?;
             match self.mode {
                 Modal::Command | Modal::Find(_) => {}
                 _ => self.buffer.clear_command(),
             }
-            match self.mode {
-                Modal::Normal => self.run_normal(None, None)?,
-                Modal::Find(find_mode) => self.run_find(find_mode)?,
-                Modal::Insert => self.run_insert()?,
-                Modal::Visual => self.run_normal(None, None)?,
-                Modal::VisualLine => self.run_normal(None, None)?,
-                Modal::Command => self.run_command_mode()?,
+            let result = match self.mode {
+                Modal::Normal => self.run_normal(None, None),
+                Modal::Find(find_mode) => self.run_find(find_mode),
+                Modal::Insert => self.run_insert(),
+                Modal::Visual => self.run_normal(None, None),
+                Modal::VisualLine => self.run_normal(None, None),
+                Modal::VisualBlock => self.run_normal(None, None),
+                Modal::Command => self.run_command_mode(),
+                Modal::CmdHistory => self.run_cmd_history(),
+                Modal::Picker => self.run_picker(),
+                Modal::Terminal => self.run_terminal(),
             };
+            // Only exiting and failing terminal I/O end the session, everything else is reported
+            match result {
+                Ok(()) => {}
+                Err(e @ (Error::ExitCall | Error::Io(_))) => return Err(e),
+                Err(e) => self.recover_from(&e),
+            }
+            self.sync_document_changes();
         }
     }
 
@@ -209,87 +799,104 @@ impl<Buff: TextBuffer> Editor<Buff> {
                 FindMode::Backwards => self.push('?'),
             }
         }
-        if self.run_command()? {
+        let finished = self.run_command()?;
+        if !finished && self.mode.is_find() {
+            self.request_match_count()?;
+        }
+        if finished {
+            self.find_match_status = None;
             let pat = &self.buffer.get_command_text()[0][1..];
+            if !pat.is_empty() {
+                self.last_search = Some((pat.to_string(), find_mode));
+                self.search_highlights.clear();
+            }
+            let (regex, literal) = RegexPattern::or_literal(pat);
+            let fell_back = literal && !pat.is_empty();
             let (history_pat, result) = match find_mode {
                 FindMode::Forwards => (
                     format!("/{pat}"),
-                    self.buffer.find(pat, self.last_normal_pos()),
+                    self.buffer.find(regex, self.last_normal_pos()),
                 ),
                 FindMode::Backwards => (
                     format!("?{pat}"),
-                    self.buffer.rfind(pat, self.last_normal_pos()),
+                    self.buffer.rfind(regex, self.last_normal_pos()),
                 ),
             };
-            self.add_to_search_history(history_pat);
+            self.add_to_search_history(history_pat, find_mode);
+            if fell_back {
+                notif_bar!(self => "Invalid regex, searching for the literal text";);
+            }
             match result {
-                Err(Error::InvalidInput) => notif_bar!("Empty find query.";),
-                Err(Error::PatternNotFound) => notif_bar!("No matches found for your pattern";),
-                Err(_) => {
-                    panic!("Unexpected error returned from find. Please contact the developers.")
+                Err(Error::InvalidInput) => notif_bar!(self => "Empty find query.";),
+                Err(e @ Error::PatternNotFound) => notif_bar!(self => e.display_user();),
+                Err(e) => self.recover_from(&e),
+                Ok(linecol) => {
+                    self.push_jump(self.last_normal_pos());
+                    self.cursor.last_text_mode_pos = linecol;
                 }
-                Ok(linecol) => self.cursor.last_text_mode_pos = linecol,
             }
             self.set_mode(Modal::Normal);
         }
         Ok(())
     }
 
-    fn run_command_mode(&mut self) -> Result<()> {
-        if self.buffer.is_command_empty() {
-            self.push(':');
-        }
-        if self.run_command()? {
-            match self.buffer.get_command_text()[0].as_str() {
-                ":q" => return Err(Error::ExitCall),
-                "/EXIT NOW" => std::process::exit(0),
-                _ => {}
-            };
-            self.set_mode(Modal::Normal);
-        }
-        Ok(())
-    }
-
-    fn run_insert(&mut self) -> Result<()> {
+    fn run_terminal(&mut self) -> Result<()> {
         self.draw_lines()?;
         let pos = self.pos();
+        let file_label = self.file_label();
         draw_bar(&mut self.viewport.terminal, &INFO_BAR, |term_width, _| {
-            get_info_bar_content(term_width, &self.mode, pos)
+            get_info_bar_content(term_width, &self.mode, &file_label, pos)
         })?;
         draw_bar(&mut self.viewport.terminal, &NOTIFICATION_BAR, |_, _| {
-            get_notif_bar_content()
+            get_notif_bar_content(&mut self.notification_queue)
         })?;
-        self.move_cursor();
-        self.force_within_bounds();
 
-        if let Event::Key(key_event) = event::read()? {
+        if let Event::Key(key_event) = self.read_event()? {
             match key_event.code {
                 KeyCode::Char(c) => self.push(c),
-                KeyCode::Enter => self.newline(),
-                KeyCode::Esc => self.set_mode(Modal::Normal),
+                KeyCode::Enter => {
+                    self.buffer.append_terminal_output("\n");
+                    let line = self.buffer.get_terminal_lines().len().saturating_sub(1);
+                    self.go(LineCol { line, col: 0 });
+                }
                 KeyCode::Backspace => self.delete(),
                 KeyCode::Left => self.cursor.bump_left(),
                 KeyCode::Right => self.cursor.bump_right(),
-                KeyCode::Up => self.cursor.bump_up(),
-                KeyCode::Down => self.cursor.bump_down(),
-                _ => {
-                    notif_bar!("nothing");
-                }
+                KeyCode::Esc => self.set_mode(Modal::Normal),
+                _ => {}
             }
-        };
+        }
         Ok(())
     }
+
+    fn run_command_mode(&mut self) -> Result<()> {
+        if self.buffer.is_command_empty() {
+            self.push(':');
+        }
+        if self.run_command()? {
+            let text = self.buffer.get_command_text()[0].clone();
+            self.add_to_command_history(&text);
+            self.set_mode(Modal::Normal);
+            match parse_command(&text) {
+                Ok(command) => self.execute_command(command)?,
+                Err(Error::ParsingError(descr)) => notif_bar!(self => descr;),
+                Err(otherwise) => Err(otherwise)?,
+            }
+        }
+        Ok(())
+    }
+
     /// Checks if the history pointer can move further in the current mode.
     ///
     /// This function determines whether there are more historical entries
     /// available in the direction the pointer is moving, based on the current mode.
-    fn can_move_history_pointer(&self) -> bool {
+    fn can_move_history_pointer(&mut self) -> bool {
         let history_len = match &self.mode {
             Modal::Command => self.command_history.len(),
             Modal::Find(FindMode::Forwards) => self.forwards_history.len(),
             Modal::Find(FindMode::Backwards) => self.backwards_history.len(),
             otherwise => {
-                notif_bar!(format!("Invalid mode `{otherwise}` asking for history pointer specs"););
+                notif_bar!(self => format!("Invalid mode `{otherwise}` asking for history pointer specs"););
                 return false;
             }
         };
@@ -297,21 +904,33 @@ impl<Buff: TextBuffer> Editor<Buff> {
         // To accomodate having an empty string always be the 0th element
         history_len >= self.history_pointer as usize
     }
-    fn navigate_history_backwards(&mut self) -> Result<()> {
+    /// Replaces the text of the command bar, moving the cursor to its end to keep typing there.
+    fn replace_command_bar(&mut self, text: String) {
+        let col = text.len();
+        self.buffer.replace_command_text(text);
+        self.go(LineCol { line: 0, col });
+    }
+
+    /// The entry `nth` places back in the history of the current mode, `0` being the empty
+    /// line typed before any entry was recalled.
+    fn get_from_history(&self, nth: u8) -> Result<Option<String>> {
+        match &self.mode {
+            Modal::Find(find_mode) => Ok(self.get_from_search_history(nth, *find_mode)),
+            Modal::Command => Ok(self.get_from_command_history(nth)),
+            otherwise => Err(Error::ProgrammingBug {
+                descr: format!("A different mode than Find or Command set as editor modal while working in the command bar `{otherwise}`"),
+            }),
+        }
+    }
+
+    pub(crate) fn navigate_history_backwards(&mut self) -> Result<()> {
         self.history_pointer += 1;
         if self.can_move_history_pointer() {
-            match &self.mode {
-                            Modal::Find(find_mode) => {
-                                let hist = self.get_from_search_history(self.history_pointer, *find_mode);
-                                if let Some(h) = hist {
-                                    if !h.is_empty() {
-                                        self.buffer.replace_command_text(h);
-                                    }
-                                }
-                            }
-                            Modal::Command => unimplemented!(),
-                            otherwise => Err(Error::ProgrammingBug {descr: format!("A different mode than Find or Command set as editor modal while working in the command bar `{otherwise}`")})?
-                        }
+            if let Some(entry) = self.get_from_history(self.history_pointer)? {
+                if !entry.is_empty() {
+                    self.replace_command_bar(entry);
+                }
+            }
         } else {
             self.history_pointer = self.history_pointer.saturating_sub(1);
         }
@@ -321,32 +940,26 @@ impl<Buff: TextBuffer> Editor<Buff> {
     fn navigate_history_forwards(&mut self) -> Result<()> {
         if self.history_pointer > 0 {
             self.history_pointer -= 1;
-            match &self.mode {
-                            Modal::Find(find_mode) => {
-                                let hist = self.get_from_search_history(self.history_pointer, *find_mode);
-                                if let Some(h) = hist {
-                                    self.buffer.replace_command_text(h);
-                                }
-                         }
-                            Modal::Command => unimplemented!(),
-                            otherwise => Err(Error::ProgrammingBug {descr: format!("A different mode than Find or Command set as editor modal while working in the command bar `{otherwise}`")})?
-                        }
+            if let Some(entry) = self.get_from_history(self.history_pointer)? {
+                self.replace_command_bar(entry);
+            }
         }
         Ok(())
     }
     fn run_command(&mut self) -> Result<bool> {
         self.draw_lines()?;
+        self.draw_quickfix()?;
         let pos = self.pos();
+        let file_label = self.file_label();
         draw_bar(&mut self.viewport.terminal, &INFO_BAR, |term_width, _| {
-            get_info_bar_content(term_width, &self.mode, pos)
-        })?;
-        draw_bar(&mut self.viewport.terminal, &COMMAND_BAR, |_, _| {
-            self.buffer.get_command_text()[0].to_string()
+            get_info_bar_content(term_width, &self.mode, &file_label, pos)
         })?;
+        let content = self.command_bar_content();
+        draw_bar(&mut self.viewport.terminal, &COMMAND_BAR, content)?;
         let (_, term_height) = terminal::size()?;
         self.move_command_cursor(term_height);
 
-        if let Event::Key(key_event) = event::read()? {
+        if let Event::Key(key_event) = self.read_event()? {
             if key_event.code != KeyCode::Up && key_event.code != KeyCode::Down {
                 self.history_pointer = 0;
             }
@@ -362,196 +975,141 @@ impl<Buff: TextBuffer> Editor<Buff> {
                     self.set_mode(Modal::Normal);
                 }
                 _ => {
-                    notif_bar!("nothing";);
+                    notif_bar!(self => "nothing";);
                 }
             }
         };
         Ok(false)
     }
 
+    /// Draws the lines of the terminal buffer in the viewport instead of the text, without any
+    /// highlighting.
+    fn draw_terminal_lines(&mut self) -> Result<()> {
+        let lines = self.buffer.get_terminal_lines();
+        let first = self.viewport.topleft.line;
+        let last = self
+            .viewport
+            .bottomright()
+            .line
+            .min(lines.len().saturating_sub(1));
+        let git_diff = HashMap::new();
+        let state = EditorState {
+            lines: lines
+                .get(first..=last)
+                .unwrap_or_default()
+                .iter()
+                .map(String::as_str)
+                .collect(),
+            first_line: first,
+            first_byte: 0,
+            cursor: self.pos(),
+            mode: self.mode,
+            style_map: RangeMap::new(),
+            git_diff: &git_diff,
+            selection: Selection::from(&self.cursor),
+            bracket_pair: None,
+            search_highlights: &[],
+            line_number_width: self.options.line_number_width,
+            number: self.options.number,
+            shiftwidth: self.options.shiftwidth,
+            show_indent_guides: false,
+            color_column: None,
+            folds: Vec::new(),
+            splash: false,
+        };
+        self.renderer.render_frame(&state)
+    }
+
     /// Draws the main content of the editor.
     ///
     /// This function:
-    /// 1. Clears the screen.
-    /// 2. Draws each line of the buffer content.
-    /// 3. Stops drawing if it reaches the bottom of the terminal or the notification/info bar.
+    /// 1. Highlights the buffer.
+    /// 2. Takes a snapshot of the lines visible in the viewport and everything drawn over them.
+    /// 3. Hands the snapshot to the renderer, which draws the frame.
     ///
     /// # Returns
     /// `Ok(())` if drawing succeeds, or an error if any terminal operation fails.
     ///
     /// # Errors
-    /// This function can return an error if terminal operations (e.g., clearing, moving cursor, writing) fail.
+    /// This function can return an error if highlighting or the renderer fails.
     pub(crate) fn draw_lines(&mut self) -> Result<()> {
-        // let (_, term_height) = terminal::size()?;
-        crossterm::queue!(
-            self.viewport.terminal,
-            crossterm::cursor::MoveTo(0, 0),
-            terminal::Clear(ClearType::All),
-        )?;
-
-        if self.is_initial_launch {
-            draw_ascii_art(&mut self.viewport.terminal)?;
-            self.is_initial_launch = false;
-            return Ok(());
+        if self.mode.is_terminal() {
+            return self.draw_terminal_lines();
         }
-        let mut byte_index = self.buffer.get_byte_offset(self.viewport.topleft);
+        let splash = self.is_initial_launch;
+        self.is_initial_launch = false;
         let own_buf = self.buffer.get_coalesced_bytes();
-        self.highlighter.parse(&own_buf);
-        let style_map = self.highlighter.highlight(&own_buf)?;
-
-        for (i, line) in self
-            .buffer
-            .get_full_lines_buffer_window(
-                Some(self.viewport.topleft),
-                Some(self.viewport.bottomright()),
-            )?
-            .iter()
-            .enumerate()
-        {
-            let line_number = self.viewport.topleft.line + i;
-
-            crossterm::queue!(
-                self.viewport.terminal,
-                crossterm::cursor::MoveDown(1),
-                crossterm::cursor::MoveToColumn(0),
-            )?;
-
-            self.create_line_numbers(line_number + 1)?;
-
-            self.draw_line_new(line, line_number, &mut byte_index, &style_map)?;
-            byte_index += 1;
+        let edits = self.buffer.take_pending_edits();
+        for edit in &edits {
+            self.folds.apply_edit(edit);
         }
-        self.viewport.terminal.flush()?;
-
-        Ok(())
-    }
-    /// Currently parsing through the tree and printing char by char, a more efficient version
-    /// would go over a lexeme representation. Whitespace or other symbol
-    /// delimited.
-    fn draw_line_new(
-        &mut self,
-        line: impl AsRef<str>,
-        absolute_ln: usize,
-        byte_offset: &mut usize,
-        style_map: &RangeMap<usize, Style>,
-    ) -> Result<()> {
-        let line = line.as_ref();
-        let selection = Selection::from(&self.cursor).normalized();
-        let default_style = &Style::default();
-
-        // Decide on highlighting
-        let line_in_highlight_bounds =
-            absolute_ln >= selection.start.line && absolute_ln < selection.end.line;
-        let highlight_whole_line = (self.mode.is_visual_line() && line_in_highlight_bounds)
-            || absolute_ln > selection.start.line
-                && (absolute_ln < selection.end.line.saturating_sub(1) && self.mode.is_visual());
-
-        let highlight_range = if highlight_whole_line {
-            0f32..f32::INFINITY
-        } else if line_in_highlight_bounds {
-            selection.start.col as f32..selection.end.col as f32
-        } else {
-            0f32..f32::NEG_INFINITY
+        let mut style_map = match self.highlighter.as_mut() {
+            Some(highlighter) => {
+                for edit in edits {
+                    highlighter.apply_edit(edit);
+                }
+                highlighter.parse(&own_buf);
+                highlighter.highlight(&own_buf, self.theme.as_ref())?
+            }
+            None => RangeMap::new(),
         };
+        if !self.semantic_tokens.is_empty() {
+            overlay_semantic_tokens(
+                &mut style_map,
+                &self.semantic_tokens,
+                self.buffer.get_normal_text(),
+                self.theme.as_ref(),
+            );
+        }
+        let bracket_pair = self
+            .find_matching_bracket(self.pos())
+            .map(|matching| (self.pos(), matching));
 
-        // Outputting
-        for (col, ch) in line.chars().enumerate() {
-            // Highlighting
-            let bg_color = if self.mode.is_any_visual() && highlight_range.contains(&(col as f32)) {
-                SetBackgroundColor(Color::Black)
-            } else {
-                SetBackgroundColor(Color::Reset)
-            };
+        // Folded lines take a single row, so more lines fit the window
+        let (topleft, bottomright) = (self.viewport.topleft, self.viewport.bottomright());
+        let rows = bottomright.line - topleft.line + 1;
+        let bottomright = LineCol {
+            line: self.folds.last_line_shown(topleft.line, rows),
+            ..bottomright
+        };
+        let state = EditorState {
+            lines: self
+                .buffer
+                .get_full_lines_buffer_window(Some(topleft), Some(bottomright))?,
+            first_line: self.viewport.topleft.line,
+            first_byte: self.buffer.get_byte_offset(LineCol {
+                line: self.viewport.topleft.line,
+                col: 0,
+            }),
+            cursor: self.pos(),
+            mode: self.mode,
+            style_map,
+            git_diff: &self.git_diff,
+            selection: Selection::from(&self.cursor),
+            bracket_pair,
+            search_highlights: &self.search_highlights,
+            line_number_width: self.options.line_number_width,
+            number: self.options.number,
+            shiftwidth: self.options.shiftwidth,
+            show_indent_guides: self.options.show_indent_guides,
+            color_column: self.options.color_column,
+            folds: self.folds.outermost(),
+            splash,
+        };
+        self.renderer.render_frame(&state)
+    }
 
-            // Styling and Printing
-            let style = style_map.get(byte_offset).unwrap_or(default_style);
-            crossterm::queue!(
-                self.viewport.terminal,
-                SetForegroundColor(style.fg),
-                bg_color,
-                style::Print(ch)
-            )?;
-            *byte_offset += ch.len_utf8();
+    /// Terminal position `pos` is drawn at, rows hidden in folds left out.
+    pub(crate) fn screen_pos(&self, pos: LineCol) -> LineCol {
+        LineCol {
+            line: self.folds.screen_row(self.viewport.topleft.line, pos.line),
+            ..self.viewport.view_cursor(pos, self.gutter_width())
         }
-        Ok(())
     }
 
-    // fn draw_line(
-    //     &self,
-    //     line: impl AsRef<str>,
-    //     absolute_ln: usize,
-    //     byte_offset: &mut usize,
-    // ) -> Result<()> {
-    //     let line = line.as_ref();
-    //     let selection = Selection::from(&self.cursor).normalized();
-    //     let mut stdout = &self.viewport.terminal;
-
-    //     let line_in_highlight_bounds =
-    //         absolute_ln >= selection.start.line && absolute_ln < selection.end.line;
-    //     let highlight_whole_line = (self.mode.is_visual_line() && line_in_highlight_bounds)
-    //         || absolute_ln > selection.start.line
-    //             && (absolute_ln < selection.end.line.saturating_sub(1) && self.mode.is_visual());
-
-    //     if highlight_whole_line {
-    //         crossterm::execute!(
-    //             stdout,
-    //             SetBackgroundColor(Color::White),
-    //             SetForegroundColor(Color::Black)
-    //         )?;
-    //         write!(stdout, "{}\r", line)?;
-    //         crossterm::execute!(stdout, ResetColor)?;
-    //     } else if self.mode.is_visual() && line_in_highlight_bounds {
-    //         let start_col = if absolute_ln == selection.start.line {
-    //             selection.start.col
-    //         } else {
-    //             0
-    //         };
-    //         let end_col = if absolute_ln == selection.end.line {
-    //             selection.end.col
-    //         } else {
-    //             line.len()
-    //         };
-
-    //         write!(stdout, "{}", &line[..start_col])?;
-
-    //         crossterm::execute!(
-    //             stdout,
-    //             SetBackgroundColor(Color::White),
-    //             SetForegroundColor(Color::Black)
-    //         )?;
-    //         write!(stdout, "{}", &line[start_col..end_col])?;
-    //         crossterm::execute!(stdout, ResetColor)?;
-
-    //         // Print part after selection
-    //         write!(stdout, "{}\r", &line[end_col..])?;
-    //     } else {
-    //         write!(stdout, "{}\r", line)?;
-    //     }
-
-    //     writeln!(stdout)?;
-    //     Ok(())
-    // }
-
-    fn create_line_numbers(&mut self, line_number: usize) -> Result<()> {
-        crossterm::execute!(
-            self.viewport.terminal,
-            style::SetForegroundColor(style::Color::Green)
-        )?;
-        let rel_line_number = (line_number as i64 - self.pos().line as i64 - 1).abs();
-        let line_number = if rel_line_number == 0 {
-            line_number as i64
-        } else {
-            rel_line_number
-        };
-
-        print!(
-            "{line_number:>width$}{separator}",
-            line_number = line_number,
-            width = LINE_NUMBER_RESERVED_COLUMNS,
-            separator = " ".repeat(LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS)
-        );
-        crossterm::execute!(self.viewport.terminal, ResetColor)?;
-        Ok(())
+    /// Amount of columns left of the text, taken by the line numbers and their separator.
+    pub(crate) fn gutter_width(&self) -> usize {
+        self.options.line_number_width + LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS
     }
 
     /// Makes sure the cursor is in bounds of the view window, if it isnt' follow the cursor with
@@ -565,12 +1123,11 @@ impl<Buff: TextBuffer> Editor<Buff> {
         let cursor_out_of_bounds =
             current_line < top_line.saturating_sub(1) || current_line > bot_line + 1;
 
-        let cursor_less_than_proximity_from_top =
-            current_line < (top_line + WINDOW_MAX_CURSOR_PROXIMITY_TO_WINDOW_BOUNDS);
-        let main_cursor_more_than_proximity =
-            current_line > WINDOW_MAX_CURSOR_PROXIMITY_TO_WINDOW_BOUNDS;
-        let cursor_less_than_proximity_from_bot = current_line
-            > bot_line - WINDOW_MAX_CURSOR_PROXIMITY_TO_WINDOW_BOUNDS - BAR_VERT_SPACE as usize;
+        let scroll_off = self.options.scroll_off;
+        let cursor_less_than_proximity_from_top = current_line < (top_line + scroll_off);
+        let main_cursor_more_than_proximity = current_line > scroll_off;
+        let cursor_less_than_proximity_from_bot =
+            current_line > bot_line.saturating_sub(scroll_off + BAR_VERT_SPACE as usize);
 
         if cursor_out_of_bounds {
             self.viewport.center(self.cursor.pos)
@@ -591,7 +1148,7 @@ impl<Buff: TextBuffer> Editor<Buff> {
     /// # Errors
     /// This function can return an error if the terminal cursor movement operation fails.
     pub fn move_cursor(&mut self) {
-        let cursor = self.viewport.view_cursor(self.pos());
+        let cursor = self.screen_pos(self.pos());
         #[allow(clippy::cast_possible_truncation)]
         let _ = crossterm::execute!(
             self.viewport.terminal,
@@ -610,3 +1167,231 @@ impl<Buff: TextBuffer> Editor<Buff> {
         );
     }
 }
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Saves what outlives the session, the marks and histories. The swap file is removed too,
+    /// unless the editor is going down with a panic and the swap is needed to recover the text.
+    pub fn end_session(&self) {
+        if !std::thread::panicking() {
+            self.remove_swap();
+        }
+        if let Err(e) = self.save_marks() {
+            error!("Failed to save the marks: {e}");
+        }
+        if let Err(e) = self.save_history() {
+            error!("Failed to save the history file: {e}");
+        }
+    }
+}
+
+/// However the session ends, by a command, Esc or a panic, it's saved on the way out. Editors of
+/// tests are left alone, they'd write to the data directory of the user.
+#[cfg(not(test))]
+impl<Buff: TextBuffer> Drop for Editor<Buff> {
+    fn drop(&mut self) {
+        self.end_session();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::buffer::DefaultBuffer;
+
+    /// Creates an editor over the given lines with the cursor at the start of the buffer.
+    pub(crate) fn new_test_editor(lines: &[&str]) -> Editor<DefaultBuffer> {
+        let text = lines.iter().map(ToString::to_string).collect();
+        let mut editor = Editor::new(DefaultBuffer::new(text), false);
+        editor.swap_dir = None;
+        editor.marks_file = None;
+        editor
+    }
+
+    #[test]
+    fn test_public_accessors() {
+        let mut editor = new_test_editor(&["first", "second"]);
+        editor.handle_char_input('j', None).unwrap();
+        editor.handle_char_input('x', Some(2)).unwrap();
+        editor.handle_char_input('v', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+        assert_eq!(editor.mode(), &Modal::Visual);
+        assert_eq!(editor.buffer_line(1), Some("cond"));
+        assert_eq!(editor.buffer_line(2), None);
+    }
+
+    #[test]
+    fn test_matching_nested_brackets() {
+        let editor = new_test_editor(&["fn f(a: [u8; (2)]) {", "    g(a[0]);", "}"]);
+        let at = |line, col| LineCol { line, col };
+        assert_eq!(editor.find_matching_bracket(at(0, 4)), Some(at(0, 17)));
+        assert_eq!(editor.find_matching_bracket(at(0, 17)), Some(at(0, 4)));
+        assert_eq!(editor.find_matching_bracket(at(0, 8)), Some(at(0, 16)));
+        assert_eq!(editor.find_matching_bracket(at(0, 13)), Some(at(0, 15)));
+        assert_eq!(editor.find_matching_bracket(at(0, 19)), Some(at(2, 0)));
+        assert_eq!(editor.find_matching_bracket(at(2, 0)), Some(at(0, 19)));
+        assert_eq!(editor.find_matching_bracket(at(0, 0)), None);
+    }
+
+    #[test]
+    fn test_unclosed_bracket_has_no_match() {
+        let editor = new_test_editor(&["(((", ")"]);
+        assert_eq!(
+            editor.find_matching_bracket(LineCol { line: 0, col: 0 }),
+            None
+        );
+        assert_eq!(
+            editor.find_matching_bracket(LineCol { line: 1, col: 0 }),
+            Some(LineCol { line: 0, col: 2 })
+        );
+    }
+
+    #[test]
+    fn test_notification_queues_are_isolated() {
+        let mut first = new_test_editor(&["text"]);
+        let mut second = new_test_editor(&["text"]);
+        notif_bar!(first => "only for the first";);
+        assert_eq!(first.notification_queue.len(), 1);
+        assert!(second.notification_queue.is_empty());
+
+        let shown = get_notif_bar_content(&mut first.notification_queue);
+        assert!(shown.contains("only for the first"));
+        assert!(first.notification_queue.is_empty());
+
+        for _ in 0..=NOTIFICATION_QUEUE_LEN {
+            second.notify("message");
+        }
+        assert_eq!(second.notification_queue.len(), NOTIFICATION_QUEUE_LEN);
+    }
+
+    #[test]
+    fn test_push_out_of_bounds_recovers() {
+        let mut editor = new_test_editor(&["short", "line"]);
+        editor.go(LineCol { line: 1, col: 2 });
+        editor.go(LineCol { line: 7, col: 40 });
+        editor.push('x');
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 2 });
+        assert_eq!(editor.buffer.get_normal_text(), ["short", "line"]);
+    }
+
+    #[test]
+    fn test_delete_out_of_bounds_recovers() {
+        let mut editor = new_test_editor(&["short"]);
+        editor.go(LineCol { line: 3, col: 1 });
+        editor.go(LineCol { line: 5, col: 1 });
+        editor.delete();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 1 });
+        assert_eq!(editor.buffer.get_normal_text(), ["short"]);
+
+        editor.go(LineCol { line: 0, col: 30 });
+        editor.delete();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 5 });
+    }
+
+    #[test]
+    fn test_terminal_mode_keeps_text_position() {
+        let mut editor = new_test_editor(&["short", "a longer line"]);
+        editor.go(LineCol { line: 1, col: 8 });
+        editor.set_mode(Modal::Terminal);
+        assert_eq!(editor.pos(), LineCol::default());
+
+        for ch in "echo hi there".chars() {
+            editor.push(ch);
+        }
+        editor.cursor.pos = LineCol { line: 5, col: 13 };
+        editor.force_within_bounds();
+        assert_eq!(editor.pos(), LineCol { line: 5, col: 13 });
+        assert_eq!(editor.buffer.get_terminal_text(), "echo hi there");
+
+        editor.set_mode(Modal::Normal);
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 8 });
+        assert_eq!(editor.buffer.get_normal_text(), ["short", "a longer line"]);
+    }
+
+    #[test]
+    fn test_terminal_mode_draws_terminal_lines() {
+        let renderer = crate::renderer::tests::MockRenderer::default();
+        let frames = std::rc::Rc::clone(&renderer.frames);
+        let mut editor = new_test_editor(&["text"]);
+        editor.renderer = Box::new(renderer);
+        editor.set_mode(Modal::Terminal);
+        for ch in "ls".chars() {
+            editor.push(ch);
+        }
+        editor.buffer.append_terminal_output("\nsrc\n$ ");
+        editor.viewport.topleft.line = 1;
+        editor.draw_lines().unwrap();
+
+        let frames = frames.borrow();
+        assert_eq!(frames.last().unwrap().lines, ["src", "$ "]);
+    }
+
+    #[test]
+    fn test_search_history_per_direction() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.add_to_search_history("/forwards", FindMode::Forwards);
+        editor.add_to_search_history("?backwards", FindMode::Backwards);
+        for find_mode in [FindMode::Forwards, FindMode::Backwards] {
+            assert_eq!(
+                editor.get_from_search_history(0, find_mode),
+                Some(String::new())
+            );
+        }
+        assert_eq!(
+            editor.get_from_search_history(1, FindMode::Forwards),
+            Some("/forwards".to_string())
+        );
+        assert_eq!(
+            editor.get_from_search_history(1, FindMode::Backwards),
+            Some("?backwards".to_string())
+        );
+        assert_eq!(editor.get_from_search_history(2, FindMode::Backwards), None);
+    }
+
+    #[test]
+    fn test_search_history_fills_command_bar() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.add_to_search_history("first", FindMode::Forwards);
+        editor.add_to_search_history("second", FindMode::Forwards);
+        editor.set_mode(Modal::Find(FindMode::Forwards));
+
+        editor.navigate_history_backwards().unwrap();
+        assert_eq!(editor.buffer.get_command_text(), ["second"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 6 });
+        editor.navigate_history_backwards().unwrap();
+        assert_eq!(editor.buffer.get_command_text(), ["first"]);
+        editor.navigate_history_forwards().unwrap();
+        assert_eq!(editor.buffer.get_command_text(), ["second"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 6 });
+    }
+
+    #[test]
+    fn test_command_history_fills_command_bar() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.add_to_command_history(":set cc=80");
+        editor.add_to_command_history(":w");
+        editor.set_mode(Modal::Command);
+
+        editor.navigate_history_backwards().unwrap();
+        assert_eq!(editor.buffer.get_command_text(), [":w"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 2 });
+        editor.navigate_history_backwards().unwrap();
+        assert_eq!(editor.buffer.get_command_text(), [":set cc=80"]);
+        // Past the oldest command the bar is left alone
+        editor.navigate_history_backwards().unwrap();
+        assert_eq!(editor.buffer.get_command_text(), [":set cc=80"]);
+        editor.navigate_history_forwards().unwrap();
+        assert_eq!(editor.buffer.get_command_text(), [":w"]);
+    }
+
+    #[test]
+    fn test_window_title() {
+        let mut editor = new_test_editor(&["text"]);
+        assert_eq!(editor.window_title(), "[No File] - NeoText");
+
+        editor.set_file_path("src/notes.txt");
+        assert_eq!(editor.window_title(), "notes.txt - NeoText");
+        editor.set_mode(Modal::Insert);
+        editor.push('a');
+        assert_eq!(editor.window_title(), "notes.txt* - NeoText");
+    }
+}
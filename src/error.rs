@@ -3,6 +3,7 @@ use derive_more::From;
 pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug, From)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     InvalidPosition,
     ExitCall,
@@ -18,14 +19,144 @@ pub enum Error {
     NowhereToGo,
     ImATeacup,
     ParsingError(String),
+    /// The language server exited or closed its end of the connection.
+    LspConnectionLost,
+    LspInitializationFailed(String),
+    /// The language server didn't announce the named capability.
+    LspCapabilityNotSupported(&'static str),
+    LspRequestTimeout {
+        method: String,
+    },
+    /// A message from the language server couldn't be deserialized.
+    LspDeserializationFailed {
+        method: String,
+        detail: String,
+    },
     #[from]
     Io(std::io::Error),
 }
 
+impl Error {
+    /// A short description of the error, meant to be shown to the user in the notification bar.
+    pub fn display_user(&self) -> &str {
+        match self {
+            Self::InvalidPosition => "Cursor position invalid",
+            Self::ExitCall => "Exiting",
+            Self::InvalidRange => "Invalid range",
+            Self::InvalidLineNumber => "Invalid line number",
+            Self::InvalidInput => "Invalid input",
+            Self::PatternNotFound => "Pattern not found",
+            Self::NoCommandAvailable => "No previous command",
+            Self::UnexpectedRegisterData => "Register contains unexpected data",
+            Self::ProgrammingBug { .. } => "Internal error, please report this bug",
+            Self::NowhereToGo => "Already at beginning/end",
+            Self::ImATeacup => "Nothing to do",
+            Self::ParsingError(descr) => descr,
+            Self::LspConnectionLost => "Language server connection lost",
+            Self::LspInitializationFailed(_) => "Language server failed to start",
+            Self::LspCapabilityNotSupported(_) => "Not supported by the language server",
+            Self::LspRequestTimeout { .. } => "Language server didn't respond in time",
+            Self::LspDeserializationFailed { .. } => "Invalid message from the language server",
+            Self::Io(_) => "File operation failed",
+        }
+    }
+}
+
 impl core::fmt::Display for Error {
     fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
-        write!(fmt, "{self:?}")
+        match self {
+            Self::Io(e) => write!(fmt, "{}: {e}", self.display_user()),
+            Self::LspInitializationFailed(detail) => {
+                write!(fmt, "{}: {detail}", self.display_user())
+            }
+            Self::LspCapabilityNotSupported(capability) => {
+                write!(fmt, "{}: {capability}", self.display_user())
+            }
+            Self::LspRequestTimeout { method } => write!(fmt, "{}: {method}", self.display_user()),
+            Self::LspDeserializationFailed { method, detail } => {
+                write!(fmt, "{} ({method}): {detail}", self.display_user())
+            }
+            otherwise => write!(fmt, "{}", otherwise.display_user()),
+        }
     }
 }
 
 impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_variant_has_user_message() {
+        let errors = [
+            Error::InvalidPosition,
+            Error::ExitCall,
+            Error::InvalidRange,
+            Error::InvalidLineNumber,
+            Error::InvalidInput,
+            Error::PatternNotFound,
+            Error::NoCommandAvailable,
+            Error::UnexpectedRegisterData,
+            Error::ProgrammingBug {
+                descr: "descr".to_string(),
+            },
+            Error::NowhereToGo,
+            Error::ImATeacup,
+            Error::ParsingError("Not an editor command: x".to_string()),
+            Error::LspConnectionLost,
+            Error::LspInitializationFailed("rust-analyzer not found".to_string()),
+            Error::LspCapabilityNotSupported("hoverProvider"),
+            Error::LspRequestTimeout {
+                method: "textDocument/hover".to_string(),
+            },
+            Error::LspDeserializationFailed {
+                method: "initialize".to_string(),
+                detail: "missing field `result`".to_string(),
+            },
+            Error::Io(std::io::Error::other("disk full")),
+        ];
+        for error in errors {
+            assert!(!error.display_user().is_empty(), "{error:?}");
+            assert_ne!(error.display_user(), format!("{error:?}"));
+        }
+    }
+
+    #[test]
+    fn test_io_display_includes_cause() {
+        let error = Error::Io(std::io::Error::other("disk full"));
+        assert_eq!(error.to_string(), "File operation failed: disk full");
+        assert_eq!(Error::ImATeacup.to_string(), "Nothing to do");
+    }
+
+    #[test]
+    fn test_lsp_errors_display_details() {
+        assert_eq!(
+            Error::LspConnectionLost.to_string(),
+            "Language server connection lost"
+        );
+        assert_eq!(
+            Error::LspCapabilityNotSupported("hoverProvider").to_string(),
+            "Not supported by the language server: hoverProvider"
+        );
+        assert_eq!(
+            Error::LspRequestTimeout {
+                method: "textDocument/hover".to_string()
+            }
+            .to_string(),
+            "Language server didn't respond in time: textDocument/hover"
+        );
+        let error = Error::LspDeserializationFailed {
+            method: "initialize".to_string(),
+            detail: "missing field `result`".to_string(),
+        };
+        assert_eq!(
+            error.display_user(),
+            "Invalid message from the language server"
+        );
+        assert_eq!(
+            error.to_string(),
+            "Invalid message from the language server (initialize): missing field `result`"
+        );
+    }
+}
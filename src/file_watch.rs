@@ -0,0 +1,74 @@
+use crate::{buffer::TextBuffer, editor::Editor, notif_bar, LineCol, Result};
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Starts watching the edited file for modifications made by other processes.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn watch_file_changes(&mut self) -> Result<()> {
+        match self.file_path.clone() {
+            Some(path) => self.tasks.watch_file(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Reloads the edited file after it changed on disk. Unsaved changes are only discarded if
+    /// the user agrees to it.
+    pub(crate) fn on_file_modified(&mut self) -> Result<()> {
+        let Some(path) = &self.file_path else {
+            return Ok(());
+        };
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Ok(());
+        };
+        // Our own writes are reported as well, there's nothing to reload after those
+        if content.lines().eq(self.buffer.get_normal_text().iter()) {
+            return Ok(());
+        }
+        if self.buffer.is_dirty() && !self.prompt("File changed on disk. Reload? (y/N)")? {
+            return Ok(());
+        }
+        self.reload_from(&content)
+    }
+
+    /// Replaces the buffer with `content` freshly read from the edited file, keeping the cursor
+    /// where it was as far as the new text allows.
+    pub(crate) fn reload_from(&mut self, content: &str) -> Result<()> {
+        let pos = self.pos();
+        self.load_text(content)?;
+        self.buffer.mark_saved();
+
+        let line = pos.line.min(self.buffer.max_line());
+        let col = pos.col.min(self.buffer.max_col(LineCol { line, col: 0 }));
+        self.go(LineCol { line, col });
+        notif_bar!(self => "File reloaded from disk";);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{buffer::TextBuffer, editor::tests::new_test_editor, LineCol};
+
+    #[test]
+    fn test_reload_preserves_cursor() {
+        let mut editor = new_test_editor(&["first", "second", "third"]);
+        editor.go(LineCol { line: 1, col: 3 });
+        editor.reload_from("first\nchanged line\nthird\n").unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["first", "changed line", "third"]
+        );
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 3 });
+        assert!(!editor.buffer.is_dirty());
+    }
+
+    #[test]
+    fn test_reload_clamps_cursor() {
+        let mut editor = new_test_editor(&["first", "second", "third"]);
+        editor.go(LineCol { line: 2, col: 4 });
+        editor.reload_from("one\nxy\n").unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["one", "xy"]);
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 2 });
+    }
+}
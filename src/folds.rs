@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use tree_sitter::{InputEdit, Point};
+
+use crate::{buffer::TextBuffer, editor::Editor, notif_bar, Error, Result};
+
+/// Manual folds of the edited file, created with `zf`. Folds may nest, but never partially
+/// overlap, so each one is either fully inside or fully outside any other. Every fold is closed,
+/// showing its lines as a single one.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Folds {
+    /// Inclusive line ranges of the folds, keyed by their start line.
+    folds: BTreeMap<usize, usize>,
+}
+
+impl Folds {
+    /// Creates a fold over the lines `start..=end`. Fails with `InvalidRange` if the fold would
+    /// partially overlap an existing one or start on the same line as one.
+    pub fn create(&mut self, start: usize, end: usize) -> Result<()> {
+        let (start, end) = (start.min(end), start.max(end));
+        let overlaps = self.folds.iter().any(|(&other_start, &other_end)| {
+            let nested = (start > other_start && end <= other_end)
+                || (other_start > start && other_end <= end);
+            let disjoint = end < other_start || other_end < start;
+            !nested && !disjoint
+        });
+        if overlaps {
+            return Err(Error::InvalidRange);
+        }
+        self.folds.insert(start, end);
+        Ok(())
+    }
+
+    /// The innermost fold containing `line`, as its start and end line.
+    pub fn fold_at(&self, line: usize) -> Option<(usize, usize)> {
+        // Nested folds start after the folds containing them, so the closest match is innermost
+        self.folds
+            .range(..=line)
+            .rev()
+            .find(|(_, &end)| end >= line)
+            .map(|(&start, &end)| (start, end))
+    }
+
+    /// Removes the innermost fold containing `line`, returning its range.
+    pub fn delete_at(&mut self, line: usize) -> Option<(usize, usize)> {
+        let (start, end) = self.fold_at(line)?;
+        self.folds.remove(&start);
+        Some((start, end))
+    }
+
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Every fold as its start and end line, ordered by start line.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.folds.iter().map(|(&start, &end)| (start, end))
+    }
+
+    /// The folds not nested in another one, which are the ones drawn, ordered by start line.
+    pub fn outermost(&self) -> Vec<(usize, usize)> {
+        let mut outermost: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in self.iter() {
+            if outermost
+                .last()
+                .is_none_or(|(_, last_end)| start > *last_end)
+            {
+                outermost.push((start, end));
+            }
+        }
+        outermost
+    }
+
+    /// The outermost fold containing `line`, whose lines are drawn as one.
+    pub fn closed_at(&self, line: usize) -> Option<(usize, usize)> {
+        self.folds
+            .range(..=line)
+            .filter(|(_, &end)| end >= line)
+            .map(|(&start, &end)| (start, end))
+            .next()
+    }
+
+    /// Screen row `line` is drawn on when `first_line` is drawn on the first one. Each fold
+    /// takes a single row, the one its lines are drawn on.
+    pub fn screen_row(&self, first_line: usize, line: usize) -> usize {
+        let line = self.closed_at(line).map_or(line, |(start, _)| start);
+        let hidden: usize = self
+            .outermost()
+            .into_iter()
+            .filter(|(start, _)| (first_line..line).contains(start))
+            .map(|(start, end)| end - start)
+            .sum();
+        line.saturating_sub(first_line).saturating_sub(hidden)
+    }
+
+    /// Last line drawn when `rows` rows are filled starting from `first_line`.
+    pub fn last_line_shown(&self, first_line: usize, rows: usize) -> usize {
+        let mut line = first_line;
+        for _ in 1..rows {
+            line = self.closed_at(line).map_or(line, |(_, end)| end) + 1;
+        }
+        self.closed_at(line).map_or(line, |(_, end)| end)
+    }
+
+    /// Moves the folds along with the lines `edit` inserted or removed before them, and resizes
+    /// those it happened inside of. Folds the edit only partially covers are dropped, as there's
+    /// no telling which lines they should be made of afterwards.
+    pub fn apply_edit(&mut self, edit: &InputEdit) {
+        let (from, old_end) = (edit.start_position, edit.old_end_position);
+        let delta = edit.new_end_position.row as isize - old_end.row as isize;
+        let shift = |line: usize| line.saturating_add_signed(delta);
+        self.folds = std::mem::take(&mut self.folds)
+            .into_iter()
+            .filter_map(|(start, end)| {
+                let start_point = Point::new(start, 0);
+                if from.row > end {
+                    Some((start, end))
+                } else if old_end <= start_point {
+                    Some((shift(start), shift(end)))
+                } else if from >= start_point && old_end.row <= end {
+                    Some((start, shift(end))).filter(|(start, end)| end >= start)
+                } else {
+                    None
+                }
+            })
+            .collect();
+    }
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Folds the lines between `start` and `end`, `zf`.
+    pub fn create_fold(&mut self, start: usize, end: usize) {
+        match self.folds.create(start, end) {
+            Ok(()) => {
+                let count = start.abs_diff(end) + 1;
+                notif_bar!(self => format!("{count} lines folded");)
+            }
+            Err(_) => notif_bar!(self => "Folds can't partially overlap";),
+        }
+    }
+
+    /// Deletes the innermost fold at the cursor, `zd`.
+    pub fn delete_fold_at_cursor(&mut self) {
+        if self.folds.delete_at(self.pos().line).is_none() {
+            notif_bar!(self => "No fold found";);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_folds_allowed() {
+        let mut folds = Folds::default();
+        folds.create(2, 10).unwrap();
+        folds.create(4, 6).unwrap();
+        folds.create(0, 12).unwrap();
+        assert_eq!(folds.iter().collect::<Vec<_>>(), [(0, 12), (2, 10), (4, 6)]);
+        assert_eq!(folds.fold_at(5), Some((4, 6)));
+        assert_eq!(folds.fold_at(8), Some((2, 10)));
+        assert_eq!(folds.fold_at(13), None);
+    }
+
+    #[test]
+    fn test_overlapping_folds_rejected() {
+        let mut folds = Folds::default();
+        folds.create(2, 6).unwrap();
+        assert!(folds.create(4, 8).is_err());
+        assert!(folds.create(0, 3).is_err());
+        assert!(folds.create(2, 4).is_err());
+        folds.create(7, 9).unwrap();
+        assert_eq!(folds.iter().collect::<Vec<_>>(), [(2, 6), (7, 9)]);
+    }
+
+    #[test]
+    fn test_folds_follow_line_edits() {
+        let edit =
+            |from: (usize, usize), old_end: (usize, usize), new_end: (usize, usize)| InputEdit {
+                start_byte: 0,
+                old_end_byte: 0,
+                new_end_byte: 0,
+                start_position: Point::new(from.0, from.1),
+                old_end_position: Point::new(old_end.0, old_end.1),
+                new_end_position: Point::new(new_end.0, new_end.1),
+            };
+        let mut folds = Folds::default();
+        folds.create(3, 6).unwrap();
+        folds.create(4, 5).unwrap();
+        folds.create(9, 10).unwrap();
+
+        // A line removed above the folds, like `dd` on the first line
+        folds.apply_edit(&edit((0, 0), (1, 0), (0, 0)));
+        assert_eq!(folds.iter().collect::<Vec<_>>(), [(2, 5), (3, 4), (8, 9)]);
+        // A line broken inside the first fold
+        folds.apply_edit(&edit((3, 2), (3, 2), (4, 0)));
+        assert_eq!(folds.iter().collect::<Vec<_>>(), [(2, 6), (3, 5), (9, 10)]);
+        // Lines removed across the start of the last fold
+        folds.apply_edit(&edit((8, 0), (10, 0), (8, 0)));
+        assert_eq!(folds.iter().collect::<Vec<_>>(), [(2, 6), (3, 5)]);
+    }
+
+    #[test]
+    fn test_screen_rows_with_folds() {
+        let mut folds = Folds::default();
+        folds.create(2, 5).unwrap();
+        folds.create(3, 4).unwrap();
+        folds.create(8, 8).unwrap();
+        assert_eq!(folds.outermost(), [(2, 5), (8, 8)]);
+        assert_eq!(folds.closed_at(4), Some((2, 5)));
+        assert_eq!(folds.screen_row(0, 1), 1);
+        assert_eq!(folds.screen_row(0, 4), 2);
+        assert_eq!(folds.screen_row(0, 6), 3);
+        assert_eq!(folds.screen_row(6, 9), 3);
+        assert_eq!(folds.last_line_shown(0, 4), 6);
+        assert_eq!(folds.last_line_shown(0, 3), 5);
+    }
+
+    #[test]
+    fn test_delete_innermost_fold() {
+        let mut folds = Folds::default();
+        folds.create(0, 9).unwrap();
+        folds.create(3, 5).unwrap();
+        assert_eq!(folds.delete_at(4), Some((3, 5)));
+        assert_eq!(folds.delete_at(4), Some((0, 9)));
+        assert_eq!(folds.delete_at(4), None);
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use crate::{buffer::TextBuffer, editor::Editor, notif_bar, utils::expand_tilde, LineCol, Result};
+
+/// Symbols besides the alphanumeric ones that `gf` takes as part of a file name.
+const FILE_NAME_SYMBOLS: [char; 6] = ['/', '.', '_', '-', '~', '+'];
+
+fn is_file_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || FILE_NAME_SYMBOLS.contains(&ch)
+}
+
+/// Finds an existing file called `name`, trying it as given, then relative to the directory of
+/// `current_file`, the working directory and finally each of the `search_path` directories.
+pub fn resolve_file_path(
+    name: &str,
+    current_file: Option<&Path>,
+    search_path: &[PathBuf],
+) -> Option<PathBuf> {
+    let name = expand_tilde(name);
+    let current_dir = current_file.and_then(Path::parent).map(Path::to_path_buf);
+    let working_dir = std::env::current_dir().ok();
+
+    std::iter::once(name.clone())
+        .chain(current_dir.map(|dir| dir.join(&name)))
+        .chain(working_dir.map(|dir| dir.join(&name)))
+        .chain(search_path.iter().map(|dir| dir.join(&name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Parses the line number following a file name as in `main.rs:42`, one-based.
+fn parse_line_suffix(rest: &str) -> Option<usize> {
+    let digits: String = rest
+        .strip_prefix(':')?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Opens the file whose name is under the cursor, `gf`. With `with_line`, `gF`, the cursor
+    /// is moved to the line number following the name after a colon.
+    pub(crate) fn goto_file_under_cursor(&mut self, with_line: bool) -> Result<()> {
+        let pos = self.pos();
+        let Some((start, name)) = self.buffer.word_at(pos, is_file_name_char) else {
+            notif_bar!(self => "E446: No file name under cursor";);
+            return Ok(());
+        };
+        let line = with_line
+            .then(|| {
+                let rest = self
+                    .buffer
+                    .line(pos.line)
+                    .ok()?
+                    .get(start.col + name.len()..)?;
+                parse_line_suffix(rest)
+            })
+            .flatten();
+        let name = name.to_string();
+
+        let Some(path) = resolve_file_path(&name, self.file_path.as_deref(), &self.options.path)
+        else {
+            notif_bar!(self => format!("E447: Can't find file \"{name}\" in path"););
+            return Ok(());
+        };
+        self.push_jump(pos);
+        self.open_file(path.clone(), false)?;
+        if self.file_path.as_ref() != Some(&path) {
+            // Opening was refused because of unsaved changes
+            return Ok(());
+        }
+        let line = line.map_or(0, |line| line.saturating_sub(1));
+        self.go(LineCol {
+            line: line.min(self.buffer.max_line()),
+            col: 0,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::tests::new_test_editor;
+
+    #[test]
+    fn test_resolve_relative_to_current_file() {
+        let dir = std::env::temp_dir().join(format!("neotext_gf_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("include")).unwrap();
+        std::fs::write(dir.join("main.c"), "").unwrap();
+        std::fs::write(dir.join("include/defs.h"), "one\ntwo\nthree\n").unwrap();
+
+        let current = dir.join("main.c");
+        assert_eq!(
+            resolve_file_path("include/defs.h", Some(&current), &[]),
+            Some(dir.join("include/defs.h"))
+        );
+        assert_eq!(resolve_file_path("defs.h", Some(&current), &[]), None);
+        assert_eq!(
+            resolve_file_path("defs.h", None, &[dir.join("include")]),
+            Some(dir.join("include/defs.h"))
+        );
+
+        let mut editor = new_test_editor(&["see defs.h:3 for details"]);
+        editor.file_path = Some(current);
+        editor.options.path = vec![dir.join("include")];
+        editor.go(LineCol { line: 0, col: 6 });
+        editor.goto_file_under_cursor(true).unwrap();
+        assert_eq!(editor.file_path, Some(dir.join("include/defs.h")));
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_file_is_reported() {
+        let mut editor = new_test_editor(&["no_such_file.txt"]);
+        editor.goto_file_under_cursor(false).unwrap();
+        assert!(editor.file_path.is_none());
+        assert!(editor.notification_queue.back().unwrap().contains("E447"));
+    }
+}
@@ -1,42 +1,111 @@
-use crate::{
-    theme::{self, Theme},
-    LineCol, Result,
-};
+use crate::{lsp::FileType, theme::Theme, Error, Result};
 use crossterm::style::Color;
 use rangemap::RangeMap;
-use tree_sitter::{Parser, Query, QueryCursor};
-use tree_sitter_rust::{language, HIGHLIGHTS_QUERY};
+use tree_sitter::{InputEdit, Parser, Query, QueryCursor};
+
+/// Languages with a bundled tree-sitter grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    Toml,
+    Markdown,
+}
+
+impl Language {
+    /// Grammar of the file type, `None` for file types without one.
+    pub const fn from_file_type(file_type: FileType) -> Option<Self> {
+        match file_type {
+            FileType::Rust => Some(Self::Rust),
+            FileType::Python => Some(Self::Python),
+            FileType::JavaScript => Some(Self::JavaScript),
+            FileType::Toml => Some(Self::Toml),
+            FileType::Markdown => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn grammar(self) -> tree_sitter::Language {
+        match self {
+            Self::Rust => tree_sitter_rust::language(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::Toml => tree_sitter_toml_ng::LANGUAGE.into(),
+            // Only the block structure, the inline grammar would need a tree per paragraph
+            Self::Markdown => tree_sitter_md::LANGUAGE.into(),
+        }
+    }
+
+    pub const fn highlights_query(self) -> &'static str {
+        match self {
+            Self::Rust => tree_sitter_rust::HIGHLIGHTS_QUERY,
+            Self::Python => tree_sitter_python::HIGHLIGHTS_QUERY,
+            Self::JavaScript => tree_sitter_javascript::HIGHLIGHT_QUERY,
+            Self::Toml => tree_sitter_toml_ng::HIGHLIGHTS_QUERY,
+            Self::Markdown => tree_sitter_md::HIGHLIGHT_QUERY_BLOCK,
+        }
+    }
+}
 
 pub struct Highlighter {
     parser: Parser,
     query: Query,
-    pub theme: Box<dyn Theme>,
     tree: Option<tree_sitter::Tree>,
 }
 impl Highlighter {
-    pub fn new(text: impl AsRef<[u8]>) -> Result<Self> {
-        let lang = &language();
+    /// Creates a highlighter parsing `text` with `language` and styling the captures of its
+    /// `highlights_query`.
+    ///
+    /// # Errors
+    /// Returns `Error::ParsingError` if the grammar isn't compatible with the tree-sitter version
+    /// or the query doesn't fit the grammar.
+    pub fn new(
+        text: impl AsRef<[u8]>,
+        language: &tree_sitter::Language,
+        highlights_query: &str,
+    ) -> Result<Self> {
         let mut parser = Parser::new();
         parser
-            .set_language(lang)
-            .expect("Couldn't create parser for the given language");
-        let query = Query::new(lang, HIGHLIGHTS_QUERY)
-            .expect("Couldn't create query for the language parser");
+            .set_language(language)
+            .map_err(|e| Error::ParsingError(e.to_string()))?;
+        let query =
+            Query::new(language, highlights_query).map_err(|e| Error::ParsingError(e.message))?;
 
         Ok(Self {
             query,
-            theme: Box::new(theme::MonoAndromeda {}),
             tree: parser.parse(text, None),
             parser,
         })
     }
+
+    /// Creates a highlighter for the grammar of `file_type`, `None` if it has none.
+    ///
+    /// # Errors
+    /// Returns `Error::ParsingError` if the grammar can't be loaded, see `Highlighter::new`.
+    pub fn for_file_type(text: impl AsRef<[u8]>, file_type: FileType) -> Result<Option<Self>> {
+        Language::from_file_type(file_type)
+            .map(|lang| Self::new(text, &lang.grammar(), lang.highlights_query()))
+            .transpose()
+    }
+
+    /// Marks the region `edit` changed in the current syntax tree, so the next `parse` only
+    /// re-parses around it. Every edit made since the last `parse` has to be applied in order.
+    pub fn apply_edit(&mut self, edit: InputEdit) {
+        if let Some(tree) = self.tree.as_mut() {
+            tree.edit(&edit);
+        }
+    }
+
     pub fn parse(&mut self, t: &[u8]) {
         let tree = self.parser.parse(t, self.tree.as_ref());
         self.tree = tree;
     }
-    pub fn highlight(&mut self, text: &[u8]) -> Result<RangeMap<usize, Style>> {
+    pub fn highlight(&mut self, text: &[u8], theme: &dyn Theme) -> Result<RangeMap<usize, Style>> {
         let mut cursor = QueryCursor::new();
-        let tree = self.tree.as_ref().expect("Parsing preceds highlighting");
+        let Some(tree) = self.tree.as_ref() else {
+            return Ok(RangeMap::new());
+        };
 
         let matches = cursor.matches(&self.query, tree.root_node(), text);
         let mut style_map = RangeMap::new();
@@ -45,8 +114,12 @@ impl Highlighter {
             for capture in match_.captures {
                 let node = capture.node;
                 let range = node.byte_range();
+                // Some grammars capture zero-width nodes, like the missing ends of Markdown blocks
+                if range.is_empty() {
+                    continue;
+                }
                 let scope = self.query.capture_names()[capture.index as usize];
-                let style = self.theme.from_str(scope);
+                let style = theme.from_str(scope);
 
                 style_map.insert(range, Style::new(style, Color::Reset, false, false));
             }
@@ -102,3 +175,78 @@ impl Style {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::MonoAndromeda;
+
+    #[test]
+    fn test_every_language_highlights() {
+        let samples = [
+            (FileType::Rust, "fn main() { let x = 1; }"),
+            (FileType::Python, "def main():\n    return 'x'\n"),
+            (FileType::JavaScript, "function main() { return 1; }"),
+            (FileType::Toml, "[package]\nname = \"x\"\n"),
+            (FileType::Markdown, "# Title\n\n```\ncode\n```\n"),
+        ];
+        for (file_type, text) in samples {
+            let mut highlighter = Highlighter::for_file_type(text, file_type)
+                .unwrap()
+                .unwrap();
+            let styles = highlighter
+                .highlight(text.as_bytes(), &MonoAndromeda)
+                .unwrap();
+            assert!(!styles.is_empty(), "{file_type:?} wasn't highlighted");
+        }
+    }
+
+    #[test]
+    fn test_incremental_parse_matches_full_parse() {
+        use crate::{
+            buffer::TextBuffer, editor::tests::new_test_editor, renderer::tests::MockRenderer,
+            LineCol,
+        };
+
+        let mut editor = new_test_editor(&["fn main() {", "    let s = \"é\";", "}"]);
+        editor.renderer = Box::new(MockRenderer::default());
+        editor.set_file_type(FileType::Rust);
+        editor.draw_lines().unwrap();
+
+        let at = |line, col| LineCol { line, col };
+        editor.buffer.save_state(at(0, 0));
+        editor
+            .buffer
+            .insert_text(at(1, 15), "ü + 1", false)
+            .unwrap();
+        editor.buffer.insert_newline(at(0, 11));
+        editor.buffer.delete_selection(at(2, 4), at(2, 8)).unwrap();
+        editor.draw_lines().unwrap();
+        editor.buffer.delete_line(1).unwrap();
+        // Back to the text before the first draw
+        editor.buffer.undo(at(0, 0)).unwrap();
+        editor
+            .buffer
+            .insert_text(at(0, 11), " // done", false)
+            .unwrap();
+        editor.draw_lines().unwrap();
+
+        let text = editor.buffer.get_coalesced_bytes();
+        let fresh = Highlighter::for_file_type(&text, FileType::Rust)
+            .unwrap()
+            .unwrap();
+        let incremental = editor.highlighter.as_ref().unwrap();
+        let sexp =
+            |highlighter: &Highlighter| highlighter.tree.as_ref().unwrap().root_node().to_sexp();
+        assert_eq!(sexp(incremental), sexp(&fresh));
+        assert!(!incremental.tree.as_ref().unwrap().root_node().has_error());
+    }
+
+    #[test]
+    fn test_file_types_without_grammar() {
+        assert!(Highlighter::for_file_type("x", FileType::PlainText)
+            .unwrap()
+            .is_none());
+        assert_eq!(Language::from_file_type(FileType::Go), None);
+    }
+}
@@ -0,0 +1,216 @@
+use std::{collections::VecDeque, path::Path, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{buffer::TextBuffer, editor::Editor, utils::data_dir, Error, Result};
+
+/// Default of the `history` option, the amount of entries kept of each history.
+pub const MAX_STORED_HISTORY: usize = 1000;
+
+/// File the command and search histories are persisted to,
+/// `~/.local/share/neotext/history.json`.
+pub fn history_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("history.json"))
+}
+
+/// The command and search histories as stored in the history file, newest entries first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryStore {
+    #[serde(default)]
+    pub command: VecDeque<String>,
+    #[serde(default)]
+    pub forwards: VecDeque<String>,
+    #[serde(default)]
+    pub backwards: VecDeque<String>,
+    /// Amount of entries kept of each history.
+    #[serde(skip, default = "default_max_len")]
+    max_len: usize,
+}
+
+const fn default_max_len() -> usize {
+    MAX_STORED_HISTORY
+}
+
+impl Default for HistoryStore {
+    fn default() -> Self {
+        Self::new(MAX_STORED_HISTORY)
+    }
+}
+
+impl HistoryStore {
+    pub const fn new(max_len: usize) -> Self {
+        Self {
+            command: VecDeque::new(),
+            forwards: VecDeque::new(),
+            backwards: VecDeque::new(),
+            max_len,
+        }
+    }
+
+    /// Stores copies of the given histories, dropping consecutive duplicates and the oldest
+    /// entries beyond the maximal length.
+    #[must_use]
+    pub fn with_histories(
+        mut self,
+        command: &VecDeque<String>,
+        forwards: &VecDeque<String>,
+        backwards: &VecDeque<String>,
+    ) -> Self {
+        self.command = self.limit(command);
+        self.forwards = self.limit(forwards);
+        self.backwards = self.limit(backwards);
+        self
+    }
+
+    fn limit(&self, history: &VecDeque<String>) -> VecDeque<String> {
+        let mut entries: Vec<&String> = history.iter().collect();
+        entries.dedup();
+        entries.into_iter().take(self.max_len).cloned().collect()
+    }
+
+    /// Reads the store from `path`, keeping at most `max_len` entries of each history. A missing
+    /// file gives an empty store.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file exists but can't be read and `Error::ParsingError` if it
+    /// isn't valid.
+    pub fn load(path: &Path, max_len: usize) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new(max_len)),
+            Err(e) => return Err(e.into()),
+        };
+        let stored: Self = serde_json::from_str(&content)
+            .map_err(|e| Error::ParsingError(format!("{}: {e}", path.display())))?;
+        Ok(Self::new(max_len).with_histories(&stored.command, &stored.forwards, &stored.backwards))
+    }
+
+    /// Writes the store to `path` as JSON, creating its directory if needed.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file or its parent directory can't be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::ParsingError(format!("Failed to serialize the history: {e}")))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Writes the histories to the history file in the user data directory.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file can't be written.
+    pub fn save_history(&self) -> Result<()> {
+        match history_path() {
+            Some(path) => self.save_history_to(&path),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the histories to `path`, keeping at most `max_history` entries of each.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file or its parent directory can't be written.
+    pub fn save_history_to(&self, path: &Path) -> Result<()> {
+        HistoryStore::new(self.options.max_history)
+            .with_histories(
+                &self.command_history,
+                &self.forwards_history,
+                &self.backwards_history,
+            )
+            .save(path)
+    }
+
+    /// Loads the histories from `path`, keeping at most `max_history` entries of each. A missing
+    /// file leaves the histories empty.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file exists but can't be read and `Error::ParsingError` if it
+    /// isn't valid.
+    pub fn load_history_from(&mut self, path: &Path) -> Result<()> {
+        let store = HistoryStore::load(path, self.options.max_history)?;
+        self.command_history = store.command;
+        self.forwards_history = store.forwards;
+        self.backwards_history = store.backwards;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::HistoryStore;
+    use crate::{buffer::TextBuffer, editor::tests::new_test_editor, modals::Modal};
+
+    #[test]
+    fn test_history_round_trip_with_dedup() {
+        let path = std::env::temp_dir()
+            .join(format!("neotext_history_{}", std::process::id()))
+            .join("history");
+
+        let mut editor = new_test_editor(&[""]);
+        editor.command_history = [":w", ":w", ":q", ":w"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        editor.forwards_history = ["/foo", "/bar", "/bar"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        editor.save_history_to(&path).unwrap();
+
+        let mut loaded = new_test_editor(&[""]);
+        loaded.load_history_from(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded.command_history, [":w", ":q", ":w"]);
+        assert_eq!(loaded.forwards_history, ["/foo", "/bar"]);
+        assert!(loaded.backwards_history.is_empty());
+
+        // The loaded commands are recalled in the command bar of the new session
+        loaded.set_mode(Modal::Command);
+        loaded.navigate_history_backwards().unwrap();
+        assert_eq!(loaded.buffer.get_command_text(), [":w"]);
+    }
+
+    #[test]
+    fn test_store_keeps_newest_entries() {
+        let history: VecDeque<String> = ["/c", "/b", "/b", "/a"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let store = HistoryStore::new(2).with_histories(&VecDeque::new(), &history, &history);
+        assert_eq!(store.forwards, ["/c", "/b"]);
+        assert!(store.command.is_empty());
+
+        // Entries with line breaks or looking like markup survive the round trip
+        let path = std::env::temp_dir()
+            .join(format!("neotext_history_store_{}", std::process::id()))
+            .join("history.json");
+        let odd: VecDeque<String> = ["first\nsecond", "[command]", "\"quoted\""]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        HistoryStore::default()
+            .with_histories(&odd, &VecDeque::new(), &VecDeque::new())
+            .save(&path)
+            .unwrap();
+        let loaded = HistoryStore::load(&path, 2).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded.command, ["first\nsecond", "[command]"]);
+    }
+
+    #[test]
+    fn test_missing_history_file() {
+        let mut editor = new_test_editor(&[""]);
+        let path = std::env::temp_dir().join("neotext_history_that_does_not_exist");
+        editor.load_history_from(&path).unwrap();
+        assert!(editor.command_history.is_empty());
+    }
+}
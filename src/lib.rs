@@ -0,0 +1,249 @@
+// Features to implement:
+//      TEXT EDITING:
+//          Syntax Highlighting
+//          Regex Command Processing
+//          Undo and Redo
+//          Terminal Mode
+//
+//          Macros
+//          Marks
+//
+//      PERFORMANCE:
+//          create a better DS for buffer
+//
+//      NEW CRATES:
+//          Telescope
+//          Configuration parsing and configurable controller
+//          LSP Integration
+//          Programmable Extensions
+//
+//      ADDONS:
+//          Screen Splits
+//          File Commands (After pressing :)  -- This is easy just inconvenient while development
+//          Different cursors (Visuals)
+//
+// Bugs To Fix:
+//      Constant crashing
+#![allow(dead_code, clippy::cast_possible_wrap)]
+use std::{fs::OpenOptions, io::Read, panic, path::PathBuf};
+
+mod error;
+use buffer::DefaultBuffer;
+use commands::{Command, INIT_SCRIPT};
+use config::Config;
+use editor::Editor;
+use error::{Error, Result};
+
+mod autosave;
+mod bars;
+pub mod buffer;
+mod commands;
+mod completion;
+mod config;
+mod copy_register;
+mod crash;
+mod cursor;
+mod editor;
+mod file_watch;
+mod folds;
+mod goto_file;
+mod highlighter;
+mod history;
+mod lsp;
+mod marks;
+mod modals;
+mod options;
+mod picker;
+mod quickfix;
+mod renderer;
+mod search;
+mod snippets;
+mod swap;
+mod tasks;
+mod text_objects;
+mod theme;
+mod utils;
+mod viewport;
+use clap::Parser;
+mod common;
+pub use common::*;
+pub use tracing::{error, info, span, warn, Instrument};
+pub use tracing_subscriber::{filter::EnvFilter, fmt::Subscriber, prelude::*, Layer};
+pub use tracing_tree::HierarchicalLayer;
+
+#[derive(Parser, Debug)]
+#[command(name = "neotext")]
+struct Cli {
+    #[arg(short, long)]
+    debug: bool,
+
+    // Open neotext on the the dedcicated testfile
+    #[arg(short = 't', long)]
+    test: bool,
+
+    // Theme file to use instead of ~/.config/neotext/theme.toml, Monokai if it can't be loaded
+    #[arg(long)]
+    theme: Option<PathBuf>,
+
+    // Read File on given path, this argument is the default argument being passed
+    #[arg(default_value = "")]
+    file: String,
+}
+/// Runs the editor on the file given on the command line, until it's closed.
+pub fn run() {
+    setup_panic();
+    let cli = Cli::parse();
+    setup_tracing(cli.debug);
+
+    let mut instance = initialize_editor(&cli);
+    instance.load_startup_theme(cli.theme.as_deref());
+    if utils::data_dir().is_some_and(|dir| crash::mark_crash_reports_seen(&dir) > 0) {
+        notif_bar!(instance => "Previous session crashed. Report at ~/.local/share/neotext/";);
+    }
+    let init_script = utils::expand_tilde(INIT_SCRIPT);
+    if init_script.exists() {
+        if let Err(Error::ExitCall) = instance.execute_command(Command::Source(init_script)) {
+            return;
+        }
+    }
+    instance.open_document();
+    if let Err(e) = instance.recover_autosave() {
+        error!("Failed to recover the autosave file: {e}");
+    }
+    if let Err(e) = instance.load_marks() {
+        error!("Failed to load the marks: {e}");
+    }
+    if let Err(e) = instance.recover_swap() {
+        error!("Failed to recover the swap file: {e}");
+    }
+    if let Err(e) = instance.set_swap_interval(instance.options.swap_interval) {
+        error!("Failed to start the swap file timer: {e}");
+    }
+    if let Err(e) = instance.watch_file_changes() {
+        error!("Failed to watch the edited file: {e}");
+    }
+
+    // The session is persisted when `instance` is dropped, see `Editor::end_session`
+    match instance.run_main_loop() {
+        Err(Error::ExitCall) => {}
+        Ok(()) => panic!("Editor should never return without an error"),
+        otherwise => {
+            info!("Err of type {otherwise:?} should be handled before reaching the main function.")
+        }
+    }
+}
+
+fn initialize_editor(cli: &Cli) -> Editor<DefaultBuffer> {
+    if cli.test {
+        return new_from_file(&"./test_file.ntxt".into());
+    }
+
+    if cli.file.is_empty() {
+        let mut editor = editor::Editor::new(DefaultBuffer::new(vec![" ".to_string()]), true);
+        let config = config::user_config_path()
+            .map(|path| Config::load(&path))
+            .unwrap_or_default();
+        if let Err(e) = editor.apply_config(&config) {
+            error!("Failed to apply the config: {e}");
+        }
+        editor
+    } else {
+        new_from_file(&cli.file.clone().into())
+    }
+}
+/// Creates a `MainEditor` instance from a file/
+/// Reads the file at `p`, converts its content to a `DefaultBuffer`,
+/// and initializes a `MainEditor` with this buffer.
+///
+/// # Arguments
+/// * `p` - Path to the file to be read.
+///
+/// # Returns
+/// A `MainEditor<DefaultBuffer>` with the file's content.
+///
+/// # Panics
+/// - If the file can't be read.
+/// - If the file content is not valid UTF-8.
+pub fn new_from_file(p: &PathBuf) -> Editor<DefaultBuffer> {
+    let readonly = utils::is_readonly(p);
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(!readonly)
+        .create(!readonly)
+        .truncate(false)
+        .open(p)
+        .expect("This should never fail.");
+
+    let mut content = String::new();
+    let _ = file.read_to_string(&mut content);
+
+    let buf = DefaultBuffer::new(content.lines().map(String::from).collect());
+    let file_type = utils::detect_filetype(p, content.lines().next().unwrap_or_default());
+    let mut editor = Editor::new(buf, false);
+    editor.set_file_type(file_type);
+    editor.set_file_path(p);
+    editor.options.readonly = readonly;
+    if let Err(e) = editor.apply_config(&Config::load_layered(p)) {
+        error!("Failed to apply the config: {e}");
+    }
+    editor.apply_modelines();
+    editor.refresh_git_diff();
+    editor
+}
+
+fn setup_tracing(debug: bool) {
+    let filter = EnvFilter::try_new("info, neotext = trace, crossterm = off")
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let stderr_layer = HierarchicalLayer::new(2)
+        .with_writer(std::io::stderr)
+        .with_targets(true)
+        .with_bracketed_fields(true);
+
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr_layer);
+
+    // Set debug to automatically output to a dbg file
+    if debug {
+        let file = std::fs::File::create("dbg").expect("Failed to create debug log file");
+        let file_layer = HierarchicalLayer::new(2)
+            .with_writer(file)
+            .with_targets(true)
+            .with_bracketed_fields(true)
+            .with_ansi(false);
+
+        subscriber.with(file_layer).init();
+    } else {
+        subscriber.init();
+    }
+}
+
+fn setup_panic() {
+    // Capture Panics
+    panic::set_hook(Box::new(|panic_info| {
+        let (filename, line) = panic_info
+            .location()
+            .map(|loc| (loc.file(), loc.line()))
+            .unwrap_or(("<unknown>", 0));
+
+        let cause = panic_info
+            .payload()
+            .downcast_ref::<String>()
+            .map(|s| s.as_str())
+            .or_else(|| panic_info.payload().downcast_ref::<&str>().copied())
+            .unwrap_or("<cause unknown>");
+
+        error!(
+            "Panic occurred in file '{}' at line {}: {}",
+            filename, line, cause
+        );
+
+        if let Some(dir) = utils::data_dir() {
+            match crash::write_crash_report(&dir, panic_info) {
+                Ok(path) => error!("Crash report written to {}", path.display()),
+                Err(e) => error!("Failed to write the crash report: {e}"),
+            }
+        }
+    }));
+}
@@ -1,8 +1,167 @@
-use super::data::initialize_params;
-use crate::Result;
-pub struct LSPClient {}
+use super::{
+    completion::CompletionItem,
+    data::{Body, Notification, Request},
+    formatting::{FormattingOptions, TextEdit},
+    parser::write_lsp_message,
+    SemanticTokens,
+};
+use crate::{buffer::IncrementalChange, Error, LineCol, Result};
+use std::{collections::HashMap, io::Write};
+
+#[derive(Debug, Default)]
+pub struct LSPClient {
+    /// File type of the documents the server is sent.
+    file_type: FileType,
+    /// Versions of the open documents by URI, increased with every `didChange`.
+    document_versions: HashMap<String, i32>,
+}
+
+/// Languages recognized by the editor, used for picking the highlighter and the language server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileType {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Bash,
+    C,
+    Cpp,
+    Go,
+    Lua,
+    Ruby,
+    Perl,
+    Toml,
+    Json,
+    Markdown,
+    Make,
+    #[default]
+    PlainText,
+}
+
+impl FileType {
+    /// Command starting the language server of the file type, `None` if there is none.
+    pub const fn language_server(self) -> Option<&'static str> {
+        match self {
+            Self::Rust => Some("rust-analyzer"),
+            Self::Python => Some("pylsp"),
+            Self::JavaScript | Self::TypeScript => Some("typescript-language-server"),
+            Self::Bash => Some("bash-language-server"),
+            Self::C | Self::Cpp => Some("clangd"),
+            Self::Go => Some("gopls"),
+            Self::Lua => Some("lua-language-server"),
+            Self::Ruby => Some("solargraph"),
+            Self::Perl
+            | Self::Toml
+            | Self::Json
+            | Self::Markdown
+            | Self::Make
+            | Self::PlainText => None,
+        }
+    }
+
+    /// Identifier of the file type in the protocol, sent along with opened documents.
+    pub const fn language_id(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::JavaScript => "javascript",
+            Self::TypeScript => "typescript",
+            Self::Bash => "shellscript",
+            Self::C => "c",
+            Self::Cpp => "cpp",
+            Self::Go => "go",
+            Self::Lua => "lua",
+            Self::Ruby => "ruby",
+            Self::Perl => "perl",
+            Self::Toml => "toml",
+            Self::Json => "json",
+            Self::Markdown => "markdown",
+            Self::Make => "makefile",
+            Self::PlainText => "plaintext",
+        }
+    }
+
+    /// Maps a file extension (without the leading dot) to its file type.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let file_type = match extension {
+            "rs" => Self::Rust,
+            "py" | "pyw" => Self::Python,
+            "js" | "mjs" | "cjs" => Self::JavaScript,
+            "ts" | "tsx" => Self::TypeScript,
+            "sh" | "bash" | "zsh" => Self::Bash,
+            "c" | "h" => Self::C,
+            "cpp" | "cc" | "cxx" | "hpp" => Self::Cpp,
+            "go" => Self::Go,
+            "lua" => Self::Lua,
+            "rb" => Self::Ruby,
+            "pl" | "pm" => Self::Perl,
+            "toml" => Self::Toml,
+            "json" => Self::Json,
+            "md" | "markdown" => Self::Markdown,
+            "mk" => Self::Make,
+            _ => return None,
+        };
+        Some(file_type)
+    }
+
+    /// Maps a vim filetype name, as used by `:set ft=` and modelines, to its file type.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let file_type = match name {
+            "rust" => Self::Rust,
+            "python" => Self::Python,
+            "javascript" => Self::JavaScript,
+            "typescript" => Self::TypeScript,
+            "sh" | "bash" | "zsh" => Self::Bash,
+            "c" => Self::C,
+            "cpp" => Self::Cpp,
+            "go" => Self::Go,
+            "lua" => Self::Lua,
+            "ruby" => Self::Ruby,
+            "perl" => Self::Perl,
+            "toml" => Self::Toml,
+            "json" => Self::Json,
+            "markdown" => Self::Markdown,
+            "make" => Self::Make,
+            "text" => Self::PlainText,
+            _ => return None,
+        };
+        Some(file_type)
+    }
+
+    /// Maps the name of an interpreter found in a shebang to its file type. Trailing version
+    /// numbers are ignored, so `python3` and `python3.12` both map to `Python`.
+    pub fn from_interpreter(interpreter: &str) -> Option<Self> {
+        let name = interpreter.trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+        let file_type = match name {
+            "python" | "pypy" => Self::Python,
+            "sh" | "bash" | "zsh" | "dash" | "ksh" => Self::Bash,
+            "node" | "nodejs" => Self::JavaScript,
+            "deno" | "ts-node" => Self::TypeScript,
+            "lua" | "luajit" => Self::Lua,
+            "ruby" => Self::Ruby,
+            "perl" => Self::Perl,
+            "make" => Self::Make,
+            _ => return None,
+        };
+        Some(file_type)
+    }
+}
 
 impl LSPClient {
+    /// Client of the language server for documents of `file_type`.
+    pub fn new(file_type: FileType) -> Self {
+        Self {
+            file_type,
+            document_versions: HashMap::new(),
+        }
+    }
+    pub const fn file_type(&self) -> FileType {
+        self.file_type
+    }
+    /// Version of the open document at `uri`, `None` if it wasn't opened.
+    pub fn document_version(&self, uri: &str) -> Option<i32> {
+        self.document_versions.get(uri).copied()
+    }
     // Not every language server can support all features defined by the protocol.
     // LSP therefore provides ‘capabilities’. A capability groups a set of language features.
     // A development tool and the language server announce their supported features using capabilities.
@@ -17,11 +176,71 @@ impl LSPClient {
     fn initialize() {
         todo!()
     }
-    fn send_request() -> Result<()> {
-        todo!()
+    /// Requests the semantic tokens of the whole document at `uri`, to be decoded with
+    /// `SemanticTokenDecoder` using the legend from the server's capabilities.
+    ///
+    /// # Errors
+    /// Returns `Error::LspConnectionLost` while the client has no connection to a server.
+    pub fn request_semantic_tokens(&self, uri: &str) -> Result<SemanticTokens> {
+        let _request = Request::semantic_tokens_req(1, uri);
+        // There is no transport to a server yet, see `send_request`
+        Err(Error::LspConnectionLost)
     }
-    fn send_notification() -> Result<()> {
-        todo!()
+    /// Requests the edits formatting `range` of the document at `uri`, with columns counted in
+    /// characters.
+    ///
+    /// # Errors
+    /// Returns `Error::LspConnectionLost` while the client has no connection to a server.
+    pub fn request_range_formatting(
+        &self,
+        uri: &str,
+        range: (LineCol, LineCol),
+        options: FormattingOptions,
+    ) -> Result<Vec<TextEdit>> {
+        let _request = Request::range_formatting_req(1, uri, range, &options);
+        // There is no transport to a server yet, see `send_request`
+        Err(Error::LspConnectionLost)
+    }
+    /// Requests the completions at `position` of the document at `uri`, its column counted in
+    /// characters.
+    ///
+    /// # Errors
+    /// Returns `Error::LspConnectionLost` while the client has no connection to a server.
+    pub fn request_completion(&self, uri: &str, position: LineCol) -> Result<Vec<CompletionItem>> {
+        let _request = Request::completion_req(1, uri, position);
+        // There is no transport to a server yet, see `send_request`
+        Err(Error::LspConnectionLost)
+    }
+    /// Notifies the server that the document at `uri` was opened with `text`.
+    ///
+    /// # Errors
+    /// Returns `Error::LspConnectionLost` while the client has no connection to a server.
+    pub fn did_open(&mut self, uri: &str, text: &str) -> Result<()> {
+        self.document_versions.insert(uri.to_string(), 0);
+        let _notification = Notification::did_open(uri, self.file_type.language_id(), 0, text);
+        // There is no transport to a server yet, see `send_notification`
+        Err(Error::LspConnectionLost)
+    }
+    /// Notifies the server of the edits made to the document at `uri` since the last
+    /// notification. Does nothing if there are none.
+    ///
+    /// # Errors
+    /// Returns `Error::LspConnectionLost` while the client has no connection to a server.
+    pub fn did_change(&mut self, uri: &str, changes: &[IncrementalChange]) -> Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let version = self.document_versions.entry(uri.to_string()).or_default();
+        *version += 1;
+        let _notification = Notification::did_change(uri, *version, changes);
+        // There is no transport to a server yet, see `send_notification`
+        Err(Error::LspConnectionLost)
+    }
+    fn send_request(writer: &mut impl Write, request: Request) -> Result<()> {
+        write_lsp_message(writer, &Body::Request(request))
+    }
+    fn send_notification(writer: &mut impl Write, notification: Notification) -> Result<()> {
+        write_lsp_message(writer, &Body::Notification(notification))
     }
 }
 enum LSPAction {}
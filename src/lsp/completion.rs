@@ -0,0 +1,72 @@
+use serde::Deserialize;
+
+/// A completion candidate offered by the server.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionItem {
+    pub label: String,
+    /// Text inserted when the item is accepted, the label if missing.
+    #[serde(default)]
+    pub insert_text: Option<String>,
+    /// Text matched against what was typed, the label if missing.
+    #[serde(default)]
+    pub filter_text: Option<String>,
+}
+
+impl CompletionItem {
+    pub fn insert_text(&self) -> &str {
+        self.insert_text.as_deref().unwrap_or(&self.label)
+    }
+
+    pub fn filter_text(&self) -> &str {
+        self.filter_text.as_deref().unwrap_or(&self.label)
+    }
+}
+
+/// Result of a completion request, which servers send either as a bare list of items or wrapped
+/// in a `CompletionList`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum CompletionResponse {
+    Items(Vec<CompletionItem>),
+    List { items: Vec<CompletionItem> },
+}
+
+impl From<CompletionResponse> for Vec<CompletionItem> {
+    fn from(response: CompletionResponse) -> Self {
+        match response {
+            CompletionResponse::Items(items) | CompletionResponse::List { items } => items,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lsp::data::Request, LineCol};
+
+    #[test]
+    fn test_completion_request() {
+        let request =
+            Request::completion_req(2, "file:///src/main.rs", LineCol { line: 4, col: 7 });
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["method"], "textDocument/completion");
+        assert_eq!(json["params"]["textDocument"]["uri"], "file:///src/main.rs");
+        assert_eq!(json["params"]["position"]["line"], 4);
+        assert_eq!(json["params"]["position"]["character"], 7);
+    }
+
+    #[test]
+    fn test_completion_response_forms() {
+        let bare: CompletionResponse =
+            serde_json::from_str(r#"[{"label": "format", "insertText": "format!"}]"#).unwrap();
+        let items = Vec::from(bare);
+        assert_eq!(items[0].insert_text(), "format!");
+        assert_eq!(items[0].filter_text(), "format");
+
+        let list: CompletionResponse =
+            serde_json::from_str(r#"{"isIncomplete": false, "items": [{"label": "from_utf8"}]}"#)
+                .unwrap();
+        assert_eq!(Vec::from(list)[0].insert_text(), "from_utf8");
+    }
+}
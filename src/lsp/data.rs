@@ -1,4 +1,5 @@
-use crate::{Error, Result};
+use super::formatting::FormattingOptions;
+use crate::{buffer::IncrementalChange, Error, LineCol, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -127,7 +128,7 @@ pub struct Response {
 }
 
 type LSPObject = HashMap<String, LSPAny>;
-type LSPArray = Vec<usize>;
+type LSPArray = Vec<LSPAny>;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -205,6 +206,7 @@ enum HeaderType {
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Notification {
+    jsonrpc: String,
     method: String,
     // Only Object or Array Param is allowed
     params: Params,
@@ -229,7 +231,148 @@ impl Default for Request {
     }
 }
 
+impl Notification {
+    /// `textDocument/didOpen` carrying the whole `text` of the opened document.
+    pub fn did_open(uri: &str, language_id: &str, version: i32, text: &str) -> Self {
+        let mut params: LSPObject = HashMap::new();
+        let mut text_document: LSPObject = HashMap::new();
+        insert!(text_document, "uri", uri);
+        insert!(text_document, "languageId", language_id);
+        insert!(text_document, "version", version);
+        insert!(text_document, "text", text);
+        insert!(params, "textDocument", text_document);
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: "textDocument/didOpen".to_string(),
+            params: Params::Named(params),
+        }
+    }
+    /// `textDocument/didChange` carrying `changes` as incremental content changes, so only the
+    /// edited ranges are sent instead of the whole document.
+    pub fn did_change(uri: &str, version: i32, changes: &[IncrementalChange]) -> Self {
+        let position = |pos: LineCol| {
+            let mut position: LSPObject = HashMap::new();
+            insert!(
+                position,
+                "line",
+                u32::try_from(pos.line).unwrap_or(u32::MAX)
+            );
+            insert!(
+                position,
+                "character",
+                u32::try_from(pos.col).unwrap_or(u32::MAX)
+            );
+            position
+        };
+        let content_changes: LSPArray = changes
+            .iter()
+            .map(|change| {
+                let mut range: LSPObject = HashMap::new();
+                insert!(range, "start", position(change.start));
+                insert!(range, "end", position(change.end));
+                let mut event: LSPObject = HashMap::new();
+                insert!(event, "range", range);
+                insert!(
+                    event,
+                    "rangeLength",
+                    u32::try_from(change.range_length).unwrap_or(u32::MAX)
+                );
+                insert!(event, "text", change.text.as_str());
+                LSPAny::Object(event)
+            })
+            .collect();
+        let mut params: LSPObject = HashMap::new();
+        let mut text_document: LSPObject = HashMap::new();
+        insert!(text_document, "uri", uri);
+        insert!(text_document, "version", version);
+        insert!(params, "textDocument", text_document);
+        insert!(params, "contentChanges", content_changes);
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: "textDocument/didChange".to_string(),
+            params: Params::Named(params),
+        }
+    }
+}
+
 impl Request {
+    pub fn semantic_tokens_req(id: usize, uri: &str) -> Self {
+        let mut params: LSPObject = HashMap::new();
+        let mut text_document: LSPObject = HashMap::new();
+        insert!(text_document, "uri", uri);
+        insert!(params, "textDocument", text_document);
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: "textDocument/semanticTokens/full".to_string(),
+            params: Params::Named(params),
+        }
+    }
+    /// Columns of `range` are counted in characters, as the protocol expects.
+    pub fn range_formatting_req(
+        id: usize,
+        uri: &str,
+        range: (LineCol, LineCol),
+        options: &FormattingOptions,
+    ) -> Self {
+        let position = |pos: LineCol| {
+            let mut position: LSPObject = HashMap::new();
+            insert!(
+                position,
+                "line",
+                u32::try_from(pos.line).unwrap_or(u32::MAX)
+            );
+            insert!(
+                position,
+                "character",
+                u32::try_from(pos.col).unwrap_or(u32::MAX)
+            );
+            position
+        };
+        let mut params: LSPObject = HashMap::new();
+        let mut text_document: LSPObject = HashMap::new();
+        insert!(text_document, "uri", uri);
+        let mut lsp_range: LSPObject = HashMap::new();
+        insert!(lsp_range, "start", position(range.0));
+        insert!(lsp_range, "end", position(range.1));
+        let mut lsp_options: LSPObject = HashMap::new();
+        insert!(lsp_options, "tabSize", options.tab_size);
+        insert!(lsp_options, "insertSpaces", options.insert_spaces);
+        insert!(params, "textDocument", text_document);
+        insert!(params, "range", lsp_range);
+        insert!(params, "options", lsp_options);
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: "textDocument/rangeFormatting".to_string(),
+            params: Params::Named(params),
+        }
+    }
+    /// Requests the completions at `position`, its column counted in characters.
+    pub fn completion_req(id: usize, uri: &str, position: LineCol) -> Self {
+        let mut params: LSPObject = HashMap::new();
+        let mut text_document: LSPObject = HashMap::new();
+        insert!(text_document, "uri", uri);
+        let mut lsp_position: LSPObject = HashMap::new();
+        insert!(
+            lsp_position,
+            "line",
+            u32::try_from(position.line).unwrap_or(u32::MAX)
+        );
+        insert!(
+            lsp_position,
+            "character",
+            u32::try_from(position.col).unwrap_or(u32::MAX)
+        );
+        insert!(params, "textDocument", text_document);
+        insert!(params, "position", lsp_position);
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: "textDocument/completion".to_string(),
+            params: Params::Named(params),
+        }
+    }
     pub fn initialization_req(initializer_params: Params) -> Self {
         Self {
             jsonrpc: "2.0".to_string(),
@@ -0,0 +1,116 @@
+use serde::Deserialize;
+
+use crate::{buffer::TextBuffer, LineCol, Result};
+
+/// Options sent along with formatting requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormattingOptions {
+    pub tab_size: u32,
+    pub insert_spaces: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A change returned by the server, positions are counted in characters.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+/// Byte column of the `character`th character of `line`, clamped to its end.
+fn byte_col(line: &str, character: usize) -> usize {
+    line.char_indices()
+        .nth(character)
+        .map_or(line.len(), |(byte, _)| byte)
+}
+
+/// Character column of the byte column `col` of `line`, as sent to the server.
+pub fn char_col(line: &str, col: usize) -> usize {
+    line.get(..col)
+        .map_or(line.chars().count(), |before| before.chars().count())
+}
+
+/// Applies the `edits` of a formatting response. Edits don't overlap and refer to the text
+/// before any of them is applied, so they're applied from the last one backwards.
+///
+/// # Errors
+/// Returns `Error::InvalidRange` if an edit lies outside of the buffer.
+pub fn apply_text_edits(buffer: &mut impl TextBuffer, edits: &[TextEdit]) -> Result<()> {
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    edits.sort_by_key(|edit| (edit.range.start.line, edit.range.start.character));
+    for edit in edits.into_iter().rev() {
+        let position = |pos: Position| -> Result<LineCol> {
+            let line = buffer.line(pos.line)?;
+            Ok(LineCol {
+                line: pos.line,
+                col: byte_col(line, pos.character),
+            })
+        };
+        let start = position(edit.range.start)?;
+        let end = position(edit.range.end)?;
+        if edit.new_text.is_empty() {
+            if start != end {
+                buffer.delete_selection(start, end)?;
+            }
+        } else {
+            buffer.replace(start, end, &edit.new_text)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::VecBuffer, lsp::data::Request};
+
+    #[test]
+    fn test_range_formatting_request() {
+        let options = FormattingOptions {
+            tab_size: 4,
+            insert_spaces: true,
+        };
+        let request = Request::range_formatting_req(
+            3,
+            "file:///src/main.rs",
+            (LineCol { line: 1, col: 0 }, LineCol { line: 2, col: 5 }),
+            &options,
+        );
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["method"], "textDocument/rangeFormatting");
+        assert_eq!(json["params"]["textDocument"]["uri"], "file:///src/main.rs");
+        assert_eq!(json["params"]["range"]["start"]["line"], 1);
+        assert_eq!(json["params"]["range"]["start"]["character"], 0);
+        assert_eq!(json["params"]["range"]["end"]["line"], 2);
+        assert_eq!(json["params"]["range"]["end"]["character"], 5);
+        assert_eq!(json["params"]["options"]["tabSize"], 4);
+        assert_eq!(json["params"]["options"]["insertSpaces"], true);
+    }
+
+    #[test]
+    fn test_apply_text_edits() {
+        let mut buffer = VecBuffer::new(vec!["fn  main(){".to_string(), "x;}".to_string()]);
+        let edits: Vec<TextEdit> = serde_json::from_str(
+            r#"[
+                {"range": {"start": {"line": 0, "character": 2}, "end": {"line": 0, "character": 4}}, "newText": " "},
+                {"range": {"start": {"line": 0, "character": 10}, "end": {"line": 0, "character": 10}}, "newText": " "},
+                {"range": {"start": {"line": 1, "character": 2}, "end": {"line": 1, "character": 2}}, "newText": "\n"}
+            ]"#,
+        )
+        .unwrap();
+        apply_text_edits(&mut buffer, &edits).unwrap();
+        assert_eq!(buffer.get_normal_text(), ["fn main() {", "x;", "}"]);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use super::{FileType, LSPClient};
+
+/// The language servers of the session, one per file type, each only told about the documents
+/// of its own type.
+#[derive(Debug, Default)]
+pub struct LspManager {
+    clients: HashMap<FileType, LSPClient>,
+}
+
+impl LspManager {
+    /// Client of the server for `file_type`, started when the first file of that type is
+    /// opened. `None` if no language server is known for the file type.
+    pub fn get_or_start(&mut self, file_type: FileType) -> Option<&mut LSPClient> {
+        file_type.language_server()?;
+        Some(
+            self.clients
+                .entry(file_type)
+                .or_insert_with(|| LSPClient::new(file_type)),
+        )
+    }
+
+    /// Client of the already running server for `file_type`.
+    pub fn get_mut(&mut self, file_type: FileType) -> Option<&mut LSPClient> {
+        self.clients.get_mut(&file_type)
+    }
+
+    /// Amount of running servers.
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::IncrementalChange, LineCol};
+
+    #[test]
+    fn test_one_server_per_file_type() {
+        let mut manager = LspManager::default();
+        assert!(manager.get_or_start(FileType::PlainText).is_none());
+
+        let rust = manager.get_or_start(FileType::Rust).unwrap();
+        let _ = rust.did_open("file:///main.rs", "fn main() {}");
+        let _ = manager
+            .get_or_start(FileType::Python)
+            .unwrap()
+            .did_open("file:///main.py", "pass");
+        manager.get_or_start(FileType::Rust).unwrap();
+        assert_eq!(manager.len(), 2);
+
+        let change = IncrementalChange {
+            start: LineCol { line: 0, col: 0 },
+            end: LineCol { line: 0, col: 0 },
+            range_length: 0,
+            text: "x".to_string(),
+        };
+        let rust = manager.get_mut(FileType::Rust).unwrap();
+        let _ = rust.did_change("file:///main.rs", &[change]);
+        assert_eq!(rust.document_version("file:///main.rs"), Some(1));
+        assert_eq!(rust.document_version("file:///main.py"), None);
+        let python = manager.get_mut(FileType::Python).unwrap();
+        assert_eq!(python.document_version("file:///main.py"), Some(0));
+    }
+}
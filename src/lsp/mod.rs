@@ -1,3 +1,13 @@
 mod client;
+mod completion;
 mod data;
+mod formatting;
+mod manager;
 mod parser;
+mod semantic_tokens;
+
+pub use client::{FileType, LSPClient};
+pub use completion::CompletionItem;
+pub use formatting::{apply_text_edits, char_col, FormattingOptions};
+pub use manager::LspManager;
+pub use semantic_tokens::{overlay_semantic_tokens, SemanticToken, SemanticTokens};
@@ -1,7 +1,9 @@
+use std::io::Write;
+
 use super::data::{Body, Header};
 use crate::{Error, Result};
 
-const CRLF: &str = r"\r\n";
+const CRLF: &str = "\r\n";
 const CRLF_BYTE_LEN: usize = CRLF.len();
 
 struct LspParser<'pl> {
@@ -58,7 +60,7 @@ impl<'pl> LspParser<'pl> {
             end_pointer: 0,
         }
     }
-    fn parse(&mut self) -> Result<Content> {
+    fn parse(&mut self) -> Result<Content<'_>> {
         let mut content = ContentBuilder::new();
         content = self.parse_header(content)?;
         content = self.parse_body(content)?;
@@ -68,9 +70,11 @@ impl<'pl> LspParser<'pl> {
         let length = content.header.clone().unwrap().content_length;
 
         let body_str = &self.payload[self.start_pointer..self.start_pointer + length as usize];
-        let body: Body = serde_json::from_str(body_str).map_err(|e| {
-            Error::ParsingError(format!("Deserializing body with serde failed: {e}"))
-        })?;
+        let body: Body =
+            serde_json::from_str(body_str).map_err(|e| Error::LspDeserializationFailed {
+                method: message_method(body_str),
+                detail: e.to_string(),
+            })?;
         Ok(content.add_body(body))
     }
     fn parse_header(&mut self, content: ContentBuilder<'pl>) -> Result<ContentBuilder<'pl>> {
@@ -93,11 +97,12 @@ impl<'pl> LspParser<'pl> {
                 + self.payload[self.start_pointer..]
                     .find(CRLF)
                     .ok_or(Error::ParsingError(
-                        "Couldn't find `\r\n` delimiter after a header section of the payload."
+                        "Couldn't find `\\r\\n` delimiter after a header section of the payload."
                             .to_string(),
                     ))?;
 
-            let value = &self.payload[self.start_pointer..self.end_pointer];
+            // Servers usually put a space after the `:`
+            let value = self.payload[self.start_pointer..self.end_pointer].trim();
             self.start_pointer = self.end_pointer;
 
             match name {
@@ -126,24 +131,45 @@ impl<'pl> LspParser<'pl> {
     }
 }
 
+/// Serializes `body` into a message ready to be sent to a language server, its JSON prefixed
+/// with a `Content-Length` header counting the bytes of the JSON.
+///
+/// # Errors
+/// Returns `Error::ParsingError` if `body` can't be serialized.
+pub fn serialize_lsp_message(body: &Body) -> Result<Vec<u8>> {
+    let json = serde_json::to_string(body)
+        .map_err(|e| Error::ParsingError(format!("Failed serializing the LSP message: {e}")))?;
+    let mut message = format!("Content-Length: {}{CRLF}{CRLF}", json.len()).into_bytes();
+    message.extend_from_slice(json.as_bytes());
+    Ok(message)
+}
+
+/// Writes `body` as a complete message to `writer`, the standard input of a language server.
+///
+/// # Errors
+/// Returns `Error::ParsingError` if `body` can't be serialized and `Error::Io` if writing fails.
+pub fn write_lsp_message(writer: &mut impl Write, body: &Body) -> Result<()> {
+    writer.write_all(&serialize_lsp_message(body)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Method of a raw JSON-RPC message, responses carry none and are reported as `response`.
+fn message_method(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| Some(value.get("method")?.as_str()?.to_string()))
+        .unwrap_or_else(|| "response".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    fn create_test_bytes(text: &str) -> Vec<u8> {
-        text.chars()
-            .flat_map(|c| match c {
-                '\r' => vec![b'\\', b'r'],
-                '\n' => vec![b'\\', b'n'],
-                _ => vec![c as u8],
-            })
-            .collect()
-    }
     #[test]
     fn parse_buffer_header() {
-        let bytes =
-            create_test_bytes("Content-Length:40\r\nContent-Type:something\r\n\r\nDontparse\n");
+        let bytes = "Content-Length:40\r\nContent-Type:something\r\n\r\nDontparse\n".as_bytes();
         let mut content_builder = ContentBuilder::new();
-        let mut parser = LspParser::new(&bytes);
+        let mut parser = LspParser::new(bytes);
         content_builder = parser.parse_header(content_builder).unwrap();
         let header = content_builder.header.unwrap();
         assert_eq!(header.content_type.unwrap(), "something");
@@ -152,9 +178,9 @@ mod tests {
 
     #[test]
     fn parse_buffer_header_length_only() {
-        let bytes = create_test_bytes("Content-Length:40\r\n\r\nDontparse\n");
+        let bytes = "Content-Length:40\r\n\r\nDontparse\n".as_bytes();
         let mut content_builder = ContentBuilder::new();
-        let mut parser = LspParser::new(&bytes);
+        let mut parser = LspParser::new(bytes);
         content_builder = parser.parse_header(content_builder).unwrap();
         let header = content_builder.header.unwrap();
         assert!(header.content_type.is_none());
@@ -163,9 +189,9 @@ mod tests {
 
     #[test]
     fn parse_buffer_header_invalid_no_content_length() {
-        let bytes = create_test_bytes("Content-Type:something\r\n\r\nDontparse\n");
+        let bytes = "Content-Type:something\r\n\r\nDontparse\n".as_bytes();
         let content_builder = ContentBuilder::new();
-        let mut parser = LspParser::new(&bytes);
+        let mut parser = LspParser::new(bytes);
         let result = parser.parse_header(content_builder);
         assert!(result.is_err());
     }
@@ -176,13 +202,44 @@ mod tests {
         let body = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"textDocument/completion\",\"params\":{\"textDocument\":{\"uri\":\"file:///path/to/file.rs\"},\"position\":{\"line\":10,\"character\":15}}}".trim();
 
         let payload = format!("{}{}", header, body);
-        let bytes = create_test_bytes(&payload);
+        let bytes = payload.as_bytes();
         let mut content_builder = ContentBuilder::new();
-        let mut parser = LspParser::new(&bytes);
+        let mut parser = LspParser::new(bytes);
         content_builder = parser.parse_header(content_builder).unwrap();
         content_builder = parser.parse_body(content_builder).unwrap();
         let body = content_builder.body.unwrap();
         assert!(body.is_request());
         assert_eq!(Body::default(), body)
     }
+
+    #[test]
+    fn serialize_message_round_trip() {
+        let body = Body::default();
+        let mut written = vec![];
+        write_lsp_message(&mut written, &body).unwrap();
+
+        let json = serde_json::to_string(&body).unwrap();
+        let header = format!("Content-Length: {}\r\n\r\n", json.len());
+        assert!(written.starts_with(header.as_bytes()));
+        assert_eq!(&written[header.len()..], json.as_bytes());
+
+        let mut parser = LspParser::new(&written);
+        assert_eq!(parser.parse().unwrap().body, body);
+    }
+
+    #[test]
+    fn parse_buffer_body_invalid() {
+        let body = "{\"jsonrpc\":\"2.0\",\"method\":\"initialized\",\"params\":7}";
+        let payload = format!("Content-Length:{}\r\n\r\n{body}", body.len());
+        let bytes = payload.as_bytes();
+        let mut parser = LspParser::new(bytes);
+        let content_builder = parser.parse_header(ContentBuilder::new()).unwrap();
+        match parser.parse_body(content_builder) {
+            Err(Error::LspDeserializationFailed { method, .. }) => {
+                assert_eq!(method, "initialized")
+            }
+            Err(e) => panic!("Expected a deserialization error, got {e:?}"),
+            Ok(_) => panic!("Expected the body to be rejected"),
+        }
+    }
 }
@@ -0,0 +1,162 @@
+use rangemap::RangeMap;
+use serde::Deserialize;
+
+use crate::{highlighter::Style, theme::Theme, LineCol};
+use crossterm::style::Color;
+
+/// Names of the token types and modifiers a server uses, announced in its
+/// `semanticTokensProvider` capability. Tokens refer to them by index.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensLegend {
+    pub token_types: Vec<String>,
+    pub token_modifiers: Vec<String>,
+}
+
+/// Result of a `textDocument/semanticTokens/full` request.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokens {
+    pub result_id: Option<String>,
+    /// Tokens as groups of five integers, positions relative to the previous token.
+    pub data: Vec<u32>,
+}
+
+/// A decoded token, columns are counted in characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub range: (LineCol, LineCol),
+    pub token_type: String,
+    /// Bit set of indices into the legend's token modifiers.
+    pub modifiers: u32,
+}
+
+pub struct SemanticTokenDecoder;
+
+impl SemanticTokenDecoder {
+    /// Decodes the relative encoding of the protocol into absolute positions. Each token is
+    /// `[delta_line, delta_start, length, token_type, modifiers]`, where `delta_start` is
+    /// relative to the previous token's start only if both are on the same line. Tokens with a
+    /// type missing from the legend and a trailing incomplete group are skipped.
+    pub fn decode(data: &[u32], legend: &SemanticTokensLegend) -> Vec<SemanticToken> {
+        let mut line = 0;
+        let mut start = 0;
+        data.chunks_exact(5)
+            .filter_map(|token| {
+                let [delta_line, delta_start, length, token_type, modifiers] = token else {
+                    return None;
+                };
+                if *delta_line > 0 {
+                    line += *delta_line as usize;
+                    start = 0;
+                }
+                start += *delta_start as usize;
+                let token_type = legend.token_types.get(*token_type as usize)?;
+                Some(SemanticToken {
+                    range: (
+                        LineCol { line, col: start },
+                        LineCol {
+                            line,
+                            col: start + *length as usize,
+                        },
+                    ),
+                    token_type: token_type.clone(),
+                    modifiers: *modifiers,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Overrides the styles in `style_map` with those of the semantic `tokens` that the theme has a
+/// color for. `lines` is the text the tokens were computed for.
+pub fn overlay_semantic_tokens(
+    style_map: &mut RangeMap<usize, Style>,
+    tokens: &[SemanticToken],
+    lines: &[String],
+    theme: &dyn Theme,
+) {
+    let mut line_starts = Vec::with_capacity(lines.len());
+    let mut offset = 0;
+    for line in lines {
+        line_starts.push(offset);
+        offset += line.len() + 1;
+    }
+
+    for token in tokens {
+        let (start, end) = token.range;
+        let (Some(line), Some(line_start)) = (lines.get(start.line), line_starts.get(start.line))
+        else {
+            continue;
+        };
+        let fg = theme.from_str(&token.token_type);
+        if fg == Color::Reset {
+            continue;
+        }
+        let byte_col = |col: usize| {
+            line.char_indices()
+                .nth(col)
+                .map_or(line.len(), |(byte, _)| byte)
+        };
+        let range = line_start + byte_col(start.col)..line_start + byte_col(end.col);
+        if !range.is_empty() {
+            style_map.insert(range, Style::new(fg, Color::Reset, false, false));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::DefaultTheme;
+
+    fn legend() -> SemanticTokensLegend {
+        SemanticTokensLegend {
+            token_types: vec!["variable".to_string(), "function".to_string()],
+            token_modifiers: vec!["declaration".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_decode_relative_positions() {
+        // `let x = f(y);` followed by a call on the next line
+        let data = [0, 4, 1, 0, 1, 0, 4, 1, 1, 0, 1, 2, 3, 1, 0, 0, 5, 1, 7, 0];
+        let tokens = SemanticTokenDecoder::decode(&data, &legend());
+        let ranges: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.range, token.token_type.as_str()))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (
+                    (LineCol { line: 0, col: 4 }, LineCol { line: 0, col: 5 }),
+                    "variable"
+                ),
+                (
+                    (LineCol { line: 0, col: 8 }, LineCol { line: 0, col: 9 }),
+                    "function"
+                ),
+                (
+                    (LineCol { line: 1, col: 2 }, LineCol { line: 1, col: 5 }),
+                    "function"
+                ),
+            ]
+        );
+        assert_eq!(tokens[0].modifiers, 1);
+    }
+
+    #[test]
+    fn test_overlay_overrides_tree_sitter_styles() {
+        let lines = vec!["let x = f(y);".to_string(), "  foo();".to_string()];
+        let mut style_map = RangeMap::new();
+        style_map.insert(0..20, Style::new(Color::Red, Color::Reset, false, false));
+
+        let tokens = SemanticTokenDecoder::decode(&[1, 2, 3, 1, 0], &legend());
+        overlay_semantic_tokens(&mut style_map, &tokens, &lines, &DefaultTheme {});
+
+        assert_eq!(style_map.get(&16).unwrap().fg, Color::Yellow);
+        assert_eq!(style_map.get(&15).unwrap().fg, Color::Red);
+        assert_eq!(style_map.get(&19).unwrap().fg, Color::Red);
+    }
+}
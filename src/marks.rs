@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buffer::TextBuffer, editor::Editor, notif_bar, utils::data_dir, Error, LineCol, Result,
+};
+
+/// Amount of marks kept in the marks file across all files, the oldest ones are evicted first.
+pub const MAX_STORED_MARKS: usize = 1000;
+
+/// File the marks of every edited file are persisted to, `~/.local/share/neotext/marks.toml`.
+pub fn marks_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("marks.toml"))
+}
+
+/// A mark as stored in the marks file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredMark {
+    pub name: char,
+    /// Absolute path of the file the mark was set in.
+    pub file: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    /// Seconds since the Unix epoch at the time the mark was saved.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MarksFile {
+    #[serde(default, rename = "mark")]
+    marks: Vec<StoredMark>,
+}
+
+/// Whether `name` can be used as a mark, only lowercase letters are supported.
+const fn is_mark_name(name: char) -> bool {
+    name.is_ascii_lowercase()
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Replaces the stored marks of `file` that are set again in `marks`, keeping the rest, and
+/// drops the oldest entries beyond `MAX_STORED_MARKS`.
+pub fn merge_marks(
+    mut stored: Vec<StoredMark>,
+    file: &Path,
+    marks: &HashMap<char, LineCol>,
+    timestamp: u64,
+) -> Vec<StoredMark> {
+    stored.retain(|mark| mark.file != file || !marks.contains_key(&mark.name));
+    stored.extend(marks.iter().map(|(name, pos)| StoredMark {
+        name: *name,
+        file: file.to_path_buf(),
+        line: pos.line,
+        col: pos.col,
+        timestamp,
+    }));
+    stored.sort_by_key(|mark| std::cmp::Reverse(mark.timestamp));
+    stored.truncate(MAX_STORED_MARKS);
+    stored
+}
+
+fn read_marks_file(path: &Path) -> Result<Vec<StoredMark>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let file: MarksFile = toml::from_str(&content)
+        .map_err(|e| Error::ParsingError(format!("{}: {e}", path.display())))?;
+    Ok(file.marks)
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Sets the mark `name` at the cursor, `m`.
+    pub(crate) fn set_mark(&mut self, name: char) {
+        if !is_mark_name(name) {
+            notif_bar!(self => "E191: Argument must be a letter";);
+            return;
+        }
+        self.marks.insert(name, self.pos());
+    }
+
+    /// Jumps to the mark `name`, to its exact position with `exact`, `` ` ``, otherwise to the
+    /// first non-blank character of its line, `'`.
+    pub(crate) fn jump_to_mark(&mut self, name: char, exact: bool) -> Result<()> {
+        let Some(mark) = self.marks.get(&name).copied() else {
+            notif_bar!(self => "E20: Mark not set";);
+            return Ok(());
+        };
+        let line = mark.line.min(self.buffer.max_line());
+        let content = self.buffer.line(line)?;
+        let col = if exact {
+            mark.col.min(content.len())
+        } else {
+            content.len() - content.trim_start().len()
+        };
+        self.push_jump(self.pos());
+        self.go(LineCol { line, col });
+        Ok(())
+    }
+
+    /// Loads the stored marks of the edited file from the marks file.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the marks file exists but can't be read and `Error::ParsingError`
+    /// if it isn't valid.
+    pub fn load_marks(&mut self) -> Result<()> {
+        let (Some(marks_file), Some(file)) = (&self.marks_file, &self.file_path) else {
+            return Ok(());
+        };
+        let file = absolute(file);
+        for mark in read_marks_file(marks_file)? {
+            if mark.file == file {
+                self.marks.insert(
+                    mark.name,
+                    LineCol {
+                        line: mark.line,
+                        col: mark.col,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the marks of the edited file into the marks file.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the marks file can't be read or written and `Error::ParsingError`
+    /// if it isn't valid.
+    pub fn save_marks(&self) -> Result<()> {
+        let (Some(marks_file), Some(file)) = (&self.marks_file, &self.file_path) else {
+            return Ok(());
+        };
+        if self.marks.is_empty() {
+            return Ok(());
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let marks = merge_marks(
+            read_marks_file(marks_file)?,
+            &absolute(file),
+            &self.marks,
+            timestamp,
+        );
+        let content = toml::to_string(&MarksFile { marks })
+            .map_err(|e| Error::ParsingError(e.to_string()))?;
+        if let Some(parent) = marks_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(marks_file, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::tests::new_test_editor;
+
+    #[test]
+    fn test_marks_persist_across_sessions() {
+        let dir = std::env::temp_dir().join(format!("neotext_marks_{}", std::process::id()));
+        let marks_file = dir.join("marks.toml");
+        let file = dir.join("main.rs");
+
+        let mut editor = new_test_editor(&["fn main() {", "    body();", "}"]);
+        editor.marks_file = Some(marks_file.clone());
+        editor.set_file_path(&file);
+        editor.go(LineCol { line: 1, col: 6 });
+        editor.handle_combination_input('a', None, 'm').unwrap();
+        editor.save_marks().unwrap();
+
+        let mut other = new_test_editor(&["other"]);
+        other.marks_file = Some(marks_file.clone());
+        other.set_file_path(dir.join("other.rs"));
+        other.set_mark('a');
+        other.save_marks().unwrap();
+
+        let mut editor = new_test_editor(&["fn main() {", "    body();", "}"]);
+        editor.marks_file = Some(marks_file);
+        editor.set_file_path(&file);
+        editor.load_marks().unwrap();
+        assert_eq!(editor.marks.len(), 1);
+        editor.handle_combination_input('a', None, '`').unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 6 });
+        editor.go(LineCol::default());
+        editor.handle_combination_input('a', None, '\'').unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 4 });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_evicts_oldest_marks() {
+        let stored: Vec<StoredMark> = (0..MAX_STORED_MARKS as u64)
+            .map(|timestamp| StoredMark {
+                name: 'a',
+                file: PathBuf::from(format!("/file{timestamp}")),
+                line: 0,
+                col: 0,
+                timestamp,
+            })
+            .collect();
+        let marks = HashMap::from([('b', LineCol { line: 3, col: 1 })]);
+        let merged = merge_marks(stored, Path::new("/new"), &marks, 5000);
+        assert_eq!(merged.len(), MAX_STORED_MARKS);
+        assert_eq!(merged[0].file, PathBuf::from("/new"));
+        assert!(merged.iter().all(|mark| mark.timestamp != 0));
+    }
+}
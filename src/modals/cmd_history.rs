@@ -0,0 +1,253 @@
+use std::{collections::VecDeque, io::Write};
+
+use crossterm::{
+    event::{Event, KeyCode},
+    style::{self, Color, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+
+use crate::{
+    bars::{draw_bar, get_info_bar_content, get_notif_bar_content, INFO_BAR, NOTIFICATION_BAR},
+    buffer::{TextBuffer, VecBuffer},
+    commands::parse_command,
+    editor::Editor,
+    notif_bar, Error, LineCol, Result,
+};
+
+use super::Modal;
+
+/// Maximum amount of commands shown in the command history window at once.
+pub const CMD_HISTORY_WINDOW_HEIGHT: usize = 8;
+
+/// The command history window opened with `q:`. Its lines are copies of the commands, oldest
+/// first, so they can be edited before being run again without changing the history itself.
+#[derive(Debug, Default)]
+pub(crate) struct CmdHistoryWindow {
+    lines: VecBuffer,
+    /// Line of the selected command.
+    pub(crate) selected: usize,
+    /// Byte column of the cursor while the selected line is edited, `None` while moving between
+    /// lines.
+    editing: Option<usize>,
+}
+
+impl CmdHistoryWindow {
+    fn new(history: &VecDeque<String>) -> Self {
+        Self {
+            lines: VecBuffer::new(history.iter().rev().cloned().collect()),
+            selected: history.len().saturating_sub(1),
+            editing: None,
+        }
+    }
+
+    /// The selected line as currently edited.
+    pub(crate) fn selected_line(&self) -> &str {
+        self.lines.line(self.selected).unwrap_or_default()
+    }
+
+    fn handle_edit_key(&mut self, col: usize, code: KeyCode) {
+        let line = self.selected_line();
+        let at = |col| LineCol {
+            line: self.selected,
+            col,
+        };
+        let previous = line[..col].chars().next_back().map(char::len_utf8);
+        let next = line[col..].chars().next().map(char::len_utf8);
+        self.editing = match code {
+            KeyCode::Char(ch) => self
+                .lines
+                .insert(at(col), ch)
+                .map_or(Some(col), |_| Some(col + ch.len_utf8())),
+            KeyCode::Backspace => match previous {
+                Some(len) if self.lines.delete(at(col)).is_ok() => Some(col - len),
+                _ => Some(col),
+            },
+            KeyCode::Left => Some(col - previous.unwrap_or_default()),
+            KeyCode::Right => Some(col + next.unwrap_or_default()),
+            KeyCode::Home => Some(0),
+            KeyCode::End => Some(line.len()),
+            KeyCode::Esc => None,
+            _ => Some(col),
+        };
+    }
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Opens the command history window with the most recent command selected, `q:`.
+    pub(crate) fn open_cmd_history(&mut self) {
+        if self.command_history.is_empty() {
+            notif_bar!(self => "No command history";);
+            return;
+        }
+        self.cmd_history = CmdHistoryWindow::new(&self.command_history);
+        self.set_mode(Modal::CmdHistory);
+    }
+
+    pub(crate) fn run_cmd_history(&mut self) -> Result<()> {
+        self.draw_lines()?;
+        self.draw_cmd_history()?;
+        let pos = self.pos();
+        let file_label = self.file_label();
+        draw_bar(&mut self.viewport.terminal, &INFO_BAR, |term_width, _| {
+            get_info_bar_content(term_width, &self.mode, &file_label, pos)
+        })?;
+        draw_bar(&mut self.viewport.terminal, &NOTIFICATION_BAR, |_, _| {
+            get_notif_bar_content(&mut self.notification_queue)
+        })?;
+
+        if let Event::Key(key_event) = self.read_event()? {
+            self.handle_cmd_history_key(key_event.code)?;
+        }
+        Ok(())
+    }
+
+    /// Moves between the commands with `j` and `k`, starts editing the selected one with `i` or
+    /// `A` and runs it with Enter. While a command is edited, keys are typed into it until Esc.
+    pub(crate) fn handle_cmd_history_key(&mut self, code: KeyCode) -> Result<()> {
+        let window = &mut self.cmd_history;
+        let last = window.lines.line_count().saturating_sub(1);
+        match (window.editing, code) {
+            (_, KeyCode::Enter) => {
+                self.set_mode(Modal::Normal);
+                let text = self.cmd_history.selected_line().to_string();
+                self.add_to_command_history(&text);
+                match parse_command(&text) {
+                    Ok(command) => self.execute_command(command)?,
+                    Err(Error::ParsingError(descr)) => notif_bar!(self => descr;),
+                    Err(otherwise) => Err(otherwise)?,
+                }
+            }
+            (Some(col), code) => window.handle_edit_key(col, code),
+            (None, KeyCode::Char('j') | KeyCode::Down) => {
+                window.selected = (window.selected + 1).min(last);
+            }
+            (None, KeyCode::Char('k') | KeyCode::Up) => {
+                window.selected = window.selected.saturating_sub(1);
+            }
+            (None, KeyCode::Char('i')) => window.editing = Some(0),
+            (None, KeyCode::Char('A')) => window.editing = Some(window.selected_line().len()),
+            (None, KeyCode::Esc | KeyCode::Char('q')) => self.set_mode(Modal::Normal),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Draws the command history window over the bottom of the text area.
+    fn draw_cmd_history(&mut self) -> Result<()> {
+        let window = &self.cmd_history;
+        let height = window
+            .lines
+            .line_count()
+            .clamp(1, CMD_HISTORY_WINDOW_HEIGHT);
+        let text_height = self.viewport.bottomright().line - self.viewport.topleft.line;
+        let top = text_height.saturating_sub(height) + 1;
+        let skip = (window.selected + 1).saturating_sub(height);
+        let width = self.viewport.terminal_dimensions.col;
+
+        let rows: Vec<(String, bool)> = window
+            .lines
+            .get_normal_text()
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .take(height)
+            .map(|(i, entry)| {
+                let row: String = entry.chars().take(width).collect();
+                (row, i == window.selected)
+            })
+            .collect();
+        // The terminal cursor follows the column of the edited command
+        let cursor = window.editing.map(|col| {
+            let line = window.selected_line();
+            (line[..col].chars().count(), top + window.selected - skip)
+        });
+
+        for (i, (row, selected)) in rows.into_iter().enumerate() {
+            let bg = if selected {
+                Color::DarkGrey
+            } else {
+                Color::Black
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            crossterm::queue!(
+                self.viewport.terminal,
+                crossterm::cursor::MoveTo(0, (top + i) as u16),
+                SetBackgroundColor(bg),
+                SetForegroundColor(Color::White),
+                style::Print(format!("{row:<width$}")),
+                ResetColor,
+            )?;
+        }
+        if let Some((col, row)) = cursor {
+            #[allow(clippy::cast_possible_truncation)]
+            crossterm::queue!(
+                self.viewport.terminal,
+                crossterm::cursor::MoveTo(col as u16, row as u16)
+            )?;
+        }
+        self.viewport.terminal.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+
+    use crate::{editor::tests::new_test_editor, modals::Modal};
+
+    #[test]
+    fn test_cmd_history_reexecutes_selected_command() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.add_to_command_history(":set indentguides=true");
+        editor.add_to_command_history(":set cc=80");
+
+        editor.handle_combination_input(':', None, 'q').unwrap();
+        assert_eq!(editor.mode, Modal::CmdHistory);
+        assert_eq!(editor.cmd_history.selected, 1);
+
+        editor.handle_cmd_history_key(KeyCode::Char('k')).unwrap();
+        editor.handle_cmd_history_key(KeyCode::Char('k')).unwrap();
+        assert_eq!(editor.cmd_history.selected, 0);
+        editor.handle_cmd_history_key(KeyCode::Enter).unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+        assert!(editor.options.show_indent_guides);
+        assert_eq!(
+            editor.command_history.front().unwrap(),
+            ":set indentguides=true"
+        );
+    }
+
+    #[test]
+    fn test_cmd_history_closes_on_escape() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.handle_combination_input(':', None, 'q').unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+
+        editor.add_to_command_history(":set indentguides=true");
+        editor.handle_combination_input(':', None, 'q').unwrap();
+        editor.handle_cmd_history_key(KeyCode::Esc).unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+        assert!(!editor.options.show_indent_guides);
+    }
+
+    #[test]
+    fn test_cmd_history_edits_before_running() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.add_to_command_history(":set cc=80");
+        editor.handle_combination_input(':', None, 'q').unwrap();
+
+        editor.handle_cmd_history_key(KeyCode::Char('A')).unwrap();
+        for code in [KeyCode::Backspace, KeyCode::Backspace, KeyCode::Char('9')] {
+            editor.handle_cmd_history_key(code).unwrap();
+        }
+        editor.handle_cmd_history_key(KeyCode::Esc).unwrap();
+        // Esc only stops editing, the window stays open
+        assert_eq!(editor.mode, Modal::CmdHistory);
+        assert_eq!(editor.cmd_history.selected_line(), ":set cc=9");
+
+        editor.handle_cmd_history_key(KeyCode::Enter).unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+        assert_eq!(editor.options.color_column, Some(9));
+        assert_eq!(editor.command_history, [":set cc=9", ":set cc=80"]);
+    }
+}
@@ -0,0 +1,154 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{buffer::TextBuffer, editor::Editor, text_objects::ObjectAction, LineCol, Result};
+
+/// Keys of a change made in Normal mode, as they are dispatched again by `.`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// A single key, such as `x`, `p` or `o`.
+    Key(char),
+    /// A two key combination with its first key first, such as `dd`, `dw` or `r` and the symbol
+    /// replaced with.
+    Combination(char, char),
+    /// `d` or `c` on a text object, such as `diw` or `ci(`.
+    TextObject {
+        action: ObjectAction,
+        around: bool,
+        object: char,
+    },
+}
+
+/// Last change made in Normal mode, repeated by `.`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedAction {
+    pub action: Action,
+    pub count: Option<i32>,
+    /// Text typed in the Insert session the change started, such as the replacement of `cw`.
+    pub inserted: Option<String>,
+    /// Where that Insert session started, while it lasts.
+    insert_start: Option<LineCol>,
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Remembers `action` as the change `.` repeats. If it started an Insert session, the text
+    /// typed in it is remembered once the session ends, see `finish_recorded_insert`.
+    pub(crate) fn record_action(&mut self, action: Action, count: Option<i32>) {
+        let insert_start = self.mode.is_insert().then(|| self.pos());
+        self.last_action = Some(RecordedAction {
+            action,
+            count,
+            inserted: insert_start.map(|_| String::new()),
+            insert_start,
+        });
+    }
+
+    /// Keeps the text typed since the recorded change entered Insert mode. Nothing is kept if the
+    /// cursor left the line, e.g. after typing a newline.
+    pub(crate) fn finish_recorded_insert(&mut self) -> Result<()> {
+        let Some(recorded) = self.last_action.as_mut() else {
+            return Ok(());
+        };
+        let Some(start) = recorded.insert_start.take() else {
+            return Ok(());
+        };
+        let pos = self.cursor.pos;
+        if pos.line == start.line && pos.col >= start.col {
+            let line = self.buffer.line(pos.line)?;
+            recorded.inserted = line.get(start.col..pos.col).map(String::from);
+        }
+        Ok(())
+    }
+
+    /// Repeats the last change, `.`. A count replaces the one the change was made with.
+    pub(crate) fn repeat_last_action(&mut self, count: Option<i32>) -> Result<()> {
+        let Some(recorded) = self.last_action.clone() else {
+            return Ok(());
+        };
+        let count = count.or(recorded.count);
+        match recorded.action {
+            Action::Key(ch) => self.handle_char_input(ch, count)?,
+            Action::Combination(first, second) => {
+                self.handle_combination_input(second, count, first)?;
+            }
+            Action::TextObject {
+                action,
+                around,
+                object,
+            } => self.apply_text_object(action, around, object)?,
+        }
+        if self.mode.is_insert() {
+            if let Some(text) = recorded.inserted.as_deref().filter(|text| !text.is_empty()) {
+                let dest = self.buffer.insert_text(self.pos(), text, false)?;
+                self.go(dest);
+            }
+            self.handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))?;
+        }
+        self.last_action = Some(RecordedAction { count, ..recorded });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{editor::tests::new_test_editor, modals::Modal, LineCol};
+
+    use super::*;
+
+    fn type_insert(editor: &mut Editor<crate::buffer::DefaultBuffer>, text: &str) {
+        for ch in text.chars() {
+            editor
+                .handle_insert_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                .unwrap();
+        }
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_dot_repeats_normal_mode_changes() {
+        let mut editor = new_test_editor(&["abcdef", "one", "two", "three", "four"]);
+        editor.handle_char_input('x', Some(2)).unwrap();
+        editor.handle_char_input('.', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text()[0], "ef");
+
+        // `r` leaves the cursor after the replaced symbol
+        editor.handle_combination_input('Z', None, 'r').unwrap();
+        editor.handle_char_input('.', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text()[0], "ZZ");
+
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.handle_combination_input('d', Some(2), 'd').unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["ZZ", "three", "four"]);
+        editor.handle_char_input('.', Some(1)).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["ZZ", "four"]);
+        assert_eq!(editor.last_action.as_ref().unwrap().count, Some(1));
+    }
+
+    #[test]
+    fn test_dot_repeats_text_object_change_with_typed_text() {
+        let mut editor = new_test_editor(&["f(a, b) + g(c)"]);
+        editor.go(LineCol { line: 0, col: 3 });
+        editor.handle_combination_input('i', None, 'c').unwrap();
+        editor.handle_char_input('(', None).unwrap();
+        assert_eq!(editor.mode, Modal::Insert);
+        type_insert(&mut editor, "x");
+        assert_eq!(editor.buffer.get_normal_text(), ["f(x) + g(c)"]);
+
+        editor.go(LineCol { line: 0, col: 9 });
+        editor.handle_char_input('.', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["f(x) + g(x)"]);
+        assert_eq!(editor.mode, Modal::Normal);
+    }
+
+    #[test]
+    fn test_dot_ignores_motions_and_yanks() {
+        let mut editor = new_test_editor(&["one two three four"]);
+        editor.handle_combination_input('w', None, 'd').unwrap();
+        editor.handle_char_input('l', Some(4)).unwrap();
+        editor.handle_combination_input('i', None, 'y').unwrap();
+        editor.handle_char_input('w', None).unwrap();
+        editor.handle_char_input('.', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), [" two four"]);
+    }
+}
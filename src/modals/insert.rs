@@ -0,0 +1,874 @@
+use std::io::Write;
+
+use crossterm::{
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    style::{self, Color, ResetColor, SetBackgroundColor},
+};
+
+use crate::{
+    bars::{draw_bar, get_info_bar_content, get_notif_bar_content, INFO_BAR, NOTIFICATION_BAR},
+    buffer::TextBuffer,
+    completion::{collect_words, CompletionMenu, COMPLETION_MENU_HEIGHT},
+    cursor::Selection,
+    editor::Editor,
+    lsp::{char_col, CompletionItem},
+    notif_bar,
+    utils::display_width,
+    LineCol, Result,
+};
+
+use super::Modal;
+
+/// Closing bracket inserted along with `open` when `auto_pairs` is on.
+const fn closing_pair(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+/// Insert session started with `I` or `A` in Visual Line mode or `o` or `A` with a count, whose
+/// text is repeated on the other selected or opened lines, or after itself, once it ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinewiseInsert {
+    /// Lines below the one typed into.
+    pub lines: std::ops::RangeInclusive<usize>,
+    /// Whether the text goes at the end of the lines, `A`, instead of their start, `I`.
+    pub append: bool,
+    /// Where typing started.
+    pub start: LineCol,
+    /// Length of the typed into line before typing started.
+    pub line_len: usize,
+    /// Extra copies of the typed text put right after it, for `A` with a count.
+    pub copies: usize,
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    pub(crate) fn run_insert(&mut self) -> Result<()> {
+        self.draw_lines()?;
+        self.draw_quickfix()?;
+        self.draw_completion_menu()?;
+        let pos = self.pos();
+        let file_label = self.file_label();
+        draw_bar(&mut self.viewport.terminal, &INFO_BAR, |term_width, _| {
+            get_info_bar_content(term_width, &self.mode, &file_label, pos)
+        })?;
+        draw_bar(&mut self.viewport.terminal, &NOTIFICATION_BAR, |_, _| {
+            get_notif_bar_content(&mut self.notification_queue)
+        })?;
+        self.move_cursor();
+        self.force_within_bounds();
+
+        if let Event::Key(key_event) = self.read_event()? {
+            self.handle_insert_key(key_event)?;
+        };
+        Ok(())
+    }
+
+    pub(crate) fn handle_insert_key(&mut self, key_event: KeyEvent) -> Result<()> {
+        if self.completion.is_some() && self.handle_completion_key(key_event)? {
+            return Ok(());
+        }
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) => self.open_completion(false)?,
+            (KeyCode::Char('p'), KeyModifiers::CONTROL) => self.open_completion(true)?,
+            (KeyCode::Char(' '), KeyModifiers::CONTROL) => self.open_lsp_completion()?,
+            // Terminals report `Ctrl+]` as `Ctrl+5` unless the keyboard enhancement protocol is on
+            (KeyCode::Char(']' | '5'), KeyModifiers::CONTROL) => self.expand_abbreviation()?,
+            (KeyCode::Char(c), _) => {
+                if !self.options.is_keyword(c) {
+                    self.expand_abbreviation()?;
+                }
+                self.push_auto_paired(c);
+                self.count_typed_char();
+            }
+            (KeyCode::Enter, _) => {
+                self.expand_abbreviation()?;
+                self.newline();
+            }
+            (KeyCode::Tab, _) => {
+                if !self.expand_snippet()? {
+                    self.insert_tab();
+                }
+            }
+            (KeyCode::BackTab, _) => self.dedent_line(),
+            (KeyCode::Esc, _) => {
+                self.snippets.cancel();
+                // The recorded text is taken before it's copied, `.` makes the copies again
+                self.finish_recorded_insert()?;
+                self.finish_linewise_insert()?;
+                self.set_mode(Modal::Normal);
+            }
+            (KeyCode::Backspace, _) => self.delete(),
+            (KeyCode::Left, _) => self.cursor.bump_left(),
+            (KeyCode::Right, _) => self.cursor.bump_right(),
+            (KeyCode::Up, _) => self.cursor.bump_up(),
+            (KeyCode::Down, _) => self.cursor.bump_down(),
+            _ => {
+                notif_bar!(self => "nothing";);
+            }
+        }
+        Ok(())
+    }
+
+    /// Enters Insert mode at the start, `I`, or end, `A`, of the first line of the Visual Line
+    /// selection, or at the left column of the Visual Block selection. The typed text is put on
+    /// the other selected lines when Insert mode is left.
+    pub(crate) fn start_linewise_insert(&mut self, append: bool) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let sel = Selection::from(&self.cursor).normalized();
+        let line_len = self.buffer.line(sel.start.line)?.len();
+        let col = if append {
+            line_len
+        } else if self.mode.is_visual_block() {
+            self.visual_block().0.col.min(line_len)
+        } else {
+            0
+        };
+        let start = LineCol {
+            line: sel.start.line,
+            col,
+        };
+        self.set_mode(Modal::Insert);
+        self.go(start);
+        self.linewise_insert = Some(LinewiseInsert {
+            lines: sel.start.line + 1..=sel.end.line,
+            append,
+            start,
+            line_len,
+            copies: 0,
+        });
+        Ok(())
+    }
+
+    /// Repeats the text typed since `start_linewise_insert` on the rest of the selected lines, at
+    /// the column typing started at unless appending. Lines ending before that column are
+    /// skipped. Copies asked for by a count go right after the typed text, leaving the cursor
+    /// at their end. Nothing is repeated if the cursor left the line, e.g. after typing a newline.
+    fn finish_linewise_insert(&mut self) -> Result<()> {
+        let Some(insert) = self.linewise_insert.take() else {
+            return Ok(());
+        };
+        if self.pos().line != insert.start.line {
+            return Ok(());
+        }
+        let line = self.buffer.line(insert.start.line)?;
+        let typed_len = line.len().saturating_sub(insert.line_len);
+        let Some(typed) = line
+            .get(insert.start.col..insert.start.col + typed_len)
+            .filter(|typed| !typed.is_empty())
+            .map(ToString::to_string)
+        else {
+            return Ok(());
+        };
+        for line in insert.lines {
+            let len = self.buffer.line(line)?.len();
+            let col = if insert.append { len } else { insert.start.col };
+            if col > len {
+                continue;
+            }
+            let at = LineCol { line, col };
+            self.buffer.replace(at, at, &typed)?;
+        }
+        if insert.copies > 0 {
+            let at = LineCol {
+                line: insert.start.line,
+                col: insert.start.col + typed.len(),
+            };
+            let copies = typed.repeat(insert.copies);
+            self.buffer.replace(at, at, &copies)?;
+            self.go(LineCol {
+                line: at.line,
+                col: at.col + copies.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Handles a key press while the completion popup is open. Returns `false` if the key closed
+    /// the popup and should still be handled as a regular insert mode key.
+    fn handle_completion_key(&mut self, key_event: KeyEvent) -> Result<bool> {
+        let Some(menu) = self.completion.as_mut() else {
+            return Ok(false);
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) => menu.select_next(),
+            (KeyCode::Char('p'), KeyModifiers::CONTROL) => menu.select_previous(),
+            (KeyCode::Enter | KeyCode::Tab, _) => self.accept_completion()?,
+            (KeyCode::Esc, _) => self.completion = None,
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT)
+                if self.options.is_keyword(c) =>
+            {
+                self.push(c);
+                self.refilter_completion()?;
+            }
+            (KeyCode::Backspace, _) => {
+                self.delete();
+                self.refilter_completion()?;
+            }
+            _ => {
+                self.completion = None;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the start and text of the word prefix right before the cursor.
+    fn completion_prefix(&self) -> Result<(LineCol, String)> {
+        let pos = self.pos();
+        let line = self.buffer.line(pos.line)?;
+        let before_cursor = line.get(..pos.col).unwrap_or(line);
+        let prefix_len: usize = before_cursor
+            .chars()
+            .rev()
+            .take_while(|ch| self.options.is_keyword(*ch))
+            .map(char::len_utf8)
+            .sum();
+        let start = LineCol {
+            line: pos.line,
+            col: before_cursor.len() - prefix_len,
+        };
+        Ok((start, before_cursor[start.col..].to_string()))
+    }
+
+    /// Opens the completion popup with the buffer words starting with the prefix before the
+    /// cursor. `backwards` selects the last candidate instead of the first one.
+    fn open_completion(&mut self, backwards: bool) -> Result<()> {
+        let (start, prefix) = self.completion_prefix()?;
+        let words = collect_words(self.buffer.get_normal_text(), |ch| {
+            self.options.is_keyword(ch)
+        });
+        self.completion = CompletionMenu::new(start, &prefix, words);
+        match self.completion.as_mut() {
+            Some(menu) if backwards => menu.select_previous(),
+            Some(_) => {}
+            None => {
+                notif_bar!(self => "No completions found";);
+            }
+        }
+        Ok(())
+    }
+
+    /// Requests the completions at the cursor from the language server of the file type and
+    /// opens the popup with them.
+    fn open_lsp_completion(&mut self) -> Result<()> {
+        let Some(uri) = self.document_uri() else {
+            notif_bar!(self => "Completion needs a file name";);
+            return Ok(());
+        };
+        let pos = self.pos();
+        let position = LineCol {
+            line: pos.line,
+            col: char_col(self.buffer.line(pos.line)?, pos.col),
+        };
+        let Some(client) = self.lsp.get_or_start(self.file_type) else {
+            notif_bar!(self => "No language server for this file type";);
+            return Ok(());
+        };
+        match client.request_completion(&uri, position) {
+            Ok(items) => self.show_lsp_completion(items)?,
+            Err(e) => notif_bar!(self => e.display_user();),
+        }
+        Ok(())
+    }
+
+    /// Opens the popup with the completion `items` of the language server, ranked against the
+    /// prefix before the cursor and the words used in the buffer.
+    pub(crate) fn show_lsp_completion(&mut self, items: Vec<CompletionItem>) -> Result<()> {
+        let (start, prefix) = self.completion_prefix()?;
+        let words = collect_words(self.buffer.get_normal_text(), |ch| {
+            self.options.is_keyword(ch)
+        });
+        self.completion = CompletionMenu::from_items(start, &prefix, items, words);
+        if self.completion.is_none() {
+            notif_bar!(self => "No completions found";);
+        }
+        Ok(())
+    }
+
+    /// Narrows the open popup down to the current prefix, closing it when nothing matches.
+    fn refilter_completion(&mut self) -> Result<()> {
+        let (start, prefix) = self.completion_prefix()?;
+        let Some(menu) = self.completion.as_mut() else {
+            return Ok(());
+        };
+        if start != menu.prefix_start || prefix.is_empty() {
+            self.completion = None;
+            return Ok(());
+        }
+        menu.filter(&prefix);
+        if menu.candidates.is_empty() {
+            self.completion = None;
+        }
+        Ok(())
+    }
+
+    /// Inserts a tab character, or spaces up to the next tab stop with `expandtab`.
+    fn insert_tab(&mut self) {
+        if !self.options.expand_tab() {
+            self.push('\t');
+            return;
+        }
+        if self.blocked_by_readonly() {
+            return;
+        }
+        let pos = self.pos();
+        let tabstop = self.options.tab_width();
+        let before = self
+            .buffer
+            .line(pos.line)
+            .ok()
+            .and_then(|line| line.get(..pos.col))
+            .unwrap_or_default();
+        let width = tabstop - display_width(before, tabstop) % tabstop;
+        match self.buffer.insert_text(pos, " ".repeat(width), false) {
+            Ok(_) => self.go(LineCol {
+                line: pos.line,
+                col: pos.col + width,
+            }),
+            Err(e) => self.recover_from(&e),
+        }
+    }
+
+    /// Removes one level of indentation from the start of the cursor line: a leading tab, or
+    /// the spaces back to the previous multiple of `shiftwidth`.
+    fn dedent_line(&mut self) {
+        if self.blocked_by_readonly() {
+            return;
+        }
+        let pos = self.pos();
+        let Ok(line) = self.buffer.line(pos.line) else {
+            return;
+        };
+        let width = if line.starts_with('\t') {
+            1
+        } else {
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            match spaces {
+                0 => return,
+                spaces => (spaces - 1) % self.options.shiftwidth.max(1) + 1,
+            }
+        };
+        for _ in 0..width {
+            if let Err(e) = self.buffer.delete(LineCol {
+                line: pos.line,
+                col: 1,
+            }) {
+                self.recover_from(&e);
+                return;
+            }
+        }
+        self.go(LineCol {
+            line: pos.line,
+            col: pos.col.saturating_sub(width),
+        });
+    }
+
+    /// Replaces the prefix before the cursor with the selected candidate and closes the popup.
+    fn accept_completion(&mut self) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let Some(menu) = self.completion.take() else {
+            return Ok(());
+        };
+        let Some(word) = menu.selected_word() else {
+            return Ok(());
+        };
+        let pos = self.pos();
+        self.buffer.replace(menu.prefix_start, pos, word)?;
+        self.go(LineCol {
+            line: menu.prefix_start.line,
+            col: menu.prefix_start.col + word.len(),
+        });
+        Ok(())
+    }
+
+    /// Inserts `c`, along with its closing bracket after the cursor when `auto_pairs` is on. A
+    /// typed closing bracket steps over the one already following the cursor instead.
+    fn push_auto_paired(&mut self, c: char) {
+        if !self.options.auto_pairs {
+            self.push(c);
+            return;
+        }
+        let pos = self.pos();
+        let next = self
+            .buffer
+            .line(pos.line)
+            .ok()
+            .and_then(|line| line.get(pos.col..))
+            .and_then(|rest| rest.chars().next());
+        if matches!(c, ')' | ']' | '}') && next == Some(c) {
+            self.cursor.bump_right();
+            return;
+        }
+        self.push(c);
+        // Nothing was inserted, e.g. because the buffer is read-only
+        if self.pos() == pos {
+            return;
+        }
+        if let Some(close) = closing_pair(c) {
+            if let Err(e) = self.buffer.insert(self.pos(), close) {
+                self.recover_from(&e);
+            }
+        }
+    }
+
+    /// Draws the completion popup below the completed prefix, scrolled so the selected
+    /// candidate is visible.
+    fn draw_completion_menu(&mut self) -> Result<()> {
+        let Some(menu) = &self.completion else {
+            return Ok(());
+        };
+        let anchor = self.screen_pos(menu.prefix_start);
+        let width = menu.candidates.iter().map(String::len).max().unwrap_or(0) + 2;
+        let skip = (menu.selected + 1).saturating_sub(COMPLETION_MENU_HEIGHT);
+
+        for (i, word) in menu
+            .candidates
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .take(COMPLETION_MENU_HEIGHT)
+        {
+            let bg = if i == menu.selected {
+                Color::DarkGrey
+            } else {
+                Color::Black
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            crossterm::queue!(
+                self.viewport.terminal,
+                crossterm::cursor::MoveTo(anchor.col as u16, (anchor.line + 2 + i - skip) as u16),
+                SetBackgroundColor(bg),
+                style::Print(format!(" {word:<width$}", width = width - 1)),
+                ResetColor,
+            )?;
+        }
+        self.viewport.terminal.flush()?;
+        Ok(())
+    }
+
+    /// Jumps to the next tab stop of the snippet being filled in, or expands the snippet whose
+    /// trigger was typed right before the cursor. Returns whether anything happened.
+    fn expand_snippet(&mut self) -> Result<bool> {
+        if self.blocked_by_readonly() {
+            return Ok(true);
+        }
+        let pos = self.pos();
+        if let Some(next) = self.snippets.next_tab_stop(pos) {
+            self.go(next);
+            return Ok(true);
+        }
+
+        let line = self.buffer.line(pos.line)?;
+        let before_cursor = line.get(..pos.col).unwrap_or(line);
+        let Some(snippet) = self
+            .snippets
+            .find_trigger(before_cursor, self.file_type, |ch| {
+                self.options.is_keyword(ch)
+            })
+        else {
+            return Ok(false);
+        };
+        let expansion = snippet.expand();
+        let start = LineCol {
+            line: pos.line,
+            col: pos.col - snippet.trigger.len(),
+        };
+
+        self.buffer.replace(start, pos, &expansion.text)?;
+        let dest = self.snippets.start(&expansion, start).unwrap_or_else(|| {
+            let last_line = expansion.text.split('\n').next_back().unwrap_or_default();
+            let line_count = expansion.text.matches('\n').count();
+            LineCol {
+                line: start.line + line_count,
+                col: last_line.len() + if line_count == 0 { start.col } else { 0 },
+            }
+        });
+        self.go(dest);
+        Ok(true)
+    }
+
+    /// Registers an insert mode abbreviation, replacing any previous expansion of `trigger`.
+    pub fn set_abbreviation(&mut self, trigger: &str, expansion: &str) {
+        self.abbreviations
+            .insert(trigger.to_string(), expansion.to_string());
+    }
+
+    /// Replaces the word ending right before the cursor with its abbreviation expansion, if one
+    /// is registered.
+    fn expand_abbreviation(&mut self) -> Result<()> {
+        // The edit that triggered the expansion reports the read-only buffer itself
+        if self.options.readonly {
+            return Ok(());
+        }
+        let pos = self.pos();
+        if pos.col == 0 || self.abbreviations.is_empty() {
+            return Ok(());
+        }
+        let before_cursor = LineCol {
+            line: pos.line,
+            col: pos.col - 1,
+        };
+        let Some((start, word)) = self
+            .buffer
+            .word_at(before_cursor, |ch| self.options.is_keyword(ch))
+        else {
+            return Ok(());
+        };
+        // Only whole words typed up to the cursor are expanded, not ones the cursor is inside of
+        if start.col + word.len() != pos.col {
+            return Ok(());
+        }
+        let Some(expansion) = self.abbreviations.get(word).cloned() else {
+            return Ok(());
+        };
+
+        self.buffer.replace(start, pos, &expansion)?;
+        self.go(LineCol {
+            line: start.line,
+            col: start.col + expansion.len(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::parse_command, editor::tests::new_test_editor};
+
+    fn type_text<Buff: TextBuffer>(editor: &mut Editor<Buff>, text: &str) {
+        for ch in text.chars() {
+            let key = KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE);
+            editor.handle_insert_key(key).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_abbreviation_expands_on_space() {
+        let mut editor = new_test_editor(&[""]);
+        editor.set_mode(Modal::Insert);
+        editor.set_abbreviation("teh", "the");
+        type_text(&mut editor, "teh ");
+        assert_eq!(editor.buffer.line(0).unwrap(), "the ");
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 4 });
+    }
+
+    #[test]
+    fn test_abbreviation_not_expanded_inside_word() {
+        let mut editor = new_test_editor(&[""]);
+        editor.set_mode(Modal::Insert);
+        editor.set_abbreviation("teh", "the");
+        type_text(&mut editor, "tehran ");
+        assert_eq!(editor.buffer.line(0).unwrap(), "tehran ");
+    }
+
+    #[test]
+    fn test_abbreviation_forced_with_ctrl_bracket() {
+        let mut editor = new_test_editor(&[""]);
+        editor.set_mode(Modal::Insert);
+        editor.set_abbreviation("fn", "function");
+        type_text(&mut editor, "fn");
+        let key = KeyEvent::new(KeyCode::Char(']'), KeyModifiers::CONTROL);
+        editor.handle_insert_key(key).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "function");
+    }
+
+    #[test]
+    fn test_snippet_expansion_and_tab_stops() {
+        let mut editor = new_test_editor(&["    "]);
+        let command = parse_command(r":snippet fn fn $1($2) {\n$0\n}").unwrap();
+        editor.execute_command(command).unwrap();
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 0, col: 4 });
+        let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+
+        type_text(&mut editor, "fn");
+        editor.handle_insert_key(tab).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["    fn () {", "", "}"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 7 });
+
+        type_text(&mut editor, "main");
+        editor.handle_insert_key(tab).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 12 });
+
+        editor.handle_insert_key(tab).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["    fn main() {", "", "}"]
+        );
+    }
+
+    #[test]
+    fn test_tab_expands_to_next_tab_stop() {
+        let mut editor = new_test_editor(&["ab"]);
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 0, col: 2 });
+        let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+        editor.handle_insert_key(tab).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "ab  ");
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 4 });
+
+        editor.options.expandtab = false;
+        editor.handle_insert_key(tab).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "ab  \t");
+    }
+
+    #[test]
+    fn test_backtab_removes_indentation() {
+        let mut editor = new_test_editor(&["      x", "\ty"]);
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 0, col: 7 });
+        let backtab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        editor.handle_insert_key(backtab).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "    x");
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 5 });
+
+        editor.go(LineCol { line: 1, col: 2 });
+        editor.handle_insert_key(backtab).unwrap();
+        assert_eq!(editor.buffer.line(1).unwrap(), "y");
+    }
+
+    #[test]
+    fn test_tab_stops_follow_display_columns() {
+        let mut editor = new_test_editor(&["\tab", "日x"]);
+        editor.set_mode(Modal::Insert);
+        let tab = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+        // The tab takes up 4 columns, "ab" ends on column 6
+        editor.go(LineCol { line: 0, col: 3 });
+        editor.handle_insert_key(tab).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "\tab  ");
+        // The wide character takes up 2 columns, "x" ends on column 3
+        editor.go(LineCol { line: 1, col: 4 });
+        editor.handle_insert_key(tab).unwrap();
+        assert_eq!(editor.buffer.line(1).unwrap(), "日x ");
+    }
+
+    #[test]
+    fn test_zero_tabstop_from_config() {
+        let mut editor = new_test_editor(&["ab"]);
+        let config = crate::config::Config {
+            tabstop: Some(0),
+            ..Default::default()
+        };
+        editor.apply_config(&config).unwrap();
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 0, col: 2 });
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "ab ");
+    }
+
+    #[test]
+    fn test_backtab_uses_shiftwidth() {
+        let mut editor = new_test_editor(&["      x"]);
+        editor.options.shiftwidth = 2;
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 0, col: 6 });
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT))
+            .unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "    x");
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 4 });
+    }
+
+    #[test]
+    fn test_filetype_indent_override() {
+        let mut editor = new_test_editor(&[""]);
+        editor.set_file_type(crate::lsp::FileType::Make);
+        editor.set_mode(Modal::Insert);
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "\t");
+    }
+
+    #[test]
+    fn test_completion_prefix_matching() {
+        let mut editor = new_test_editor(&["format foo fold", ""]);
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 1, col: 0 });
+        type_text(&mut editor, "fo");
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL))
+            .unwrap();
+        assert_eq!(
+            editor.completion.as_ref().unwrap().candidates,
+            ["fold", "foo", "format"]
+        );
+
+        type_text(&mut editor, "r");
+        assert_eq!(editor.completion.as_ref().unwrap().candidates, ["format"]);
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .unwrap();
+        assert!(editor.completion.is_none());
+        assert_eq!(editor.buffer.line(1).unwrap(), "format");
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 6 });
+    }
+
+    #[test]
+    fn test_completion_cycling() {
+        let mut editor = new_test_editor(&["format foo fold", ""]);
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 1, col: 0 });
+        type_text(&mut editor, "fo");
+        let ctrl_n = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        let ctrl_p = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+
+        editor.handle_insert_key(ctrl_p).unwrap();
+        assert_eq!(
+            editor.completion.as_ref().unwrap().selected_word(),
+            Some("format")
+        );
+        editor.handle_insert_key(ctrl_n).unwrap();
+        editor.handle_insert_key(ctrl_n).unwrap();
+        assert_eq!(
+            editor.completion.as_ref().unwrap().selected_word(),
+            Some("foo")
+        );
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(editor.buffer.line(1).unwrap(), "foo");
+    }
+
+    #[test]
+    fn test_lsp_completion_is_fuzzy_ranked() {
+        let mut editor = new_test_editor(&["let fmt_args = 1;", ""]);
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 1, col: 0 });
+        type_text(&mut editor, "fmt");
+        let items = ["from_utf8", "format", "fmt_args"]
+            .map(|label| CompletionItem {
+                label: label.to_string(),
+                insert_text: None,
+                filter_text: None,
+            })
+            .to_vec();
+        editor.show_lsp_completion(items).unwrap();
+        assert_eq!(
+            editor.completion.as_ref().unwrap().candidates,
+            ["fmt_args", "format", "from_utf8"]
+        );
+
+        type_text(&mut editor, "_");
+        assert_eq!(editor.completion.as_ref().unwrap().candidates, ["fmt_args"]);
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(editor.buffer.line(1).unwrap(), "fmt_args");
+    }
+
+    #[test]
+    fn test_lsp_completion_needs_a_file_name() {
+        let mut editor = new_test_editor(&[""]);
+        editor.set_mode(Modal::Insert);
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::CONTROL))
+            .unwrap();
+        assert!(editor.completion.is_none());
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("Completion needs a file name"));
+    }
+
+    #[test]
+    fn test_completion_dismissed_with_esc() {
+        let mut editor = new_test_editor(&["format", ""]);
+        editor.set_mode(Modal::Insert);
+        editor.go(LineCol { line: 1, col: 0 });
+        type_text(&mut editor, "f");
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL))
+            .unwrap();
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert!(editor.completion.is_none());
+        assert_eq!(editor.mode, Modal::Insert);
+        assert_eq!(editor.buffer.line(1).unwrap(), "f");
+    }
+
+    #[test]
+    fn test_iabbrev_command() {
+        let mut editor = new_test_editor(&[""]);
+        let command = parse_command(":iabbrev teh the").unwrap();
+        editor.execute_command(command).unwrap();
+        editor.set_mode(Modal::Insert);
+        type_text(&mut editor, "teh.");
+        assert_eq!(editor.buffer.line(0).unwrap(), "the.");
+    }
+
+    #[test]
+    fn test_readonly_blocks_insert() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.options.readonly = true;
+        editor.set_mode(Modal::Insert);
+        type_text(&mut editor, "abc");
+        for key in [KeyCode::Enter, KeyCode::Backspace] {
+            editor
+                .handle_insert_key(KeyEvent::new(key, KeyModifiers::NONE))
+                .unwrap();
+        }
+        assert_eq!(editor.buffer.get_normal_text(), ["text"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_auto_pairs() {
+        let mut editor = new_test_editor(&[""]);
+        editor.options.auto_pairs = true;
+        editor.set_mode(Modal::Insert);
+        type_text(&mut editor, "f(x");
+        assert_eq!(editor.buffer.line(0).unwrap(), "f(x)");
+        type_text(&mut editor, ");");
+        assert_eq!(editor.buffer.line(0).unwrap(), "f(x);");
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 5 });
+    }
+
+    #[test]
+    fn test_visual_line_insert_on_every_line() {
+        let mut editor = new_test_editor(&["one", "two", "three", "four"]);
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.set_mode(Modal::VisualLine);
+        editor.go(LineCol { line: 2, col: 2 });
+        editor.handle_char_input('I', None).unwrap();
+        assert_eq!(editor.mode, Modal::Insert);
+        for ch in "hello".chars() {
+            let key = KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE);
+            editor.handle_insert_key(key).unwrap();
+        }
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["helloone", "hellotwo", "hellothree", "four"]
+        );
+
+        editor.go(LineCol { line: 3, col: 0 });
+        editor.set_mode(Modal::VisualLine);
+        editor.go(LineCol { line: 2, col: 0 });
+        editor.handle_char_input('A', None).unwrap();
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Char(';'), KeyModifiers::NONE))
+            .unwrap();
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(editor.buffer.line(2).unwrap(), "hellothree;");
+        assert_eq!(editor.buffer.line(3).unwrap(), "four;");
+    }
+}
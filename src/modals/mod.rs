@@ -1,4 +1,11 @@
+mod cmd_history;
+mod dot_repeat;
+mod insert;
 mod modal;
 mod normal;
 
+pub(crate) use cmd_history::CmdHistoryWindow;
+pub use dot_repeat::{Action, RecordedAction};
+pub(crate) use insert::LinewiseInsert;
 pub use modal::{FindMode, Modal};
+pub use normal::{CharMotionKind, PendingOperator};
@@ -1,18 +1,26 @@
 use std::fmt::Display;
 
 /// Contains the main modal variants of the editor.
-#[derive(Default, Debug, PartialEq, Eq)]
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Modal {
     #[default]
     Normal,
     Insert,
     Visual,
     VisualLine,
+    /// Visual mode selecting the same columns on every line between the anchor and the cursor.
+    VisualBlock,
     Find(FindMode),
     Command,
+    /// Window listing the command history, opened with `q:`.
+    CmdHistory,
+    /// Picker listing the results of `:Grep`.
+    Picker,
+    /// Input goes to the terminal buffer instead of the text.
+    Terminal,
 }
 
-#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum FindMode {
     #[default]
     Forwards,
@@ -35,8 +43,11 @@ impl Modal {
     pub const fn is_visual_line(&self) -> bool {
         matches!(self, Self::VisualLine)
     }
+    pub const fn is_visual_block(&self) -> bool {
+        matches!(self, Self::VisualBlock)
+    }
     pub const fn is_any_visual(&self) -> bool {
-        matches!(self, Self::VisualLine) || matches!(self, Self::Visual)
+        matches!(self, Self::Visual | Self::VisualLine | Self::VisualBlock)
     }
 
     pub const fn is_find(&self) -> bool {
@@ -46,6 +57,18 @@ impl Modal {
     pub const fn is_command(&self) -> bool {
         matches!(self, Self::Command)
     }
+
+    pub const fn is_cmd_history(&self) -> bool {
+        matches!(self, Self::CmdHistory)
+    }
+
+    pub const fn is_picker(&self) -> bool {
+        matches!(self, Self::Picker)
+    }
+
+    pub const fn is_terminal(&self) -> bool {
+        matches!(self, Self::Terminal)
+    }
 }
 
 impl Display for Modal {
@@ -57,6 +80,10 @@ impl Display for Modal {
             Self::Insert => "INSERT",
             Self::Visual => "VISUAL",
             Self::VisualLine => "VISUAL LINE",
+            Self::VisualBlock => "VISUAL BLOCK",
+            Self::CmdHistory => "COMMAND HISTORY",
+            Self::Picker => "PICKER",
+            Self::Terminal => "TERMINAL",
         };
         write!(f, "{disp}")
     }
@@ -1,19 +1,53 @@
-use std::process::exit;
-
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyModifiers};
 
 use crate::{
     bars::{draw_bar, get_info_bar_content, get_notif_bar_content, INFO_BAR, NOTIFICATION_BAR},
-    buffer::TextBuffer,
+    buffer::{block_columns, TextBuffer},
     cursor::Selection,
     editor::Editor,
     error::Error,
-    notif_bar, repeat, LineCol, Result,
+    lsp::{apply_text_edits, char_col, FormattingOptions},
+    notif_bar, repeat,
+    text_objects::ObjectAction,
+    LineCol, Result,
 };
 
-const SCROLL_JUMP_DISTANCE: usize = 25;
+use super::{Action, FindMode, LinewiseInsert, Modal};
+
+/// Direction and target of a character motion, repeated by `;` and `,`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharMotionKind {
+    /// `f`, onto the next occurrence.
+    FindNext,
+    /// `F`, onto the previous occurrence.
+    FindPrev,
+    /// `t`, right before the next occurrence.
+    ToNext,
+    /// `T`, right after the previous occurrence.
+    ToPrev,
+}
 
-use super::{FindMode, Modal};
+impl CharMotionKind {
+    /// The same motion in the other direction, as repeated by `,`.
+    pub const fn reversed(self) -> Self {
+        match self {
+            Self::FindNext => Self::FindPrev,
+            Self::FindPrev => Self::FindNext,
+            Self::ToNext => Self::ToPrev,
+            Self::ToPrev => Self::ToNext,
+        }
+    }
+}
+
+/// Operator entered in Normal mode that acts on what the following key selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingOperator {
+    /// `zf`, folds the lines covered by the motion.
+    Fold,
+    /// `d`, `c` or `y` followed by `i` or `a`, or `i` or `a` in Visual mode, waiting for the kind
+    /// of text object. `around` is set for `a`.
+    TextObject { action: ObjectAction, around: bool },
+}
 
 impl<Buff: TextBuffer> Editor<Buff> {
     pub(crate) fn run_normal(
@@ -22,17 +56,19 @@ impl<Buff: TextBuffer> Editor<Buff> {
         prev_char: Option<char>,
     ) -> Result<()> {
         self.draw_lines()?;
+        self.draw_quickfix()?;
         let pos = self.pos();
+        let file_label = self.file_label();
         draw_bar(&mut self.viewport.terminal, &INFO_BAR, |term_width, _| {
-            get_info_bar_content(term_width, &self.mode, pos)
+            get_info_bar_content(term_width, &self.mode, &file_label, pos)
         })?;
         draw_bar(&mut self.viewport.terminal, &NOTIFICATION_BAR, |_, _| {
-            get_notif_bar_content()
+            get_notif_bar_content(&mut self.notification_queue)
         })?;
         self.move_cursor();
         self.force_within_bounds();
 
-        if let Event::Key(key_event) = event::read()? {
+        if let Event::Key(key_event) = self.read_event()? {
             match (key_event.code, key_event.modifiers) {
                 (KeyCode::Char(ch), mods) => {
                     if let Some(prev) = prev_char {
@@ -47,9 +83,9 @@ impl<Buff: TextBuffer> Editor<Buff> {
                 }
                 (KeyCode::End, _) => self.move_to_end_of_line(),
                 (KeyCode::Home, _) => self.move_to_first_col(),
-                (KeyCode::Esc, _) => exit(0),
+                (KeyCode::Esc, _) => return Err(Error::ExitCall),
                 _ => {
-                    notif_bar!("nothing");
+                    notif_bar!(self => "nothing");
                 }
             }
         }
@@ -62,24 +98,127 @@ impl<Buff: TextBuffer> Editor<Buff> {
         carry_over: Option<i32>,
         prev: char,
     ) -> Result<()> {
+        let records = !self.mode.is_any_visual()
+            && matches!(
+                (prev, ch),
+                ('d', 'd') | ('d' | 'c', 'w' | 'W' | 'e' | 'E' | 'b' | 'B') | ('r', _)
+            );
         match (prev, ch) {
-            ('d', 'd') => repeat!(self.buffer.delete_line(self.pos().line); carry_over),
+            ('d', 'd') => {
+                if !self.blocked_by_readonly() {
+                    let line = self.pos().line;
+                    let count = usize::try_from(carry_over.unwrap_or(1)).unwrap_or(1);
+                    let last = (line + count.max(1) - 1).min(self.buffer.max_line());
+                    let deleted = format!(
+                        "\n{}",
+                        self.buffer.get_normal_text()[line..=last].join("\n")
+                    );
+                    let register = self.pending_register.take();
+                    self.copy_register.yank(deleted.as_str(), register)?;
+                    self.copy_register.push_into_numbered_registers(deleted);
+                    self.buffer.save_state(self.pos());
+                    for _ in line..=last {
+                        let dest = self.buffer.delete_line(line)?;
+                        self.go(dest);
+                    }
+                }
+            }
+            (operator @ ('d' | 'c'), motion @ ('w' | 'W' | 'e' | 'E' | 'b' | 'B')) => {
+                if !self.blocked_by_readonly() {
+                    self.delete_word_motion(motion, carry_over)?;
+                    if operator == 'c' {
+                        self.set_mode(Modal::Insert);
+                    }
+                }
+            }
+            (operator @ ('d' | 'c' | 'y'), scope @ ('i' | 'a')) => {
+                let action = match operator {
+                    'd' => ObjectAction::Delete,
+                    'c' => ObjectAction::Change,
+                    _ => ObjectAction::Yank,
+                };
+                self.pending_operator = Some(PendingOperator::TextObject {
+                    action,
+                    around: scope == 'a',
+                });
+            }
+            ('q', ':') => self.open_cmd_history(),
+            (' ', 'g') => self.grep_word_under_cursor()?,
+            ('g', 'q') if self.mode.is_any_visual() => self.format_selection()?,
+            ('g', 'a') => {
+                let message = self
+                    .buffer
+                    .line(self.pos().line)?
+                    .get(self.pos().col..)
+                    .and_then(|rest| rest.chars().next())
+                    .map_or_else(|| "NUL".to_string(), describe_char);
+                notif_bar!(self => message;);
+            }
+            ('g', 'i') => {
+                let pos = self.pos();
+                if let Some(dest) = self.last_insert_pos {
+                    self.push_jump(pos);
+                    self.go(dest);
+                }
+                self.set_mode(Modal::Insert);
+            }
+            ('g', 'v') => self.reselect_last_visual(),
+            ('g', 'e') => repeat!(self.move_to_previous_word_end(false); carry_over),
+            ('g', 'E') => repeat!(self.move_to_previous_word_end(true); carry_over),
+            ('g', 'n') => self.select_search_match(false)?,
+            ('g', 'N') => self.select_search_match(true)?,
+            ('g', 'f') => self.goto_file_under_cursor(false)?,
+            ('g', 'F') => self.goto_file_under_cursor(true)?,
             ('g', 'g') => {
                 let col = self.pos().col;
                 self.go(LineCol { line: 0, col });
             }
+            ('z', 'z') => self.viewport.scroll_to_center_at(self.pos().line),
+            ('z', 't') => self.viewport.scroll_to_top_at(self.pos().line),
+            ('z', 'b') => self.viewport.scroll_to_bottom_at(self.pos().line),
+            ('z', 's') => self.viewport.scroll_to_left_at(self.pos().col),
+            ('z', 'e') => {
+                let gutter_width = self.gutter_width();
+                self.viewport
+                    .scroll_to_right_at(self.pos().col, gutter_width);
+            }
+            ('z', 'f') if self.mode.is_any_visual() => {
+                let sel = Selection::from(&self.cursor).normalized();
+                self.create_fold(sel.start.line, sel.end.line);
+                self.set_mode(Modal::Normal);
+            }
+            ('z', 'f') => self.pending_operator = Some(PendingOperator::Fold),
+            ('z', 'd') => self.delete_fold_at_cursor(),
+            ('z', 'E') => self.folds.clear(),
+            ('z', 'h') => {
+                repeat! {self.viewport.move_left(1); carry_over}
+            }
+            ('z', 'l') => {
+                repeat! {self.viewport.move_right(1); carry_over}
+            }
             ('t', pat) => self.move_to_char(pat)?,
             ('T', pat) => self.move_back_to_char(pat)?,
             ('f', pat) => self.find_next_char(pat, carry_over)?,
             ('F', pat) => self.find_previous_char(pat, carry_over)?,
-            ('r', pat) => self.replace_under_cursor(pat)?,
+            ('r', pat) => {
+                self.buffer.save_state(self.pos());
+                self.replace_under_cursor(pat)?;
+            }
+            ('"', register) => self.pending_register = Some(register),
+            ('m', name) => self.set_mark(name),
+            ('\'', name) => self.jump_to_mark(name, false)?,
+            ('`', name) => self.jump_to_mark(name, true)?,
             (_, _) => {
-                notif_bar!("nothing");
+                notif_bar!(self => "nothing");
             }
         }
+        if records {
+            self.record_action(Action::Combination(prev, ch), carry_over);
+        }
         Ok(())
     }
     fn find_next_char(&mut self, pat: char, carry_over: Option<i32>) -> Result<()> {
+        self.last_char_motion = Some((pat, CharMotionKind::FindNext));
         repeat! {{
             let mut pos = self.pos();
             if self.buffer.max_col(pos) > pos.col + 1 {
@@ -92,12 +231,14 @@ impl<Buff: TextBuffer> Editor<Buff> {
     }
 
     fn find_previous_char(&mut self, pat: char, carry_over: Option<i32>) -> Result<()> {
+        self.last_char_motion = Some((pat, CharMotionKind::FindPrev));
         repeat! {{
             self.go(self.buffer.rfind(pat, self.pos())?);
         }; carry_over}
         Ok(())
     }
     fn move_to_char(&mut self, pat: char) -> Result<()> {
+        self.last_char_motion = Some((pat, CharMotionKind::ToNext));
         let dest = self.buffer.find(pat, self.pos())?;
         self.go(dest);
         let mut dest = self.pos();
@@ -107,6 +248,7 @@ impl<Buff: TextBuffer> Editor<Buff> {
     }
 
     fn move_back_to_char(&mut self, pat: char) -> Result<()> {
+        self.last_char_motion = Some((pat, CharMotionKind::ToPrev));
         let dest = self.buffer.rfind(pat, self.pos())?;
         self.go(dest);
         let mut dest = self.pos();
@@ -114,42 +256,87 @@ impl<Buff: TextBuffer> Editor<Buff> {
         self.go(dest);
         Ok(())
     }
+    /// Repeats the last `f`, `F`, `t` or `T` motion, `;`, or the same motion in the other
+    /// direction with `reverse`, `,`. Repeated `t` and `T` skip the occurrence next to the
+    /// cursor so they don't get stuck on it.
+    fn repeat_char_motion(&mut self, reverse: bool, carry_over: Option<i32>) -> Result<()> {
+        let Some((pat, kind)) = self.last_char_motion else {
+            notif_bar!(self => "No previous character motion";);
+            return Ok(());
+        };
+        let motion = if reverse { kind.reversed() } else { kind };
+        let result = self.replay_char_motion(pat, motion, carry_over);
+        // Replaying goes through the motions themselves, which remember the reversed one
+        self.last_char_motion = Some((pat, kind));
+        result
+    }
+    fn replay_char_motion(
+        &mut self,
+        pat: char,
+        motion: CharMotionKind,
+        carry_over: Option<i32>,
+    ) -> Result<()> {
+        repeat! {{
+            let start = self.pos();
+            let line_len = self.buffer.line(start.line)?.len();
+            let moved = match motion {
+                CharMotionKind::FindNext => self.find_next_char(pat, None),
+                CharMotionKind::FindPrev => self.find_previous_char(pat, None),
+                CharMotionKind::ToNext => {
+                    self.go(LineCol { col: (start.col + 2).min(line_len), ..start });
+                    self.move_to_char(pat)
+                }
+                CharMotionKind::ToPrev => {
+                    self.go(LineCol { col: start.col.saturating_sub(1), ..start });
+                    self.move_back_to_char(pat)
+                }
+            };
+            if let Err(e) = moved {
+                self.go(start);
+                return Err(e);
+            }
+        }; carry_over}
+        Ok(())
+    }
     /// Unnecessary until redo and scrolling
     pub fn handle_modifiers(&mut self, ch: char, carry_over: Option<i32>, modifiers: KeyModifiers) {
         if modifiers.contains(KeyModifiers::CONTROL) {
             match ch {
-                'd' => {
-                    repeat! {{
-                        self.cursor.jump_down(SCROLL_JUMP_DISTANCE, self.buffer.max_line());
-                        self.viewport.center(self.pos());
-                    }; carry_over
-                    }
-                }
-                'u' => {
-                    repeat! {{
-                        self.cursor.jump_up(SCROLL_JUMP_DISTANCE);
-                        self.viewport.center(self.pos());
-                    }; carry_over
-                    }
-                }
+                'd' => repeat!(self.jump_down(); carry_over),
+                'u' => repeat!(self.jump_up(); carry_over),
+                'o' => self.jump_back(),
+                'r' => repeat!(self.redo(); carry_over),
+                'v' => self.set_mode(Modal::VisualBlock),
                 _ => (),
             }
         }
     }
     pub fn handle_char_input(&mut self, ch: char, carry_over: Option<i32>) -> Result<()> {
+        if !ch.is_ascii_digit() {
+            if let Some(operator) = self.pending_operator.take() {
+                return self.apply_pending_operator(operator, ch, carry_over);
+            }
+        }
+        let records = !self.mode.is_any_visual()
+            && matches!(ch, 'x' | 'X' | '~' | 'p' | 'P' | 'o' | 'i' | 'A');
         match ch {
-            combination @ ('r' | 't' | 'd' | 'z' | 'f' | 'g' | 'F' | 'T') => {
+            combination @ ('r' | 't' | 'd' | 'c' | 'z' | 'f' | 'g' | 'F' | 'T' | 'q' | 'm'
+            | '\'' | '`' | '"' | ' ') => {
                 if combination == 'd' && self.mode.is_any_visual() {
-                    let sel = Selection::from(&self.cursor).normalized();
-
-                    let dest = self.buffer.delete_selection(sel.start, sel.end)?;
-                    self.cursor.pos = dest;
-                    self.set_mode(Modal::Normal)
+                    return self.delete_visual_selection();
+                }
+                if combination == 'c' && self.mode.is_visual_block() {
+                    return self.delete_visual_block(true);
                 }
                 self.run_normal(carry_over, Some(combination))?;
             }
             'y' => {
-                if self.mode.is_any_visual() {
+                if self.mode.is_visual_block() {
+                    let sel = self.visual_block_text()?;
+                    let register = self.pending_register.take();
+                    self.copy_register.yank(sel, register)?;
+                    self.set_mode(Modal::Normal)
+                } else if self.mode.is_any_visual() {
                     let sel = self.buffer.get_buffer_window(
                         Some(self.cursor.last_text_mode_pos),
                         Some(self.pos()),
@@ -159,31 +346,53 @@ impl<Buff: TextBuffer> Editor<Buff> {
                     } else {
                         sel.join("\n").to_string()
                     };
-                    let chars: Vec<char> = sel.chars().collect();
-                    self.copy_register.yank(chars, None)?;
+                    let register = self.pending_register.take();
+                    self.copy_register.yank(sel, register)?;
                     self.set_mode(Modal::Normal)
+                } else {
+                    self.run_normal(carry_over, Some('y'))?;
                 }
             }
+            'i' | 'a' if self.mode.is_any_visual() => {
+                self.pending_operator = Some(PendingOperator::TextObject {
+                    action: ObjectAction::Select,
+                    around: ch == 'a',
+                });
+            }
             'i' => {
                 if !self.mode.is_any_visual() {
                     self.set_mode(Modal::Insert)
                 }
             }
-            'p' => self.paste_register_content(None, false)?,
-            'P' => self.paste_register_content(None, true)?,
-            'o' => {
-                self.set_mode(Modal::Insert);
-                self.newline();
+            'u' if self.mode.is_any_visual() => self.change_selection_case(false)?,
+            'U' if self.mode.is_any_visual() => self.change_selection_case(true)?,
+            'u' => repeat!(self.undo(); carry_over),
+            'p' => {
+                self.buffer.save_state(self.pos());
+                let register = self.pending_register.take();
+                repeat!(self.paste_register_content(register, false)?; carry_over);
+            }
+            'P' => {
+                self.buffer.save_state(self.pos());
+                let register = self.pending_register.take();
+                repeat!(self.paste_above(register)?; carry_over);
             }
+            'o' => self.open_lines_below(carry_over),
             ':' => self.set_mode(Modal::Command),
             'v' => self.set_mode(Modal::Visual),
             'V' => self.set_mode(Modal::VisualLine),
+            ';' => self.repeat_char_motion(false, carry_over)?,
+            ',' => self.repeat_char_motion(true, carry_over)?,
+            'n' => self.repeat_search(false, carry_over)?,
+            'N' => self.repeat_search(true, carry_over)?,
+            '*' => repeat!(self.search_word_under_cursor(FindMode::Forwards)?; carry_over),
+            '#' => repeat!(self.search_word_under_cursor(FindMode::Backwards)?; carry_over),
             '/' => self.set_mode(Modal::Find(FindMode::Forwards)),
             '?' => self.set_mode(Modal::Find(FindMode::Backwards)),
             'h' => repeat!(self.cursor.bump_left(); carry_over),
             'l' => repeat!(self.cursor.bump_right(); carry_over),
-            'k' => repeat!(self.cursor.bump_up(); carry_over),
-            'j' => repeat!(self.cursor.bump_down(); carry_over),
+            'k' => repeat!(self.move_up_over_folds(); carry_over),
+            'j' => repeat!(self.move_down_over_folds(); carry_over),
             'J' => {
                 if self.mode.is_any_visual() {
                     // Add Join Lines
@@ -191,29 +400,248 @@ impl<Buff: TextBuffer> Editor<Buff> {
             }
             'W' => repeat!(self.move_to_next_word_after_whitespace()?; carry_over),
             'w' => repeat!(self.move_to_next_non_alphanumeric()?; carry_over),
-            'G' => self.move_to_lowest_line(),
-            'x' => self.delete_under_cursor()?,
-            'X' => self.delete_before_cursor()?,
-            'A' => self.move_to_end_of_line_and_insert(),
+            'H' | 'M' | 'L' => self.move_within_viewport(ch, carry_over),
+            '}' => {
+                self.push_jump(self.pos());
+                repeat!(self.move_to_paragraph_boundary(true); carry_over);
+            }
+            '{' => {
+                self.push_jump(self.pos());
+                repeat!(self.move_to_paragraph_boundary(false); carry_over);
+            }
+            'b' => repeat!(self.move_to_previous_word_start(false); carry_over),
+            'B' => repeat!(self.move_to_previous_word_start(true); carry_over),
+            'e' => repeat!(self.move_to_word_end(false); carry_over),
+            'E' => repeat!(self.move_to_word_end(true); carry_over),
+            'G' => match carry_over {
+                Some(line) => self.move_to_line(line),
+                None => self.move_to_lowest_line(),
+            },
+            'x' => {
+                self.buffer.save_state(self.pos());
+                if carry_over.is_some_and(|count| count > 1) && !self.blocked_by_readonly() {
+                    let count = usize::try_from(carry_over.unwrap_or(1)).unwrap_or(1);
+                    let deleted = self
+                        .buffer
+                        .line(self.pos().line)?
+                        .get(self.pos().col..)
+                        .unwrap_or_default()
+                        .chars()
+                        .take(count)
+                        .collect();
+                    self.save_deletion(deleted);
+                }
+                repeat!(
+                    self.delete_under_cursor()?;
+                    carry_over,
+                    self.pos().col >= self.buffer.max_col(self.pos())
+                );
+            }
+            '~' => {
+                if !self.blocked_by_readonly() {
+                    self.buffer.save_state(self.pos());
+                    repeat!(
+                        self.toggle_case_under_cursor()?;
+                        carry_over,
+                        self.pos().col >= self.buffer.max_col(self.pos())
+                    );
+                }
+            }
+            'X' => {
+                self.buffer.save_state(self.pos());
+                repeat!(self.delete_before_cursor()?; carry_over, self.pos().col == 0);
+            }
+            'I' if self.mode.is_visual_block() => self.start_linewise_insert(false)?,
+            'I' | 'A' if self.mode.is_visual_line() => self.start_linewise_insert(ch == 'A')?,
+            'A' => self.move_to_end_of_line_and_insert(carry_over)?,
             '_' => self.move_to_first_non_whitespace_col()?,
             '$' => self.move_to_end_of_line(),
+            '%' => self.jump_to_matching_bracket()?,
+            '.' => self.repeat_last_action(carry_over)?,
             '0'..='9' => self.handle_number_input(ch, carry_over),
             _ => {
-                notif_bar!("nothing");
+                notif_bar!(self => "nothing");
             }
         }
+        if records {
+            self.record_action(Action::Key(ch), carry_over);
+        }
+        Ok(())
+    }
+    /// Applies `operator` to what the key typed after it selects.
+    fn apply_pending_operator(
+        &mut self,
+        operator: PendingOperator,
+        key: char,
+        carry_over: Option<i32>,
+    ) -> Result<()> {
+        match operator {
+            PendingOperator::Fold => self.fold_over_motion(key, carry_over),
+            PendingOperator::TextObject { action, around } => {
+                self.apply_text_object(action, around, key)?;
+                if matches!(action, ObjectAction::Delete | ObjectAction::Change) {
+                    let object = Action::TextObject {
+                        action,
+                        around,
+                        object: key,
+                    };
+                    self.record_action(object, carry_over);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Moves a line up, `k`, over a fold as if it were a single line, onto its first line.
+    fn move_up_over_folds(&mut self) {
+        if let Some((start, _)) = self.folds.closed_at(self.pos().line) {
+            self.cursor.pos.line = start;
+        }
+        self.cursor.bump_up();
+        if let Some((start, _)) = self.folds.closed_at(self.pos().line) {
+            self.cursor.pos.line = start;
+        }
+    }
+
+    /// Moves a line down, `j`, over a fold as if it were a single line.
+    fn move_down_over_folds(&mut self) {
+        if let Some((_, end)) = self.folds.closed_at(self.pos().line) {
+            self.cursor.pos.line = end;
+        }
+        self.cursor.bump_down();
+    }
+
+    /// Folds the lines from the cursor to where the `motion` moves it, leaving the cursor in
+    /// place. Only linewise motions are accepted, any other key cancels the fold.
+    fn fold_over_motion(&mut self, motion: char, carry_over: Option<i32>) -> Result<()> {
+        if !matches!(motion, 'j' | 'k' | 'G' | 'H' | 'M' | 'L') {
+            return Ok(());
+        }
+        let start = self.pos();
+        self.handle_char_input(motion, carry_over)?;
+        let end = self.pos();
+        self.go(start);
+        self.create_fold(start.line, end.line);
+        Ok(())
+    }
+
+    /// Deletes the visual selection, `d` in Visual mode, and returns to Normal mode.
+    fn delete_visual_selection(&mut self) -> Result<()> {
+        if self.mode.is_visual_block() {
+            return self.delete_visual_block(false);
+        }
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let sel = Selection::from(&self.cursor).normalized();
+
+        let deleted = self.buffer.get_text(sel.start, sel.end)?;
+        self.save_deletion(deleted);
+        self.buffer.save_state(sel.start);
+        let dest = self.buffer.delete_selection(sel.start, sel.end)?;
+        self.set_mode(Modal::Normal);
+        self.cursor.pos = dest;
+        Ok(())
+    }
+
+    /// Deletes the Visual Block selection, `d`, and returns to Normal mode. With `change`, `c`,
+    /// Insert mode is entered instead and the typed text is put on every selected line.
+    fn delete_visual_block(&mut self, change: bool) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let (from, to) = self.visual_block();
+        let deleted = self.visual_block_text()?;
+        self.save_deletion(deleted);
+        self.buffer.save_state(from);
+        self.buffer.delete_block(from, to)?;
+        if !change {
+            self.set_mode(Modal::Normal);
+            self.go(from);
+            return Ok(());
+        }
+        let line_len = self.buffer.line(from.line)?.len();
+        let start = LineCol {
+            line: from.line,
+            col: from.col.min(line_len),
+        };
+        self.set_mode(Modal::Insert);
+        self.go(start);
+        self.linewise_insert = Some(LinewiseInsert {
+            lines: from.line + 1..=to.line,
+            append: false,
+            start,
+            line_len,
+            copies: 0,
+        });
         Ok(())
     }
+
+    /// Top left corner and exclusive bottom right corner of the Visual Block selection. The
+    /// column under the cursor is part of it.
+    pub(crate) fn visual_block(&self) -> (LineCol, LineCol) {
+        let (anchor, pos) = (self.cursor.last_text_mode_pos, self.pos());
+        (
+            LineCol {
+                line: anchor.line.min(pos.line),
+                col: anchor.col.min(pos.col),
+            },
+            LineCol {
+                line: anchor.line.max(pos.line),
+                col: anchor.col.max(pos.col) + 1,
+            },
+        )
+    }
+
+    /// Text of the Visual Block selection, a line of it for every selected line.
+    fn visual_block_text(&self) -> Result<String> {
+        let (from, to) = self.visual_block();
+        let lines = (from.line..=to.line)
+            .map(|line| {
+                let line = self.buffer.line(line)?;
+                Ok(block_columns(line, from.col, to.col)
+                    .map_or("", |(start, end)| &line[start..end])
+                    .to_string())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(lines.join("\n"))
+    }
+
+    /// Enters the mode of the last visual selection again with the same anchor and cursor,
+    /// `gv`. Positions past the end of the buffer are clamped to it.
+    fn reselect_last_visual(&mut self) {
+        let Some((anchor, pos, mode)) = self.last_visual_selection else {
+            notif_bar!(self => "No previous visual selection";);
+            return;
+        };
+        let clamp = |pos: LineCol| {
+            let line = pos.line.min(self.buffer.max_line());
+            let col = pos.col.min(self.buffer.max_col(LineCol { line, col: 0 }));
+            LineCol { line, col }
+        };
+        let (anchor, pos) = (clamp(anchor), clamp(pos));
+        self.set_mode(mode);
+        self.cursor.last_text_mode_pos = anchor;
+        self.cursor.pos = pos;
+    }
+
+    /// Keeps `deleted` in the numbered registers if it spans more than a single symbol.
+    pub(crate) fn save_deletion(&mut self, deleted: String) {
+        if deleted.contains('\n') || deleted.chars().nth(1).is_some() {
+            self.copy_register.push_into_numbered_registers(deleted);
+        }
+    }
     fn paste_register_content(&mut self, register: Option<char>, newline: bool) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
         let register_content = self.copy_register.get_from_register(register)?;
-        let mut pos = self.pos();
-        pos.line -= 1;
         let dest =
             self.buffer
                 .insert_text(self.pos(), String::from_iter(register_content), newline);
         let dest = match dest {
             Err(Error::InvalidInput) => {
-                notif_bar!("Register empty.");
+                notif_bar!(self => "Register empty.");
                 self.pos()
             }
             otherwise => otherwise?,
@@ -222,26 +650,290 @@ impl<Buff: TextBuffer> Editor<Buff> {
         Ok(())
     }
 
+    /// Reverts the last change, `u`, moving the cursor to where it was made.
+    pub fn undo(&mut self) {
+        match self.buffer.undo(self.pos()) {
+            Ok(dest) => self.go_after_history_step(dest),
+            Err(_) => notif_bar!(self => "Already at oldest change";),
+        }
+    }
+
+    /// Applies the last undone change again, `Ctrl-r`.
+    pub fn redo(&mut self) {
+        match self.buffer.redo(self.pos()) {
+            Ok(dest) => self.go_after_history_step(dest),
+            Err(_) => notif_bar!(self => "Already at newest change";),
+        }
+    }
+
+    fn go_after_history_step(&mut self, dest: LineCol) {
+        let line = dest.line.min(self.buffer.max_line());
+        let col = dest.col.min(self.buffer.max_col(LineCol { line, col: 0 }));
+        self.prev_pos = self.pos();
+        self.go(LineCol { line, col });
+    }
+
+    /// Amount of lines `Ctrl-d` and `Ctrl-u` scroll by, half the window unless `scroll` is set.
+    pub fn scroll_distance(&self) -> usize {
+        self.options
+            .scroll_jump_distance
+            .unwrap_or(self.viewport.terminal_dimensions.line / 2)
+            .max(1)
+    }
+
+    /// Moves the cursor `scroll_distance` lines down and centers the window on it, `Ctrl-d`.
+    pub fn jump_down(&mut self) {
+        self.cursor
+            .jump_down(self.scroll_distance(), self.buffer.max_line());
+        self.viewport.center(self.pos());
+    }
+
+    /// Moves the cursor `scroll_distance` lines up and centers the window on it, `Ctrl-u`.
+    pub fn jump_up(&mut self) {
+        self.cursor.jump_up(self.scroll_distance());
+        self.viewport.center(self.pos());
+    }
+
+    /// Pastes the content of `register` above the cursor line, `P`. Line-wise content, which
+    /// starts with a newline, goes on its own lines above the cursor line, character-wise content
+    /// is put at the start of the cursor line. The cursor lands on the first pasted line.
+    pub(crate) fn paste_above(&mut self, register: Option<char>) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let content = String::from_iter(self.copy_register.get_from_register(register)?);
+        let line_start = LineCol {
+            line: self.pos().line,
+            col: 0,
+        };
+        let (text, linewise) = match content.strip_prefix('\n') {
+            Some(lines) => (lines, true),
+            None => (content.as_str(), false),
+        };
+        if text.is_empty() {
+            notif_bar!(self => "Register empty.");
+            return Ok(());
+        }
+        if linewise {
+            self.buffer
+                .replace(line_start, line_start, &format!("{text}\n"))?;
+        } else {
+            self.buffer.insert_text(line_start, text, false)?;
+        }
+        self.go(line_start);
+        Ok(())
+    }
+
+    /// Lowercases the visual selection, or uppercases it with `upper`, leaving the cursor at its
+    /// start. The symbol under the cursor is part of the selection.
+    fn change_selection_case(&mut self, upper: bool) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let (start, end) = self.visual_selection_range()?;
+        let text = self.buffer.get_text(start, end)?;
+        let changed = if upper {
+            text.to_uppercase()
+        } else {
+            text.to_lowercase()
+        };
+        if !changed.is_empty() && changed != text {
+            self.buffer.save_state(start);
+            self.buffer.replace(start, end, &changed)?;
+        }
+        self.set_mode(Modal::Normal);
+        self.go(start);
+        Ok(())
+    }
+
+    /// Formats the visual selection through the language server, `gq`.
+    fn format_selection(&mut self) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let (start, end) = self.visual_selection_range()?;
+        self.set_mode(Modal::Normal);
+        self.go(start);
+        let Some(uri) = self.document_uri() else {
+            notif_bar!(self => "Formatting needs a file name";);
+            return Ok(());
+        };
+        let to_lsp = |pos: LineCol| -> Result<LineCol> {
+            Ok(LineCol {
+                line: pos.line,
+                col: char_col(self.buffer.line(pos.line)?, pos.col),
+            })
+        };
+        let range = (to_lsp(start)?, to_lsp(end)?);
+        let options = FormattingOptions {
+            tab_size: u32::try_from(self.options.tab_width()).unwrap_or(u32::MAX),
+            insert_spaces: self.options.expand_tab(),
+        };
+        let Some(client) = self.lsp.get_or_start(self.file_type) else {
+            notif_bar!(self => "No language server for this file type";);
+            return Ok(());
+        };
+        match client.request_range_formatting(&uri, range, options) {
+            Ok(edits) => {
+                if !edits.is_empty() {
+                    self.buffer.save_state(start);
+                    apply_text_edits(&mut self.buffer, &edits)?;
+                }
+            }
+            Err(e) => notif_bar!(self => e.display_user();),
+        }
+        Ok(())
+    }
+
+    /// Start and exclusive end of the visual selection. The symbol under the cursor is part of
+    /// it, and in Visual Line mode the selected lines are covered entirely.
+    pub(crate) fn visual_selection_range(&self) -> Result<(LineCol, LineCol)> {
+        let sel = Selection::from(&self.cursor).normalized();
+        if self.mode.is_visual_line() {
+            let end = LineCol {
+                line: sel.end.line,
+                col: 0,
+            };
+            Ok((
+                LineCol {
+                    line: sel.start.line,
+                    col: 0,
+                },
+                LineCol {
+                    line: end.line,
+                    col: self.buffer.max_col(end),
+                },
+            ))
+        } else {
+            let line = self.buffer.line(sel.end.line)?;
+            let col = line
+                .get(sel.end.col..)
+                .and_then(|rest| rest.chars().next())
+                .map_or(line.len(), |ch| sel.end.col + ch.len_utf8());
+            Ok((
+                sel.start,
+                LineCol {
+                    line: sel.end.line,
+                    col,
+                },
+            ))
+        }
+    }
+
+    /// Swaps the case of the symbol under the cursor and moves past it. Symbols without case
+    /// are only moved over.
+    fn toggle_case_under_cursor(&mut self) -> Result<()> {
+        let pos = self.pos();
+        let Some(ch) = self
+            .buffer
+            .line(pos.line)?
+            .get(pos.col..)
+            .and_then(|rest| rest.chars().next())
+        else {
+            return Ok(());
+        };
+        let toggled: String = if ch.is_uppercase() {
+            ch.to_lowercase().collect()
+        } else {
+            ch.to_uppercase().collect()
+        };
+        if toggled != ch.to_string() {
+            let end = LineCol {
+                line: pos.line,
+                col: pos.col + ch.len_utf8(),
+            };
+            self.buffer.replace(pos, end, &toggled)?;
+        }
+        self.go(LineCol {
+            line: pos.line,
+            col: pos.col + toggled.len(),
+        });
+        Ok(())
+    }
+
     fn replace_under_cursor(&mut self, ch: char) -> Result<()> {
         self.delete_under_cursor()?;
         self.push(ch);
         Ok(())
     }
     fn delete_under_cursor(&mut self) -> Result<()> {
-        let mut delete_dest = self.pos();
-        delete_dest.col += 1;
-        let dest = self.buffer.delete(delete_dest)?;
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        // `delete` removes the symbol before the position it's given, so pass the end of the one
+        // under the cursor
+        let pos = self.pos();
+        let Some(under) = self
+            .buffer
+            .line(pos.line)?
+            .get(pos.col..)
+            .and_then(|rest| rest.chars().next())
+        else {
+            return Ok(());
+        };
+        let dest = self.buffer.delete(LineCol {
+            line: pos.line,
+            col: pos.col + under.len_utf8(),
+        })?;
         self.go(dest);
         Ok(())
     }
+    /// Deletes the symbol left of the cursor, `X`. Does nothing at the start of a line rather
+    /// than joining it onto the previous one.
     fn delete_before_cursor(&mut self) -> Result<()> {
+        if self.blocked_by_readonly() || self.pos().col == 0 {
+            return Ok(());
+        }
         let dest = self.buffer.delete(self.pos())?;
         self.go(dest);
         Ok(())
     }
-    fn move_to_end_of_line_and_insert(&mut self) {
+    /// Opens `count` blank lines below the cursor line and starts typing on the first, `o`. The
+    /// typed text is repeated on the other opened lines when Insert mode is left.
+    fn open_lines_below(&mut self, count: Option<i32>) {
+        if self.blocked_by_readonly() {
+            return;
+        }
+        let line = self.pos().line;
+        let count = usize::try_from(count.unwrap_or(1)).unwrap_or(1).max(1);
+        // Entering Insert mode first saves the state before the opened lines, so they're undone
+        // along with the typed text
+        self.set_mode(Modal::Insert);
+        for _ in 0..count {
+            self.buffer.insert_newline(LineCol { line, col: 0 });
+        }
+        let start = LineCol {
+            line: line + 1,
+            col: 0,
+        };
+        self.go(start);
+        if count > 1 {
+            self.linewise_insert = Some(LinewiseInsert {
+                lines: line + 2..=line + count,
+                append: false,
+                start,
+                line_len: 0,
+                copies: 0,
+            });
+        }
+    }
+    /// Enters Insert mode at the end of the line, `A`. With a count the typed text is repeated
+    /// that many times when Insert mode is left.
+    fn move_to_end_of_line_and_insert(&mut self, count: Option<i32>) -> Result<()> {
         self.move_to_end_of_line();
         self.set_mode(Modal::Insert);
+        let copies = usize::try_from(count.unwrap_or(1)).unwrap_or(1).max(1) - 1;
+        if copies > 0 {
+            let start = self.pos();
+            self.linewise_insert = Some(LinewiseInsert {
+                lines: start.line + 1..=start.line,
+                append: true,
+                start,
+                line_len: self.buffer.line(start.line)?.len(),
+                copies,
+            });
+        }
+        Ok(())
     }
     fn move_to_lowest_line(&mut self) {
         let mut pos = self.pos();
@@ -249,6 +941,14 @@ impl<Buff: TextBuffer> Editor<Buff> {
         pos.line = dest;
         self.go(pos);
     }
+    /// Moves to the one-based `line` as given by a count, clamped to the buffer.
+    fn move_to_line(&mut self, line: i32) {
+        let mut pos = self.pos();
+        pos.line = usize::try_from(line.saturating_sub(1))
+            .unwrap_or_default()
+            .min(self.buffer.max_line());
+        self.go(pos);
+    }
     pub fn move_to_end_of_line(&mut self) {
         let mut pos = self.pos();
         let dest = self.buffer.max_col(pos);
@@ -260,11 +960,49 @@ impl<Buff: TextBuffer> Editor<Buff> {
         pos.col = 0;
         self.go(pos);
     }
+    /// Jumps to the bracket matching the one under the cursor, or the one right before it at the
+    /// end of a line. Off a bracket the first bracket after the cursor on its line is used, like
+    /// Vim does.
+    fn jump_to_matching_bracket(&mut self) -> Result<()> {
+        let pos = self.pos();
+        let is_bracket = |ch: char| "()[]{}".contains(ch);
+        let line = self.buffer.line(pos.line)?;
+        let bracket = line
+            .get(pos.col..)
+            .and_then(|rest| rest.char_indices().find(|(_, ch)| is_bracket(*ch)))
+            .map(|(col, _)| pos.col + col)
+            .or_else(|| {
+                // Past the last symbol the cursor sits right after it
+                let (col, ch) = line.get(..pos.col)?.char_indices().next_back()?;
+                (pos.col == line.len() && is_bracket(ch)).then_some(col)
+            });
+        let dest = bracket.and_then(|col| {
+            self.find_matching_bracket(LineCol {
+                line: pos.line,
+                col,
+            })
+        });
+        match dest {
+            Some(dest) => {
+                self.push_jump(pos);
+                self.go(dest);
+            }
+            None => notif_bar!(self => "No matching bracket";),
+        }
+        Ok(())
+    }
+    /// Moves to the first word character of the line, `_`, or to its first non-blank one if it
+    /// has none. Word characters are decided by `iskeyword`.
     fn move_to_first_non_whitespace_col(&mut self) -> Result<()> {
-        let mut pos = self.pos();
-        pos.col = 0;
-        let dest = self.buffer.find(|ch| !char::is_whitespace(ch), pos)?;
-        self.go(dest);
+        let line = self.buffer.line(self.pos().line)?;
+        let col = line
+            .find(|ch| self.options.is_keyword(ch))
+            .or_else(|| line.find(|ch: char| !ch.is_whitespace()))
+            .unwrap_or(0);
+        self.go(LineCol {
+            line: self.pos().line,
+            col,
+        });
         Ok(())
     }
     fn move_to_next_word_after_whitespace(&mut self) -> Result<()> {
@@ -286,10 +1024,221 @@ impl<Buff: TextBuffer> Editor<Buff> {
         };
 
         let mut dest = self.buffer.find(|ch| !char::is_whitespace(ch), pos)?;
-        dest = self.buffer.find(|ch| !char::is_alphanumeric(ch), dest)?;
+        dest = self.buffer.find(|ch| !self.options.is_keyword(ch), dest)?;
         self.go(dest);
         Ok(())
     }
+    /// Moves to the start of the word before the cursor, or of the one it's in. With `big` words
+    /// are only delimited by whitespace. An empty line counts as a word of its own.
+    fn move_to_previous_word_start(&mut self, big: bool) {
+        let Some(mut pos) = self.step_back(self.pos()) else {
+            return;
+        };
+        while self.char_at(pos).is_whitespace() {
+            if pos.col == 0 && self.buffer.max_col(pos) == 0 {
+                self.go(pos);
+                return;
+            }
+            match self.step_back(pos) {
+                Some(prev) => pos = prev,
+                None => break,
+            }
+        }
+        let class = self.options.word_class(self.char_at(pos), big);
+        while let Some(prev) = self.step_back(pos) {
+            if self.options.word_class(self.char_at(prev), big) != class {
+                break;
+            }
+            pos = prev;
+        }
+        self.go(pos);
+    }
+
+    /// Moves to the last symbol of the word before the one the cursor is in, `ge`. With `big`
+    /// words are only delimited by whitespace. A line break separates words like whitespace.
+    fn move_to_previous_word_end(&mut self, big: bool) {
+        let pos = self.pos();
+        let class = self.options.word_class(self.char_at(pos), big);
+        let Ok(found) = self
+            .buffer
+            .rfind(|ch| self.options.word_class(ch, big) != class, pos)
+        else {
+            return;
+        };
+        let dest = if found.line < pos.line {
+            // The word starts its line, so the previous one ends on an earlier line
+            let line_start = LineCol {
+                line: pos.line,
+                col: 0,
+            };
+            self.buffer
+                .rfind(|ch: char| !ch.is_whitespace(), line_start)
+        } else if self.char_at(found).is_whitespace() {
+            self.buffer.rfind(|ch: char| !ch.is_whitespace(), found)
+        } else {
+            Ok(found)
+        };
+        if let Ok(dest) = dest {
+            self.go(dest);
+        }
+    }
+
+    /// Moves to the last symbol of the word after the cursor, or of the one it's in. With `big`
+    /// words are only delimited by whitespace.
+    fn move_to_word_end(&mut self, big: bool) {
+        let Some(mut pos) = self.step_forward(self.pos()) else {
+            return;
+        };
+        while self.char_at(pos).is_whitespace() {
+            match self.step_forward(pos) {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+        let class = self.options.word_class(self.char_at(pos), big);
+        while let Some(next) = self.step_forward(pos) {
+            if self.options.word_class(self.char_at(next), big) != class {
+                break;
+            }
+            pos = next;
+        }
+        self.go(pos);
+    }
+
+    /// Moves to the top of the visible lines with `H`, the middle with `M` and the bottom with
+    /// `L`. A count moves that many lines from the top or bottom instead, staying on screen.
+    fn move_within_viewport(&mut self, position: char, carry_over: Option<i32>) {
+        let top = self.viewport.topleft.line.min(self.buffer.max_line());
+        let bottom = self
+            .viewport
+            .last_visible_line()
+            .min(self.buffer.max_line());
+        let offset = usize::try_from(carry_over.unwrap_or(1).saturating_sub(1)).unwrap_or_default();
+        let line = match position {
+            'H' => (top + offset).min(bottom),
+            'L' => bottom.saturating_sub(offset).max(top),
+            _ => top + (bottom - top) / 2,
+        };
+        let col = self
+            .pos()
+            .col
+            .min(self.buffer.max_col(LineCol { line, col: 0 }));
+        self.go(LineCol { line, col });
+    }
+
+    /// Moves to the next blank line below the cursor, or above it if not `forwards`, skipping the
+    /// run of blank lines the cursor is already in. Stops at the first or last line if there is none.
+    fn move_to_paragraph_boundary(&mut self, forwards: bool) {
+        let is_blank = |line: usize| {
+            self.buffer
+                .line(line)
+                .is_ok_and(|content| content.trim().is_empty())
+        };
+        let current = self.pos().line;
+        let in_blank = is_blank(current);
+        let dest = if forwards {
+            let last = self.buffer.max_line();
+            (current + 1..=last)
+                .skip_while(|line| in_blank && is_blank(*line))
+                .find(|line| is_blank(*line))
+                .map_or_else(
+                    || LineCol {
+                        line: last,
+                        col: self.buffer.max_col(LineCol { line: last, col: 0 }),
+                    },
+                    |line| LineCol { line, col: 0 },
+                )
+        } else {
+            (0..current)
+                .rev()
+                .skip_while(|line| in_blank && is_blank(*line))
+                .find(|line| is_blank(*line))
+                .map_or_else(LineCol::default, |line| LineCol { line, col: 0 })
+        };
+        self.go(dest);
+    }
+
+    /// Deletes from the cursor to where the word `motion` moves it, `dw`, `de`, `db` and their
+    /// WORD variants. Like the motion itself, `e` includes the last symbol of the word, and `w`
+    /// stops at the end of the line.
+    fn delete_word_motion(&mut self, motion: char, carry_over: Option<i32>) -> Result<()> {
+        let start = self.pos();
+        match motion {
+            'w' => repeat!(self.move_to_next_non_alphanumeric()?; carry_over),
+            'W' => repeat!(self.move_to_next_word_after_whitespace()?; carry_over),
+            'b' | 'B' => repeat!(self.move_to_previous_word_start(motion == 'B'); carry_over),
+            _ => repeat!(self.move_to_word_end(motion == 'E'); carry_over),
+        }
+        let mut end = self.pos();
+        if matches!(motion, 'e' | 'E') {
+            end = self.step_forward(end).unwrap_or(LineCol {
+                line: end.line,
+                col: self.buffer.max_col(end),
+            });
+        } else if matches!(motion, 'w' | 'W') && end.line > start.line {
+            end = LineCol {
+                line: start.line,
+                col: self.buffer.max_col(start),
+            };
+        }
+        let (from, to) = if end < start {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        if from != to {
+            let deleted = self.buffer.get_text(from, to)?;
+            self.save_deletion(deleted);
+            self.buffer.save_state(start);
+            self.buffer.delete_selection(from, to)?;
+        }
+        self.go(from);
+        Ok(())
+    }
+
+    /// Symbol at `pos`, the end of a line reads as a newline.
+    fn char_at(&self, pos: LineCol) -> char {
+        self.buffer
+            .line(pos.line)
+            .ok()
+            .and_then(|line| line.get(pos.col..)?.chars().next())
+            .unwrap_or('\n')
+    }
+
+    /// Position of the symbol after the one at `pos`, moving onto the next line past its end.
+    fn step_forward(&self, pos: LineCol) -> Option<LineCol> {
+        let len = self.buffer.max_col(pos);
+        if pos.col < len {
+            Some(LineCol {
+                line: pos.line,
+                col: pos.col + self.char_at(pos).len_utf8(),
+            })
+        } else {
+            (pos.line < self.buffer.max_line()).then_some(LineCol {
+                line: pos.line + 1,
+                col: 0,
+            })
+        }
+    }
+
+    /// Position of the symbol before the one at `pos`, moving onto the end of the previous line.
+    fn step_back(&self, pos: LineCol) -> Option<LineCol> {
+        if pos.col > 0 {
+            let line = self.buffer.line(pos.line).ok()?;
+            let (col, _) = line.get(..pos.col)?.char_indices().next_back()?;
+            Some(LineCol {
+                line: pos.line,
+                col,
+            })
+        } else {
+            let line = pos.line.checked_sub(1)?;
+            Some(LineCol {
+                line,
+                col: self.buffer.max_col(LineCol { line, col: 0 }),
+            })
+        }
+    }
+
     fn handle_number_input(&mut self, num: char, carry_over: Option<i32>) {
         let digit = i32::from(num as u8 - b'0');
         let new_carry_over = carry_over.map_or(digit, |current_carry_over| {
@@ -299,6 +1248,621 @@ impl<Buff: TextBuffer> Editor<Buff> {
     }
 }
 
+/// Code point of `ch` in decimal, hexadecimal and octal followed by its Unicode name, as shown
+/// by `ga`.
+pub fn describe_char(ch: char) -> String {
+    let code = u32::from(ch);
+    let mut description = format!("<{ch}> {code}, Hex {code:x}, Octal {code:o}");
+    if let Some(name) = unicode_names2::name(ch) {
+        description.push_str(&format!(", Unicode: {name}"));
+    }
+    description
+}
+
 pub fn concatenate_ints(a: i32, b: i32) -> i32 {
     format!("{a}{b}").parse().unwrap_or(a)
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::{buffer::TextBuffer, editor::tests::new_test_editor, modals::Modal, LineCol};
+
+    #[test]
+    fn test_count_deletes_under_cursor() {
+        let mut editor = new_test_editor(&["abcdefgh"]);
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_char_input('x', Some(5)).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "agh");
+
+        editor.handle_char_input('x', Some(9)).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_count_pastes_copies() {
+        let mut editor = new_test_editor(&["xy"]);
+        editor.copy_register.yank(vec!['a', 'b'], None).unwrap();
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_char_input('p', Some(3)).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "xabababy");
+    }
+
+    #[test]
+    fn test_count_appends_copies() {
+        let mut editor = new_test_editor(&["abc"]);
+        editor.handle_char_input('A', Some(3)).unwrap();
+        for ch in "foo".chars() {
+            let key = KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE);
+            editor.handle_insert_key(key).unwrap();
+        }
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "abcfoofoofoo");
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 12 });
+
+        editor.handle_char_input('.', None).unwrap();
+        assert_eq!(editor.buffer.line(0).unwrap(), "abcfoofoofoofoofoofoo");
+    }
+
+    #[test]
+    fn test_paste_above_line_yank() {
+        let mut editor = new_test_editor(&["first", "second", "third"]);
+        editor.copy_register.yank("\nthird", None).unwrap();
+        editor.go(LineCol { line: 1, col: 3 });
+        editor.handle_char_input('P', None).unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["first", "third", "second", "third"]
+        );
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+
+        editor.copy_register.yank(vec!['a', 'b'], None).unwrap();
+        editor.go(LineCol { line: 2, col: 3 });
+        editor.handle_char_input('P', None).unwrap();
+        assert_eq!(editor.buffer.line(2).unwrap(), "absecond");
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+    }
+
+    #[test]
+    fn test_count_opens_lines_below() {
+        let mut editor = new_test_editor(&["first", "last"]);
+        editor.handle_char_input('o', Some(3)).unwrap();
+        assert_eq!(editor.mode, Modal::Insert);
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+        for ch in "text".chars() {
+            let key = KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE);
+            editor.handle_insert_key(key).unwrap();
+        }
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["first", "text", "text", "text", "last"]
+        );
+
+        editor.go(LineCol { line: 4, col: 0 });
+        editor.handle_char_input('o', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 5, col: 0 });
+        assert_eq!(editor.buffer.max_line(), 5);
+    }
+
+    #[test]
+    fn test_scroll_distance_option() {
+        let lines: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut editor = new_test_editor(&lines);
+        editor.viewport.terminal_dimensions.line = 30;
+        editor.handle_modifiers('d', None, KeyModifiers::CONTROL);
+        assert_eq!(editor.pos().line, 15);
+
+        editor.options.scroll_jump_distance = Some(5);
+        editor.jump_down();
+        assert_eq!(editor.pos().line, 20);
+        editor.handle_modifiers('u', Some(2), KeyModifiers::CONTROL);
+        assert_eq!(editor.pos().line, 10);
+    }
+
+    #[test]
+    fn test_repeat_char_motion() {
+        let mut editor = new_test_editor(&["a tiny test text"]);
+        editor.handle_combination_input('t', None, 'f').unwrap();
+        assert_eq!(editor.pos().col, 2);
+        editor.handle_char_input(';', None).unwrap();
+        assert_eq!(editor.pos().col, 7);
+        editor.handle_char_input(';', Some(2)).unwrap();
+        assert_eq!(editor.pos().col, 12);
+        editor.handle_char_input(',', None).unwrap();
+        assert_eq!(editor.pos().col, 10);
+        assert_eq!(
+            editor.last_char_motion,
+            Some(('t', super::CharMotionKind::FindNext))
+        );
+
+        editor.go(LineCol::default());
+        editor.handle_combination_input('t', None, 't').unwrap();
+        assert_eq!(editor.pos().col, 1);
+        editor.handle_char_input(';', None).unwrap();
+        assert_eq!(editor.pos().col, 6);
+        editor.handle_char_input(',', None).unwrap();
+        assert_eq!(editor.pos().col, 3);
+    }
+
+    #[test]
+    fn test_count_deletes_lines() {
+        let mut editor = new_test_editor(&["one", "two", "three", "four"]);
+        editor.go(LineCol { line: 1, col: 2 });
+        editor.handle_combination_input('d', Some(3), 'd').unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["one"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+        assert_eq!(
+            String::from_iter(editor.copy_register.get_from_register(None).unwrap()),
+            "\ntwo\nthree\nfour"
+        );
+
+        editor.handle_char_input('P', None).unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["two", "three", "four", "one"]
+        );
+
+        editor.go(LineCol::default());
+        editor.handle_combination_input('d', Some(9), 'd').unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), [""]);
+        editor.buffer.undo(editor.pos()).unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["two", "three", "four", "one"]
+        );
+    }
+
+    #[test]
+    fn test_deleted_lines_go_to_numbered_registers() {
+        let mut editor = new_test_editor(&["first", "second", "third"]);
+        editor.handle_combination_input('d', None, 'd').unwrap();
+        editor.handle_combination_input('d', None, 'd').unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["third"]);
+
+        let register = |name| editor.copy_register.get_from_register(Some(name)).unwrap();
+        assert_eq!(String::from_iter(register('1')), "\nsecond");
+        assert_eq!(String::from_iter(register('2')), "\nfirst");
+
+        editor.go(LineCol { line: 0, col: 5 });
+        editor.handle_combination_input('1', None, '"').unwrap();
+        editor.handle_char_input('p', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["third", "second"]);
+    }
+
+    #[test]
+    fn test_gv_reselects_after_visual_delete() {
+        let mut editor = new_test_editor(&["one two three", "four"]);
+        editor.go(LineCol { line: 0, col: 4 });
+        editor.handle_char_input('v', None).unwrap();
+        editor.go(LineCol { line: 0, col: 7 });
+        editor.handle_char_input('d', None).unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+        assert_eq!(editor.buffer.line(0).unwrap(), "one  three");
+
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.handle_combination_input('v', None, 'g').unwrap();
+        assert_eq!(editor.mode, Modal::Visual);
+        assert_eq!(
+            editor.cursor.last_text_mode_pos,
+            LineCol { line: 0, col: 4 }
+        );
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 7 });
+
+        editor.set_mode(Modal::Normal);
+        editor.handle_char_input('V', None).unwrap();
+        editor.handle_char_input('j', None).unwrap();
+        editor.handle_char_input('d', None).unwrap();
+        editor.handle_combination_input('v', None, 'g').unwrap();
+        assert_eq!(editor.mode, Modal::VisualLine);
+        assert_eq!(
+            editor.cursor.last_text_mode_pos,
+            LineCol { line: 0, col: 0 }
+        );
+    }
+
+    #[test]
+    fn test_count_goes_to_line() {
+        let mut editor = new_test_editor(&["a", "b", "c"]);
+        editor.handle_char_input('G', Some(2)).unwrap();
+        assert_eq!(editor.pos().line, 1);
+        editor.handle_char_input('G', None).unwrap();
+        assert_eq!(editor.pos().line, 2);
+    }
+
+    #[test]
+    fn test_percent_jumps_between_brackets() {
+        let mut editor = new_test_editor(&["fn main() {", "    let x = (1);", "}"]);
+        editor.go(LineCol { line: 0, col: 10 });
+        editor.handle_char_input('%', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+        editor.handle_char_input('%', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 10 });
+
+        editor.jump_back();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+
+        // Off a bracket the next one on the line is matched
+        editor.go(LineCol { line: 1, col: 4 });
+        editor.handle_char_input('%', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 14 });
+    }
+
+    #[test]
+    fn test_percent_without_matching_bracket() {
+        let mut editor = new_test_editor(&["f(x", "no brackets"]);
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_char_input('%', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 1 });
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("No matching bracket"));
+
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.notification_queue.clear();
+        editor.handle_char_input('%', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+        assert_eq!(editor.notification_queue.len(), 1);
+    }
+
+    #[test]
+    fn test_visual_uppercase_changes_only_the_selection() {
+        let mut editor = new_test_editor(&["say HeLLo world", "other"]);
+        editor.go(LineCol { line: 0, col: 4 });
+        editor.handle_char_input('v', None).unwrap();
+        editor.go(LineCol { line: 0, col: 8 });
+        editor.handle_char_input('U', None).unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["say HELLO world", "other"]
+        );
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 4 });
+        assert!(!editor.mode.is_any_visual());
+
+        editor.buffer.undo(editor.pos()).unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["say HeLLo world", "other"]
+        );
+    }
+
+    #[test]
+    fn test_tilde_toggles_case() {
+        let mut editor = new_test_editor(&["aB3ü!"]);
+        editor.handle_char_input('~', Some(4)).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["Ab3Ü!"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 5 });
+
+        editor.handle_char_input('~', Some(5)).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["Ab3Ü!"]);
+    }
+
+    #[test]
+    fn test_word_backward_and_end_motions() {
+        let mut editor = new_test_editor(&["let some_name = foo.bar;", "next"]);
+        editor.go(LineCol { line: 0, col: 8 });
+        editor.handle_char_input('b', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 4 });
+        editor.handle_char_input('b', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+
+        editor.handle_char_input('e', Some(2)).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 12 });
+        editor.go(LineCol { line: 0, col: 16 });
+        editor.handle_char_input('E', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 23 });
+        editor.handle_char_input('e', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 3 });
+        editor.handle_char_input('B', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+    }
+
+    #[test]
+    fn test_word_backward_stops_on_empty_lines() {
+        let mut editor = new_test_editor(&["one", "", "  ", "two"]);
+        editor.go(LineCol { line: 3, col: 0 });
+        editor.handle_char_input('b', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+        editor.handle_char_input('B', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+        editor.handle_char_input('b', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_previous_word_end_motion() {
+        let mut editor = new_test_editor(&["one two.three", "  four"]);
+        editor.go(LineCol { line: 1, col: 4 });
+        editor.handle_combination_input('e', None, 'g').unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 12 });
+        editor.handle_combination_input('e', None, 'g').unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 7 });
+        editor.handle_combination_input('e', Some(2), 'g').unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 2 });
+        editor.handle_combination_input('e', None, 'g').unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 2 });
+
+        editor.go(LineCol { line: 0, col: 10 });
+        editor.handle_combination_input('E', None, 'g').unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 2 });
+    }
+
+    #[test]
+    fn test_delete_word_motions() {
+        let mut editor = new_test_editor(&["one two three"]);
+        editor.go(LineCol { line: 0, col: 4 });
+        editor.handle_combination_input('e', None, 'd').unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["one  three"]);
+
+        editor.go(LineCol { line: 0, col: 5 });
+        editor.handle_combination_input('b', None, 'd').unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["three"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_paragraph_motions() {
+        let mut editor = new_test_editor(&["a", "b", "", "", "c", "  ", "d"]);
+        editor.handle_char_input('}', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+        editor.handle_char_input('}', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 5, col: 0 });
+        editor.handle_char_input('}', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 6, col: 1 });
+
+        editor.handle_char_input('{', Some(2)).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 3, col: 0 });
+        editor.jump_back();
+        assert_eq!(editor.pos(), LineCol { line: 6, col: 1 });
+        editor.go(LineCol { line: 3, col: 0 });
+        editor.handle_char_input('{', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_screen_line_motions() {
+        let lines: Vec<String> = (0..100).map(|i| format!("line {i}")).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut editor = new_test_editor(&lines);
+        editor.viewport.terminal_dimensions = LineCol { line: 24, col: 80 };
+        editor.viewport.topleft.line = 10;
+        editor.go(LineCol { line: 20, col: 7 });
+
+        editor.handle_char_input('H', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 10, col: 7 });
+        editor.handle_char_input('L', None).unwrap();
+        assert_eq!(editor.pos().line, editor.viewport.last_visible_line());
+        assert!(editor.viewport.is_line_visible(editor.pos().line));
+        editor.handle_char_input('M', None).unwrap();
+        assert_eq!(editor.pos().line, 20);
+
+        editor.handle_char_input('H', Some(3)).unwrap();
+        assert_eq!(editor.pos().line, 12);
+        editor.handle_char_input('L', Some(2)).unwrap();
+        assert_eq!(editor.pos().line, editor.viewport.last_visible_line() - 1);
+    }
+
+    #[test]
+    fn test_describe_char() {
+        assert_eq!(
+            super::describe_char('é'),
+            "<é> 233, Hex e9, Octal 351, Unicode: LATIN SMALL LETTER E WITH ACUTE"
+        );
+        assert_eq!(
+            super::describe_char('a'),
+            "<a> 97, Hex 61, Octal 141, Unicode: LATIN SMALL LETTER A"
+        );
+    }
+
+    #[test]
+    fn test_gi_returns_to_last_insert_position() {
+        let mut editor = new_test_editor(&["first", "second"]);
+        editor.go(LineCol { line: 1, col: 3 });
+        editor.handle_char_input('i', None).unwrap();
+        for key in [KeyCode::Char('x'), KeyCode::Char('y'), KeyCode::Esc] {
+            editor
+                .handle_insert_key(KeyEvent::new(key, KeyModifiers::NONE))
+                .unwrap();
+        }
+        assert_eq!(editor.buffer.get_normal_text(), ["first", "secxyond"]);
+
+        editor.go(LineCol { line: 0, col: 0 });
+        editor.handle_combination_input('i', None, 'g').unwrap();
+        assert!(editor.mode.is_insert());
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 5 });
+        editor.jump_back();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_fold_operator_with_motion() {
+        let mut editor = new_test_editor(&["a", "b", "c", "d", "e", "f", "g"]);
+        editor.go(LineCol { line: 3, col: 0 });
+        editor.handle_combination_input('f', None, 'z').unwrap();
+        editor.handle_char_input('k', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 3, col: 0 });
+        assert_eq!(editor.folds.iter().collect::<Vec<_>>(), [(2, 3)]);
+
+        // `j` takes the fold below as a single line
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.handle_combination_input('f', None, 'z').unwrap();
+        editor.handle_char_input('j', Some(2)).unwrap();
+        assert_eq!(editor.folds.iter().collect::<Vec<_>>(), [(1, 4), (2, 3)]);
+
+        editor.go(LineCol { line: 3, col: 0 });
+        editor.handle_combination_input('d', None, 'z').unwrap();
+        assert_eq!(editor.folds.iter().collect::<Vec<_>>(), [(1, 4)]);
+        editor.handle_combination_input('E', None, 'z').unwrap();
+        assert_eq!(editor.folds.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_folds_move_with_edits_and_are_skipped() {
+        let mut editor = new_test_editor(&["a", "b", "c", "d", "e", "f"]);
+        let renderer = crate::renderer::tests::MockRenderer::default();
+        let frames = std::rc::Rc::clone(&renderer.frames);
+        editor.renderer = Box::new(renderer);
+        editor.go(LineCol { line: 2, col: 0 });
+        editor.handle_combination_input('f', None, 'z').unwrap();
+        editor.handle_char_input('j', Some(2)).unwrap();
+        assert_eq!(editor.folds.iter().collect::<Vec<_>>(), [(2, 4)]);
+
+        editor.go(LineCol { line: 0, col: 0 });
+        editor.handle_combination_input('d', None, 'd').unwrap();
+        editor.draw_lines().unwrap();
+        assert_eq!(editor.folds.iter().collect::<Vec<_>>(), [(1, 3)]);
+        assert_eq!(frames.borrow().last().unwrap().folds, [(1, 3)]);
+
+        editor.handle_char_input('j', Some(2)).unwrap();
+        assert_eq!(editor.pos().line, 4);
+        editor.handle_char_input('k', None).unwrap();
+        assert_eq!(editor.pos().line, 1);
+    }
+
+    #[test]
+    fn test_delete_under_and_before_cursor() {
+        let mut editor = new_test_editor(&["añb", "next"]);
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_char_input('x', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["ab", "next"]);
+
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.handle_char_input('X', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["ab", "next"]);
+        editor.go(LineCol { line: 1, col: 2 });
+        editor.handle_char_input('X', Some(5)).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["ab", "xt"]);
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+    }
+
+    #[test]
+    fn test_undo_and_redo_keys() {
+        let mut editor = new_test_editor(&["abc", "def"]);
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_char_input('x', Some(2)).unwrap();
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.handle_combination_input('z', None, 'r').unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["a", "zef"]);
+
+        editor.handle_char_input('u', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["a", "def"]);
+        editor.handle_char_input('u', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["abc", "def"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 1 });
+        editor.handle_char_input('u', None).unwrap();
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("oldest change"));
+
+        editor.handle_modifiers('r', Some(2), KeyModifiers::CONTROL);
+        assert_eq!(editor.buffer.get_normal_text(), ["a", "zef"]);
+    }
+
+    #[test]
+    fn test_undo_whole_insert_session() {
+        let mut editor = new_test_editor(&["one"]);
+        editor.handle_char_input('o', None).unwrap();
+        for ch in "two".chars() {
+            editor
+                .handle_insert_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                .unwrap();
+        }
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        // A session without any typing leaves nothing to undo
+        editor.handle_char_input('i', None).unwrap();
+        editor
+            .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["one", "two"]);
+
+        editor.handle_char_input('u', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["one"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_visual_block_delete_and_yank() {
+        let mut editor = new_test_editor(&["abcdef", "gh", "ijklmn"]);
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_modifiers('v', None, KeyModifiers::CONTROL);
+        assert_eq!(editor.mode, Modal::VisualBlock);
+        editor.handle_char_input('j', Some(2)).unwrap();
+        editor.handle_char_input('l', Some(2)).unwrap();
+        editor.handle_char_input('y', None).unwrap();
+        assert_eq!(
+            String::from_iter(editor.copy_register.get_from_register(None).unwrap()),
+            "bcd\nh\njkl"
+        );
+
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_modifiers('v', None, KeyModifiers::CONTROL);
+        editor.handle_char_input('j', Some(2)).unwrap();
+        editor.handle_char_input('l', Some(2)).unwrap();
+        editor.handle_char_input('d', None).unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+        assert_eq!(editor.buffer.get_normal_text(), ["aef", "g", "imn"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 1 });
+    }
+
+    #[test]
+    fn test_visual_block_change_and_insert_repeat_on_lines() {
+        let type_insert = |editor: &mut crate::editor::Editor<_>, text: &str| {
+            for ch in text.chars() {
+                editor
+                    .handle_insert_key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                    .unwrap();
+            }
+            editor
+                .handle_insert_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+                .unwrap();
+        };
+        let mut editor = new_test_editor(&["let a = 1;", "x", "let b = 2;"]);
+        editor.go(LineCol { line: 0, col: 4 });
+        editor.handle_modifiers('v', None, KeyModifiers::CONTROL);
+        editor.handle_char_input('j', Some(2)).unwrap();
+        editor.handle_char_input('c', None).unwrap();
+        assert_eq!(editor.mode, Modal::Insert);
+        type_insert(&mut editor, "val");
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["let val = 1;", "x", "let val = 2;"]
+        );
+
+        editor.go(LineCol { line: 0, col: 0 });
+        editor.handle_modifiers('v', None, KeyModifiers::CONTROL);
+        editor.handle_char_input('j', Some(2)).unwrap();
+        editor.handle_char_input('I', None).unwrap();
+        type_insert(&mut editor, "// ");
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["// let val = 1;", "// x", "// let val = 2;"]
+        );
+    }
+
+    #[test]
+    fn test_underscore_moves_to_first_keyword_char() {
+        let mut editor = new_test_editor(&["    (-foo)", "  )"]);
+        editor.handle_char_input('_', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 6 });
+
+        editor.options.iskeyword.insert('-');
+        editor.handle_char_input('_', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 5 });
+
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.handle_char_input('_', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 2 });
+    }
+}
@@ -0,0 +1,553 @@
+use crate::{history::MAX_STORED_HISTORY, lsp::FileType, utils::expand_tilde, Error, Result};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Characters that are considered part of a keyword on top of the alphanumeric ones.
+const DEFAULT_ISKEYWORD: [char; 1] = ['_'];
+const DEFAULT_SHIFTWIDTH: usize = 4;
+const DEFAULT_TABSTOP: usize = 4;
+const DEFAULT_SWAP_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_UPDATE_COUNT: usize = 200;
+const DEFAULT_SCROLL_OFF: usize = 6;
+const DEFAULT_LINE_NUMBER_WIDTH: usize = 5;
+const DEFAULT_GREP_COMMAND: &str = "rg --json";
+
+/// Runtime configurable editor settings, modifiable through `:set`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorOptions {
+    /// Characters that are part of a word in addition to the alphanumeric ones. Used by the word
+    /// motions and word-based text objects.
+    pub iskeyword: HashSet<char>,
+    /// How often a dirty buffer is written to its autosave file, `None` disables auto-saving.
+    /// Set in seconds through `:set autosave=30`, `0` turns it off.
+    pub auto_save_interval: Option<Duration>,
+    /// How often the buffer is written to its swap file, `None` disables the timer.
+    /// Set in seconds through `:set swapinterval=30`, `0` turns it off.
+    pub swap_interval: Option<Duration>,
+    /// Amount of characters typed after which the buffer is written to its swap file, `None`
+    /// disables it. Set through `:set updatecount=200`, `0` turns it off.
+    pub update_count: Option<usize>,
+    /// Amount of columns a single level of indentation takes.
+    pub shiftwidth: usize,
+    /// Amount of columns a tab character spans.
+    pub tabstop: usize,
+    /// Whether indentation is inserted as spaces instead of tabs.
+    pub expandtab: bool,
+    /// Settings of specific file types, e.g. tabs for Makefiles.
+    pub filetype_overrides: HashMap<FileType, FileTypeOverride>,
+    /// Overrides of the current file type, taking precedence over `tabstop`, `expandtab` and
+    /// `iskeyword` until those are set explicitly.
+    pub local_overrides: FileTypeOverride,
+    /// Whether vertical guides are drawn in the leading whitespace at every indentation level.
+    pub show_indent_guides: bool,
+    /// Column to highlight as a line length guide, counted from 1 like in vim.
+    pub color_column: Option<usize>,
+    /// Whether modelines in opened files are applied.
+    pub modeline: bool,
+    /// Whether edits to the buffer are refused, set for files opened without write permission.
+    pub readonly: bool,
+    /// Command run by `:make`, `None` picks one based on the project.
+    pub build_cmd: Option<String>,
+    /// Whether searches for patterns without uppercase letters ignore case.
+    pub smart_case: bool,
+    /// Directories searched by `gf` for files not found relative to the current one.
+    pub path: Vec<PathBuf>,
+    /// Amount of lines `Ctrl-d` and `Ctrl-u` move the cursor by, `None` scrolls half the window.
+    /// Set through `:set scroll=10`, `0` goes back to half the window.
+    pub scroll_jump_distance: Option<usize>,
+    /// Amount of entries kept in each of the command and search histories.
+    pub max_history: usize,
+    /// Minimal amount of lines kept between the cursor and the top and bottom of the window.
+    pub scroll_off: usize,
+    /// Amount of columns the git change marker and the line number take in the gutter.
+    pub line_number_width: usize,
+    /// Whether the cursor line shows its own number, the other lines being numbered relative
+    /// to it either way. Off, the cursor line shows `0`.
+    pub number: bool,
+    /// Whether typing an opening bracket in Insert mode also inserts its closing one.
+    pub auto_pairs: bool,
+    /// Whether trailing whitespace is removed from every line when the file is written.
+    pub trim_on_save: bool,
+    /// Whether searches continue from the other end of the buffer once they hit one.
+    pub wrap_search: bool,
+    /// Command run by `:Grep`, given the pattern and the directory to search. Its output is read
+    /// as `rg --json` events or as `path:line:col:text` lines, e.g. `ag --vimgrep`.
+    pub grep_command: String,
+}
+
+impl Default for EditorOptions {
+    fn default() -> Self {
+        Self {
+            iskeyword: HashSet::from(DEFAULT_ISKEYWORD),
+            auto_save_interval: None,
+            swap_interval: Some(DEFAULT_SWAP_INTERVAL),
+            update_count: Some(DEFAULT_UPDATE_COUNT),
+            shiftwidth: DEFAULT_SHIFTWIDTH,
+            tabstop: DEFAULT_TABSTOP,
+            expandtab: true,
+            filetype_overrides: HashMap::from([
+                (FileType::Make, FileTypeOverride::tabs()),
+                (FileType::Go, FileTypeOverride::tabs()),
+            ]),
+            local_overrides: FileTypeOverride::default(),
+            show_indent_guides: false,
+            color_column: None,
+            modeline: true,
+            readonly: false,
+            build_cmd: None,
+            smart_case: false,
+            path: Vec::new(),
+            scroll_jump_distance: None,
+            max_history: MAX_STORED_HISTORY,
+            scroll_off: DEFAULT_SCROLL_OFF,
+            line_number_width: DEFAULT_LINE_NUMBER_WIDTH,
+            number: true,
+            auto_pairs: false,
+            trim_on_save: false,
+            wrap_search: true,
+            grep_command: DEFAULT_GREP_COMMAND.to_string(),
+        }
+    }
+}
+
+/// Settings of a file type, overriding the global ones where set.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileTypeOverride {
+    pub tabstop: Option<usize>,
+    pub expandtab: Option<bool>,
+    /// Written like the `iskeyword` option, e.g. `"_,-"`.
+    #[serde(deserialize_with = "deserialize_iskeyword")]
+    pub iskeyword: Option<HashSet<char>>,
+}
+
+impl FileTypeOverride {
+    fn tabs() -> Self {
+        Self {
+            expandtab: Some(false),
+            ..Self::default()
+        }
+    }
+
+    /// Settings of `self`, falling back to those of `other` where `self` has none.
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            tabstop: self.tabstop.or(other.tabstop),
+            expandtab: self.expandtab.or(other.expandtab),
+            iskeyword: self.iskeyword.or(other.iskeyword),
+        }
+    }
+}
+
+/// Characters listed in an `iskeyword` value, separated by commas or not.
+pub fn parse_iskeyword(value: &str) -> HashSet<char> {
+    value.chars().filter(|ch| *ch != ',').collect()
+}
+
+fn deserialize_iskeyword<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<HashSet<char>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    String::deserialize(deserializer).map(|value| Some(parse_iskeyword(&value)))
+}
+
+/// The operation a `:set` command performs on an option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetAction {
+    /// `:set option=value`
+    Assign(String),
+    /// `:set option+=value`
+    Add(String),
+    /// `:set option-=value`
+    Remove(String),
+    /// `:set option&`
+    Reset,
+}
+
+impl EditorOptions {
+    /// Checks whether `ch` is a word character according to `iskeyword`.
+    pub fn is_keyword(&self, ch: char) -> bool {
+        let iskeyword = self.local_overrides.iskeyword.as_ref();
+        ch.is_alphanumeric() || iskeyword.unwrap_or(&self.iskeyword).contains(&ch)
+    }
+
+    /// Class of `ch` for word motions and text objects, consecutive symbols of one class form a
+    /// word. Whitespace, keyword symbols and the remaining ones are each a class, with `big` only
+    /// whitespace and the rest.
+    pub fn word_class(&self, ch: char, big: bool) -> u8 {
+        if ch.is_whitespace() {
+            0
+        } else if big || self.is_keyword(ch) {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Whether a search for `pattern` ignores case.
+    pub fn ignores_case(&self, pattern: &str) -> bool {
+        self.smart_case && !pattern.chars().any(char::is_uppercase)
+    }
+
+    /// Width of a tab stop in the current file. A `tabstop` of 0 from the config file or a file
+    /// type override is taken as 1.
+    pub fn tab_width(&self) -> usize {
+        self.local_overrides.tabstop.unwrap_or(self.tabstop).max(1)
+    }
+
+    /// Whether the Tab key inserts spaces in the current file.
+    pub fn expand_tab(&self) -> bool {
+        self.local_overrides.expandtab.unwrap_or(self.expandtab)
+    }
+
+    /// Switches to the overrides of `file_type`.
+    pub fn use_filetype_overrides(&mut self, file_type: FileType) {
+        self.local_overrides = self
+            .filetype_overrides
+            .get(&file_type)
+            .cloned()
+            .unwrap_or_default();
+    }
+
+    /// Applies a `:set` action to the option with the given name.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidInput` if the option is unknown or the value can't be applied to it.
+    pub fn apply(&mut self, option: &str, action: SetAction) -> Result<()> {
+        match (option, action) {
+            ("iskeyword" | "isk", SetAction::Assign(value)) => {
+                self.iskeyword = parse_iskeyword(&value);
+                self.local_overrides.iskeyword = None;
+            }
+            ("iskeyword" | "isk", SetAction::Add(value)) => {
+                // Edit the set in effect, which may be the one of the file type
+                if let Some(local) = self.local_overrides.iskeyword.take() {
+                    self.iskeyword = local;
+                }
+                self.iskeyword.extend(parse_iskeyword(&value));
+            }
+            ("iskeyword" | "isk", SetAction::Remove(value)) => {
+                if let Some(local) = self.local_overrides.iskeyword.take() {
+                    self.iskeyword = local;
+                }
+                for ch in parse_iskeyword(&value) {
+                    self.iskeyword.remove(&ch);
+                }
+            }
+            ("iskeyword" | "isk", SetAction::Reset) => {
+                self.iskeyword = HashSet::from(DEFAULT_ISKEYWORD);
+                self.local_overrides.iskeyword = None;
+            }
+            ("autosave", SetAction::Assign(value)) => {
+                let seconds: u64 = value.parse().map_err(|_| Error::InvalidInput)?;
+                self.auto_save_interval = (seconds > 0).then(|| Duration::from_secs(seconds));
+            }
+            ("autosave", SetAction::Reset) => self.auto_save_interval = None,
+            ("swapinterval", SetAction::Assign(value)) => {
+                let seconds: u64 = value.parse().map_err(|_| Error::InvalidInput)?;
+                self.swap_interval = (seconds > 0).then(|| Duration::from_secs(seconds));
+            }
+            ("swapinterval", SetAction::Reset) => {
+                self.swap_interval = Some(DEFAULT_SWAP_INTERVAL);
+            }
+            ("updatecount" | "uc", SetAction::Assign(value)) => {
+                let count: usize = value.parse().map_err(|_| Error::InvalidInput)?;
+                self.update_count = (count > 0).then_some(count);
+            }
+            ("updatecount" | "uc", SetAction::Reset) => {
+                self.update_count = Some(DEFAULT_UPDATE_COUNT);
+            }
+            ("shiftwidth" | "sw", SetAction::Assign(value)) => {
+                self.shiftwidth = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            ("shiftwidth" | "sw", SetAction::Reset) => self.shiftwidth = DEFAULT_SHIFTWIDTH,
+            ("tabstop" | "ts", SetAction::Assign(value)) => {
+                self.tabstop = value
+                    .parse()
+                    .ok()
+                    .filter(|tabstop| *tabstop > 0)
+                    .ok_or(Error::InvalidInput)?;
+                self.local_overrides.tabstop = None;
+            }
+            ("tabstop" | "ts", SetAction::Reset) => {
+                self.tabstop = DEFAULT_TABSTOP;
+                self.local_overrides.tabstop = None;
+            }
+            ("expandtab" | "et", SetAction::Assign(value)) => {
+                self.expandtab = parse_bool(&value)?;
+                self.local_overrides.expandtab = None;
+            }
+            ("expandtab" | "et", SetAction::Reset) => {
+                self.expandtab = true;
+                self.local_overrides.expandtab = None;
+            }
+            ("show_indent_guides" | "indentguides", SetAction::Assign(value)) => {
+                self.show_indent_guides = parse_bool(&value)?;
+            }
+            ("show_indent_guides" | "indentguides", SetAction::Reset) => {
+                self.show_indent_guides = false;
+            }
+            ("colorcolumn" | "cc", SetAction::Assign(value)) => {
+                self.color_column = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.parse().map_err(|_| Error::InvalidInput)?)
+                };
+            }
+            ("colorcolumn" | "cc", SetAction::Reset) => self.color_column = None,
+            ("modeline" | "ml", SetAction::Assign(value)) => self.modeline = parse_bool(&value)?,
+            ("modeline" | "ml", SetAction::Reset) => self.modeline = true,
+            ("readonly" | "ro", SetAction::Assign(value)) => self.readonly = parse_bool(&value)?,
+            ("readonly" | "ro", SetAction::Reset) => self.readonly = false,
+            ("build_cmd" | "makeprg", SetAction::Assign(value)) => self.build_cmd = Some(value),
+            ("build_cmd" | "makeprg", SetAction::Reset) => self.build_cmd = None,
+            ("smart_case" | "smartcase" | "scs", SetAction::Assign(value)) => {
+                self.smart_case = parse_bool(&value)?;
+            }
+            ("smart_case" | "smartcase" | "scs", SetAction::Reset) => self.smart_case = false,
+            ("path" | "pa", SetAction::Assign(value)) => self.path = parse_paths(&value),
+            ("path" | "pa", SetAction::Add(value)) => self.path.extend(parse_paths(&value)),
+            ("path" | "pa", SetAction::Remove(value)) => {
+                let removed = parse_paths(&value);
+                self.path.retain(|dir| !removed.contains(dir));
+            }
+            ("path" | "pa", SetAction::Reset) => self.path.clear(),
+            ("scroll" | "scr", SetAction::Assign(value)) => {
+                let lines: usize = value.parse().map_err(|_| Error::InvalidInput)?;
+                self.scroll_jump_distance = (lines > 0).then_some(lines);
+            }
+            ("scroll" | "scr", SetAction::Reset) => self.scroll_jump_distance = None,
+            ("history" | "hi", SetAction::Assign(value)) => {
+                self.max_history = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            ("history" | "hi", SetAction::Reset) => self.max_history = MAX_STORED_HISTORY,
+            ("scrolloff" | "so", SetAction::Assign(value)) => {
+                self.scroll_off = value.parse().map_err(|_| Error::InvalidInput)?;
+            }
+            ("scrolloff" | "so", SetAction::Reset) => self.scroll_off = DEFAULT_SCROLL_OFF,
+            ("numberwidth" | "nuw", SetAction::Assign(value)) => {
+                // The git change marker takes the first column, the line number needs another
+                self.line_number_width = value
+                    .parse()
+                    .ok()
+                    .filter(|width| *width > 1)
+                    .ok_or(Error::InvalidInput)?;
+            }
+            ("numberwidth" | "nuw", SetAction::Reset) => {
+                self.line_number_width = DEFAULT_LINE_NUMBER_WIDTH;
+            }
+            ("number" | "nu", SetAction::Assign(value)) => self.number = parse_bool(&value)?,
+            ("number" | "nu", SetAction::Reset) => self.number = true,
+            ("auto_pairs" | "autopairs", SetAction::Assign(value)) => {
+                self.auto_pairs = parse_bool(&value)?;
+            }
+            ("auto_pairs" | "autopairs", SetAction::Reset) => self.auto_pairs = false,
+            ("trim_on_save" | "trimonsave", SetAction::Assign(value)) => {
+                self.trim_on_save = parse_bool(&value)?;
+            }
+            ("trim_on_save" | "trimonsave", SetAction::Reset) => self.trim_on_save = false,
+            ("wrapscan" | "ws", SetAction::Assign(value)) => {
+                self.wrap_search = parse_bool(&value)?;
+            }
+            ("wrapscan" | "ws", SetAction::Reset) => self.wrap_search = true,
+            ("grep_command" | "grepprg" | "gp", SetAction::Assign(value)) => {
+                self.grep_command = value;
+            }
+            ("grep_command" | "grepprg" | "gp", SetAction::Reset) => {
+                self.grep_command = DEFAULT_GREP_COMMAND.to_string();
+            }
+            _ => return Err(Error::InvalidInput),
+        }
+        Ok(())
+    }
+}
+
+/// Splits a comma separated list of directories.
+fn parse_paths(value: &str) -> Vec<PathBuf> {
+    value
+        .split(',')
+        .filter(|dir| !dir.is_empty())
+        .map(expand_tilde)
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "true" | "on" | "1" => Ok(true),
+        "false" | "off" | "0" => Ok(false),
+        _ => Err(Error::InvalidInput),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_iskeyword() {
+        let options = EditorOptions::default();
+        assert!(options.is_keyword('a'));
+        assert!(options.is_keyword('7'));
+        assert!(options.is_keyword('_'));
+        assert!(!options.is_keyword('-'));
+    }
+
+    #[test]
+    fn test_iskeyword_add_and_reset() {
+        let mut options = EditorOptions::default();
+        options
+            .apply("iskeyword", SetAction::Add("-".to_string()))
+            .unwrap();
+        assert!(options.is_keyword('-'));
+
+        options.apply("iskeyword", SetAction::Reset).unwrap();
+        assert!(!options.is_keyword('-'));
+        assert!(options.is_keyword('_'));
+    }
+
+    #[test]
+    fn test_filetype_iskeyword() {
+        let mut options = EditorOptions::default();
+        options.filetype_overrides.insert(
+            FileType::Lua,
+            FileTypeOverride {
+                iskeyword: Some(parse_iskeyword("_,-")),
+                ..FileTypeOverride::default()
+            },
+        );
+        options.use_filetype_overrides(FileType::Lua);
+        assert!(options.is_keyword('-'));
+        options.use_filetype_overrides(FileType::Rust);
+        assert!(!options.is_keyword('-'));
+
+        // `:set` edits the set of the file type rather than the global one under it
+        options.use_filetype_overrides(FileType::Lua);
+        options
+            .apply("isk", SetAction::Remove("_".to_string()))
+            .unwrap();
+        assert!(options.is_keyword('-'));
+        assert!(!options.is_keyword('_'));
+    }
+
+    #[test]
+    fn test_swap_options() {
+        let mut options = EditorOptions::default();
+        assert_eq!(options.swap_interval, Some(Duration::from_secs(30)));
+        assert_eq!(options.update_count, Some(200));
+
+        options
+            .apply("swapinterval", SetAction::Assign("0".to_string()))
+            .unwrap();
+        assert_eq!(options.swap_interval, None);
+        options
+            .apply("uc", SetAction::Assign("50".to_string()))
+            .unwrap();
+        assert_eq!(options.update_count, Some(50));
+        options.apply("updatecount", SetAction::Reset).unwrap();
+        assert_eq!(options.update_count, Some(200));
+    }
+
+    #[test]
+    fn test_iskeyword_remove() {
+        let mut options = EditorOptions::default();
+        options
+            .apply("isk", SetAction::Remove("_".to_string()))
+            .unwrap();
+        assert!(!options.is_keyword('_'));
+    }
+
+    #[test]
+    fn test_autosave_interval() {
+        let mut options = EditorOptions::default();
+        options
+            .apply("autosave", SetAction::Assign("30".to_string()))
+            .unwrap();
+        assert_eq!(options.auto_save_interval, Some(Duration::from_secs(30)));
+
+        options
+            .apply("autosave", SetAction::Assign("0".to_string()))
+            .unwrap();
+        assert_eq!(options.auto_save_interval, None);
+        assert!(options
+            .apply("autosave", SetAction::Assign("soon".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_set_overrides_filetype_indent() {
+        let mut options = EditorOptions::default();
+        options.use_filetype_overrides(FileType::Make);
+        assert!(!options.expand_tab());
+
+        options
+            .apply("expandtab", SetAction::Assign("true".to_string()))
+            .unwrap();
+        assert!(options.expand_tab());
+        assert!(options
+            .apply("tabstop", SetAction::Assign("0".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_color_column_and_unsupported_wrap() {
+        let mut options = EditorOptions::default();
+        options
+            .apply("cc", SetAction::Assign("80".to_string()))
+            .unwrap();
+        assert_eq!(options.color_column, Some(80));
+        options
+            .apply("colorcolumn", SetAction::Assign(String::new()))
+            .unwrap();
+        assert_eq!(options.color_column, None);
+        // Long lines aren't wrapped yet, so the option isn't accepted
+        assert!(options
+            .apply("wrap", SetAction::Assign("true".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_path_list() {
+        let mut options = EditorOptions::default();
+        options
+            .apply("path", SetAction::Assign("src,include".to_string()))
+            .unwrap();
+        options
+            .apply("pa", SetAction::Remove("src".to_string()))
+            .unwrap();
+        assert_eq!(options.path, [PathBuf::from("include")]);
+    }
+
+    #[test]
+    fn test_numeric_options() {
+        let mut options = EditorOptions::default();
+        assert_eq!(options.scroll_jump_distance, None);
+        assert_eq!(options.max_history, MAX_STORED_HISTORY);
+
+        options
+            .apply("so", SetAction::Assign("0".to_string()))
+            .unwrap();
+        assert_eq!(options.scroll_off, 0);
+        assert!(options
+            .apply("numberwidth", SetAction::Assign("1".to_string()))
+            .is_err());
+        options
+            .apply("nuw", SetAction::Assign("7".to_string()))
+            .unwrap();
+        assert_eq!(options.line_number_width, 7);
+        options.apply("scrolloff", SetAction::Reset).unwrap();
+        assert_eq!(options.scroll_off, 6);
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        let mut options = EditorOptions::default();
+        assert!(options.apply("nonexistent", SetAction::Reset).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+use std::{io::Write, path::PathBuf};
+
+use crossterm::{
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    style::{self, Color, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use serde_json::Value;
+
+use crate::{
+    bars::{draw_bar, get_info_bar_content, get_notif_bar_content, INFO_BAR, NOTIFICATION_BAR},
+    buffer::TextBuffer,
+    editor::Editor,
+    modals::Modal,
+    notif_bar, LineCol, Result,
+};
+
+/// Maximum amount of results drawn in the picker at once, below its prompt line.
+pub const PICKER_WINDOW_HEIGHT: usize = 10;
+
+/// A line matched by the grep command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    pub path: PathBuf,
+    /// Position of the match, indexed from zero.
+    pub pos: LineCol,
+    /// The matched line without its line ending.
+    pub text: String,
+}
+
+/// Parses a line printed by the grep command. Understands the `match` events of `rg --json` and
+/// the `path:line:col:text` format of `--vimgrep` as printed by `ag` and `ack`, anything else is
+/// ignored.
+pub fn parse_grep_line(line: &str) -> Option<GrepMatch> {
+    if line.starts_with('{') {
+        let event: Value = serde_json::from_str(line).ok()?;
+        if event["type"] != "match" {
+            return None;
+        }
+        let data = &event["data"];
+        let path = data["path"]["text"].as_str()?;
+        let line = data["line_number"].as_u64()?;
+        let col = data["submatches"][0]["start"].as_u64().unwrap_or(0);
+        let text = data["lines"]["text"].as_str()?;
+        return Some(GrepMatch {
+            path: clean_path(path),
+            pos: LineCol {
+                line: usize::try_from(line).ok()?.saturating_sub(1),
+                col: usize::try_from(col).ok()?,
+            },
+            text: text.trim_end_matches(['\n', '\r']).to_string(),
+        });
+    }
+    let mut parts = line.splitn(4, ':');
+    let path = parts.next()?;
+    let line: usize = parts.next()?.parse().ok()?;
+    let col: usize = parts.next()?.parse().ok()?;
+    Some(GrepMatch {
+        path: clean_path(path),
+        pos: LineCol {
+            line: line.saturating_sub(1),
+            col: col.saturating_sub(1),
+        },
+        text: parts.next()?.to_string(),
+    })
+}
+
+/// Drops the `./` the grep command prefixes paths with when searching the current directory.
+fn clean_path(path: &str) -> PathBuf {
+    PathBuf::from(path.strip_prefix("./").unwrap_or(path))
+}
+
+/// Results of `:Grep`, collected while the grep command streams them in and narrowed down by
+/// typing into the picker.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FilePicker {
+    /// Pattern the grep command was started with.
+    pub query: String,
+    /// Text typed into the picker, results are kept if their path contains it.
+    pub filter: String,
+    pub results: Vec<GrepMatch>,
+    /// Index of the selected result among the filtered ones.
+    pub selected: usize,
+    /// Whether the grep command is still running.
+    pub searching: bool,
+}
+
+impl FilePicker {
+    pub fn new(query: String) -> Self {
+        Self {
+            query,
+            searching: true,
+            ..Self::default()
+        }
+    }
+
+    /// Results whose path matches the filter, in the order they came in.
+    pub fn filtered(&self) -> impl Iterator<Item = &GrepMatch> {
+        let filter = self.filter.to_lowercase();
+        self.results.iter().filter(move |result| {
+            result
+                .path
+                .to_string_lossy()
+                .to_lowercase()
+                .contains(&filter)
+        })
+    }
+
+    pub fn selected(&self) -> Option<&GrepMatch> {
+        self.filtered().nth(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        let last = self.filtered().count().saturating_sub(1);
+        self.selected = (self.selected + 1).min(last);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.selected = 0;
+    }
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Starts the grep command for `query` and opens the picker its results stream into,
+    /// `:Grep` and `<Space>g`.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub(crate) fn grep(&mut self, query: String) -> Result<()> {
+        if query.is_empty() {
+            notif_bar!(self => "E471: Argument required";);
+            return Ok(());
+        }
+        self.tasks
+            .spawn_grep(self.options.grep_command.clone(), query.clone())?;
+        self.picker = Some(FilePicker::new(query));
+        self.set_mode(Modal::Picker);
+        Ok(())
+    }
+
+    /// Greps for the word under the cursor, `<Space>g`.
+    pub(crate) fn grep_word_under_cursor(&mut self) -> Result<()> {
+        let Some(word) = self
+            .buffer
+            .word_at(self.pos(), |ch| self.options.is_keyword(ch))
+            .map(|(_, word)| word.to_string())
+        else {
+            notif_bar!(self => "No word under cursor";);
+            return Ok(());
+        };
+        self.grep(word)
+    }
+
+    /// Adds a result of the grep command to the picker, unless it belongs to an earlier search.
+    pub(crate) fn on_grep_match(&mut self, query: &str, entry: GrepMatch) {
+        if let Some(picker) = self.picker.as_mut().filter(|picker| picker.query == query) {
+            picker.results.push(entry);
+        }
+    }
+
+    /// Marks the search as done, reporting why the grep command couldn't run if it failed.
+    pub(crate) fn on_grep_finished(&mut self, query: &str, error: Option<String>) {
+        let Some(picker) = self.picker.as_mut().filter(|picker| picker.query == query) else {
+            return;
+        };
+        picker.searching = false;
+        if let Some(error) = error {
+            notif_bar!(self => error;);
+        } else if picker.results.is_empty() {
+            notif_bar!(self => format!("No matches for {query}"););
+        }
+    }
+
+    pub(crate) fn run_picker(&mut self) -> Result<()> {
+        self.draw_lines()?;
+        self.draw_picker()?;
+        let pos = self.pos();
+        let file_label = self.file_label();
+        draw_bar(&mut self.viewport.terminal, &INFO_BAR, |term_width, _| {
+            get_info_bar_content(term_width, &self.mode, &file_label, pos)
+        })?;
+        draw_bar(&mut self.viewport.terminal, &NOTIFICATION_BAR, |_, _| {
+            get_notif_bar_content(&mut self.notification_queue)
+        })?;
+
+        if let Event::Key(key_event) = self.read_event()? {
+            self.handle_picker_key(key_event)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_picker_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(picker) = self.picker.as_mut() else {
+            self.set_mode(Modal::Normal);
+            return Ok(());
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match (key.code, ctrl) {
+            (KeyCode::Down, _) | (KeyCode::Char('n'), true) => picker.select_next(),
+            (KeyCode::Up, _) | (KeyCode::Char('p'), true) => picker.select_previous(),
+            (KeyCode::Char(ch), false) => {
+                let filter = format!("{}{ch}", picker.filter);
+                picker.set_filter(filter);
+            }
+            (KeyCode::Backspace, _) => {
+                let mut filter = picker.filter.clone();
+                filter.pop();
+                picker.set_filter(filter);
+            }
+            (KeyCode::Enter, _) => {
+                let selected = picker.selected().cloned();
+                self.close_picker();
+                if let Some(entry) = selected {
+                    self.jump_to_grep_match(entry)?;
+                }
+            }
+            (KeyCode::Esc, _) => self.close_picker(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn close_picker(&mut self) {
+        self.picker = None;
+        self.set_mode(Modal::Normal);
+    }
+
+    /// Opens the file of `entry` if it isn't the edited one and moves the cursor to the match.
+    fn jump_to_grep_match(&mut self, entry: GrepMatch) -> Result<()> {
+        let same_file = self.file_path.as_ref().is_some_and(|path| {
+            std::path::absolute(path).ok() == std::path::absolute(&entry.path).ok()
+        });
+        self.push_jump(self.pos());
+        if !same_file {
+            self.open_file(entry.path.clone(), false)?;
+            if self.file_path.as_ref() != Some(&entry.path) {
+                // Opening was refused because of unsaved changes
+                return Ok(());
+            }
+        }
+        let line = entry.pos.line.min(self.buffer.max_line());
+        let col = entry
+            .pos
+            .col
+            .min(self.buffer.max_col(LineCol { line, col: 0 }));
+        self.go(LineCol { line, col });
+        Ok(())
+    }
+
+    /// Draws the picker over the bottom of the text area, a prompt line with the filter followed
+    /// by the results matching it.
+    pub(crate) fn draw_picker(&mut self) -> Result<()> {
+        let Some(picker) = &self.picker else {
+            return Ok(());
+        };
+        let text_height = self.viewport.bottomright().line - self.viewport.topleft.line;
+        let top = text_height.saturating_sub(PICKER_WINDOW_HEIGHT);
+        let skip = (picker.selected + 1).saturating_sub(PICKER_WINDOW_HEIGHT);
+        let width = self.viewport.terminal_dimensions.col;
+
+        let total = picker.filtered().count();
+        let status = if picker.searching { "searching " } else { "" };
+        let prompt = format!(
+            "Grep {}> {}  ({status}{total}/{})",
+            picker.query,
+            picker.filter,
+            picker.results.len()
+        );
+        let mut rows: Vec<(String, bool)> = vec![(prompt, false)];
+        rows.extend(
+            picker
+                .filtered()
+                .enumerate()
+                .skip(skip)
+                .take(PICKER_WINDOW_HEIGHT)
+                .map(|(i, result)| {
+                    let row = format!(
+                        "{}:{}:{}: {}",
+                        result.path.display(),
+                        result.pos.line + 1,
+                        result.pos.col + 1,
+                        result.text.trim()
+                    );
+                    (row, i == picker.selected)
+                }),
+        );
+        rows.resize(PICKER_WINDOW_HEIGHT + 1, (String::new(), false));
+
+        for (i, (row, selected)) in rows.into_iter().enumerate() {
+            let row: String = row.chars().take(width).collect();
+            let bg = if selected {
+                Color::DarkGrey
+            } else {
+                Color::Black
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            crossterm::queue!(
+                self.viewport.terminal,
+                crossterm::cursor::MoveTo(0, (top + i) as u16),
+                SetBackgroundColor(bg),
+                SetForegroundColor(Color::White),
+                style::Print(format!("{row:<width$}")),
+                ResetColor,
+            )?;
+        }
+        self.viewport.terminal.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor::tests::new_test_editor, tasks::EditorMessage};
+
+    const RG_MATCH: &str = r#"{"type":"match","data":{"path":{"text":"./src/main.rs"},"lines":{"text":"fn main() {\n"},"line_number":3,"absolute_offset":20,"submatches":[{"match":{"text":"main"},"start":3,"end":7}]}}"#;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_parse_grep_output() {
+        assert_eq!(
+            parse_grep_line(RG_MATCH),
+            Some(GrepMatch {
+                path: PathBuf::from("src/main.rs"),
+                pos: LineCol { line: 2, col: 3 },
+                text: "fn main() {".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_grep_line(r#"{"type":"begin","data":{"path":{"text":"src/main.rs"}}}"#),
+            None
+        );
+        assert_eq!(
+            parse_grep_line("src/lib.rs:10:5:    let x = 1;"),
+            Some(GrepMatch {
+                path: PathBuf::from("src/lib.rs"),
+                pos: LineCol { line: 9, col: 4 },
+                text: "    let x = 1;".to_string(),
+            })
+        );
+        assert_eq!(parse_grep_line("Binary file matches"), None);
+    }
+
+    #[test]
+    fn test_picker_filters_streamed_results_by_path() {
+        let mut editor = new_test_editor(&["text"]);
+        editor.picker = Some(FilePicker::new("main".to_string()));
+        editor.set_mode(Modal::Picker);
+        for path in ["src/main.rs", "src/editor.rs", "tests/main.rs"] {
+            let entry = GrepMatch {
+                path: PathBuf::from(path),
+                pos: LineCol::default(),
+                text: "main".to_string(),
+            };
+            editor
+                .handle_message(EditorMessage::GrepMatch {
+                    query: "main".to_string(),
+                    entry,
+                })
+                .unwrap();
+        }
+        editor
+            .handle_message(EditorMessage::GrepMatch {
+                query: "stale".to_string(),
+                entry: parse_grep_line(RG_MATCH).unwrap(),
+            })
+            .unwrap();
+        assert_eq!(editor.picker.as_ref().unwrap().results.len(), 3);
+
+        for ch in "main".chars() {
+            editor.handle_picker_key(key(KeyCode::Char(ch))).unwrap();
+        }
+        editor.handle_picker_key(key(KeyCode::Down)).unwrap();
+        editor.handle_picker_key(key(KeyCode::Down)).unwrap();
+        let picker = editor.picker.as_ref().unwrap();
+        assert_eq!(picker.filtered().count(), 2);
+        assert_eq!(
+            picker.selected().unwrap().path,
+            PathBuf::from("tests/main.rs")
+        );
+
+        editor.handle_picker_key(key(KeyCode::Esc)).unwrap();
+        assert_eq!(editor.mode, Modal::Normal);
+        assert!(editor.picker.is_none());
+    }
+
+    #[test]
+    fn test_picker_jumps_to_selected_match() {
+        let dir = std::env::temp_dir().join(format!("neotext_grep_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("found.rs");
+        std::fs::write(&file, "first\nsecond match\n").unwrap();
+
+        let mut editor = new_test_editor(&["text"]);
+        let mut picker = FilePicker::new("match".to_string());
+        picker.results.push(GrepMatch {
+            path: file.clone(),
+            pos: LineCol { line: 1, col: 7 },
+            text: "second match".to_string(),
+        });
+        editor.picker = Some(picker);
+        editor.set_mode(Modal::Picker);
+        editor.handle_picker_key(key(KeyCode::Enter)).unwrap();
+
+        assert_eq!(editor.mode, Modal::Normal);
+        assert_eq!(editor.file_path, Some(file));
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 7 });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crossterm::style::{self, Color, ResetColor, SetBackgroundColor, SetForegroundColor};
+
+use crate::{buffer::TextBuffer, editor::Editor, notif_bar, LineCol, Result};
+
+/// Maximum amount of entries drawn in the quickfix window at once.
+pub const QUICKFIX_WINDOW_HEIGHT: usize = 8;
+const SPINNER_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+const SPINNER_FRAME_MILLIS: u128 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuickfixKind {
+    Error,
+    Warning,
+}
+
+/// A location reported by a build, with lines and columns indexed from one as printed by the
+/// compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickfixEntry {
+    pub path: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    pub kind: QuickfixKind,
+    pub message: String,
+}
+
+/// Locations collected from the last `:make`, navigated with `:cnext` and `:cprev`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct QuickfixList {
+    pub entries: Vec<QuickfixEntry>,
+    pub current: usize,
+    /// Whether the quickfix window is shown.
+    pub open: bool,
+}
+
+impl QuickfixList {
+    pub fn set(&mut self, entries: Vec<QuickfixEntry>) {
+        self.entries = entries;
+        self.current = 0;
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == QuickfixKind::Error)
+            .count()
+    }
+
+    pub fn next(&mut self) -> Option<&QuickfixEntry> {
+        if self.current + 1 < self.entries.len() {
+            self.current += 1;
+        }
+        self.entries.get(self.current)
+    }
+
+    pub fn previous(&mut self) -> Option<&QuickfixEntry> {
+        self.current = self.current.saturating_sub(1);
+        self.entries.get(self.current)
+    }
+}
+
+fn parse_kind(text: &str) -> Option<(QuickfixKind, &str)> {
+    let (kind, rest) = text.split_once(':')?;
+    let kind = match kind.trim() {
+        kind if kind == "error" || kind.starts_with("error[") => QuickfixKind::Error,
+        "warning" => QuickfixKind::Warning,
+        _ => return None,
+    };
+    Some((kind, rest.trim()))
+}
+
+/// Parses `path:line:col` and `path:line`, returning the path and the line and column.
+fn parse_location(text: &str) -> Option<(PathBuf, usize, usize)> {
+    let mut parts = text.rsplitn(3, ':');
+    let last = parts.next()?.parse().ok()?;
+    let second = parts.next()?;
+    match (second.parse(), parts.next()) {
+        (Ok(line), Some(path)) if !path.is_empty() => Some((PathBuf::from(path), line, last)),
+        _ if !second.is_empty() => Some((PathBuf::from(second), last, 1)),
+        _ => None,
+    }
+}
+
+/// Extracts the errors and warnings from compiler output, relative paths are resolved against
+/// `dir`. Understands the one-line `file:line:col: error: message` format of gcc and clang as
+/// well as rustc diagnostics, whose location follows the message on a `-->` line.
+pub fn parse_build_output(output: &str, dir: &Path) -> Vec<QuickfixEntry> {
+    let mut entries = vec![];
+    let mut pending: Option<(QuickfixKind, String)> = None;
+
+    for line in output.lines() {
+        if let Some(location) = line.trim_start().strip_prefix("--> ") {
+            if let (Some((kind, message)), Some((path, line, col))) =
+                (pending.take(), parse_location(location.trim()))
+            {
+                entries.push(QuickfixEntry {
+                    path: dir.join(path),
+                    line,
+                    col,
+                    kind,
+                    message,
+                });
+            }
+            continue;
+        }
+        // rustc puts the message first and the location on one of the following lines
+        if let Some((kind, message)) = parse_kind(line) {
+            pending = Some((kind, message.to_string()));
+            continue;
+        }
+        // gcc and clang: `file:line:col: error: message`
+        let Some((location, rest)) = line
+            .match_indices(": ")
+            .find_map(|(at, _)| Some((&line[..at], parse_kind(&line[at + 2..])?)))
+        else {
+            continue;
+        };
+        if let Some((path, line, col)) = parse_location(location) {
+            let (kind, message) = rest;
+            entries.push(QuickfixEntry {
+                path: dir.join(path),
+                line,
+                col,
+                kind,
+                message: message.to_string(),
+            });
+        }
+    }
+    entries
+}
+
+/// Finds the build command for the project containing `path`: `cargo build` in the closest
+/// directory with a `Cargo.toml`, `make` in the current directory otherwise.
+pub fn default_build_command(path: &Path) -> (String, PathBuf) {
+    let start = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    start
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map_or_else(
+            || ("make".to_string(), PathBuf::from(".")),
+            |dir| ("cargo build".to_string(), dir.to_path_buf()),
+        )
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Starts the build command in the background, its output populates the quickfix list once
+    /// it finishes.
+    pub(crate) fn make(&mut self) -> Result<()> {
+        if self.build_started.is_some() {
+            notif_bar!(self => "A build is already running";);
+            return Ok(());
+        }
+        let anchor = self.file_path.clone().unwrap_or_else(|| PathBuf::from("."));
+        let (default_command, dir) = default_build_command(&anchor);
+        let command = self.options.build_cmd.clone().unwrap_or(default_command);
+        self.tasks.spawn_build(command, dir)?;
+        self.build_started = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Fills the quickfix list from the output of a finished build, opening the quickfix window
+    /// if it failed with errors.
+    pub(crate) fn on_build_finished(&mut self, output: &str, dir: &Path, success: bool) {
+        self.build_started = None;
+        self.quickfix.set(parse_build_output(output, dir));
+        let errors = self.quickfix.error_count();
+        if errors > 0 {
+            self.quickfix.open = true;
+            notif_bar!(self => format!("Build failed with {errors} errors"););
+        } else if success {
+            notif_bar!(self => "Build succeeded";);
+        } else {
+            notif_bar!(self => "Build failed";);
+        }
+    }
+
+    /// Spinner frame shown in the info bar while a build is running.
+    pub(crate) fn build_indicator(&self) -> String {
+        self.build_started.map_or_else(String::new, |started| {
+            let frame = started.elapsed().as_millis() / SPINNER_FRAME_MILLIS;
+            #[allow(clippy::cast_possible_truncation)]
+            let spinner = SPINNER_FRAMES[frame as usize % SPINNER_FRAMES.len()];
+            format!("  {spinner} building")
+        })
+    }
+
+    /// Moves the cursor to the current quickfix entry, opening its file if needed.
+    pub(crate) fn jump_to_quickfix_entry(&mut self, entry: QuickfixEntry) -> Result<()> {
+        let same_file = self.file_path.as_ref().is_some_and(|path| {
+            std::path::absolute(path).ok() == std::path::absolute(&entry.path).ok()
+        });
+        if !same_file {
+            self.open_file(entry.path.clone(), false)?;
+            if self.file_path.as_ref() != Some(&entry.path) {
+                // Opening was refused because of unsaved changes
+                return Ok(());
+            }
+        }
+        let line = entry.line.saturating_sub(1).min(self.buffer.max_line());
+        let col = entry
+            .col
+            .saturating_sub(1)
+            .min(self.buffer.max_col(LineCol { line, col: 0 }));
+        self.go(LineCol { line, col });
+        notif_bar!(self => entry.message;);
+        Ok(())
+    }
+
+    /// Draws the quickfix window over the bottom of the text area.
+    pub(crate) fn draw_quickfix(&mut self) -> Result<()> {
+        if !self.quickfix.open {
+            return Ok(());
+        }
+        let height = self.quickfix.entries.len().clamp(1, QUICKFIX_WINDOW_HEIGHT);
+        let text_height = self.viewport.bottomright().line - self.viewport.topleft.line;
+        let top = text_height.saturating_sub(height) + 1;
+        let skip = (self.quickfix.current + 1).saturating_sub(height);
+        let width = self.viewport.terminal_dimensions.col;
+
+        let rows: Vec<(String, bool)> = if self.quickfix.entries.is_empty() {
+            vec![("No errors".to_string(), false)]
+        } else {
+            self.quickfix
+                .entries
+                .iter()
+                .enumerate()
+                .skip(skip)
+                .take(height)
+                .map(|(i, entry)| {
+                    let kind = match entry.kind {
+                        QuickfixKind::Error => "error",
+                        QuickfixKind::Warning => "warning",
+                    };
+                    let row = format!(
+                        "{}:{}:{} {kind}: {}",
+                        entry.path.display(),
+                        entry.line,
+                        entry.col,
+                        entry.message
+                    );
+                    (row, i == self.quickfix.current)
+                })
+                .collect()
+        };
+
+        for (i, (row, selected)) in rows.into_iter().enumerate() {
+            let row: String = row.chars().take(width).collect();
+            let bg = if selected {
+                Color::DarkGrey
+            } else {
+                Color::Black
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            crossterm::queue!(
+                self.viewport.terminal,
+                crossterm::cursor::MoveTo(0, (top + i) as u16),
+                SetBackgroundColor(bg),
+                SetForegroundColor(Color::White),
+                style::Print(format!("{row:<width$}")),
+                ResetColor,
+            )?;
+        }
+        self.viewport.terminal.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rustc_output() {
+        let output = r#"   Compiling demo v0.1.0 (/work/demo)
+error[E0308]: mismatched types
+ --> src/main.rs:4:18
+  |
+4 |     let x: u32 = "text";
+  |            ---   ^^^^^^ expected `u32`, found `&str`
+  |            |
+  |            expected due to this
+
+warning: unused variable: `y`
+  --> src/lib.rs:10:9
+   |
+10 |     let y = 3;
+   |         ^ help: if this is intentional, prefix it with an underscore: `_y`
+
+error: could not compile `demo` (bin "demo") due to 1 previous error
+"#;
+        let entries = parse_build_output(output, Path::new("/work/demo"));
+        assert_eq!(
+            entries,
+            vec![
+                QuickfixEntry {
+                    path: PathBuf::from("/work/demo/src/main.rs"),
+                    line: 4,
+                    col: 18,
+                    kind: QuickfixKind::Error,
+                    message: "mismatched types".to_string(),
+                },
+                QuickfixEntry {
+                    path: PathBuf::from("/work/demo/src/lib.rs"),
+                    line: 10,
+                    col: 9,
+                    kind: QuickfixKind::Warning,
+                    message: "unused variable: `y`".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_gcc_output() {
+        let output = "main.c: In function 'main':
+main.c:5:12: error: 'x' undeclared (first use in this function)
+main.c:7:3: warning: implicit declaration of function 'foo'
+main.c:5:12: note: each undeclared identifier is reported only once
+";
+        let entries = parse_build_output(output, Path::new("/src"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, PathBuf::from("/src/main.c"));
+        assert_eq!((entries[0].line, entries[0].col), (5, 12));
+        assert_eq!(entries[0].kind, QuickfixKind::Error);
+        assert_eq!(
+            entries[0].message,
+            "'x' undeclared (first use in this function)"
+        );
+        assert_eq!(entries[1].kind, QuickfixKind::Warning);
+    }
+
+    #[test]
+    fn test_quickfix_navigation() {
+        let entry = |line| QuickfixEntry {
+            path: PathBuf::from("a.rs"),
+            line,
+            col: 1,
+            kind: QuickfixKind::Error,
+            message: String::new(),
+        };
+        let mut list = QuickfixList::default();
+        list.set(vec![entry(1), entry(2)]);
+        assert_eq!(list.next().unwrap().line, 2);
+        assert_eq!(list.next().unwrap().line, 2);
+        assert_eq!(list.previous().unwrap().line, 1);
+        assert_eq!(list.previous().unwrap().line, 1);
+    }
+}
@@ -0,0 +1,396 @@
+use std::{collections::HashMap, io::Write};
+
+use crossterm::{
+    style::{self, Color, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+use rangemap::RangeMap;
+
+use crate::{
+    cursor::Selection,
+    editor::LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS,
+    highlighter::Style,
+    modals::Modal,
+    utils::{draw_ascii_art, DiffStatus},
+    LineCol, Result,
+};
+
+const INDENT_GUIDE: char = '│';
+const INDENT_GUIDE_BLOCK: char = '╎';
+const COLOR_COLUMN: Color = Color::AnsiValue(236);
+const FOLD_BACKGROUND: Color = Color::AnsiValue(238);
+
+/// Snapshot of everything a frame shows, built by the editor before every redraw.
+pub struct EditorState<'a> {
+    /// Buffer lines visible in the viewport.
+    pub lines: Vec<&'a str>,
+    /// Buffer line the first of `lines` is at.
+    pub first_line: usize,
+    /// Byte offset of the first visible line into the text `style_map` was built from.
+    pub first_byte: usize,
+    pub cursor: LineCol,
+    pub mode: Modal,
+    /// Syntax highlighting of the whole buffer, keyed by byte offset.
+    pub style_map: RangeMap<usize, Style>,
+    /// Lines changed since the last commit, drawn as markers in the gutter.
+    pub git_diff: &'a HashMap<usize, DiffStatus>,
+    /// Visual selection, only drawn in the visual modes.
+    pub selection: Selection,
+    /// The bracket under the cursor and the one matching it.
+    pub bracket_pair: Option<(LineCol, LineCol)>,
+    /// Start and byte length of the highlighted search matches.
+    pub search_highlights: &'a [(LineCol, usize)],
+    /// Amount of columns the git change marker and the line number take.
+    pub line_number_width: usize,
+    /// Whether the cursor line shows its own number instead of `0`.
+    pub number: bool,
+    pub shiftwidth: usize,
+    pub show_indent_guides: bool,
+    /// Column highlighted as a line length guide, counted from 1.
+    pub color_column: Option<usize>,
+    /// Start and end line of the folds drawn as a single line, none nested in another.
+    pub folds: Vec<(usize, usize)>,
+    /// Whether the introduction screen is shown instead of the buffer.
+    pub splash: bool,
+}
+
+/// Draws the text area of the editor, letting the drawing backend be swapped out.
+pub trait Renderer {
+    /// Draws a whole frame of the text area from `state`.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the output can't be written.
+    fn render_frame(&mut self, state: &EditorState) -> Result<()>;
+}
+
+/// Renderer drawing to the terminal through crossterm.
+pub struct TerminalRenderer {
+    terminal: std::io::Stdout,
+}
+
+impl Default for TerminalRenderer {
+    fn default() -> Self {
+        Self {
+            terminal: std::io::stdout(),
+        }
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn render_frame(&mut self, state: &EditorState) -> Result<()> {
+        crossterm::queue!(
+            self.terminal,
+            crossterm::cursor::MoveTo(0, 0),
+            terminal::Clear(ClearType::All),
+        )?;
+        if state.splash {
+            return draw_ascii_art(&mut self.terminal);
+        }
+
+        let mut byte_index = state.first_byte;
+        for (i, line) in state.lines.iter().enumerate() {
+            let line_number = state.first_line + i;
+            let fold = state
+                .folds
+                .iter()
+                .find(|(start, end)| (*start..=*end).contains(&line_number));
+            if fold.is_some_and(|(start, _)| *start != line_number) {
+                byte_index += line.len() + 1;
+                continue;
+            }
+            crossterm::queue!(
+                self.terminal,
+                crossterm::cursor::MoveDown(1),
+                crossterm::cursor::MoveToColumn(0),
+            )?;
+            self.draw_line_number(state, line_number)?;
+            match fold {
+                Some((start, end)) => {
+                    crossterm::queue!(
+                        self.terminal,
+                        SetBackgroundColor(FOLD_BACKGROUND),
+                        style::Print(fold_summary(line, end - start + 1)),
+                        SetBackgroundColor(Color::Reset),
+                    )?;
+                    byte_index += line.len();
+                }
+                None => self.draw_line(state, line, line_number, &mut byte_index)?,
+            }
+            byte_index += 1;
+        }
+        self.terminal.flush()?;
+        Ok(())
+    }
+}
+
+impl TerminalRenderer {
+    fn draw_line_number(&mut self, state: &EditorState, line_number: usize) -> Result<()> {
+        // The first reserved column holds the git change marker
+        let (marker, marker_color) = gutter_marker(state.git_diff.get(&line_number));
+        let rel_line_number = line_number.abs_diff(state.cursor.line);
+        let shown = if rel_line_number == 0 && state.number {
+            line_number + 1
+        } else {
+            rel_line_number
+        };
+        crossterm::queue!(
+            self.terminal,
+            SetForegroundColor(marker_color),
+            style::Print(marker),
+            SetForegroundColor(Color::Green),
+            style::Print(format!(
+                "{shown:>width$}{separator}",
+                width = state.line_number_width - 1,
+                separator = " ".repeat(LINE_NUMBER_SEPARATOR_EMPTY_COLUMNS)
+            )),
+            ResetColor
+        )?;
+        Ok(())
+    }
+
+    /// Currently parsing through the tree and printing char by char, a more efficient version
+    /// would go over a lexeme representation. Whitespace or other symbol
+    /// delimited.
+    fn draw_line(
+        &mut self,
+        state: &EditorState,
+        line: &str,
+        absolute_ln: usize,
+        byte_offset: &mut usize,
+    ) -> Result<()> {
+        let opens_block = line.trim_end().ends_with('{');
+        let indent_level = line.len() - line.trim_start_matches(' ').len();
+        let selection = state.selection.normalized();
+        let default_style = &Style::default();
+
+        // Decide on highlighting
+        let line_in_highlight_bounds =
+            absolute_ln >= selection.start.line && absolute_ln < selection.end.line;
+        let highlight_whole_line = (state.mode.is_visual_line() && line_in_highlight_bounds)
+            || absolute_ln > selection.start.line
+                && (absolute_ln < selection.end.line.saturating_sub(1) && state.mode.is_visual());
+
+        // Visual Block selects the same columns on every line, wherever the anchor is
+        let (anchor, cursor) = (state.selection.start, state.selection.end);
+        let block_lines = anchor.line.min(cursor.line)..=anchor.line.max(cursor.line);
+        let block_cols = anchor.col.min(cursor.col)..=anchor.col.max(cursor.col);
+        let in_block = |byte_col: usize| {
+            state.mode.is_visual_block()
+                && block_lines.contains(&absolute_ln)
+                && block_cols.contains(&byte_col)
+        };
+
+        let highlight_range = if state.mode.is_visual_block() {
+            0f32..f32::NEG_INFINITY
+        } else if highlight_whole_line {
+            0f32..f32::INFINITY
+        } else if line_in_highlight_bounds {
+            selection.start.col as f32..selection.end.col as f32
+        } else {
+            0f32..f32::NEG_INFINITY
+        };
+
+        // Outputting
+        for (col, (byte_col, ch)) in line.char_indices().enumerate() {
+            // Highlighting
+            let here = LineCol {
+                line: absolute_ln,
+                col: byte_col,
+            };
+            let bg_color = if in_block(byte_col)
+                || state.mode.is_any_visual() && highlight_range.contains(&(col as f32))
+            {
+                SetBackgroundColor(Color::Black)
+            } else if state
+                .bracket_pair
+                .is_some_and(|(from, to)| here == from || here == to)
+            {
+                SetBackgroundColor(Color::DarkGrey)
+            } else if state.search_highlights.iter().any(|(start, len)| {
+                start.line == absolute_ln && (start.col..start.col + len).contains(&byte_col)
+            }) {
+                SetBackgroundColor(Color::DarkYellow)
+            } else if state.color_column == Some(col + 1) {
+                SetBackgroundColor(COLOR_COLUMN)
+            } else {
+                SetBackgroundColor(Color::Reset)
+            };
+
+            // Styling and Printing
+            let guide = indent_guide(col, indent_level, state.shiftwidth, opens_block)
+                .filter(|_| state.show_indent_guides);
+            if let Some(guide) = guide {
+                crossterm::queue!(
+                    self.terminal,
+                    SetForegroundColor(Color::DarkGrey),
+                    bg_color,
+                    style::Print(guide)
+                )?;
+                *byte_offset += ch.len_utf8();
+                continue;
+            }
+            let style = state.style_map.get(byte_offset).unwrap_or(default_style);
+            crossterm::queue!(
+                self.terminal,
+                SetForegroundColor(style.fg),
+                bg_color,
+                style::Print(ch)
+            )?;
+            *byte_offset += ch.len_utf8();
+        }
+        if let Some(padding) = color_column_padding(line.chars().count(), state.color_column) {
+            crossterm::queue!(
+                self.terminal,
+                SetBackgroundColor(Color::Reset),
+                style::Print(" ".repeat(padding)),
+                SetBackgroundColor(COLOR_COLUMN),
+                style::Print(' '),
+                SetBackgroundColor(Color::Reset),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Line drawn in place of a fold of `line_count` lines starting with `first_line`.
+fn fold_summary(first_line: &str, line_count: usize) -> String {
+    format!("+--{line_count:>3} lines: {}", first_line.trim())
+}
+
+/// Spaces to print after a line of `width` columns before the color column is reached, `None`
+/// if the line already covers it.
+fn color_column_padding(width: usize, color_column: Option<usize>) -> Option<usize> {
+    color_column
+        .filter(|column| *column > width)
+        .map(|column| column - width - 1)
+}
+
+/// Guide drawn at column `col` of a line indented by `indent_level` columns, if any. Guides sit
+/// on every `shiftwidth` column of the leading whitespace, lines opening a block get a dashed
+/// one.
+fn indent_guide(
+    col: usize,
+    indent_level: usize,
+    shiftwidth: usize,
+    opens_block: bool,
+) -> Option<char> {
+    if col >= indent_level || shiftwidth == 0 || !col.is_multiple_of(shiftwidth) {
+        return None;
+    }
+    Some(if opens_block {
+        INDENT_GUIDE_BLOCK
+    } else {
+        INDENT_GUIDE
+    })
+}
+
+/// Character and color drawn in the gutter for a line with the given git status.
+fn gutter_marker(status: Option<&DiffStatus>) -> (char, Color) {
+    match status {
+        Some(DiffStatus::Added) => ('+', Color::Green),
+        Some(DiffStatus::Modified) => ('~', Color::Yellow),
+        Some(DiffStatus::Deleted) => ('-', Color::Red),
+        None => (' ', Color::Reset),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::editor::tests::new_test_editor;
+
+    /// What a `MockRenderer` was asked to draw in a frame.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub(crate) struct Frame {
+        pub lines: Vec<String>,
+        pub cursor: LineCol,
+        pub mode: Modal,
+        pub selection: Option<(LineCol, LineCol)>,
+        pub folds: Vec<(usize, usize)>,
+    }
+
+    /// Renderer recording every frame instead of drawing it.
+    #[derive(Default, Clone)]
+    pub(crate) struct MockRenderer {
+        pub frames: Rc<RefCell<Vec<Frame>>>,
+    }
+
+    impl Renderer for MockRenderer {
+        fn render_frame(&mut self, state: &EditorState) -> Result<()> {
+            let selection = state.selection.normalized();
+            self.frames.borrow_mut().push(Frame {
+                lines: state.lines.iter().map(ToString::to_string).collect(),
+                cursor: state.cursor,
+                mode: state.mode,
+                selection: state
+                    .mode
+                    .is_any_visual()
+                    .then_some((selection.start, selection.end)),
+                folds: state.folds.clone(),
+            });
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_indent_guides_for_three_levels() {
+        let guides: Vec<Option<char>> =
+            (0..13).map(|col| indent_guide(col, 12, 4, false)).collect();
+        for (col, guide) in guides.iter().enumerate() {
+            let expected = [0, 4, 8].contains(&col).then_some(INDENT_GUIDE);
+            assert_eq!(*guide, expected, "column {col}");
+        }
+        assert_eq!(indent_guide(4, 12, 4, true), Some(INDENT_GUIDE_BLOCK));
+        assert_eq!(indent_guide(0, 0, 4, false), None);
+    }
+
+    #[test]
+    fn test_color_column_past_short_lines() {
+        assert_eq!(color_column_padding(5, Some(8)), Some(2));
+        assert_eq!(color_column_padding(7, Some(8)), Some(0));
+        assert_eq!(color_column_padding(8, Some(8)), None);
+        assert_eq!(color_column_padding(5, None), None);
+    }
+
+    #[test]
+    fn test_fold_summary() {
+        assert_eq!(
+            fold_summary("    fn main() {", 12),
+            "+-- 12 lines: fn main() {"
+        );
+    }
+
+    #[test]
+    fn test_gutter_markers() {
+        let git_diff = crate::utils::parse_diff_hunks("@@ -1 +1 @@\n@@ -2,0 +3 @@\n");
+        let markers: Vec<char> = (0..3)
+            .map(|line| gutter_marker(git_diff.get(&line)).0)
+            .collect();
+        assert_eq!(markers, ['~', ' ', '+']);
+    }
+
+    #[test]
+    fn test_frame_snapshot_of_visual_selection() {
+        let renderer = MockRenderer::default();
+        let frames = Rc::clone(&renderer.frames);
+        let mut editor = new_test_editor(&["first", "second", "third"]);
+        editor.renderer = Box::new(renderer);
+
+        editor.go(LineCol { line: 1, col: 1 });
+        editor.set_mode(Modal::Visual);
+        editor.go(LineCol { line: 2, col: 3 });
+        editor.draw_lines().unwrap();
+
+        let frames = frames.borrow();
+        let frame = frames.last().unwrap();
+        assert_eq!(frame.lines, ["first", "second", "third"]);
+        assert_eq!(frame.cursor, LineCol { line: 2, col: 3 });
+        assert_eq!(frame.mode, Modal::Visual);
+        assert_eq!(
+            frame.selection,
+            Some((LineCol { line: 1, col: 1 }, LineCol { line: 2, col: 3 }))
+        );
+    }
+}
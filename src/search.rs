@@ -0,0 +1,688 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    buffer::TextBuffer,
+    common::{RegexPattern, WholeWordPattern},
+    editor::Editor,
+    modals::{FindMode, Modal},
+    notif_bar, Error, LineCol, Result,
+};
+
+/// Amount of positions kept in the jump list.
+pub const MAX_JUMPS: usize = 100;
+
+/// Describes which of `total` matches the cursor is on as "match M of N", `index` being the one
+/// it's on, or as "N matches" when it's on none.
+pub fn match_count(index: Option<usize>, total: usize) -> String {
+    index.map_or_else(
+        || format!("{total} matches"),
+        |index| format!("match {} of {total}", index + 1),
+    )
+}
+
+/// Finds which of the `matches` of a query typed in Find mode the search would land on from
+/// `pos`, wrapping around the end of the file like the search does.
+fn landing_match(matches: &[LineCol], pos: LineCol, find_mode: FindMode) -> Option<usize> {
+    match find_mode {
+        FindMode::Forwards => matches
+            .iter()
+            .position(|found| *found >= pos)
+            .or_else(|| (!matches.is_empty()).then_some(0)),
+        FindMode::Backwards => matches
+            .iter()
+            .rposition(|found| *found < pos)
+            .or_else(|| matches.len().checked_sub(1)),
+    }
+}
+
+/// Expands the replacement of a substitute command for a match with the capture `groups`, group
+/// `0` being the whole match. `\0` to `\9` and `&` insert a group, `\&` and `\\` a literal `&`
+/// and backslash. References to groups the pattern doesn't have expand to nothing.
+pub fn expand_replacement(replacement: &str, groups: &[String]) -> String {
+    let group = |index: usize| groups.get(index).map_or("", String::as_str);
+    let mut expanded = String::new();
+    let mut chars = replacement.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '&' => expanded.push_str(group(0)),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => expanded.push_str(group(digit as usize - '0' as usize)),
+                Some(escaped) => expanded.push(escaped),
+                None => expanded.push('\\'),
+            },
+            ch => expanded.push(ch),
+        }
+    }
+    expanded
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Replaces the matches of `pattern` on `lines` with `replacement`, only the first one on
+    /// each line unless `global` is set. The cursor is left at the start of the last changed
+    /// line.
+    pub(crate) fn substitute(
+        &mut self,
+        pattern: &RegexPattern,
+        replacement: &str,
+        global: bool,
+        lines: RangeInclusive<usize>,
+    ) -> Result<()> {
+        if self.blocked_by_readonly() {
+            return Ok(());
+        }
+        let mut substitutions = 0;
+        let mut changed_lines = vec![];
+        for line in lines {
+            let content = self.buffer.line(line)?.to_string();
+            let mut new_content = String::new();
+            let mut offset = 0;
+            while let Some((start, end, groups)) = pattern.captures_at(&content, offset) {
+                new_content.push_str(&content[offset..start]);
+                new_content.push_str(&expand_replacement(replacement, &groups));
+                substitutions += 1;
+                offset = end;
+                if start == end {
+                    // Step over a symbol so an empty match isn't found again at the same spot
+                    let Some(ch) = content[end..].chars().next() else {
+                        break;
+                    };
+                    new_content.push(ch);
+                    offset += ch.len_utf8();
+                }
+                if !global || offset >= content.len() {
+                    break;
+                }
+            }
+            new_content.push_str(content.get(offset..).unwrap_or_default());
+            if new_content != content {
+                changed_lines.push((line, content.len(), new_content));
+            }
+        }
+
+        if substitutions == 0 {
+            notif_bar!(self => Error::PatternNotFound.display_user().to_string(););
+            return Ok(());
+        }
+        self.buffer.save_state(self.pos());
+        for (line, len, new_content) in &changed_lines {
+            let (start, end) = (
+                LineCol {
+                    line: *line,
+                    col: 0,
+                },
+                LineCol {
+                    line: *line,
+                    col: *len,
+                },
+            );
+            self.buffer.replace(start, end, new_content)?;
+        }
+        if let Some((line, ..)) = changed_lines.last() {
+            self.go(LineCol {
+                line: *line,
+                col: 0,
+            });
+        }
+        notif_bar!(self => format!(
+            "{substitutions} substitutions on {} lines",
+            changed_lines.len()
+        ););
+        Ok(())
+    }
+
+    /// Remembers `pos` in the jump list before a jump moves the cursor away from it.
+    pub(crate) fn push_jump(&mut self, pos: LineCol) {
+        if self.jump_list.last() == Some(&pos) {
+            return;
+        }
+        self.jump_list.push(pos);
+        if self.jump_list.len() > MAX_JUMPS {
+            self.jump_list.remove(0);
+        }
+    }
+
+    /// Moves the cursor back to the last position in the jump list.
+    pub(crate) fn jump_back(&mut self) {
+        if let Some(pos) = self.jump_list.pop() {
+            let line = pos.line.min(self.buffer.max_line());
+            let col = pos.col.min(self.buffer.max_col(LineCol { line, col: 0 }));
+            self.go(LineCol { line, col });
+        }
+    }
+
+    /// Start positions of every match of the search `pattern` in the buffer, in order.
+    pub(crate) fn search_matches(&self, pattern: &str) -> Vec<LineCol> {
+        if pattern.is_empty() {
+            return vec![];
+        }
+        self.buffer.find_all(RegexPattern::or_literal(pattern).0)
+    }
+
+    /// Jumps to the next match of the last search, `n`, or to the previous one with `reverse`,
+    /// `N`. The direction is relative to the one the search was made in and wraps around the
+    /// ends of the buffer. A `count` skips that many matches, recording a single jump.
+    pub(crate) fn repeat_search(&mut self, reverse: bool, count: Option<i32>) -> Result<()> {
+        let Some((pattern, find_mode)) = self.last_search.clone() else {
+            notif_bar!(self => "No previous search";);
+            return Ok(());
+        };
+        let forwards = (find_mode == FindMode::Forwards) != reverse;
+        let regex = RegexPattern::or_literal(&pattern).0;
+        let pos = self.pos();
+        let mut found = Ok(pos);
+        for _ in 0..count.unwrap_or(1).max(1) {
+            let Ok(from) = found else {
+                break;
+            };
+            found = if forwards {
+                self.find_after(&regex, from)
+            } else {
+                self.find_before(&regex, from)
+            };
+        }
+        match found {
+            Ok(dest) => {
+                self.push_jump(pos);
+                self.go(dest);
+                let prefix = if forwards { '/' } else { '?' };
+                self.show_match_count(&format!("{prefix}{pattern}"), &pattern, dest);
+            }
+            Err(e @ Error::PatternNotFound) => {
+                notif_bar!(self => e.display_user(););
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// Selects the next match of the last search in Visual mode, `gn`, or the previous one with
+    /// `backwards`, `gN`. A match at the cursor counts as the next one. In a visual mode the
+    /// selection is extended to the match instead.
+    pub(crate) fn select_search_match(&mut self, backwards: bool) -> Result<()> {
+        let Some((pattern, _)) = self.last_search.clone() else {
+            notif_bar!(self => "No previous search";);
+            return Ok(());
+        };
+        let regex = RegexPattern::or_literal(&pattern).0;
+        let pos = self.pos();
+        let found = if backwards {
+            self.find_before(&regex, pos)
+        } else {
+            self.buffer.find(regex.clone(), pos).or_else(|e| {
+                if self.options.wrap_search {
+                    self.buffer.find(regex.clone(), LineCol::default())
+                } else {
+                    Err(e)
+                }
+            })
+        };
+        let start = match found {
+            Ok(start) => start,
+            Err(e @ Error::PatternNotFound) => {
+                notif_bar!(self => e.display_user(););
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let end = LineCol {
+            line: start.line,
+            col: regex
+                .captures_at(self.buffer.line(start.line)?, start.col)
+                .map_or(start.col, |(_, end, _)| end),
+        };
+        if !self.mode.is_any_visual() {
+            self.set_mode(Modal::Visual);
+            self.cursor.last_text_mode_pos = if backwards { end } else { start };
+        }
+        self.cursor.pos = if backwards { start } else { end };
+        Ok(())
+    }
+
+    /// Searches for the word under the cursor as a whole word, `*` forwards and `#` backwards,
+    /// wrapping around the ends of the buffer unless `wrapscan` is off. Every occurrence is highlighted.
+    pub(crate) fn search_word_under_cursor(&mut self, find_mode: FindMode) -> Result<()> {
+        let pos = self.pos();
+        let Some((start, word)) = self
+            .buffer
+            .word_at(pos, |ch| self.options.is_keyword(ch))
+            .map(|(start, word)| (start, word.to_string()))
+        else {
+            notif_bar!(self => "No word under cursor";);
+            return Ok(());
+        };
+        let mut pattern = WholeWordPattern::new(&word, self.options.iskeyword.clone());
+        if self.options.ignores_case(&word) {
+            pattern = pattern.ignore_case();
+        }
+        let matches = self.buffer.find_all(pattern.clone());
+        let regex = pattern.as_regex();
+        let dest = match find_mode {
+            FindMode::Forwards => matches
+                .iter()
+                .find(|found| **found > start)
+                .or_else(|| matches.first().filter(|_| self.options.wrap_search))
+                .copied(),
+            FindMode::Backwards => self
+                .buffer
+                .rfind(pattern, start)
+                .ok()
+                .or_else(|| matches.last().copied().filter(|_| self.options.wrap_search)),
+        };
+        self.search_highlights = matches.iter().map(|found| (*found, word.len())).collect();
+        self.last_search = Some((regex, find_mode));
+        if let Some(dest) = dest {
+            self.push_jump(pos);
+            self.go(dest);
+            let index = matches.iter().position(|found| *found == dest);
+            notif_bar!(self => match_count(index, matches.len()););
+        }
+        Ok(())
+    }
+
+    /// Shows the repeated `search` along with which of the matches of `pattern` the cursor is
+    /// on, as "/foo  match M of N".
+    pub(crate) fn show_match_count(&mut self, search: &str, pattern: &str, at: LineCol) {
+        let matches = self.search_matches(pattern);
+        let index = matches.iter().position(|pos| *pos == at);
+        notif_bar!(self => format!("{search}  {}", match_count(index, matches.len())););
+    }
+
+    /// Counts the matches of the query typed in Find mode on the background runtime, unless
+    /// they're already known. The result is shown by `on_search_matches`.
+    pub(crate) fn request_match_count(&mut self) -> Result<()> {
+        let query = self.buffer.get_command_text()[0]
+            .get(1..)
+            .unwrap_or_default()
+            .to_string();
+        if query.is_empty() {
+            self.find_match_status = None;
+            return Ok(());
+        }
+        if self
+            .find_match_status
+            .as_ref()
+            .is_some_and(|(counted, _)| *counted == query)
+        {
+            return Ok(());
+        }
+        let lines = self.buffer.get_normal_text().to_vec();
+        self.tasks.spawn_match_count(query, lines)
+    }
+
+    /// Shows the `matches` of `query` next to it, if it's still the query being typed.
+    pub(crate) fn on_search_matches(&mut self, query: &str, matches: &[LineCol]) {
+        let Modal::Find(find_mode) = self.mode else {
+            return;
+        };
+        if self.buffer.get_command_text()[0].get(1..) != Some(query) {
+            return;
+        }
+        let index = landing_match(matches, self.cursor.last_text_mode_pos, find_mode);
+        let status = match_count(index, matches.len());
+        self.find_match_status = Some((query.to_string(), status));
+    }
+
+    /// Generates the text of the command bar for `draw_bar`, with the match count of the query
+    /// right-aligned in Find mode.
+    pub(crate) fn command_bar_content(&self) -> impl FnOnce(usize, usize) -> String {
+        let text = self.buffer.get_command_text()[0].clone();
+        let status = match &self.find_match_status {
+            Some((query, status)) if self.mode.is_find() && text.get(1..) == Some(query) => {
+                Some(status.clone())
+            }
+            _ => None,
+        };
+        move |term_width, _| match status {
+            Some(status) => {
+                let width = term_width.saturating_sub(text.chars().count());
+                format!("{text}{status:>width$}")
+            }
+            None => text,
+        }
+    }
+
+    /// First match of `pattern` after `pos`, wrapping around to the start of the buffer unless
+    /// `wrapscan` is off.
+    fn find_after(&self, pattern: &RegexPattern, pos: LineCol) -> Result<LineCol> {
+        let line_len = self.buffer.max_col(pos);
+        let start = if pos.col < line_len {
+            Some(LineCol {
+                line: pos.line,
+                col: pos.col + 1,
+            })
+        } else {
+            (pos.line < self.buffer.max_line()).then_some(LineCol {
+                line: pos.line + 1,
+                col: 0,
+            })
+        };
+        start
+            .map_or(Err(Error::PatternNotFound), |start| {
+                self.buffer.find(pattern.clone(), start)
+            })
+            .or_else(|e| {
+                if self.options.wrap_search {
+                    self.buffer.find(pattern.clone(), LineCol::default())
+                } else {
+                    Err(e)
+                }
+            })
+    }
+
+    /// Last match of `pattern` before `pos`, wrapping around to the end of the buffer unless
+    /// `wrapscan` is off.
+    fn find_before(&self, pattern: &RegexPattern, pos: LineCol) -> Result<LineCol> {
+        self.buffer.rfind(pattern.clone(), pos).or_else(|e| {
+            if self.options.wrap_search {
+                self.buffer
+                    .rfind(pattern.clone(), self.buffer.max_linecol())
+            } else {
+                Err(e)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{expand_replacement, landing_match, match_count};
+    use crate::{
+        buffer::TextBuffer,
+        commands::parse_command,
+        editor::tests::new_test_editor,
+        modals::{FindMode, Modal},
+        LineCol,
+    };
+
+    #[test]
+    fn test_n_and_shift_n_after_forward_search() {
+        let mut editor = new_test_editor(&["foo bar", "bar foo", "foo"]);
+        editor.last_search = Some(("foo".to_string(), FindMode::Forwards));
+
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 4 });
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+
+        editor.handle_char_input('N', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+        editor.handle_char_input('N', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 4 });
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("?foo  match 2 of 3"));
+
+        editor.jump_back();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+    }
+
+    #[test]
+    fn test_count_n_skips_matches_with_one_jump() {
+        let mut editor = new_test_editor(&["foo", "foo", "bar", "foo", "foo", "foo", "foo"]);
+        editor.last_search = Some(("foo".to_string(), FindMode::Forwards));
+        editor.handle_char_input('n', Some(5)).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 6, col: 0 });
+        editor.handle_char_input('N', Some(2)).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 4, col: 0 });
+
+        editor.jump_back();
+        assert_eq!(editor.pos(), LineCol { line: 6, col: 0 });
+        editor.jump_back();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_n_and_gn_with_regex_search() {
+        let mut editor = new_test_editor(&["let f(a) = 1;", "fn  main() {}", "fn other() {}"]);
+        editor.last_search = Some((r"fn\s+\w+".to_string(), FindMode::Forwards));
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("match 1 of 2"));
+
+        editor.select_search_match(false).unwrap();
+        assert_eq!(editor.mode, Modal::Visual);
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 8 });
+
+        // Not a valid regex, so it's searched for literally
+        editor.set_mode(Modal::Normal);
+        editor.last_search = Some(("f(a".to_string(), FindMode::Forwards));
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 4 });
+    }
+
+    #[test]
+    fn test_n_after_backward_search() {
+        let mut editor = new_test_editor(&["foo", "bar", "foo"]);
+        editor.go(LineCol { line: 1, col: 0 });
+        editor.last_search = Some(("foo".to_string(), FindMode::Backwards));
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_match_count_while_typing_query() {
+        let mut editor = new_test_editor(&["foo bar", "bar foo", "foo"]);
+        editor.go(LineCol { line: 0, col: 2 });
+        editor.set_mode(Modal::Find(FindMode::Forwards));
+        for ch in "/foo".chars() {
+            editor.push(ch);
+        }
+        editor.request_match_count().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let message = loop {
+            if let Some(message) = editor.tasks.try_recv() {
+                break message;
+            }
+            assert!(Instant::now() < deadline, "match count never arrived");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        editor.handle_message(message).unwrap();
+        let (query, status) = editor.find_match_status.clone().unwrap();
+        assert_eq!(query, "foo");
+        assert_eq!(status, "match 2 of 3");
+        assert!(editor.command_bar_content()(40, 1).ends_with("match 2 of 3"));
+
+        assert_eq!(
+            landing_match(&[], LineCol::default(), FindMode::Forwards),
+            None
+        );
+        let matches = [LineCol { line: 0, col: 0 }, LineCol { line: 1, col: 4 }];
+        assert_eq!(
+            landing_match(&matches, LineCol { line: 2, col: 0 }, FindMode::Forwards),
+            Some(0)
+        );
+        assert_eq!(
+            landing_match(&matches, LineCol { line: 0, col: 0 }, FindMode::Backwards),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_match_count_format() {
+        assert_eq!(match_count(Some(1), 3), "match 2 of 3");
+        assert_eq!(match_count(None, 3), "3 matches");
+
+        let mut editor = new_test_editor(&["foo", "foo"]);
+        editor.handle_char_input('*', None).unwrap();
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("match 2 of 2"));
+    }
+
+    #[test]
+    fn test_star_matches_whole_words_only() {
+        let mut editor = new_test_editor(&["let x = 1", "letter"]);
+        editor.handle_char_input('*', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+        assert_eq!(editor.search_highlights, [(LineCol { line: 0, col: 0 }, 3)]);
+        assert_eq!(
+            editor.last_search,
+            Some((r"\blet\b".to_string(), FindMode::Forwards))
+        );
+    }
+
+    #[test]
+    fn test_star_remembers_an_escaped_pattern() {
+        let mut editor = new_test_editor(&["$x = 1", "y = $x.$xs + axb"]);
+        editor.options.iskeyword.insert('$');
+        editor.handle_char_input('*', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 4 });
+        assert_eq!(
+            editor.last_search,
+            Some((r"\$x\b".to_string(), FindMode::Forwards))
+        );
+        // `$` isn't an end of line anchor and `$xs` is no match
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_n_after_star_skips_partial_matches() {
+        let mut editor = new_test_editor(&["foo", "foobar", "foo.bar", "barfoo", "foo"]);
+        editor.handle_char_input('*', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 4, col: 0 });
+
+        editor.handle_char_input('#', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+        // # searched backwards, so N goes forwards past "barfoo"
+        editor.handle_char_input('N', Some(2)).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_hash_searches_backwards() {
+        let mut editor = new_test_editor(&["foo", "bar foo", "foo"]);
+        editor.go(LineCol { line: 2, col: 0 });
+        editor.handle_char_input('#', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 4 });
+        editor.handle_char_input('#', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+        editor.handle_char_input('#', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 2, col: 0 });
+    }
+
+    #[test]
+    fn test_search_stops_at_end_without_wrapscan() {
+        let mut editor = new_test_editor(&["foo", "bar foo"]);
+        editor.options.wrap_search = false;
+        editor.last_search = Some(("foo".to_string(), FindMode::Forwards));
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 4 });
+        editor.handle_char_input('n', None).unwrap();
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 4 });
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("not found"));
+    }
+
+    #[test]
+    fn test_gn_selects_next_match() {
+        let mut editor = new_test_editor(&["a foo b foo", "foo"]);
+        editor.last_search = Some(("foo".to_string(), FindMode::Forwards));
+        editor.handle_combination_input('n', None, 'g').unwrap();
+        assert_eq!(editor.mode, Modal::Visual);
+        assert_eq!(
+            editor.cursor.last_text_mode_pos,
+            LineCol { line: 0, col: 2 }
+        );
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 5 });
+
+        editor.handle_combination_input('n', None, 'g').unwrap();
+        assert_eq!(
+            editor.cursor.last_text_mode_pos,
+            LineCol { line: 0, col: 2 }
+        );
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 11 });
+
+        editor.set_mode(Modal::Normal);
+        editor.go(LineCol { line: 1, col: 2 });
+        editor.handle_combination_input('N', None, 'g').unwrap();
+        assert_eq!(
+            editor.cursor.last_text_mode_pos,
+            LineCol { line: 1, col: 3 }
+        );
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+    }
+
+    #[test]
+    fn test_substitute_swaps_capture_groups() {
+        let mut editor = new_test_editor(&["foobar and foobar", "foobar"]);
+        let command = parse_command(r"s/(foo)(bar)/\2\1/").unwrap();
+        editor.execute_command(command).unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["barfoo and foobar", "foobar"]
+        );
+
+        let command = parse_command(r"%s/(foo)(bar)/[\2\1]/g").unwrap();
+        editor.execute_command(command).unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["barfoo and [barfoo]", "[barfoo]"]
+        );
+        assert_eq!(editor.pos(), LineCol { line: 1, col: 0 });
+    }
+
+    #[test]
+    fn test_substitute_keeps_anchors_and_emptied_lines() {
+        let mut editor = new_test_editor(&["  indented", "gone"]);
+        editor
+            .execute_command(parse_command(r"s/^ //g").unwrap())
+            .unwrap();
+        editor
+            .execute_command(parse_command(r"%s/^gone$//").unwrap())
+            .unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), [" indented", ""]);
+    }
+
+    #[test]
+    fn test_substitute_on_line_range() {
+        let mut editor = new_test_editor(&["a a", "a a", "a a", "a a"]);
+        editor
+            .execute_command(parse_command("3,2s/a/b/g").unwrap())
+            .unwrap();
+        assert_eq!(
+            editor.buffer.get_normal_text(),
+            ["a a", "b b", "b b", "a a"]
+        );
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("4 substitutions on 2 lines"));
+
+        editor
+            .execute_command(parse_command("4,5s/a/b/").unwrap())
+            .unwrap();
+        assert_eq!(editor.buffer.get_normal_text()[3], "a a");
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("Invalid range"));
+    }
+
+    #[test]
+    fn test_expand_replacement() {
+        let groups = ["ab".to_string(), "a".to_string(), "b".to_string()];
+        assert_eq!(expand_replacement(r"\2\1", &groups), "ba");
+        assert_eq!(expand_replacement(r"<&> \& \\ \7", &groups), r"<ab> & \ ");
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{lsp::FileType, LineCol};
+
+/// A piece of text inserted in place of its trigger. The body may contain `$1`, `$2`, ... tab
+/// stops visited in order, and a final `$0` stop where the cursor ends up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub trigger: String,
+    pub body: String,
+    /// File type the snippet is restricted to, `None` makes it available everywhere.
+    pub filetype: Option<FileType>,
+}
+
+/// The result of expanding a snippet body, the text with the tab-stop markers removed and the
+/// marker locations relative to the start of the text, in the order they should be visited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub text: String,
+    pub tab_stops: Vec<LineCol>,
+}
+
+/// Tab stops of the snippet currently being filled in.
+#[derive(Debug, Clone)]
+struct ActiveSnippet {
+    /// Remaining tab stops, in absolute buffer positions.
+    tab_stops: Vec<LineCol>,
+    /// The position the cursor was placed at when jumping to the current tab stop, used to shift
+    /// the remaining stops by whatever was typed since.
+    last_jump: LineCol,
+}
+
+#[derive(Debug, Default)]
+pub struct SnippetEngine {
+    snippets: Vec<Snippet>,
+    active: Option<ActiveSnippet>,
+}
+
+impl Snippet {
+    /// Splits the body into the plain text and the positions of its tab stops.
+    pub fn expand(&self) -> Expansion {
+        let mut text = String::with_capacity(self.body.len());
+        let mut stops: Vec<(usize, LineCol)> = vec![];
+        let mut pos = LineCol::default();
+        let mut chars = self.body.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            if ch == '$' && chars.peek().is_some_and(char::is_ascii_digit) {
+                let mut number = 0;
+                while let Some(digit) = chars.peek().and_then(|ch| ch.to_digit(10)) {
+                    number = number * 10 + digit as usize;
+                    chars.next();
+                }
+                stops.push((number, pos));
+                continue;
+            }
+            text.push(ch);
+            if ch == '\n' {
+                pos.line += 1;
+                pos.col = 0;
+            } else {
+                pos.col += ch.len_utf8();
+            }
+        }
+
+        // `$0` is the final stop, visited after all the numbered ones
+        stops.sort_by_key(|(number, _)| if *number == 0 { usize::MAX } else { *number });
+        Expansion {
+            text,
+            tab_stops: stops.into_iter().map(|(_, pos)| pos).collect(),
+        }
+    }
+}
+
+impl SnippetEngine {
+    pub fn add(&mut self, snippet: Snippet) {
+        self.snippets
+            .retain(|s| !(s.trigger == snippet.trigger && s.filetype == snippet.filetype));
+        self.snippets.push(snippet);
+    }
+
+    /// Finds the snippet with the longest trigger that the text before the cursor ends with.
+    /// A trigger only matches when it isn't preceded by a word character.
+    pub fn find_trigger(
+        &self,
+        before_cursor: &str,
+        filetype: FileType,
+        is_keyword: impl Fn(char) -> bool,
+    ) -> Option<&Snippet> {
+        self.snippets
+            .iter()
+            .filter(|s| s.filetype.is_none_or(|ft| ft == filetype))
+            .filter(|s| !s.trigger.is_empty() && before_cursor.ends_with(&s.trigger))
+            .filter(|s| {
+                before_cursor[..before_cursor.len() - s.trigger.len()]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|ch| !is_keyword(ch))
+            })
+            .max_by_key(|s| s.trigger.len())
+    }
+
+    /// Starts a tab-stop session for an expansion inserted at `at`, returning the position of
+    /// the first stop.
+    pub fn start(&mut self, expansion: &Expansion, at: LineCol) -> Option<LineCol> {
+        let mut tab_stops: Vec<LineCol> = expansion
+            .tab_stops
+            .iter()
+            .map(|stop| LineCol {
+                line: at.line + stop.line,
+                col: if stop.line == 0 {
+                    at.col + stop.col
+                } else {
+                    stop.col
+                },
+            })
+            .collect();
+        if tab_stops.is_empty() {
+            self.active = None;
+            return None;
+        }
+        let first = tab_stops.remove(0);
+        self.active = (!tab_stops.is_empty()).then_some(ActiveSnippet {
+            tab_stops,
+            last_jump: first,
+        });
+        Some(first)
+    }
+
+    /// Advances to the next tab stop of the active snippet. `cursor` is the current cursor
+    /// position, anything typed between the previous stop and it shifts the remaining stops.
+    pub fn next_tab_stop(&mut self, cursor: LineCol) -> Option<LineCol> {
+        let active = self.active.as_mut()?;
+        let from = active.last_jump;
+        let line_delta = cursor.line as isize - from.line as isize;
+        for stop in &mut active.tab_stops {
+            if stop.line == from.line {
+                stop.col =
+                    (stop.col as isize + cursor.col as isize - from.col as isize).max(0) as usize;
+            }
+            stop.line = (stop.line as isize + line_delta).max(0) as usize;
+        }
+
+        let next = active.tab_stops.remove(0);
+        active.last_jump = next;
+        if active.tab_stops.is_empty() {
+            self.active = None;
+        }
+        Some(next)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Abandons the active tab-stop session.
+    pub fn cancel(&mut self) {
+        self.active = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(trigger: &str, body: &str) -> Snippet {
+        Snippet {
+            trigger: trigger.to_string(),
+            body: body.to_string(),
+            filetype: None,
+        }
+    }
+
+    #[test]
+    fn test_expand_orders_tab_stops() {
+        let expansion = snippet("fn", "fn $1($2) {\n    $0\n}").expand();
+        assert_eq!(expansion.text, "fn () {\n    \n}");
+        assert_eq!(
+            expansion.tab_stops,
+            vec![
+                LineCol { line: 0, col: 3 },
+                LineCol { line: 0, col: 4 },
+                LineCol { line: 1, col: 4 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_trigger_requires_word_boundary() {
+        let mut engine = SnippetEngine::default();
+        engine.add(snippet("fn", "fn $1"));
+        let is_keyword = |ch: char| ch.is_alphanumeric();
+        assert!(engine
+            .find_trigger("    fn", FileType::Rust, is_keyword)
+            .is_some());
+        assert!(engine
+            .find_trigger("    xfn", FileType::Rust, is_keyword)
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_trigger_respects_filetype() {
+        let mut engine = SnippetEngine::default();
+        engine.add(Snippet {
+            filetype: Some(FileType::Python),
+            ..snippet("def", "def $1():")
+        });
+        let is_keyword = |ch: char| ch.is_alphanumeric();
+        assert!(engine
+            .find_trigger("def", FileType::Rust, is_keyword)
+            .is_none());
+        assert!(engine
+            .find_trigger("def", FileType::Python, is_keyword)
+            .is_some());
+    }
+
+    #[test]
+    fn test_tab_stops_shift_with_typed_text() {
+        let mut engine = SnippetEngine::default();
+        let expansion = snippet("fn", "fn $1($2)$0").expand();
+        let first = engine
+            .start(&expansion, LineCol { line: 2, col: 4 })
+            .unwrap();
+        assert_eq!(first, LineCol { line: 2, col: 7 });
+
+        // Typing `main` at the first stop moves the second one by four columns
+        let second = engine.next_tab_stop(LineCol { line: 2, col: 11 }).unwrap();
+        assert_eq!(second, LineCol { line: 2, col: 12 });
+        let last = engine.next_tab_stop(second).unwrap();
+        assert_eq!(last, LineCol { line: 2, col: 13 });
+        assert!(!engine.is_active());
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use crate::{buffer::TextBuffer, editor::Editor, notif_bar, utils::data_dir, Result};
+
+/// Directory the swap files of all edited files are kept in, `~/.local/share/neotext/swaps`.
+pub fn swap_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("swaps"))
+}
+
+/// Swap file of `path` inside `dir`. The absolute path of the file is encoded into the name with
+/// `%` in place of the separators, so files with the same name in different directories don't
+/// share a swap file.
+pub fn swap_path(dir: &Path, path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let encoded: String = path
+        .to_string_lossy()
+        .chars()
+        .map(|ch| if std::path::is_separator(ch) { '%' } else { ch })
+        .collect();
+    dir.join(format!("{encoded}.swp"))
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Swap file of the edited file, `None` if there is no file or swap files are disabled.
+    fn swap_file(&self) -> Option<PathBuf> {
+        let dir = self.swap_dir.as_ref()?;
+        let path = self.file_path.as_ref()?;
+        Some(swap_path(dir, path))
+    }
+
+    /// Writes the buffer to the swap file of the edited file, creating the swap directory if
+    /// needed.
+    pub(crate) fn write_swap(&mut self) {
+        self.chars_since_swap = 0;
+        let Some(swap) = self.swap_file() else {
+            return;
+        };
+        let written = swap
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(Into::into)
+            .and_then(|()| self.save_to_file(&swap));
+        if let Err(e) = written {
+            notif_bar!(self => format!(
+                "Writing swap file {} failed: {}",
+                swap.display(),
+                e.display_user()
+            ););
+        }
+    }
+
+    /// Counts a character typed in Insert mode, writing the swap file once `updatecount` of them
+    /// were typed since it was last written.
+    pub(crate) fn count_typed_char(&mut self) {
+        let Some(update_count) = self.options.update_count else {
+            return;
+        };
+        self.chars_since_swap += 1;
+        if self.chars_since_swap >= update_count {
+            self.write_swap();
+        }
+    }
+
+    /// Deletes the swap file of the edited file, done when the editor exits cleanly.
+    pub fn remove_swap(&self) {
+        if let Some(swap) = self.swap_file() {
+            let _ = std::fs::remove_file(swap);
+        }
+    }
+
+    /// Offers to restore the buffer from the swap file left behind by a session that didn't exit
+    /// cleanly, then creates the swap file of the current session. The recovered text replaces
+    /// the buffer but is not written to the edited file until saved.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the prompt can't be drawn or the swap file can't be read.
+    pub fn recover_swap(&mut self) -> Result<()> {
+        let Some(swap) = self.swap_file() else {
+            return Ok(());
+        };
+        if swap.exists() && self.prompt("Swap file found. Recover? (y/N)")? {
+            let content = std::fs::read_to_string(&swap)?;
+            self.load_text(&content)?;
+            notif_bar!(self => format!("Recovered {}", swap.display()););
+        }
+        self.write_swap();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor::tests::new_test_editor, modals::Modal, tasks::EditorMessage};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn test_swap_path_encodes_directories() {
+        assert_eq!(
+            swap_path(Path::new("/swaps"), Path::new("/home/user/main.rs")),
+            PathBuf::from("/swaps/%home%user%main.rs.swp")
+        );
+    }
+
+    #[test]
+    fn test_swap_written_on_timer_and_after_typing() {
+        let dir = std::env::temp_dir().join(format!("neotext_swaps_{}", std::process::id()));
+        let file = dir.join("notes.txt");
+        let mut editor = new_test_editor(&["hello"]);
+        editor.swap_dir = Some(dir.clone());
+        editor.set_file_path(&file);
+        let swap = swap_path(&dir, &file);
+
+        editor.handle_message(EditorMessage::TriggerSwap).unwrap();
+        assert_eq!(std::fs::read_to_string(&swap).unwrap(), "hello\n");
+
+        editor.options.update_count = Some(3);
+        editor.set_mode(Modal::Insert);
+        for ch in "abc".chars() {
+            let key = KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE);
+            editor.handle_insert_key(key).unwrap();
+            if ch == 'b' {
+                assert_eq!(std::fs::read_to_string(&swap).unwrap(), "hello\n");
+            }
+        }
+        assert_eq!(std::fs::read_to_string(&swap).unwrap(), "abchello\n");
+
+        editor.remove_swap();
+        assert!(!swap.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
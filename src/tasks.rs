@@ -0,0 +1,378 @@
+use std::{
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::{
+    runtime::{Builder, Runtime},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+
+use crate::{
+    common::RegexPattern,
+    picker::{parse_grep_line, GrepMatch},
+    LineCol, Pattern, Result,
+};
+
+/// Messages sent from background tasks to the main editor loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorMessage {
+    /// The auto-save interval elapsed.
+    TriggerAutoSave,
+    /// The swap file interval elapsed.
+    TriggerSwap,
+    /// The watched file was modified by another process.
+    FileModified,
+    /// A build started with `:make` exited.
+    BuildFinished {
+        /// Standard output and error of the build command.
+        output: String,
+        /// Directory the build command ran in.
+        dir: PathBuf,
+        success: bool,
+    },
+    /// Every occurrence of `query` in the text it was searched in.
+    SearchMatches {
+        query: String,
+        matches: Vec<LineCol>,
+    },
+    /// A line matched by the grep command started for `query`.
+    GrepMatch { query: String, entry: GrepMatch },
+    /// The grep command started for `query` exited, `error` tells why if it couldn't run.
+    GrepFinished {
+        query: String,
+        error: Option<String>,
+    },
+}
+
+/// Owns the runtime the editor's background tasks run on and the channel they report back
+/// through. The runtime is only started once the first task is spawned.
+#[derive(Debug)]
+pub struct BackgroundTasks {
+    runtime: Option<Runtime>,
+    sender: UnboundedSender<EditorMessage>,
+    receiver: UnboundedReceiver<EditorMessage>,
+    auto_save: Option<(Duration, JoinHandle<()>)>,
+    swap: Option<(Duration, JoinHandle<()>)>,
+    file_watch: Option<RecommendedWatcher>,
+}
+
+impl Default for BackgroundTasks {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            runtime: None,
+            sender,
+            receiver,
+            auto_save: None,
+            swap: None,
+            file_watch: None,
+        }
+    }
+}
+
+impl BackgroundTasks {
+    fn runtime(&mut self) -> Result<&Runtime> {
+        if self.runtime.is_none() {
+            let runtime = Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("neotext-background")
+                .enable_io()
+                .enable_time()
+                .build()?;
+            self.runtime = Some(runtime);
+        }
+        Ok(self
+            .runtime
+            .as_ref()
+            .expect("Runtime was just initialized."))
+    }
+
+    /// Spawns a task sending `message` every `period`, the first one after a full period.
+    fn spawn_interval(
+        &mut self,
+        period: Duration,
+        message: EditorMessage,
+    ) -> Result<JoinHandle<()>> {
+        let sender = self.sender.clone();
+        Ok(self.runtime()?.spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if sender.send(message.clone()).is_err() {
+                    break;
+                }
+            }
+        }))
+    }
+
+    /// (Re)starts the auto-save timer, `None` stops it. Does nothing if the interval is unchanged.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn set_auto_save_interval(&mut self, interval: Option<Duration>) -> Result<()> {
+        if self.auto_save.as_ref().map(|(period, _)| *period) == interval {
+            return Ok(());
+        }
+        if let Some((_, handle)) = self.auto_save.take() {
+            handle.abort();
+        }
+        if let Some(period) = interval {
+            let handle = self.spawn_interval(period, EditorMessage::TriggerAutoSave)?;
+            self.auto_save = Some((period, handle));
+        }
+        Ok(())
+    }
+
+    /// (Re)starts the swap file timer, `None` stops it. Does nothing if the interval is
+    /// unchanged.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn set_swap_interval(&mut self, interval: Option<Duration>) -> Result<()> {
+        if self.swap.as_ref().map(|(period, _)| *period) == interval {
+            return Ok(());
+        }
+        if let Some((_, handle)) = self.swap.take() {
+            handle.abort();
+        }
+        if let Some(period) = interval {
+            let handle = self.spawn_interval(period, EditorMessage::TriggerSwap)?;
+            self.swap = Some((period, handle));
+        }
+        Ok(())
+    }
+
+    /// Starts watching `path`, sending `FileModified` whenever it's written or replaced.
+    /// Replaces the previously watched file, if any.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file system watcher can't be started.
+    pub fn watch_file(&mut self, path: PathBuf) -> Result<()> {
+        self.file_watch = None;
+        let path = std::path::absolute(&path)?;
+        let sender = self.sender.clone();
+        let watched = path.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                let changed = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+                if changed && event.paths.contains(&watched) {
+                    let _ = sender.send(EditorMessage::FileModified);
+                }
+            })
+            .map_err(watch_error)?;
+        // Editors often save by renaming a new file over the old one, which a watch on the file
+        // itself wouldn't survive, so its directory is watched instead
+        let dir = path.parent().unwrap_or(&path);
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+        self.file_watch = Some(watcher);
+        Ok(())
+    }
+
+    /// Runs `command` through the shell in `dir` as a task of the runtime, sending
+    /// `BuildFinished` with its output once it exits.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn spawn_build(&mut self, command: String, dir: PathBuf) -> Result<()> {
+        let sender = self.sender.clone();
+        self.runtime()?.spawn(async move {
+            let (output, success) = match tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .current_dir(&dir)
+                .stdin(Stdio::null())
+                .output()
+                .await
+            {
+                Ok(output) => {
+                    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                    text.push_str(&String::from_utf8_lossy(&output.stderr));
+                    (text, output.status.success())
+                }
+                Err(e) => (format!("Failed to run `{command}`: {e}"), false),
+            };
+            let _ = sender.send(EditorMessage::BuildFinished {
+                output,
+                dir,
+                success,
+            });
+        });
+        Ok(())
+    }
+
+    /// Runs the grep command `command` with `query` and the current directory as its last
+    /// arguments on a blocking thread of the runtime, sending every match as `GrepMatch` as soon
+    /// as it's printed and `GrepFinished` once the command exits.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn spawn_grep(&mut self, command: String, query: String) -> Result<()> {
+        let sender = self.sender.clone();
+        self.runtime()?.spawn_blocking(move || {
+            let mut words = command.split_whitespace();
+            let Some(program) = words.next() else {
+                let error = Some("The grep command is empty".to_string());
+                let _ = sender.send(EditorMessage::GrepFinished { query, error });
+                return;
+            };
+            let child = Command::new(program)
+                .args(words)
+                .arg(&query)
+                .arg(".")
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            let mut child = match child {
+                Ok(child) => child,
+                Err(e) => {
+                    let error = Some(format!("Failed to run `{command}`: {e}"));
+                    let _ = sender.send(EditorMessage::GrepFinished { query, error });
+                    return;
+                }
+            };
+            if let Some(stdout) = child.stdout.take() {
+                for line in BufReader::new(stdout)
+                    .lines()
+                    .map_while(std::io::Result::ok)
+                {
+                    let Some(entry) = parse_grep_line(&line) else {
+                        continue;
+                    };
+                    let message = EditorMessage::GrepMatch {
+                        query: query.clone(),
+                        entry,
+                    };
+                    if sender.send(message).is_err() {
+                        let _ = child.kill();
+                        break;
+                    }
+                }
+            }
+            let _ = child.wait();
+            let _ = sender.send(EditorMessage::GrepFinished { query, error: None });
+        });
+        Ok(())
+    }
+
+    /// Finds every occurrence of `query` in `lines` on a blocking thread of the runtime, sending
+    /// the matches as `SearchMatches`.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the background runtime can't be started.
+    pub fn spawn_match_count(&mut self, query: String, lines: Vec<String>) -> Result<()> {
+        let sender = self.sender.clone();
+        self.runtime()?.spawn_blocking(move || {
+            let matches = RegexPattern::or_literal(&query).0.find_all_pattern(&lines);
+            let _ = sender.send(EditorMessage::SearchMatches { query, matches });
+        });
+        Ok(())
+    }
+
+    /// Returns the next pending message without blocking.
+    pub fn try_recv(&mut self) -> Option<EditorMessage> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Sends a message to the main loop as if it came from a background task.
+    pub fn send(&self, message: EditorMessage) {
+        let _ = self.sender.send(message);
+    }
+}
+
+fn watch_error(error: notify::Error) -> crate::Error {
+    match error.kind {
+        notify::ErrorKind::Io(e) => e.into(),
+        kind => std::io::Error::other(format!("{kind:?}")).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_auto_save_interval_sends_messages() {
+        let mut tasks = BackgroundTasks::default();
+        tasks
+            .set_auto_save_interval(Some(Duration::from_millis(10)))
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let message = loop {
+            if let Some(message) = tasks.try_recv() {
+                break message;
+            }
+            assert!(Instant::now() < deadline, "No auto-save message received");
+            std::thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(message, EditorMessage::TriggerAutoSave);
+
+        tasks.set_auto_save_interval(None).unwrap();
+        assert!(tasks.auto_save.is_none());
+    }
+
+    #[test]
+    fn test_file_watch_reports_writes() {
+        let dir = std::env::temp_dir().join(format!("neotext_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("watched.txt");
+        std::fs::write(&path, "before").unwrap();
+
+        let mut tasks = BackgroundTasks::default();
+        tasks.watch_file(path.clone()).unwrap();
+        std::fs::write(dir.join("other.txt"), "unrelated").unwrap();
+        std::fs::write(&path, "after").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let message = loop {
+            if let Some(message) = tasks.try_recv() {
+                break message;
+            }
+            assert!(Instant::now() < deadline, "No file watch message received");
+            std::thread::sleep(Duration::from_millis(5));
+        };
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(message, EditorMessage::FileModified);
+    }
+
+    #[test]
+    fn test_build_sends_output() {
+        let mut tasks = BackgroundTasks::default();
+        tasks
+            .spawn_build(
+                "echo out; echo err >&2; exit 1".to_string(),
+                PathBuf::from("."),
+            )
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let message = loop {
+            if let Some(message) = tasks.try_recv() {
+                break message;
+            }
+            assert!(Instant::now() < deadline, "No build message received");
+            std::thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(
+            message,
+            EditorMessage::BuildFinished {
+                output: "out\nerr\n".to_string(),
+                dir: PathBuf::from("."),
+                success: false,
+            }
+        );
+    }
+}
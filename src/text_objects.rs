@@ -0,0 +1,427 @@
+use crate::{
+    buffer::TextBuffer, cursor::Selection, editor::Editor, modals::Modal, notif_bar,
+    options::EditorOptions, LineCol, Result,
+};
+
+/// What is done with a text object once its kind is typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectAction {
+    /// `d`, removes the object.
+    Delete,
+    /// `c`, removes the object and starts Insert mode.
+    Change,
+    /// `y`, copies the object.
+    Yank,
+    /// `i` or `a` in Visual mode, selects the object.
+    Select,
+}
+
+/// The inner part of the text object `obj` at `pos`, `iw`, `i(`, `i"` and the like. Brackets and
+/// quotes are left out, and a word is only the run of symbols of the same kind the cursor is in,
+/// as decided by `iskeyword`. The end of the selection is exclusive.
+pub fn select_inner(
+    buf: &impl TextBuffer,
+    pos: LineCol,
+    obj: char,
+    options: &EditorOptions,
+) -> Option<Selection> {
+    match obj {
+        'w' | 'W' => {
+            word_run(buf.line(pos.line).ok()?, pos, obj == 'W', options).map(|(start, end)| {
+                Selection {
+                    start: LineCol { col: start, ..pos },
+                    end: LineCol { col: end, ..pos },
+                }
+            })
+        }
+        '"' | '\'' | '`' => {
+            let (open, close) = quote_pair(buf.line(pos.line).ok()?, pos.col, obj)?;
+            Some(Selection {
+                start: LineCol {
+                    col: open + 1,
+                    ..pos
+                },
+                end: LineCol { col: close, ..pos },
+            })
+        }
+        _ => {
+            let (open, close) = bracket_pair(obj)?;
+            let (start, end) = enclosing_brackets(buf.get_normal_text(), pos, open, close)?;
+            Some(Selection {
+                start: LineCol {
+                    col: start.col + open.len_utf8(),
+                    ..start
+                },
+                end,
+            })
+        }
+    }
+}
+
+/// The text object `obj` at `pos` with its surroundings, `aw`, `a(`, `a"` and the like. Brackets
+/// and quotes are included, as is the whitespace after a word or quoted text, or the whitespace
+/// before it when there is none after. The end of the selection is exclusive.
+pub fn select_around(
+    buf: &impl TextBuffer,
+    pos: LineCol,
+    obj: char,
+    options: &EditorOptions,
+) -> Option<Selection> {
+    let (start, end) = match obj {
+        'w' | 'W' => {
+            let line = buf.line(pos.line).ok()?;
+            let big = obj == 'W';
+            let (start, end) = word_run(line, pos, big, options)?;
+            if line[start..end].starts_with(char::is_whitespace) {
+                // On whitespace the word after it is taken along instead
+                let word_end = word_run(line, LineCol { col: end, ..pos }, big, options)
+                    .map_or(end, |(_, word_end)| word_end);
+                (start, word_end)
+            } else {
+                with_surrounding_whitespace(line, start, end)
+            }
+        }
+        '"' | '\'' | '`' => {
+            let line = buf.line(pos.line).ok()?;
+            let (open, close) = quote_pair(line, pos.col, obj)?;
+            with_surrounding_whitespace(line, open, close + obj.len_utf8())
+        }
+        _ => {
+            let (open, close) = bracket_pair(obj)?;
+            let (start, end) = enclosing_brackets(buf.get_normal_text(), pos, open, close)?;
+            return Some(Selection {
+                start,
+                end: LineCol {
+                    col: end.col + close.len_utf8(),
+                    ..end
+                },
+            });
+        }
+    };
+    Some(Selection {
+        start: LineCol { col: start, ..pos },
+        end: LineCol { col: end, ..pos },
+    })
+}
+
+/// Opening and closing bracket of the bracket text objects, `b` and `B` standing for `(` and `{`.
+fn bracket_pair(obj: char) -> Option<(char, char)> {
+    match obj {
+        '(' | ')' | 'b' => Some(('(', ')')),
+        '[' | ']' => Some(('[', ']')),
+        '{' | '}' | 'B' => Some(('{', '}')),
+        _ => None,
+    }
+}
+
+/// Columns of the run of symbols of one kind containing `pos`, the end exclusive. The kinds are
+/// those of `EditorOptions::word_class`. At the end of a line the last symbol is used.
+fn word_run(
+    line: &str,
+    pos: LineCol,
+    big: bool,
+    options: &EditorOptions,
+) -> Option<(usize, usize)> {
+    let class = |ch: char| options.word_class(ch, big);
+    let col = if pos.col < line.len() {
+        pos.col
+    } else {
+        line.char_indices().next_back()?.0
+    };
+    let under = class(line.get(col..)?.chars().next()?);
+    let start = line[..col]
+        .char_indices()
+        .rev()
+        .take_while(|(_, ch)| class(*ch) == under)
+        .last()
+        .map_or(col, |(idx, _)| idx);
+    let end = line[col..]
+        .char_indices()
+        .find(|(_, ch)| class(*ch) != under)
+        .map_or(line.len(), |(idx, _)| col + idx);
+    Some((start, end))
+}
+
+/// Widens `start..end` over the whitespace following it, or the whitespace preceding it if none
+/// follows.
+fn with_surrounding_whitespace(line: &str, start: usize, end: usize) -> (usize, usize) {
+    let trailing = line[end..]
+        .char_indices()
+        .find(|(_, ch)| !ch.is_whitespace())
+        .map_or(line.len(), |(idx, _)| end + idx);
+    if trailing > end {
+        return (start, trailing);
+    }
+    let leading = line[..start]
+        .char_indices()
+        .rev()
+        .take_while(|(_, ch)| ch.is_whitespace())
+        .last()
+        .map_or(start, |(idx, _)| idx);
+    (leading, end)
+}
+
+/// Columns of the `quote` pair on `line` around `col`, or the first pair after it. Quotes are
+/// paired up from the start of the line, skipping those escaped with a backslash.
+fn quote_pair(line: &str, col: usize, quote: char) -> Option<(usize, usize)> {
+    let mut escaped = false;
+    let quotes: Vec<usize> = line
+        .char_indices()
+        .filter(|(_, ch)| {
+            let is_quote = *ch == quote && !escaped;
+            escaped = *ch == '\\' && !escaped;
+            is_quote
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+    quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(_, close)| *close >= col)
+}
+
+/// Positions of the `open` bracket enclosing `pos` and the `close` bracket matching it, skipping
+/// nested pairs. A bracket under the cursor counts as enclosing it.
+fn enclosing_brackets(
+    lines: &[String],
+    pos: LineCol,
+    open: char,
+    close: char,
+) -> Option<(LineCol, LineCol)> {
+    let under = lines.get(pos.line)?.get(pos.col..)?.chars().next();
+    let start = if under == Some(open) {
+        pos
+    } else {
+        // Brackets closed before the cursor are waiting for their opening partner
+        let mut depth = 0usize;
+        let mut before = lines[..=pos.line]
+            .iter()
+            .enumerate()
+            .rev()
+            .flat_map(|(line, text)| {
+                text.char_indices()
+                    .rev()
+                    .filter(move |(col, _)| line < pos.line || *col < pos.col)
+                    .map(move |(col, ch)| (LineCol { line, col }, ch))
+            });
+        before.find_map(|(at, ch)| {
+            if ch == close {
+                depth += 1;
+            } else if ch == open {
+                if depth == 0 {
+                    return Some(at);
+                }
+                depth -= 1;
+            }
+            None
+        })?
+    };
+
+    let mut depth = 0usize;
+    let mut after = lines
+        .iter()
+        .enumerate()
+        .skip(start.line)
+        .flat_map(|(line, text)| {
+            text.char_indices()
+                .filter(move |(col, _)| line > start.line || *col > start.col)
+                .map(move |(col, ch)| (LineCol { line, col }, ch))
+        });
+    let end = after.find_map(|(at, ch)| {
+        if ch == open {
+            depth += 1;
+        } else if ch == close {
+            if depth == 0 {
+                return Some(at);
+            }
+            depth -= 1;
+        }
+        None
+    })?;
+    Some((start, end))
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Applies `action` to the text object `obj` at the cursor, its surroundings included if
+    /// `around`. The cursor ends up at the start of the object, or the object is selected for
+    /// `ObjectAction::Select`.
+    pub(crate) fn apply_text_object(
+        &mut self,
+        action: ObjectAction,
+        around: bool,
+        obj: char,
+    ) -> Result<()> {
+        let pos = self.pos();
+        let sel = if around {
+            select_around(&self.buffer, pos, obj, &self.options)
+        } else {
+            select_inner(&self.buffer, pos, obj, &self.options)
+        };
+        let Some(Selection { start, end }) = sel else {
+            notif_bar!(self => "No text object found";);
+            return Ok(());
+        };
+        match action {
+            ObjectAction::Select => {
+                self.cursor.last_text_mode_pos = start;
+                self.cursor.pos = end;
+                return Ok(());
+            }
+            ObjectAction::Yank => {
+                let text = self.buffer.get_text(start, end)?;
+                let register = self.pending_register.take();
+                self.copy_register.yank(text, register)?;
+            }
+            ObjectAction::Delete | ObjectAction::Change => {
+                if self.blocked_by_readonly() {
+                    return Ok(());
+                }
+                if start != end {
+                    let deleted = self.buffer.get_text(start, end)?;
+                    self.save_deletion(deleted);
+                    self.buffer.save_state(pos);
+                    self.delete_keeping_lines(start, end)?;
+                }
+                if action == ObjectAction::Change {
+                    self.set_mode(Modal::Insert);
+                }
+            }
+        }
+        self.go(start);
+        Ok(())
+    }
+
+    /// Deletes the text between `start` and the exclusive `end`. Unlike `delete_selection`, a
+    /// range covering whole lines leaves an empty line behind rather than removing them.
+    fn delete_keeping_lines(&mut self, start: LineCol, end: LineCol) -> Result<()> {
+        if start.col > 0 || end.col < self.buffer.max_col(end) {
+            self.buffer.delete_selection(start, end)?;
+            return Ok(());
+        }
+        // Keep the first symbol so the lines stay, then remove it on its own
+        let first_len = self
+            .buffer
+            .line(start.line)?
+            .chars()
+            .next()
+            .map_or(0, char::len_utf8);
+        let after_first = LineCol {
+            col: first_len,
+            ..start
+        };
+        if after_first != end {
+            self.buffer.delete_selection(after_first, end)?;
+        }
+        if first_len > 0 {
+            self.buffer.delete(after_first)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor::tests::new_test_editor, options::SetAction};
+
+    fn sel(editor_lines: &[&str], pos: LineCol, obj: char, around: bool) -> Option<String> {
+        let editor = new_test_editor(editor_lines);
+        let sel = if around {
+            select_around(&editor.buffer, pos, obj, &editor.options)
+        } else {
+            select_inner(&editor.buffer, pos, obj, &editor.options)
+        }?;
+        editor.buffer.get_text(sel.start, sel.end).ok()
+    }
+
+    #[test]
+    fn test_word_objects() {
+        let at = |col| LineCol { line: 0, col };
+        let line = ["let some_name = foo.bar;"];
+        assert_eq!(sel(&line, at(6), 'w', false).unwrap(), "some_name");
+        assert_eq!(sel(&line, at(6), 'w', true).unwrap(), "some_name ");
+        assert_eq!(sel(&line, at(19), 'w', false).unwrap(), ".");
+        assert_eq!(sel(&line, at(17), 'W', false).unwrap(), "foo.bar;");
+        assert_eq!(sel(&line, at(17), 'W', true).unwrap(), " foo.bar;");
+        assert_eq!(sel(&line, at(3), 'w', true).unwrap(), " some_name");
+        assert_eq!(sel(&[""], at(0), 'w', false), None);
+    }
+
+    #[test]
+    fn test_word_objects_follow_iskeyword() {
+        let mut editor = new_test_editor(&["a foo-bar b"]);
+        editor.go(LineCol { line: 0, col: 3 });
+        editor
+            .options
+            .apply("iskeyword", SetAction::Add("-".to_string()))
+            .unwrap();
+        editor
+            .apply_text_object(ObjectAction::Delete, false, 'w')
+            .unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["a  b"]);
+    }
+
+    #[test]
+    fn test_bracket_objects() {
+        let lines = ["call(a, (b), c) + d", "f(x,", "  y)"];
+        let at = |line, col| LineCol { line, col };
+        assert_eq!(sel(&lines, at(0, 6), '(', false).unwrap(), "a, (b), c");
+        assert_eq!(sel(&lines, at(0, 9), ')', false).unwrap(), "b");
+        assert_eq!(sel(&lines, at(0, 10), 'b', true).unwrap(), "(b)");
+        assert_eq!(sel(&lines, at(0, 4), '(', true).unwrap(), "(a, (b), c)");
+        assert_eq!(sel(&lines, at(2, 2), '(', false).unwrap(), "x,\n  y");
+        assert_eq!(sel(&lines, at(0, 17), '(', false), None);
+        assert_eq!(sel(&lines, at(0, 6), '{', false), None);
+    }
+
+    #[test]
+    fn test_quote_objects() {
+        let at = |col| LineCol { line: 0, col };
+        let line = [r#"x = "one \" two" + "three""#];
+        assert_eq!(sel(&line, at(6), '"', false).unwrap(), r#"one \" two"#);
+        assert_eq!(sel(&line, at(4), '"', true).unwrap(), r#""one \" two" "#);
+        assert_eq!(sel(&line, at(0), '"', false).unwrap(), r#"one \" two"#);
+        assert_eq!(sel(&line, at(22), '"', true).unwrap(), r#" "three""#);
+        assert_eq!(sel(&line, at(6), '\'', false), None);
+    }
+
+    #[test]
+    fn test_operators_on_text_objects() {
+        let mut editor = new_test_editor(&["foo(bar, baz)", "word"]);
+        editor.go(LineCol { line: 0, col: 5 });
+        editor.handle_combination_input('i', None, 'd').unwrap();
+        editor.handle_char_input('(', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["foo()", "word"]);
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 4 });
+
+        editor.go(LineCol { line: 1, col: 2 });
+        editor.handle_combination_input('i', None, 'c').unwrap();
+        editor.handle_char_input('w', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["foo()", ""]);
+        assert_eq!(editor.mode, Modal::Insert);
+
+        editor.set_mode(Modal::Normal);
+        editor.go(LineCol { line: 0, col: 1 });
+        editor.handle_combination_input('a', None, 'y').unwrap();
+        editor.handle_char_input('w', None).unwrap();
+        let yanked = editor.copy_register.get_from_register(None).unwrap();
+        assert_eq!(String::from_iter(yanked), "foo");
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn test_visual_mode_selects_text_object() {
+        let mut editor = new_test_editor(&["say \"hi there\" now"]);
+        editor.set_mode(Modal::Visual);
+        editor.go(LineCol { line: 0, col: 8 });
+        editor.handle_char_input('i', None).unwrap();
+        editor.handle_char_input('"', None).unwrap();
+        assert_eq!(
+            editor.cursor.last_text_mode_pos,
+            LineCol { line: 0, col: 5 }
+        );
+        assert_eq!(editor.pos(), LineCol { line: 0, col: 13 });
+        editor.handle_char_input('d', None).unwrap();
+        assert_eq!(editor.buffer.get_normal_text(), ["say \"\" now"]);
+    }
+}
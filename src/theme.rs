@@ -1,7 +1,184 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
 use crossterm::style::Color;
+use serde::Deserialize;
+
+use crate::{
+    buffer::TextBuffer,
+    editor::Editor,
+    error, notif_bar,
+    utils::{config_dir, expand_tilde},
+    Error, Result,
+};
 
 pub trait Theme {
+    #[allow(clippy::wrong_self_convention)]
     fn from_str(&self, element: &str) -> Color;
+    /// Name the theme is selected by in `:colorscheme`.
+    fn name(&self) -> &str;
+}
+
+/// Built-in theme called `name`, ignoring case.
+pub fn builtin_theme(name: &str) -> Option<Box<dyn Theme>> {
+    Some(match name.to_lowercase().as_str() {
+        "default" => Box::new(DefaultTheme {}),
+        "sonokai" => Box::new(Sonokai),
+        "monoandromeda" | "mono_andromeda" => Box::new(MonoAndromeda),
+        "monokai" => Box::new(Monokai),
+        "gruvbox" => Box::new(Gruvbox),
+        _ => return None,
+    })
+}
+
+/// Theme file loaded at startup when none is given with `--theme`,
+/// `~/.config/neotext/theme.toml`.
+pub fn user_theme_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("theme.toml"))
+}
+
+/// Loads the TOML theme file at `path`, see `TomlTheme`.
+///
+/// # Errors
+/// Returns `Error::Io` if the file can't be read and `Error::ParsingError` if it isn't a valid
+/// theme.
+pub fn load_theme(path: &Path) -> Result<Box<dyn Theme>> {
+    Ok(Box::new(TomlTheme::load(path)?))
+}
+
+/// Parses a color of a theme file, either `#rrggbb` or a name such as `dark_grey`.
+fn parse_color(value: &str) -> Result<Color> {
+    let invalid = || Error::ParsingError(format!("Invalid color: {value}"));
+    match value.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                    .ok_or_else(invalid)
+            };
+            Ok(Color::Rgb {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            })
+        }
+        Some(_) => Err(invalid()),
+        None => Color::try_from(value).map_err(|()| invalid()),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: Option<String>,
+    /// Color of the scopes without one of their own.
+    default: Option<String>,
+    #[serde(default)]
+    colors: HashMap<String, String>,
+}
+
+/// Theme loaded from a TOML file mapping highlight scopes to colors:
+///
+/// ```toml
+/// name = "mine"
+/// default = "#f8f8f2"
+///
+/// [colors]
+/// keyword = "#f92672"
+/// "function.method" = "cyan"
+/// ```
+///
+/// Scopes without a color fall back to the one before their first `.`, so `keyword` also covers
+/// `keyword.return`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TomlTheme {
+    name: String,
+    default: Color,
+    colors: HashMap<String, Color>,
+}
+
+impl TomlTheme {
+    /// Reads the theme file at `path`, named after the file unless it sets `name`.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file can't be read and `Error::ParsingError` if it isn't a
+    /// valid theme.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let file: ThemeFile = toml::from_str(&content)
+            .map_err(|e| Error::ParsingError(format!("{}: {e}", path.display())))?;
+        let name = file.name.unwrap_or_else(|| {
+            path.file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        });
+        Ok(Self {
+            name,
+            default: file
+                .default
+                .as_deref()
+                .map_or(Ok(Color::Reset), parse_color)?,
+            colors: file
+                .colors
+                .iter()
+                .map(|(scope, color)| Ok((scope.clone(), parse_color(color)?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl Theme for TomlTheme {
+    fn from_str(&self, el: &str) -> Color {
+        self.colors
+            .get(el)
+            .or_else(|| self.colors.get(el.split('.').next()?))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<Buff: TextBuffer> Editor<Buff> {
+    /// Switches to the theme `name`, a built-in one or the path of a TOML theme file,
+    /// `:colorscheme`. The new colors show up from the next redraw on.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if a theme file can't be read and `Error::ParsingError` if it isn't
+    /// valid.
+    pub(crate) fn set_colorscheme(&mut self, name: &str) -> Result<()> {
+        let is_path = name.contains(std::path::is_separator) || name.ends_with(".toml");
+        let theme: Box<dyn Theme> = if is_path {
+            load_theme(&expand_tilde(name))?
+        } else if let Some(theme) = builtin_theme(name) {
+            theme
+        } else {
+            notif_bar!(self => format!("E185: Cannot find color scheme '{name}'"););
+            return Ok(());
+        };
+        self.theme = theme;
+        Ok(())
+    }
+
+    /// Loads the theme file given with `--theme`, or the user theme file if there is one. A theme
+    /// file that is missing or invalid is reported and replaced by Monokai.
+    pub fn load_startup_theme(&mut self, path: Option<&Path>) {
+        let path = match path {
+            Some(path) => expand_tilde(&path.to_string_lossy()),
+            None => match user_theme_path() {
+                Some(path) if path.exists() => path,
+                _ => return,
+            },
+        };
+        self.theme = load_theme(&path).unwrap_or_else(|e| {
+            error!("Failed to load the theme {}: {e}", path.display());
+            notif_bar!(self => format!("Can't load theme, using Monokai: {}", e.display_user()););
+            Box::new(Monokai)
+        });
+    }
 }
 
 pub struct DefaultTheme {}
@@ -56,6 +233,10 @@ impl Theme for DefaultTheme {
             _ => Color::Reset,
         }
     }
+
+    fn name(&self) -> &str {
+        "default"
+    }
 }
 
 // All credits for this theme go to sainnhe - `https://github.com/sainnhe/sonokai`
@@ -218,6 +399,10 @@ impl Theme for Sonokai {
             }, // Default text color in Monokai
         }
     }
+
+    fn name(&self) -> &str {
+        "sonokai"
+    }
 }
 pub struct MonoAndromeda;
 
@@ -379,6 +564,10 @@ impl Theme for MonoAndromeda {
             }, // fg (foreground)
         }
     }
+
+    fn name(&self) -> &str {
+        "monoandromeda"
+    }
 }
 
 pub struct Monokai;
@@ -541,4 +730,207 @@ impl Theme for Monokai {
             }, // Default text color in Monokai
         }
     }
+
+    fn name(&self) -> &str {
+        "monokai"
+    }
+}
+
+// All credits for this palette go to morhetz - `https://github.com/morhetz/gruvbox`
+pub struct Gruvbox;
+
+impl Theme for Gruvbox {
+    fn from_str(&self, el: &str) -> Color {
+        match el {
+            // Keywords
+            "keyword"
+            | "keyword.operator"
+            | "keyword.function"
+            | "keyword.coroutine"
+            | "keyword.import"
+            | "keyword.type"
+            | "keyword.modifier"
+            | "keyword.repeat"
+            | "keyword.return"
+            | "keyword.debug"
+            | "keyword.exception"
+            | "keyword.conditional"
+            | "keyword.directive" => Color::Rgb {
+                r: 251,
+                g: 73,
+                b: 52,
+            }, // red
+
+            // Functions and methods
+            "function"
+            | "function.builtin"
+            | "function.call"
+            | "function.macro"
+            | "function.method"
+            | "function.method.call"
+            | "constructor" => Color::Rgb {
+                r: 184,
+                g: 187,
+                b: 38,
+            }, // green
+
+            // Strings and characters
+            "string"
+            | "string.documentation"
+            | "string.regexp"
+            | "string.escape"
+            | "string.special"
+            | "string.special.symbol"
+            | "string.special.url"
+            | "string.special.path"
+            | "character"
+            | "character.special" => Color::Rgb {
+                r: 184,
+                g: 187,
+                b: 38,
+            }, // green
+
+            // Numbers and constants
+            "number" | "number.float" | "constant" | "constant.builtin" | "constant.macro"
+            | "boolean" => Color::Rgb {
+                r: 211,
+                g: 134,
+                b: 155,
+            }, // purple
+
+            // Comments
+            "comment"
+            | "comment.documentation"
+            | "comment.error"
+            | "comment.warning"
+            | "comment.todo"
+            | "comment.note" => Color::Rgb {
+                r: 146,
+                g: 131,
+                b: 116,
+            }, // gray
+
+            // Types and classes
+            "type" | "type.builtin" | "type.definition" => Color::Rgb {
+                r: 250,
+                g: 189,
+                b: 47,
+            }, // yellow
+
+            // Attributes and properties
+            "attribute" | "attribute.builtin" | "property" | "variable.member" => Color::Rgb {
+                r: 142,
+                g: 192,
+                b: 124,
+            }, // aqua
+
+            // Modules
+            "module" | "module.builtin" | "label" => Color::Rgb {
+                r: 131,
+                g: 165,
+                b: 152,
+            }, // blue
+
+            // Macros and special punctuation
+            "punctuation.special" | "operator" => Color::Rgb {
+                r: 254,
+                g: 128,
+                b: 25,
+            }, // orange
+
+            // Default for unspecified elements
+            _ => Color::Rgb {
+                r: 235,
+                g: 219,
+                b: 178,
+            }, // fg
+        }
+    }
+
+    fn name(&self) -> &str {
+        "gruvbox"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::parse_command, editor::tests::new_test_editor};
+
+    #[test]
+    fn test_colorscheme_switches_theme() {
+        let mut editor = new_test_editor(&["fn main() {}"]);
+        assert_eq!(editor.theme.name(), "monoandromeda");
+        let command = parse_command(":colorscheme Gruvbox").unwrap();
+        editor.execute_command(command).unwrap();
+        assert_eq!(editor.theme.name(), "gruvbox");
+
+        editor.set_colorscheme("nonexistent").unwrap();
+        assert_eq!(editor.theme.name(), "gruvbox");
+    }
+
+    #[test]
+    fn test_startup_theme_falls_back_to_monokai() {
+        let dir = std::env::temp_dir().join(format!("neotext_startup_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("theme.toml");
+        std::fs::write(&path, "name = \"mine\"\n[colors]\nkeyword = \"#f92672\"\n").unwrap();
+
+        let mut editor = new_test_editor(&["text"]);
+        editor.load_startup_theme(Some(&path));
+        assert_eq!(editor.theme.name(), "mine");
+
+        editor.load_startup_theme(Some(&dir.join("missing.toml")));
+        assert_eq!(editor.theme.name(), "monokai");
+        assert!(editor
+            .notification_queue
+            .back()
+            .unwrap()
+            .contains("using Monokai"));
+
+        std::fs::write(&path, "[colors]\nkeyword = 5\n").unwrap();
+        editor.set_colorscheme("gruvbox").unwrap();
+        editor.load_startup_theme(Some(&path));
+        assert_eq!(editor.theme.name(), "monokai");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_toml_theme_falls_back_to_scope_prefix() {
+        let dir = std::env::temp_dir().join(format!("neotext_theme_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mine.toml");
+        std::fs::write(
+            &path,
+            "default = \"#f8f8f2\"\n[colors]\nkeyword = \"#f92672\"\nstring = \"green\"\n",
+        )
+        .unwrap();
+
+        let mut editor = new_test_editor(&["text"]);
+        editor.set_colorscheme(&path.display().to_string()).unwrap();
+        let theme = editor.theme.as_ref();
+        assert_eq!(theme.name(), "mine");
+        let pink = Color::Rgb {
+            r: 249,
+            g: 38,
+            b: 114,
+        };
+        assert_eq!(theme.from_str("keyword.return"), pink);
+        assert_eq!(theme.from_str("string"), Color::Green);
+        assert_eq!(
+            theme.from_str("comment"),
+            Color::Rgb {
+                r: 248,
+                g: 248,
+                b: 242
+            }
+        );
+
+        std::fs::write(&path, "[colors]\nkeyword = \"#zz0000\"\n").unwrap();
+        assert!(matches!(
+            TomlTheme::load(&path),
+            Err(Error::ParsingError(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
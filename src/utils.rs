@@ -1,8 +1,12 @@
-use std::io::stdout;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use crossterm::{cursor, execute, style, terminal};
+use unicode_width::UnicodeWidthChar;
 
-use crate::error::Result;
+use crate::{error::Result, lsp::FileType};
 
 #[macro_export]
 macro_rules! repeat {
@@ -27,6 +31,191 @@ macro_rules! repeat {
         }
     }};
 }
+/// Returns the home directory of the current user, if it's known.
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// Expands a leading `~` in a user provided path to the home directory.
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Directory holding the user configuration, `~/.config/neotext`.
+pub fn config_dir() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(".config").join("neotext"))
+}
+
+/// Directory holding persistent editor data such as crash reports, `~/.local/share/neotext`.
+pub fn data_dir() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(".local").join("share").join("neotext"))
+}
+
+/// Change of a line compared to the version of the file in git, shown in the gutter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Modified,
+    /// Lines were removed right below this one.
+    Deleted,
+}
+
+/// Runs `git diff -U0` on the file at `path` and maps the changed lines, indexed from zero, to
+/// their status. Files outside of a repository have no changes.
+///
+/// # Errors
+/// Returns `Error::Io` if git can't be run.
+pub fn git_diff_lines(path: &Path) -> Result<HashMap<usize, DiffStatus>> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path.file_name().unwrap_or(path.as_os_str());
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["diff", "--no-color", "-U0", "--"])
+        .arg(file_name)
+        .output()?;
+    if !output.status.success() {
+        return Ok(HashMap::new());
+    }
+    Ok(parse_diff_hunks(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the hunk headers of a unified diff produced with `-U0`. Changed lines that replace
+/// removed ones are modified, any surplus is added, and pure removals mark the line above them.
+pub fn parse_diff_hunks(diff: &str) -> HashMap<usize, DiffStatus> {
+    // `start,count` with the count defaulting to one when omitted
+    let parse_range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+
+    let mut lines = HashMap::new();
+    for header in diff.lines().filter(|line| line.starts_with("@@ ")) {
+        let mut ranges = header.split_whitespace().skip(1);
+        let (Some(old), Some(new)) = (
+            ranges
+                .next()
+                .and_then(|r| r.strip_prefix('-'))
+                .and_then(parse_range),
+            ranges
+                .next()
+                .and_then(|r| r.strip_prefix('+'))
+                .and_then(parse_range),
+        ) else {
+            continue;
+        };
+        let ((_, removed), (start, added)) = (old, new);
+
+        if added == 0 {
+            lines.insert(start.saturating_sub(1), DiffStatus::Deleted);
+            continue;
+        }
+        for (i, line) in (start - 1..start - 1 + added).enumerate() {
+            let status = if i < removed {
+                DiffStatus::Modified
+            } else {
+                DiffStatus::Added
+            };
+            lines.insert(line, status);
+        }
+    }
+    lines
+}
+
+/// Whether the file at `path` exists but can't be written to.
+pub fn is_readonly(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|meta| meta.permissions().readonly())
+}
+
+/// Amount of terminal columns `text` takes up, with tabs reaching to the next multiple of
+/// `tabstop` and wide characters taking two columns.
+pub fn display_width(text: &str, tabstop: usize) -> usize {
+    text.chars().fold(0, |width, ch| match ch {
+        '\t' => width + tabstop - width % tabstop,
+        ch => width + ch.width().unwrap_or_default(),
+    })
+}
+
+/// Parses a vim modeline such as `# vim: set ft=python ts=4 sw=4:` or `// vi:ts=2:et`, returning
+/// the options it sets. Boolean options map to `true`, or `false` when prefixed with `no`.
+/// Returns `None` if the line contains no modeline.
+pub fn parse_modeline(line: &str) -> Option<HashMap<String, String>> {
+    let rest = ["vim:", "Vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        let at = line.find(marker)?;
+        (at == 0 || line[..at].ends_with(char::is_whitespace)).then(|| &line[at + marker.len()..])
+    })?;
+    let rest = rest.trim_start();
+
+    let options: Vec<&str> = match rest
+        .strip_prefix("set ")
+        .or_else(|| rest.strip_prefix("se "))
+    {
+        // `set` form, the options end at the next colon and anything after it is ignored
+        Some(args) => args.split(':').next()?.split_whitespace().collect(),
+        None => rest
+            .split(|ch: char| ch == ':' || ch.is_whitespace())
+            .filter(|option| !option.is_empty())
+            .collect(),
+    };
+
+    let settings: HashMap<String, String> = options
+        .into_iter()
+        .map(|option| match option.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => match option.strip_prefix("no") {
+                Some(key) => (key.to_string(), "false".to_string()),
+                None => (option.to_string(), "true".to_string()),
+            },
+        })
+        .collect();
+    (!settings.is_empty()).then_some(settings)
+}
+
+/// Detects the file type of a file, first by its extension or name, and if that fails by the
+/// interpreter named in the shebang on its first line.
+///
+/// # Examples
+/// ```
+/// let ft = detect_filetype(Path::new("deploy"), "#!/usr/bin/env python3");
+/// assert_eq!(ft, FileType::Python);
+/// ```
+pub fn detect_filetype(path: &Path, first_line: &str) -> FileType {
+    let by_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(FileType::from_extension);
+    let by_name = || match path.file_name().and_then(|name| name.to_str()) {
+        Some("Makefile" | "makefile" | "GNUmakefile") => Some(FileType::Make),
+        Some("Cargo.lock") => Some(FileType::Toml),
+        _ => None,
+    };
+
+    by_extension
+        .or_else(by_name)
+        .or_else(|| filetype_from_shebang(first_line))
+        .unwrap_or_default()
+}
+
+/// Parses a shebang line such as `#!/bin/bash` or `#!/usr/bin/env -S python3 -u`.
+fn filetype_from_shebang(line: &str) -> Option<FileType> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    let interpreter = if program == "env" {
+        words.find(|word| !word.starts_with('-'))?
+    } else {
+        program
+    };
+    FileType::from_interpreter(interpreter)
+}
+
 pub fn draw_ascii_art(term: &mut std::io::Stdout) -> Result<()> {
     let (term_width, term_height) = terminal::size()?;
     let art_lines: Vec<&str> = ASCII_INTRODUCTION_SCREEN2.lines().collect();
@@ -76,3 +265,103 @@ const ASCII_INTRODUCTION_SCREEN2: &str = "
 ██║╚██╗██║██╔══╝  ██║   ██║   ██║   ██╔══╝   ██╔██╗    ██║   
 ██║ ╚████║███████╗╚██████╔╝   ██║   ███████╗██╔╝ ██╗   ██║   
 ╚═╝  ╚═══╝╚══════╝ ╚═════╝    ╚═╝   ╚══════╝╚═╝  ╚═╝   ╚═╝   ";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_by_extension() {
+        assert_eq!(detect_filetype(Path::new("main.rs"), ""), FileType::Rust);
+        assert_eq!(detect_filetype(Path::new("Makefile"), ""), FileType::Make);
+    }
+
+    #[test]
+    fn test_detect_python_shebang_without_extension() {
+        assert_eq!(
+            detect_filetype(Path::new("deploy"), "#!/usr/bin/env python3"),
+            FileType::Python
+        );
+    }
+
+    #[test]
+    fn test_detect_bash_shebang_with_unknown_extension() {
+        assert_eq!(
+            detect_filetype(Path::new("run.cmd"), "#!/bin/bash"),
+            FileType::Bash
+        );
+    }
+
+    #[test]
+    fn test_extension_takes_precedence_over_shebang() {
+        assert_eq!(
+            detect_filetype(Path::new("script.rb"), "#!/usr/bin/env python3"),
+            FileType::Ruby
+        );
+    }
+
+    #[test]
+    fn test_detect_unknown() {
+        assert_eq!(
+            detect_filetype(Path::new("notes"), "just some text"),
+            FileType::PlainText
+        );
+    }
+
+    #[test]
+    fn test_parse_modeline_set_form() {
+        let settings = parse_modeline("# vim: set ft=python ts=4 sw=4 noet: trailing").unwrap();
+        assert_eq!(settings["ft"], "python");
+        assert_eq!(settings["ts"], "4");
+        assert_eq!(settings["sw"], "4");
+        assert_eq!(settings["et"], "false");
+        assert_eq!(settings.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_modeline_colon_form() {
+        let settings = parse_modeline("// vi:ts=2:et").unwrap();
+        assert_eq!(settings["ts"], "2");
+        assert_eq!(settings["et"], "true");
+    }
+
+    #[test]
+    fn test_parse_modeline_requires_marker() {
+        assert!(parse_modeline("let regex: Regex = foo;").is_none());
+        assert!(parse_modeline("fn main() {}").is_none());
+    }
+
+    #[test]
+    fn test_parse_diff_hunks() {
+        let diff = "diff --git a/main.rs b/main.rs
+index 3b18e51..a4c2b7e 100644
+--- a/main.rs
++++ b/main.rs
+@@ -2,0 +3,2 @@ fn main() {
++    let a = 1;
++    let b = 2;
+@@ -10 +12 @@ fn helper() {
+-    old();
++    new();
+@@ -20,2 +22,3 @@
+-x
+-y
++x2
++y2
++z2
+@@ -30,3 +31,0 @@
+-gone
+-gone
+-gone
+";
+        let lines = parse_diff_hunks(diff);
+        assert_eq!(lines[&2], DiffStatus::Added);
+        assert_eq!(lines[&3], DiffStatus::Added);
+        assert_eq!(lines[&11], DiffStatus::Modified);
+        assert_eq!(lines[&21], DiffStatus::Modified);
+        assert_eq!(lines[&22], DiffStatus::Modified);
+        assert_eq!(lines[&23], DiffStatus::Added);
+        assert_eq!(lines[&30], DiffStatus::Deleted);
+        assert_eq!(lines.len(), 7);
+    }
+}
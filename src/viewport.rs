@@ -1,8 +1,10 @@
 use crossterm::execute;
 
-use crate::{editor::LEFT_RESERVED_COLUMNS, LineCol};
+use crate::LineCol;
 
 const BAR_GAP: u16 = 2;
+/// Dimensions assumed when the terminal size can't be queried, e.g. when running headless.
+const FALLBACK_TERMINAL_DIMENSIONS: (u16, u16) = (80, 24);
 
 #[derive(Debug)]
 pub struct Viewport {
@@ -14,11 +16,14 @@ pub struct Viewport {
 impl Default for Viewport {
     fn default() -> Self {
         let mut terminal = std::io::stdout();
-        let _ = execute!(
-            terminal,
-            crossterm::terminal::EnterAlternateScreen,
-            crossterm::terminal::DisableLineWrap
-        );
+        // Unit tests construct viewports without owning the terminal, so the screen is left alone
+        if cfg!(not(test)) {
+            let _ = execute!(
+                terminal,
+                crossterm::terminal::EnterAlternateScreen,
+                crossterm::terminal::DisableLineWrap
+            );
+        }
         Self {
             topleft: LineCol { line: 0, col: 0 },
             terminal_dimensions: Self::get_new_dimensions(),
@@ -29,7 +34,7 @@ impl Default for Viewport {
 
 impl Viewport {
     fn get_new_dimensions() -> LineCol {
-        let xy = crossterm::terminal::size().expect("Need terminal information");
+        let xy = crossterm::terminal::size().unwrap_or(FALLBACK_TERMINAL_DIMENSIONS);
         LineCol {
             line: xy.1 as usize,
             col: xy.0 as usize,
@@ -54,9 +59,54 @@ impl Viewport {
         self.topleft.line = cursor.line.saturating_sub(half_height);
         self.topleft.col = cursor.col.saturating_sub(half_width);
     }
-    pub fn view_cursor(&self, cursor: LineCol) -> LineCol {
+    /// Scrolls so that `cursor_line` is the first visible line, as done by `zt`.
+    pub fn scroll_to_top_at(&mut self, cursor_line: usize) {
+        self.topleft.line = cursor_line;
+        debug_assert!(self.is_line_visible(cursor_line));
+    }
+    /// Scrolls so that `cursor_line` is the last line visible above the bars, as done by `zb`.
+    pub fn scroll_to_bottom_at(&mut self, cursor_line: usize) {
+        self.topleft.line = cursor_line.saturating_sub(self.text_height().saturating_sub(1));
+        debug_assert!(self.is_line_visible(cursor_line));
+    }
+    /// Scrolls so that `cursor_line` is in the middle of the lines above the bars, as done by
+    /// `zz`.
+    pub fn scroll_to_center_at(&mut self, cursor_line: usize) {
+        self.topleft.line = cursor_line.saturating_sub(self.text_height() / 2);
+    }
+    /// Scrolls horizontally so that `cursor_col` is the first visible column, as done by `zs`.
+    pub fn scroll_to_left_at(&mut self, cursor_col: usize) {
+        self.topleft.col = cursor_col;
+    }
+    /// Scrolls horizontally so that `cursor_col` is the last column visible right of the
+    /// `gutter_width` columns of the line numbers, as done by `ze`.
+    pub fn scroll_to_right_at(&mut self, cursor_col: usize, gutter_width: usize) {
+        let text_width = self
+            .terminal_dimensions
+            .col
+            .saturating_sub(gutter_width)
+            .max(1);
+        self.topleft.col = cursor_col.saturating_sub(text_width - 1);
+    }
+    /// Amount of buffer lines that fit above the info and notification bars.
+    fn text_height(&self) -> usize {
+        self.terminal_dimensions
+            .line
+            .saturating_sub(BAR_GAP as usize)
+            .max(1)
+    }
+    /// Last buffer line drawn above the bars.
+    pub fn last_visible_line(&self) -> usize {
+        self.topleft.line + self.text_height() - 1
+    }
+    pub fn is_line_visible(&self, line: usize) -> bool {
+        (self.topleft.line..self.topleft.line + self.text_height()).contains(&line)
+    }
+    /// Terminal position of `cursor`, with the text starting right after the `gutter_width`
+    /// columns of the line numbers.
+    pub fn view_cursor(&self, cursor: LineCol, gutter_width: usize) -> LineCol {
         let mut c = cursor - self.topleft;
-        c.col += LEFT_RESERVED_COLUMNS - 1;
+        c.col += gutter_width;
         c
     }
     pub fn update_dimensions(&mut self) {
@@ -70,13 +120,61 @@ impl Viewport {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_to_top_and_bottom() {
+        let mut viewport = Viewport {
+            terminal_dimensions: LineCol { line: 24, col: 80 },
+            ..Viewport::default()
+        };
+        let cursor = LineCol { line: 40, col: 0 };
+
+        viewport.scroll_to_top_at(cursor.line);
+        assert_eq!(viewport.view_cursor(cursor, 0).line, 0);
+
+        viewport.scroll_to_bottom_at(cursor.line);
+        assert_eq!(
+            viewport.view_cursor(cursor, 0).line,
+            24 - BAR_GAP as usize - 1
+        );
+        assert!(viewport.is_line_visible(cursor.line));
+        assert!(!viewport.is_line_visible(cursor.line + 1));
+
+        viewport.scroll_to_bottom_at(3);
+        assert_eq!(viewport.topleft.line, 0);
+    }
+
+    #[test]
+    fn test_scroll_horizontally() {
+        let mut viewport = Viewport {
+            terminal_dimensions: LineCol { line: 24, col: 80 },
+            ..Viewport::default()
+        };
+        let cursor = LineCol { line: 0, col: 100 };
+
+        viewport.scroll_to_left_at(cursor.col);
+        assert_eq!(viewport.view_cursor(cursor, 9).col, 9);
+
+        viewport.scroll_to_right_at(cursor.col, 9);
+        assert_eq!(viewport.view_cursor(cursor, 9).col, 79);
+
+        viewport.scroll_to_right_at(3, 9);
+        assert_eq!(viewport.topleft.col, 0);
+    }
+}
+
+#[cfg(not(test))]
 impl Drop for Viewport {
     fn drop(&mut self) {
         let _raw = crossterm::terminal::disable_raw_mode();
         let _exe = crossterm::execute!(
             self.terminal,
             crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
-            crossterm::terminal::LeaveAlternateScreen
+            crossterm::terminal::LeaveAlternateScreen,
+            crossterm::terminal::SetTitle("")
         );
     }
 }